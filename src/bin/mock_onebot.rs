@@ -0,0 +1,23 @@
+// 模拟的Onebot客户端, 脚本格式见 teleporter::onebot::mock_client
+//
+// 用法: cargo run --features test-util --bin mock_onebot -- <ws地址> <脚本文件>
+use anyhow::{Context, Result};
+use teleporter::onebot::mock_client::{self, Script};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt().with_target(false).init();
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Usage: {} <ws-url> <script.json>", args[0]);
+        std::process::exit(1);
+    }
+
+    let script: Script =
+        serde_json::from_str(&std::fs::read_to_string(&args[2]).context("Failed to read script")?)
+            .context("Failed to parse script")?;
+    mock_client::run(&args[1], script).await?;
+
+    Ok(())
+}
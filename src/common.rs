@@ -1,20 +1,37 @@
 use core::fmt;
 use core::hash::Hash;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 
+use age::secrecy::SecretString;
+use anyhow::Context;
 use config::Config;
 use serde::Deserialize;
 
 pub static CONFIG_PATH: &str = "config.toml";
+const DB_FILE: &str = "porter.db";
+// 解锁密钥文件的口令所在的环境变量
+const SECRETS_PASSPHRASE_ENV: &str = "TELEPORTER_SECRETS_PASSPHRASE";
+// 示例配置中的管理页面密码, 不允许直接使用
+const DEFAULT_WEB_PASSWORD: &str = "admin";
 
 pub type RemoteChatKey = (Endpoint, ChatType, String);
+// 重新加载配置时调整日志级别
+pub type LogLevelReloader = Arc<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
 
 /// Teleporter 配置
 #[derive(Debug, Deserialize)]
 pub struct TeleporterConfig {
     pub telegram: TelegramConfig,
-    pub onebot: OnebotConfig,
+    /// 可以配置多个监听, 如 QQ 和微信使用不同的端口和 token
+    #[serde(deserialize_with = "one_or_many")]
+    pub onebot: Vec<OnebotConfig>,
     pub general: GeneralConfig,
+    pub storage: Option<StorageConfig>,
+    pub web: Option<WebConfig>,
 }
 
 /// Telegram 配置
@@ -32,6 +49,210 @@ pub struct TelegramConfig {
     pub proxy_url: Option<String>,
     // Enable search
     pub enable_search: bool,
+    // Max media size in MB, larger media will be compressed or replaced by a notice
+    pub max_media_size: Option<usize>,
+    // Max size of downloaded media cache in MB
+    pub media_cache_size: Option<usize>,
+    // Mark remote messages as read after delivered to Telegram
+    pub mark_as_read: Option<bool>,
+    // Extra bots for sending messages of specified endpoints
+    pub extra_bots: Option<Vec<BotConfig>>,
+    // Translation provider for translating remote messages
+    pub translate: Option<TranslateConfig>,
+    // Transcription provider for voice messages
+    pub transcribe: Option<TranscribeConfig>,
+    // GIF conversion limits of each platform, longer animations are sent as video
+    pub gif_limit: Option<HashMap<String, GifLimitConfig>>,
+    // Cron schedule (minute hour day month weekday) of statistics report sent to admin
+    pub stats_report: Option<String>,
+    // Max number of media downloaded, converted and uploaded at the same time
+    pub media_workers: Option<usize>,
+    // Max number of chats whose messages are processed at the same time
+    pub chat_workers: Option<usize>,
+    // Max attempts of uploading each file part to Telegram
+    pub upload_attempts: Option<usize>,
+    // Reconnect notification policy of each endpoint, "default" for the others
+    pub reconnect_notice: Option<HashMap<String, ReconnectNoticeConfig>>,
+    // Custom emoji id of archive topic icons, keyed by "platform.chat_type", platform or chat type
+    pub topic_icon: Option<HashMap<String, i64>>,
+    // Static map image url template attached to locations, with {lat} and {lon} placeholders
+    pub static_map: Option<String>,
+    // Send large files to remote as temp file path or url instead of base64
+    pub file_transfer: Option<FileTransferConfig>,
+    // Bot API style chat id for system notices, falls back to admin private chat
+    pub notice_chat: Option<i64>,
+    // Language of bot replies and system notices
+    pub language: Option<Language>,
+    // Language of each admin, admin user id -> language, falls back to language
+    pub admin_language: Option<HashMap<String, Language>>,
+    // Command aliases, alias without slash -> command without slash
+    pub command_aliases: Option<HashMap<String, String>>,
+    // Quick actions triggered by admin reactions, emoji or custom emoji id -> action
+    pub reaction_actions: Option<HashMap<String, ReactionAction>>,
+    // Bot API style chat id where favorite messages are forwarded, falls back to admin private chat
+    pub favorites_chat: Option<i64>,
+    // Audio format of voice notes sent to remote, keyed by endpoint or platform, "default" for the others
+    pub voice_format: Option<HashMap<String, VoiceFormat>>,
+    // Path of silk encoder (silk-v3-encoder) used for silk voice format
+    pub silk_encoder: Option<String>,
+    // Open a topic per private chat without link or archive instead of sending to admin private chat
+    pub conversation_window: Option<ConversationWindowConfig>,
+    // Strip EXIF metadata (such as GPS) and apply orientation of JPEG photos sent to remote
+    pub strip_exif: Option<bool>,
+    // Signature prefixed to messages sent to remote in chats using the default signature setting
+    pub signature: Option<String>,
+}
+
+/// 私聊对话窗口配置
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ConversationWindowConfig {
+    /// 创建对话窗口的话题群 (Bot API 格式的 ID), Bot 需要有管理话题的权限
+    pub chat: i64,
+    /// 超过该天数没有消息的对话窗口被删除, 默认 7
+    pub inactive_days: Option<i64>,
+}
+
+/// 发送到远端的语音格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceFormat {
+    /// 不转码, 直接发送 Telegram 的 OPUS (ogg)
+    #[default]
+    Ogg,
+    /// 转成 AMR-NB
+    Amr,
+    /// 转成 SILK (QQ 语音)
+    Silk,
+}
+
+/// 管理员对转发的消息添加反应时执行的操作
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReactionAction {
+    /// 撤回远端的消息
+    Recall,
+    /// 置顶消息, 远端群同时设为精华消息
+    Pin,
+    /// 转发到收藏对话
+    Favorite,
+}
+
+/// Bot回复和系统通知使用的语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    /// 英文
+    #[default]
+    En,
+    /// 中文
+    Zh,
+}
+
+/// 大文件传输配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileTransferConfig {
+    /// 超过该大小(MB)的文件保存为临时文件发送, 默认 8
+    pub threshold: Option<usize>,
+    /// Onebot 实现访问临时目录的路径, 与本机路径不同时配置 (如 Docker 挂载)
+    pub path: Option<String>,
+    /// 通过管理页面下载临时文件的地址, 如 http://porter:8080/transfer, 配置后优先于路径
+    pub url: Option<String>,
+    /// Onebot 实现不在同一主机时, 通过 upload_file_stream 分段上传临时文件 (NapCat 扩展), 默认 false
+    pub stream: Option<bool>,
+}
+
+/// GIF 转换限制
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct GifLimitConfig {
+    /// 最长时长(秒)
+    pub max_duration: f64,
+    /// 最多帧数
+    pub max_frames: u64,
+    /// TGS贴纸转成GIF的最大字节数
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+}
+
+/// 远端连接和断开的提示策略
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ReconnectNoticeConfig {
+    /// 不再提示
+    #[serde(default)]
+    pub mute: bool,
+    /// 抑制窗口(秒), 窗口内只提示第一次变化, 之后的变化在窗口结束时合并提示, 0为每次都提示
+    pub window: Option<i64>,
+}
+
+/// 翻译服务配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct TranslateConfig {
+    /// 翻译服务提供方: deepl, google, openai
+    pub provider: String,
+    /// API key
+    pub api_key: String,
+    /// 自定义 API 地址, 用于 DeepL Pro 或 OpenAI 兼容的服务
+    pub api_url: Option<String>,
+    /// OpenAI 兼容服务使用的模型
+    pub model: Option<String>,
+}
+
+/// 语音转写服务配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct TranscribeConfig {
+    /// 转写服务提供方: whisper_cpp, openai
+    pub provider: String,
+    /// whisper.cpp 可执行文件路径
+    pub binary: Option<String>,
+    /// whisper.cpp 的模型文件或 OpenAI 兼容服务使用的模型
+    pub model: Option<String>,
+    /// API key
+    pub api_key: Option<String>,
+    /// 自定义 API 地址, 用于 OpenAI 兼容的服务
+    pub api_url: Option<String>,
+}
+
+/// 额外的 Telegram Bot 配置
+#[derive(Debug, Deserialize)]
+pub struct BotConfig {
+    /// Telegram Bot token
+    pub bot_token: String,
+    /// 由该 Bot 发送消息的端点, 如 qq:12345
+    pub endpoints: Vec<String>,
+    /// 连接方式, 默认 mtproto
+    #[serde(default)]
+    pub transport: BotTransport,
+    /// 自建 Bot API 服务器的地址, 默认 https://api.telegram.org
+    pub api_url: Option<String>,
+}
+
+/// 额外的 Bot 连接 Telegram 的方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotTransport {
+    /// 与主 Bot 相同, 使用 api_id 和 api_hash 以 MTProto 连接
+    #[default]
+    Mtproto,
+    /// 使用 HTTP Bot API, 只用于发送消息, 无法检查已删除的 Topic
+    BotApi,
+}
+
+// 兼容单个 [onebot] 和多个 [[onebot]]
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(item) => vec![item],
+        OneOrMany::Many(items) => items,
+    })
 }
 
 /// Onebot 配置
@@ -41,6 +262,50 @@ pub struct OnebotConfig {
     pub addr: String,
     /// 连接验证 token
     pub token: Option<String>,
+    /// 各平台每个对话每分钟最多发送的消息数, 如 qq = 20, 0 表示不限制
+    pub rate_limit: Option<HashMap<String, u32>>,
+    /// 按端点ID (X-Self-ID) 限制可调用的API和接收的事件, 未配置的端点不限制
+    pub permissions: Option<HashMap<String, EndpointPermission>>,
+    /// 每个端点的事件队列长度, 默认 1024
+    pub queue_size: Option<usize>,
+    /// 事件队列满时的处理方式, 默认暂停读取
+    pub overflow: Option<OverflowPolicy>,
+}
+
+/// 事件队列满时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// 丢弃最早的事件并提示管理员
+    DropOldest,
+    /// 暂停读取该端点的WebSocket, 直到队列有空位; 有API调用等待响应时继续读取, 超出两倍长度后丢弃最早的事件
+    #[default]
+    Pause,
+}
+
+/// 端点权限配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct EndpointPermission {
+    /// 允许调用的API, 如 send_msg, 未配置时不限制
+    pub actions: Option<Vec<String>>,
+    /// 允许接收的事件类型, 如 message, notice, 未配置时不限制, 元事件总是接收
+    pub events: Option<Vec<String>>,
+}
+
+impl EndpointPermission {
+    pub fn allow_action(&self, action: &str) -> bool {
+        self.actions
+            .as_ref()
+            .is_none_or(|actions| actions.iter().any(|a| a == action))
+    }
+
+    pub fn allow_event(&self, post_type: &str) -> bool {
+        post_type == "meta_event"
+            || self
+                .events
+                .as_ref()
+                .is_none_or(|events| events.iter().any(|e| e == post_type))
+    }
 }
 
 /// 通用配置
@@ -48,16 +313,188 @@ pub struct OnebotConfig {
 pub struct GeneralConfig {
     /// 日志级别
     pub log_level: String,
+    /// 日志格式, text 或 json, 默认为 text
+    pub log_format: Option<String>,
+    /// age 口令加密的密钥文件, 内容为 TOML 格式的 名称 = 值, 配置中以 secret:名称 引用
+    pub secrets_file: Option<String>,
+}
+
+/// 存储配置
+#[derive(Debug, Deserialize)]
+pub struct StorageConfig {
+    /// 数据库, 会话, 日志以及索引的存放目录
+    pub data_dir: String,
+    /// 数据库连接地址, 支持sqlite, postgres和mysql
+    pub database_url: Option<String>,
+    /// 日志, 索引和临时文件的保留策略, 未配置时不清理
+    pub retention: Option<RetentionConfig>,
+}
+
+/// 存储保留策略
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetentionConfig {
+    /// 日志保留天数, 默认 30
+    pub log_days: Option<u64>,
+    /// 日志的最大总大小(GB), 超过时从最早的日志开始删除
+    pub log_size: Option<f64>,
+    /// 搜索索引保留天数, 未配置时不删除
+    pub index_days: Option<i64>,
+    /// 系统临时目录中本程序残留的临时文件保留小时数, 默认 24
+    pub temp_hours: Option<u64>,
+}
+
+/// 管理页面配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebConfig {
+    /// HTTP 监听地址
+    pub addr: String,
+    /// 登录用户名
+    pub username: String,
+    /// 登录密码
+    pub password: String,
 }
 
 impl TeleporterConfig {
     pub fn load() -> Self {
-        let config = Config::builder()
+        Self::try_load().unwrap()
+    }
+
+    pub fn try_load() -> anyhow::Result<Self> {
+        let mut config: Self = Config::builder()
             .add_source(config::File::with_name(CONFIG_PATH))
-            .build()
-            .unwrap();
+            .build()?
+            .try_deserialize()?;
+        config.resolve_secrets()?;
+
+        // 主Bot的收消息, 命令和回调都依赖 MTProto, bot_api 只能用于额外Bot发送消息
+        if config.telegram.api_id == 0 || config.telegram.api_hash.is_empty() {
+            anyhow::bail!(
+                "telegram.api_id and telegram.api_hash are required: the primary bot always connects over MTProto, transport = \"bot_api\" is only supported for send-only extra_bots"
+            );
+        }
+
+        // 管理页面可以修改链接和归档, 不允许空密码或默认密码
+        if let Some(web) = &config.web {
+            if web.password.is_empty() || web.password == DEFAULT_WEB_PASSWORD {
+                anyhow::bail!("web.password must be set to a non-empty, non-default value");
+            }
+        }
+
+        Ok(config)
+    }
+
+    // 替换配置中以 env: 或 secret: 引用的敏感信息
+    fn resolve_secrets(&mut self) -> anyhow::Result<()> {
+        let secrets = Secrets::load(self.general.secrets_file.as_deref())?;
+
+        let telegram = &mut self.telegram;
+        secrets.resolve(&mut telegram.bot_token)?;
+        secrets.resolve(&mut telegram.api_hash)?;
+        for bot in telegram.extra_bots.iter_mut().flatten() {
+            secrets.resolve(&mut bot.bot_token)?;
+        }
+        if let Some(translate) = &mut telegram.translate {
+            secrets.resolve(&mut translate.api_key)?;
+        }
+        if let Some(api_key) = telegram
+            .transcribe
+            .as_mut()
+            .and_then(|t| t.api_key.as_mut())
+        {
+            secrets.resolve(api_key)?;
+        }
+        for token in self.onebot.iter_mut().filter_map(|o| o.token.as_mut()) {
+            secrets.resolve(token)?;
+        }
+        if let Some(web) = &mut self.web {
+            secrets.resolve(&mut web.password)?;
+        }
+        if let Some(database_url) = self.storage.as_mut().and_then(|s| s.database_url.as_mut()) {
+            secrets.resolve(database_url)?;
+        }
+
+        Ok(())
+    }
+
+    // 合并各监听的频率限制, 同一平台以先配置的为准
+    pub fn rate_limit(&self) -> HashMap<String, u32> {
+        let mut rate_limit = HashMap::new();
+        for onebot in &self.onebot {
+            for (platform, limit) in onebot.rate_limit.iter().flatten() {
+                rate_limit.entry(platform.clone()).or_insert(*limit);
+            }
+        }
+        rate_limit
+    }
+
+    pub fn retention(&self) -> Option<RetentionConfig> {
+        self.storage.as_ref().and_then(|s| s.retention.clone())
+    }
+
+    // 未配置时使用当前目录
+    pub fn data_dir(&self) -> PathBuf {
+        match &self.storage {
+            Some(storage) => PathBuf::from(&storage.data_dir),
+            None => PathBuf::from("."),
+        }
+    }
+
+    // 未配置时使用数据目录下的sqlite
+    pub fn database_url(&self) -> String {
+        match self.storage.as_ref().and_then(|s| s.database_url.clone()) {
+            Some(url) => url,
+            None => format!(
+                "sqlite://{}?mode=rwc",
+                self.data_dir().join(DB_FILE).display()
+            ),
+        }
+    }
+}
+
+// 从加密的密钥文件中读取的敏感信息
+struct Secrets(HashMap<String, String>);
+
+impl Secrets {
+    fn load(path: Option<&str>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self(HashMap::new()));
+        };
+
+        let passphrase = std::env::var(SECRETS_PASSPHRASE_ENV).with_context(|| {
+            format!("{} is required to unlock {}", SECRETS_PASSPHRASE_ENV, path)
+        })?;
+        let encrypted = std::fs::read(path).with_context(|| format!("Failed to read {}", path))?;
+
+        // 同时支持二进制和 ASCII armor 格式
+        let decryptor = age::Decryptor::new(age::armor::ArmoredReader::new(&encrypted[..]))?;
+        let identity = age::scrypt::Identity::new(SecretString::from(passphrase));
+        let mut reader = decryptor.decrypt(std::iter::once(&identity as &dyn age::Identity))?;
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+
+        let secrets = Config::builder()
+            .add_source(config::File::from_str(&content, config::FileFormat::Toml))
+            .build()?
+            .try_deserialize()?;
+
+        Ok(Self(secrets))
+    }
+
+    fn resolve(&self, value: &mut String) -> anyhow::Result<()> {
+        let resolved = if let Some(name) = value.strip_prefix("env:") {
+            std::env::var(name)
+                .with_context(|| format!("Environment variable {} is not set", name))?
+        } else if let Some(name) = value.strip_prefix("secret:") {
+            self.0
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Secret {} is not found", name))?
+        } else {
+            return Ok(());
+        };
+        *value = resolved;
 
-        config.try_deserialize().unwrap()
+        Ok(())
     }
 }
 
@@ -137,6 +574,8 @@ impl FromStr for Endpoint {
 pub enum ChatType {
     Private,
     Group,
+    // QQ频道的子频道, 目标ID为 频道ID/子频道ID
+    Guild,
 }
 
 impl fmt::Display for ChatType {
@@ -144,6 +583,7 @@ impl fmt::Display for ChatType {
         match self {
             ChatType::Private => f.write_str("private"),
             ChatType::Group => f.write_str("group"),
+            ChatType::Guild => f.write_str("guild"),
         }
     }
 }
@@ -155,6 +595,7 @@ impl FromStr for ChatType {
         match s {
             "private" => Ok(ChatType::Private),
             "group" => Ok(ChatType::Group),
+            "guild" => Ok(ChatType::Guild),
             _ => Err(format!("invalid chat type: {}", s)),
         }
     }
@@ -193,3 +634,339 @@ impl FromStr for DeliveryStatus {
         }
     }
 }
+
+// 可单独设置转发方式的媒体类型
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Video,
+    Voice,
+    File,
+    Sticker,
+}
+
+impl MediaKind {
+    pub const ALL: [MediaKind; 5] = [
+        MediaKind::Image,
+        MediaKind::Video,
+        MediaKind::Voice,
+        MediaKind::File,
+        MediaKind::Sticker,
+    ];
+
+    // 不上传时显示的占位名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            MediaKind::Image => "图片",
+            MediaKind::Video => "视频",
+            MediaKind::Voice => "语音",
+            MediaKind::File => "文件",
+            MediaKind::Sticker => "表情",
+        }
+    }
+}
+
+impl fmt::Display for MediaKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MediaKind::Image => f.write_str("image"),
+            MediaKind::Video => f.write_str("video"),
+            MediaKind::Voice => f.write_str("voice"),
+            MediaKind::File => f.write_str("file"),
+            MediaKind::Sticker => f.write_str("sticker"),
+        }
+    }
+}
+
+impl FromStr for MediaKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "image" => Ok(MediaKind::Image),
+            "video" => Ok(MediaKind::Video),
+            "voice" => Ok(MediaKind::Voice),
+            "file" => Ok(MediaKind::File),
+            "sticker" => Ok(MediaKind::Sticker),
+            _ => Err(format!("invalid media kind: {}", s)),
+        }
+    }
+}
+
+// 媒体的转发方式: 上传, 只发送链接, 或者只保留文字占位
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum MediaMode {
+    Upload,
+    Link,
+    Drop,
+}
+
+impl MediaMode {
+    // 设置界面中依次切换
+    pub fn next(&self) -> Self {
+        match self {
+            MediaMode::Upload => MediaMode::Link,
+            MediaMode::Link => MediaMode::Drop,
+            MediaMode::Drop => MediaMode::Upload,
+        }
+    }
+}
+
+impl fmt::Display for MediaMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MediaMode::Upload => f.write_str("upload"),
+            MediaMode::Link => f.write_str("link"),
+            MediaMode::Drop => f.write_str("drop"),
+        }
+    }
+}
+
+impl FromStr for MediaMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upload" => Ok(MediaMode::Upload),
+            "link" => Ok(MediaMode::Link),
+            "drop" => Ok(MediaMode::Drop),
+            _ => Err(format!("invalid media mode: {}", s)),
+        }
+    }
+}
+
+// 群成员变动的提示级别: 全部提示, 只提示被移出, 或者关闭
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum MemberNotice {
+    All,
+    Kick,
+    Off,
+}
+
+impl MemberNotice {
+    // 设置界面中依次切换
+    pub fn next(&self) -> Self {
+        match self {
+            MemberNotice::All => MemberNotice::Kick,
+            MemberNotice::Kick => MemberNotice::Off,
+            MemberNotice::Off => MemberNotice::All,
+        }
+    }
+}
+
+impl fmt::Display for MemberNotice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MemberNotice::All => f.write_str("all"),
+            MemberNotice::Kick => f.write_str("kick"),
+            MemberNotice::Off => f.write_str("off"),
+        }
+    }
+}
+
+impl FromStr for MemberNotice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(MemberNotice::All),
+            "kick" => Ok(MemberNotice::Kick),
+            "off" => Ok(MemberNotice::Off),
+            _ => Err(format!("invalid member notice: {}", s)),
+        }
+    }
+}
+
+// 发往远端的消息的署名: 使用配置的默认署名, 加上Telegram发送者的名称, 或不加署名
+// 署名只是名称前缀, 剧透等提示不受影响
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signature {
+    Default,
+    Name,
+    None,
+}
+
+impl Signature {
+    // 设置界面中依次切换
+    pub fn next(&self) -> Self {
+        match self {
+            Signature::Default => Signature::Name,
+            Signature::Name => Signature::None,
+            Signature::None => Signature::Default,
+        }
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Signature::Default => f.write_str("default"),
+            Signature::Name => f.write_str("name"),
+            Signature::None => f.write_str("none"),
+        }
+    }
+}
+
+impl FromStr for Signature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Signature::Default),
+            "name" => Ok(Signature::Name),
+            "none" => Ok(Signature::None),
+            _ => Err(format!("invalid signature: {}", s)),
+        }
+    }
+}
+
+// 内容过滤规则命中后的处理: 丢弃消息, 打码命中的内容, 或者照常转发并提醒管理员
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum FilterAction {
+    Drop,
+    Redact,
+    Flag,
+}
+
+impl fmt::Display for FilterAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FilterAction::Drop => f.write_str("drop"),
+            FilterAction::Redact => f.write_str("redact"),
+            FilterAction::Flag => f.write_str("flag"),
+        }
+    }
+}
+
+impl FromStr for FilterAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop" => Ok(FilterAction::Drop),
+            "redact" => Ok(FilterAction::Redact),
+            "flag" => Ok(FilterAction::Flag),
+            _ => Err(format!("invalid filter action: {}", s)),
+        }
+    }
+}
+
+// 内容过滤规则作用的方向: in 为远端到Telegram, out 为Telegram到远端
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum FilterDirection {
+    Both,
+    In,
+    Out,
+}
+
+impl FilterDirection {
+    pub fn contains(&self, direction: FilterDirection) -> bool {
+        *self == FilterDirection::Both || *self == direction
+    }
+}
+
+impl fmt::Display for FilterDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FilterDirection::Both => f.write_str("both"),
+            FilterDirection::In => f.write_str("in"),
+            FilterDirection::Out => f.write_str("out"),
+        }
+    }
+}
+
+impl FromStr for FilterDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "both" => Ok(FilterDirection::Both),
+            "in" => Ok(FilterDirection::In),
+            "out" => Ok(FilterDirection::Out),
+            _ => Err(format!("invalid filter direction: {}", s)),
+        }
+    }
+}
+
+// 简化的cron表达式: 分 时 日 月 周, 每项支持 *, 数字, 列表, 范围和步长, 如 "0 9 * * 1"
+// 各项按位记录允许的值, 与标准cron一致, 日和周都有限制(不以*开头)时满足其一即可
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // 日和周都有限制
+    day_or_weekday: bool,
+}
+
+impl CronSchedule {
+    pub fn matches<T: chrono::Datelike + chrono::Timelike>(&self, time: &T) -> bool {
+        let day = self.days & (1 << time.day()) != 0;
+        let weekday = self.weekdays & (1 << time.weekday().num_days_from_sunday()) != 0;
+        self.minutes & (1 << time.minute()) != 0
+            && self.hours & (1 << time.hour()) != 0
+            && self.months & (1 << time.month()) != 0
+            && match self.day_or_weekday {
+                true => day || weekday,
+                false => day && weekday,
+            }
+    }
+
+    fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+        let mut bits = 0u64;
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().map_err(|e| e.to_string())?),
+                None => (part, 1),
+            };
+            let (start, end) = match range.split_once('-') {
+                _ if range == "*" => (min, max),
+                Some((start, end)) => (
+                    start.parse::<u32>().map_err(|e| e.to_string())?,
+                    end.parse::<u32>().map_err(|e| e.to_string())?,
+                ),
+                None => {
+                    let value = range.parse::<u32>().map_err(|e| e.to_string())?;
+                    // 带步长的单个值表示从该值开始到最大值
+                    (value, if part.contains('/') { max } else { value })
+                }
+            };
+            if step == 0 || start < min || end > max || start > end {
+                return Err(format!("invalid cron field: {}", field));
+            }
+            for value in (start..=end).step_by(step as usize) {
+                bits |= 1 << value;
+            }
+        }
+
+        Ok(bits)
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("invalid cron schedule: {}", s));
+        }
+
+        // 周日可以写成0或7
+        let mut weekdays = Self::parse_field(fields[4], 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+
+        Ok(CronSchedule {
+            minutes: Self::parse_field(fields[0], 0, 59)?,
+            hours: Self::parse_field(fields[1], 0, 23)?,
+            days: Self::parse_field(fields[2], 1, 31)?,
+            months: Self::parse_field(fields[3], 1, 12)?,
+            weekdays,
+            day_or_weekday: !fields[2].starts_with('*') && !fields[4].starts_with('*'),
+        })
+    }
+}
@@ -0,0 +1,5 @@
+pub mod common;
+pub mod onebot;
+pub mod telegram;
+
+use telegram::telegram_pylon::TelegramPylon;
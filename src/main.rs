@@ -1,6 +1,4 @@
-mod common;
-mod onebot;
-mod telegram;
+use std::sync::Arc;
 
 #[cfg(not(target_env = "msvc"))]
 use tikv_jemallocator::Jemalloc;
@@ -9,11 +7,12 @@ use tokio::sync::{broadcast, mpsc};
 use tracing::Level;
 use tracing_log::LogTracer;
 use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::{EnvFilter, fmt};
+use tracing_subscriber::{EnvFilter, fmt, reload};
 
-use crate::common::TeleporterConfig;
-use crate::onebot::onebot_pylon::OnebotPylon;
-use crate::telegram::telegram_pylon::TelegramPylon;
+use teleporter::common::{LogLevelReloader, TeleporterConfig};
+use teleporter::onebot::onebot_pylon::OnebotPylon;
+use teleporter::telegram::telegram_pylon::TelegramPylon;
+use teleporter::telegram::web_service::RecentErrorLayer;
 
 #[cfg(not(target_env = "msvc"))]
 #[global_allocator]
@@ -25,6 +24,11 @@ const BUFFER_SIZE: usize = 1024;
 async fn main() {
     let config = TeleporterConfig::load();
 
+    // 确保数据目录存在
+    let data_dir = config.data_dir();
+    std::fs::create_dir_all(&data_dir).expect("Failed to create data directory");
+    let database_url = config.database_url();
+
     // 设置日志
     LogTracer::init().expect("Failed to set logger");
     let log_level = config
@@ -32,20 +36,63 @@ async fn main() {
         .log_level
         .parse::<Level>()
         .unwrap_or(Level::INFO);
-    let file_appender = tracing_appender::rolling::daily("logs", "porter.log");
+    let file_appender = tracing_appender::rolling::daily(data_dir.join("logs"), "porter.log");
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
+    // JSON格式带上当前的span, 便于按trace_id和对话查询
+    let json = config.general.log_format.as_deref() == Some("json");
+    // 日志级别可以在重新加载配置时调整
+    let (filter, filter_handle) = reload::Layer::new(log_filter(log_level));
+    let log_reloader: LogLevelReloader = Arc::new(move |log_level: &str| {
+        let log_level = log_level.parse::<Level>().map_err(|e| e.to_string())?;
+        filter_handle
+            .reload(log_filter(log_level))
+            .map_err(|e| e.to_string())
+    });
     let subscriber = tracing_subscriber::registry()
-        .with(
-            EnvFilter::from_default_env()
-                .add_directive(log_level.into())
-                .add_directive("sqlx::query=off".parse().unwrap()),
-        )
-        .with(fmt::Layer::new().with_writer(std::io::stdout))
-        .with(fmt::Layer::new().with_writer(non_blocking).with_ansi(false));
+        .with(filter)
+        .with((!json).then(|| fmt::Layer::new().with_writer(std::io::stdout)))
+        .with((!json).then(|| {
+            fmt::Layer::new()
+                .with_writer(non_blocking.clone())
+                .with_ansi(false)
+        }))
+        .with(json.then(|| {
+            fmt::Layer::new()
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .with_writer(std::io::stdout)
+        }))
+        .with(json.then(|| {
+            fmt::Layer::new()
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .with_writer(non_blocking)
+        }))
+        .with(RecentErrorLayer);
     tracing::subscriber::set_global_default(subscriber).expect("Unable to set a global subscriber");
 
-    let telegram_pylon = TelegramPylon::new(config.telegram).await.unwrap();
-    let onebot_pylon = OnebotPylon::new(config.onebot).await.unwrap();
+    let rate_limit = config.rate_limit();
+    let retention = config.retention();
+    let telegram_pylon = TelegramPylon::new(
+        config.telegram,
+        &data_dir,
+        &database_url,
+        rate_limit,
+        retention,
+        config.web,
+        log_reloader,
+    )
+    .await
+    .unwrap();
+    if config.onebot.is_empty() {
+        panic!("At least one onebot listener is required");
+    }
+    let mut onebot_pylons = Vec::new();
+    for onebot in config.onebot {
+        onebot_pylons.push(OnebotPylon::new(onebot).await.unwrap());
+    }
 
     let (event_sender, event_receiver) = mpsc::channel(BUFFER_SIZE);
     let (api_sender, api_receiver) = mpsc::channel(BUFFER_SIZE);
@@ -88,12 +135,36 @@ async fn main() {
             .await;
     });
 
-    let onebot_handle = tokio::spawn(async move {
-        onebot_pylon
-            .run(event_sender, api_receiver, onebot_shutdown_tx.subscribe())
-            .await;
-    });
+    // 每个监听一个OnebotPylon, 共用事件通道, API请求按端点所在的监听转发
+    let mut onebot_handles = Vec::new();
+    let mut routes = Vec::new();
+    for onebot_pylon in onebot_pylons {
+        let (pylon_api_sender, pylon_api_receiver) = mpsc::channel(BUFFER_SIZE);
+        routes.push((onebot_pylon.clone(), pylon_api_sender));
+        let event_sender = event_sender.clone();
+        let shutdown_rx = onebot_shutdown_tx.subscribe();
+        onebot_handles.push(tokio::spawn(async move {
+            onebot_pylon
+                .run(event_sender, pylon_api_receiver, shutdown_rx)
+                .await;
+        }));
+    }
+    drop(event_sender);
+    onebot_handles.push(tokio::spawn(OnebotPylon::route_requests(
+        routes,
+        api_receiver,
+        onebot_shutdown_tx.subscribe(),
+    )));
 
-    let _ = tokio::try_join!(telegram_handle, onebot_handle);
+    let _ = tokio::join!(
+        telegram_handle,
+        futures_util::future::join_all(onebot_handles)
+    );
     tracing::info!("Main components have completed shutdown...");
 }
+
+fn log_filter(log_level: Level) -> EnvFilter {
+    EnvFilter::from_default_env()
+        .add_directive(log_level.into())
+        .add_directive("sqlx::query=off".parse().unwrap())
+}
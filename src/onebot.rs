@@ -1,2 +1,5 @@
+pub mod event_queue;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock_client;
 pub mod onebot_pylon;
 pub mod protocol;
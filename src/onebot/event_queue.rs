@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use tokio::sync::Notify;
+
+use super::protocol::OnebotEvent;
+use crate::common::{Endpoint, OverflowPolicy};
+
+lazy_static! {
+    // 各端点当前的事件队列, 用于查看队列长度
+    static ref QUEUES: DashMap<Endpoint, Arc<EventQueue>> = DashMap::new();
+}
+
+// 端点的事件队列, 在读取WebSocket和转发给Telegram之间缓冲
+pub struct EventQueue {
+    events: std::sync::Mutex<VecDeque<OnebotEvent>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    // 有新事件
+    pushed: Notify,
+    // 有空位
+    popped: Notify,
+    // 上次提示后丢弃的事件数
+    dropped: AtomicU64,
+    // 累计丢弃的事件数
+    total_dropped: AtomicU64,
+    // 等待该连接响应的API调用数
+    calls_in_flight: AtomicUsize,
+    closed: AtomicBool,
+}
+
+// 队列状态
+pub struct QueueStats {
+    pub endpoint: Endpoint,
+    pub depth: usize,
+    pub capacity: usize,
+    pub total_dropped: u64,
+}
+
+impl EventQueue {
+    // 创建端点的队列, 替换掉该端点之前连接的队列
+    pub fn register(endpoint: &Endpoint, capacity: usize, policy: OverflowPolicy) -> Arc<Self> {
+        let queue = Arc::new(Self {
+            events: std::sync::Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity: capacity.max(1),
+            policy,
+            pushed: Notify::new(),
+            popped: Notify::new(),
+            dropped: AtomicU64::new(0),
+            total_dropped: AtomicU64::new(0),
+            calls_in_flight: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+        });
+        if let Some(old) = QUEUES.insert(endpoint.clone(), queue.clone()) {
+            old.close();
+        }
+        queue
+    }
+
+    // 移除端点的队列, 已在队列中的事件仍会被转发
+    pub fn unregister(endpoint: &Endpoint, queue: &Arc<Self>) {
+        QUEUES.remove_if(endpoint, |_, q| Arc::ptr_eq(q, queue));
+        queue.close();
+    }
+
+    pub fn stats() -> Vec<QueueStats> {
+        QUEUES
+            .iter()
+            .map(|e| QueueStats {
+                endpoint: e.key().clone(),
+                depth: e.value().depth(),
+                capacity: e.value().capacity,
+                total_dropped: e.value().total_dropped.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.events.lock().unwrap().len()
+    }
+
+    // 端点当前连接的队列
+    pub fn get(endpoint: &Endpoint) -> Option<Arc<Self>> {
+        QUEUES.get(endpoint).map(|queue| queue.clone())
+    }
+
+    // 发出API调用, 在响应返回前暂停读取会使等待响应的处理永远无法完成
+    pub fn call_started(&self) {
+        self.calls_in_flight.fetch_add(1, Ordering::Relaxed);
+        self.popped.notify_one();
+    }
+
+    pub fn call_finished(&self) {
+        let _ = self
+            .calls_in_flight
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+
+    // 放入事件, 队列满时按策略丢弃最早的事件或等待空位
+    // 暂停策略下有API调用等待响应时继续放入(最多到两倍长度), 以便读取排在事件之后的响应
+    pub async fn push(&self, event: OnebotEvent) {
+        loop {
+            let popped = self.popped.notified();
+            {
+                let mut events = self.events.lock().unwrap();
+                if events.len() < self.capacity || self.closed.load(Ordering::Relaxed) {
+                    events.push_back(event);
+                    break;
+                }
+                let calls_in_flight = self.calls_in_flight.load(Ordering::Relaxed) > 0;
+                if self.policy == OverflowPolicy::Pause
+                    && calls_in_flight
+                    && events.len() < self.capacity * 2
+                {
+                    events.push_back(event);
+                    break;
+                }
+                if self.policy == OverflowPolicy::DropOldest || calls_in_flight {
+                    events.pop_front();
+                    events.push_back(event);
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    self.total_dropped.fetch_add(1, Ordering::Relaxed);
+                    break;
+                }
+            }
+            popped.await;
+        }
+        self.pushed.notify_one();
+    }
+
+    // 取出事件, 队列关闭且为空时返回None
+    pub async fn pop(&self) -> Option<OnebotEvent> {
+        loop {
+            let pushed = self.pushed.notified();
+            {
+                let mut events = self.events.lock().unwrap();
+                if let Some(event) = events.pop_front() {
+                    self.popped.notify_one();
+                    return Some(event);
+                }
+                if self.closed.load(Ordering::Relaxed) {
+                    return None;
+                }
+            }
+            pushed.await;
+        }
+    }
+
+    // 取出上次提示后丢弃的事件数
+    pub fn take_dropped(&self) -> u64 {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.pushed.notify_one();
+        self.popped.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::common::Platform;
+    use crate::onebot::protocol::event::{Event, LifecycleEvent, MetaEvent};
+
+    fn endpoint(id: &str) -> Endpoint {
+        Endpoint {
+            platform: Platform::QQ,
+            id: id.to_string(),
+        }
+    }
+
+    fn event(endpoint: &Endpoint) -> OnebotEvent {
+        OnebotEvent {
+            endpoint: endpoint.clone(),
+            raw: Arc::new(Event::Meta(MetaEvent::Lifecycle(LifecycleEvent {
+                time: 0,
+                self_id: endpoint.id.clone(),
+                sub_type: "connect".to_string(),
+            }))),
+        }
+    }
+
+    #[tokio::test]
+    async fn pause_waits_for_space() {
+        let endpoint = endpoint("1");
+        let queue = EventQueue::register(&endpoint, 1, OverflowPolicy::Pause);
+        queue.push(event(&endpoint)).await;
+
+        let push = queue.push(event(&endpoint));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), push)
+                .await
+                .is_err()
+        );
+        EventQueue::unregister(&endpoint, &queue);
+    }
+
+    #[tokio::test]
+    async fn pause_keeps_reading_while_call_in_flight() {
+        let endpoint = endpoint("2");
+        let queue = EventQueue::register(&endpoint, 1, OverflowPolicy::Pause);
+        queue.push(event(&endpoint)).await;
+
+        // 已经在等待空位时发出API调用, 也应继续放入
+        let waiting = {
+            let queue = queue.clone();
+            let endpoint = endpoint.clone();
+            tokio::spawn(async move { queue.push(event(&endpoint)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        queue.call_started();
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("push is still paused while an API call is in flight")
+            .unwrap();
+        assert_eq!(queue.depth(), 2);
+
+        // 超过两倍长度时丢弃最早的事件, 不再等待
+        tokio::time::timeout(Duration::from_secs(1), queue.push(event(&endpoint)))
+            .await
+            .unwrap();
+        assert_eq!(queue.depth(), 2);
+        assert_eq!(queue.take_dropped(), 1);
+
+        queue.call_finished();
+        EventQueue::unregister(&endpoint, &queue);
+    }
+}
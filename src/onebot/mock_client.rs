@@ -0,0 +1,150 @@
+// 模拟的Onebot客户端, 按脚本向Teleporter上报事件并应答API请求,
+// 用于在没有真实QQ/微信账号的情况下测试转发流程
+//
+// 脚本为JSON格式:
+// {
+//   "self_id": "10000",
+//   "token": "test",
+//   "user_agent": "LLOneBot/mock",
+//   "responses": { "get_login_info": { "user_id": 10000, "nickname": "mock" } },
+//   "events": [ { "delay_ms": 1000, "event": { "post_type": "message", ... } } ],
+//   "linger_ms": 5000
+// }
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+// 事件发送完后等待API请求的默认时间
+const LINGER_MS: u64 = 5000;
+
+#[derive(Deserialize)]
+pub struct Script {
+    pub self_id: String,
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+    // 各API的返回数据, 未配置的返回null, send_msg默认返回自增的消息ID
+    #[serde(default)]
+    pub responses: HashMap<String, Value>,
+    #[serde(default)]
+    pub events: Vec<ScriptEvent>,
+    #[serde(default)]
+    pub linger_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ScriptEvent {
+    #[serde(default)]
+    pub delay_ms: u64,
+    pub event: Value,
+}
+
+fn default_user_agent() -> String {
+    "LLOneBot/mock".to_string()
+}
+
+// 连接到ws地址并执行脚本, 返回收到的全部API请求
+pub async fn run(url: &str, script: Script) -> Result<Vec<Value>> {
+    let mut request = url.into_client_request()?;
+    let headers = request.headers_mut();
+    if let Some(token) = &script.token {
+        headers.insert("Authorization", format!("Bearer {}", token).parse()?);
+    }
+    headers.insert("X-Self-ID", script.self_id.parse()?);
+    headers.insert("User-Agent", script.user_agent.parse()?);
+
+    let (ws_stream, _) = tokio_tungstenite::connect_async(request).await?;
+    let (mut write, mut read) = ws_stream.split();
+    tracing::info!("Connected to {}", url);
+
+    // 先上报连接事件, 与真实客户端一致
+    let connect = json!({
+        "time": Utc::now().timestamp(),
+        "self_id": script.self_id,
+        "post_type": "meta_event",
+        "meta_event_type": "lifecycle",
+        "sub_type": "connect",
+    });
+    write
+        .send(Message::Text(connect.to_string().into()))
+        .await?;
+
+    let mut requests = Vec::new();
+    let mut events = script.events.into_iter();
+    let mut pending = events.next();
+    let mut message_id: i64 = 0;
+    let linger = tokio::time::sleep(Duration::from_millis(script.linger_ms.unwrap_or(LINGER_MS)));
+    tokio::pin!(linger);
+
+    let next_event = tokio::time::sleep(Duration::from_millis(
+        pending.as_ref().map_or(0, |event| event.delay_ms),
+    ));
+    tokio::pin!(next_event);
+
+    loop {
+        tokio::select! {
+            // 按延迟依次上报脚本中的事件
+            _ = &mut next_event, if pending.is_some() => {
+                let event = pending.take().unwrap();
+                tracing::info!("-> {}", event.event);
+                write.send(Message::Text(event.event.to_string().into())).await?;
+
+                pending = events.next();
+                let now = tokio::time::Instant::now();
+                match &pending {
+                    Some(event) => {
+                        next_event.as_mut().reset(now + Duration::from_millis(event.delay_ms))
+                    }
+                    // 全部发送后再等待一段时间接收API请求
+                    None => {
+                        let linger_ms = script.linger_ms.unwrap_or(LINGER_MS);
+                        linger.as_mut().reset(now + Duration::from_millis(linger_ms))
+                    }
+                }
+            }
+            // 应答Teleporter的API请求
+            msg = read.next() => {
+                let Some(msg) = msg else {
+                    tracing::info!("Connection closed");
+                    break;
+                };
+                let Message::Text(text) = msg? else {
+                    continue;
+                };
+                tracing::info!("<- {}", text);
+
+                let request: Value = serde_json::from_str(&text)?;
+                let action = request["action"].as_str().unwrap_or("");
+                let data = match script.responses.get(action) {
+                    Some(data) => data.clone(),
+                    None if action == "send_msg" => {
+                        message_id += 1;
+                        json!({ "message_id": message_id })
+                    }
+                    None => Value::Null,
+                };
+                let response = json!({
+                    "echo": request["echo"],
+                    "status": "ok",
+                    "retcode": 0,
+                    "data": data,
+                });
+                write.send(Message::Text(response.to_string().into())).await?;
+                requests.push(request);
+            }
+            _ = &mut linger, if pending.is_none() => {
+                break;
+            }
+        }
+    }
+
+    Ok(requests)
+}
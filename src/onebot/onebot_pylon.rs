@@ -1,26 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::Arc;
 
 use anyhow::Result;
 use chrono::Utc;
+use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
+use lazy_static::lazy_static;
 use serde_json;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{Mutex, broadcast, mpsc, oneshot};
-use tokio::time::Duration;
+use tokio::time::{Duration, Instant};
 use tokio_tungstenite::tungstenite::handshake::server::ErrorResponse;
 use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 use tokio_tungstenite::{WebSocketStream, tungstenite};
 
+use super::event_queue::EventQueue;
 use super::protocol::payload::Payload;
 use super::protocol::request::Request;
 use super::protocol::response::Response;
 use super::protocol::{OnebotEvent, OnebotRequest};
-use crate::common::{Endpoint, OnebotConfig, Platform};
-use crate::onebot::protocol::event::{Event, LifecycleEvent, MetaEvent};
+use crate::common::{Endpoint, EndpointPermission, OnebotConfig, OverflowPolicy, Platform};
+use crate::onebot::protocol::event::{Event, LifecycleEvent, MetaEvent, QueueOverflowEvent};
 
 type EndpointsSenderChannal = Arc<Mutex<HashMap<Endpoint, mpsc::Sender<Arc<Request>>>>>;
-type ResponsePendingChannal = Arc<Mutex<HashMap<String, oneshot::Sender<Result<Arc<Response>>>>>>;
+type ResponsePendingChannal = Arc<Mutex<HashMap<String, PendingResponse>>>;
+type RecentEvents = Arc<std::sync::Mutex<RecentEventLog>>;
+type Permissions = Arc<HashMap<String, EndpointPermission>>;
 
 // 通道的缓冲区大小
 const BUFFER_SIZE: usize = 1024;
@@ -32,6 +38,57 @@ const WS_READ_BUFFER_SIZE: usize = 8 * 1024 * 1024;
 const WS_MAX_MESSAGE_SIZE: usize = 512 * 1024 * 1024;
 // WebSocket最大帧大小
 const WS_MAX_FRAME_SIZE: usize = 256 * 1024 * 1024;
+// 事件去重的时间窗口(秒)
+const DEDUP_WINDOW: i64 = 5 * 60;
+// 事件队列的默认长度
+const DEFAULT_QUEUE_SIZE: usize = 1024;
+// 丢弃事件的提示间隔
+const OVERFLOW_NOTICE_INTERVAL: Duration = Duration::from_secs(60);
+// 记录事件队列长度的间隔
+const QUEUE_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+// 清理超时API调用的间隔
+const PENDING_CLEANUP_INTERVAL: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    // 各端点等待响应的API调用数
+    static ref PENDING_CALLS: DashMap<Endpoint, usize> = DashMap::new();
+    // 各端点上次记录后的API调用数和超时数
+    static ref TIMEOUT_STATS: DashMap<Endpoint, (u64, u64)> = DashMap::new();
+}
+
+// 最近收到的事件, 按接收顺序排列以便从队首移除过期记录
+#[derive(Default)]
+struct RecentEventLog {
+    received: HashMap<(Endpoint, u64), i64>,
+    order: VecDeque<(i64, (Endpoint, u64))>,
+}
+
+// 等待响应的API调用
+struct PendingResponse {
+    endpoint: Endpoint,
+    action: String,
+    sent_at: Instant,
+    ret: oneshot::Sender<Result<Arc<Response>>>,
+}
+
+#[derive(Debug)]
+pub struct ApiTimeout {
+    pub endpoint: Endpoint,
+    pub action: String,
+    pub elapsed: u64,
+}
+
+impl std::fmt::Display for ApiTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "API {} of client({}) timed out after {}s",
+            self.action, self.endpoint, self.elapsed
+        )
+    }
+}
+
+impl std::error::Error for ApiTimeout {}
 
 #[derive(Clone)]
 pub struct OnebotPylon {
@@ -43,6 +100,14 @@ pub struct OnebotPylon {
     endpoints_sender: EndpointsSenderChannal,
     // 待返回的API响应
     response_pending: ResponsePendingChannal,
+    // 最近收到的事件, 用于重连后的重复事件过滤
+    recent_events: RecentEvents,
+    // 各端点可调用的API和接收的事件
+    permissions: Permissions,
+    // 各端点事件队列的长度
+    queue_size: usize,
+    // 事件队列满时的处理方式
+    overflow: OverflowPolicy,
 }
 
 impl OnebotPylon {
@@ -52,6 +117,10 @@ impl OnebotPylon {
             bearer: config.token.map(|token| format!("Bearer {}", token)),
             endpoints_sender: Arc::new(Mutex::new(HashMap::new())),
             response_pending: Arc::new(Mutex::new(HashMap::new())),
+            recent_events: Arc::default(),
+            permissions: Arc::new(config.permissions.unwrap_or_default()),
+            queue_size: config.queue_size.unwrap_or(DEFAULT_QUEUE_SIZE),
+            overflow: config.overflow.unwrap_or_default(),
         })
     }
 
@@ -68,24 +137,44 @@ impl OnebotPylon {
         // 将收到的API请求转发给对应端点
         let endpoints_sender = self.endpoints_sender.clone();
         let pending = self.response_pending.clone();
+        let permissions = self.permissions.clone();
         let mut api_shutdown_rx = shutdown_rx.resubscribe();
         let api_handle = tokio::spawn(async move {
             loop {
                 tokio::select! {
                     Some(req) = api_receiver.recv() => {
+                        // 拒绝端点未被允许的API调用
+                        if let Some(permission) = permissions.get(&req.endpoint.id) {
+                            let action = req.raw.get_action();
+                            if !permission.allow_action(&action) {
+                                if let Err(e) = req.ret.send(Err(anyhow::anyhow!(
+                                    "Action {} is not allowed for client({})",
+                                    action,
+                                    req.endpoint
+                                ))) {
+                                    tracing::warn!("Failed to send response: {:?}", e);
+                                }
+                                continue;
+                            }
+                        }
                         if let Some(sender) = endpoints_sender.lock().await.get(&req.endpoint) {
                             let echo = req.raw.get_echo();
-                            pending.lock().await.insert(echo.clone(), req.ret);
+                            TIMEOUT_STATS.entry(req.endpoint.clone()).or_default().0 += 1;
+                            pending.lock().await.insert(
+                                echo.clone(),
+                                PendingResponse {
+                                    endpoint: req.endpoint.clone(),
+                                    action: req.raw.get_action(),
+                                    sent_at: Instant::now(),
+                                    ret: req.ret,
+                                },
+                            );
                             if let Err(e) = sender.send(req.raw).await {
                                 tracing::warn!("Failed to send request: {}", e);
-                                if let Err(e) = pending
-                                    .lock()
-                                    .await
-                                    .remove(echo.as_str())
-                                    .unwrap()
-                                    .send(Err(e.into()))
-                                {
-                                    tracing::warn!("Failed to send response: {:?}", e);
+                                if let Some(p) = pending.lock().await.remove(echo.as_str()) {
+                                    if let Err(e) = p.ret.send(Err(e.into())) {
+                                        tracing::warn!("Failed to send response: {:?}", e);
+                                    }
                                 }
                             }
                         } else if let Err(e) = req
@@ -103,6 +192,77 @@ impl OnebotPylon {
             }
         });
 
+        // 定时记录积压的事件队列, 只记录连接到本监听的端点
+        let endpoints_sender = self.endpoints_sender.clone();
+        let mut report_shutdown_rx = shutdown_rx.resubscribe();
+        let report_handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(QUEUE_REPORT_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        let endpoints = endpoints_sender.lock().await;
+                        let stats = EventQueue::stats()
+                            .into_iter()
+                            .filter(|stats| endpoints.contains_key(&stats.endpoint));
+                        for stats in stats {
+                            if stats.depth * 5 >= stats.capacity * 4 {
+                                tracing::warn!(
+                                    "Onebot client ({}) event queue is nearly full: {}/{}, dropped: {}",
+                                    stats.endpoint,
+                                    stats.depth,
+                                    stats.capacity,
+                                    stats.total_dropped
+                                );
+                            } else if stats.depth > 0 {
+                                tracing::info!(
+                                    "Onebot client ({}) event queue depth: {}/{}, dropped: {}",
+                                    stats.endpoint,
+                                    stats.depth,
+                                    stats.capacity,
+                                    stats.total_dropped
+                                );
+                            }
+                        }
+                    }
+                    Ok(_) = report_shutdown_rx.recv() => {
+                        break;
+                    }
+                }
+            }
+        });
+
+        // 定时清理超时未响应的API调用, 并记录各端点的超时比例
+        let pending = self.response_pending.clone();
+        let mut janitor_shutdown_rx = shutdown_rx.resubscribe();
+        let janitor_handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PENDING_CLEANUP_INTERVAL);
+            let mut report = tokio::time::interval(QUEUE_REPORT_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        Self::expire_pending(&pending).await;
+                    }
+                    _ = report.tick() => {
+                        TIMEOUT_STATS.retain(|endpoint, (calls, timeouts)| {
+                            if *timeouts > 0 {
+                                tracing::warn!(
+                                    "Onebot client ({}) API timeouts: {}/{} ({:.1}%)",
+                                    endpoint,
+                                    timeouts,
+                                    calls,
+                                    *timeouts as f64 * 100.0 / (*calls).max(1) as f64
+                                );
+                            }
+                            false
+                        });
+                    }
+                    Ok(_) = janitor_shutdown_rx.recv() => {
+                        break;
+                    }
+                }
+            }
+        });
+
         let this = self.clone();
         let accept_handle = tokio::spawn(async move {
             loop {
@@ -131,10 +291,42 @@ impl OnebotPylon {
             }
         });
 
-        let _ = tokio::try_join!(api_handle, accept_handle);
+        let _ = tokio::try_join!(api_handle, accept_handle, report_handle, janitor_handle);
         tracing::info!("OnebotPylon shutdown complete");
     }
 
+    // 端点是否连接到本监听
+    pub async fn has_endpoint(&self, endpoint: &Endpoint) -> bool {
+        self.endpoints_sender.lock().await.contains_key(endpoint)
+    }
+
+    // 按端点所在的监听转发API请求, 未连接的端点交给第一个监听返回错误
+    pub async fn route_requests(
+        pylons: Vec<(OnebotPylon, mpsc::Sender<OnebotRequest>)>,
+        mut api_receiver: mpsc::Receiver<OnebotRequest>,
+        mut shutdown_rx: broadcast::Receiver<()>,
+    ) {
+        loop {
+            tokio::select! {
+                Some(req) = api_receiver.recv() => {
+                    let mut target = &pylons[0].1;
+                    for (pylon, sender) in &pylons {
+                        if pylon.has_endpoint(&req.endpoint).await {
+                            target = sender;
+                            break;
+                        }
+                    }
+                    if let Err(e) = target.send(req).await {
+                        tracing::warn!("Failed to route request: {}", e);
+                    }
+                }
+                Ok(_) = shutdown_rx.recv() => {
+                    break;
+                }
+            }
+        }
+    }
+
     pub async fn call_api(
         api_sender: mpsc::Sender<OnebotRequest>,
         endpoint: Endpoint,
@@ -142,8 +334,10 @@ impl OnebotPylon {
     ) -> Result<Arc<Response>> {
         let (ret, rx) = oneshot::channel();
 
+        let _pending = PendingCall::new(&endpoint);
+        let action = request.get_action();
         let req = OnebotRequest {
-            endpoint,
+            endpoint: endpoint.clone(),
             raw: Arc::new(request),
             ret,
         };
@@ -151,13 +345,87 @@ impl OnebotPylon {
             return Err(anyhow::anyhow!("Failed to send request: {}", e));
         }
 
-        match tokio::time::timeout(Duration::from_secs(API_TIMOUT), rx).await {
+        // 正常由清理任务返回超时, 这里只是兜底
+        let timeout = Duration::from_secs(API_TIMOUT) + PENDING_CLEANUP_INTERVAL * 2;
+        match tokio::time::timeout(timeout, rx).await {
             Ok(Ok(result)) => result,
             Ok(Err(e)) => Err(e.into()),
-            Err(e) => Err(e.into()),
+            Err(_) => Err(ApiTimeout {
+                endpoint,
+                action,
+                elapsed: timeout.as_secs(),
+            }
+            .into()),
+        }
+    }
+
+    // 让超时未响应的API调用返回错误
+    async fn expire_pending(pending: &ResponsePendingChannal) {
+        let expired: Vec<PendingResponse> = {
+            let mut pending = pending.lock().await;
+            let echoes: Vec<String> = pending
+                .iter()
+                .filter(|(_, p)| p.sent_at.elapsed() >= Duration::from_secs(API_TIMOUT))
+                .map(|(echo, _)| echo.clone())
+                .collect();
+            echoes
+                .iter()
+                .filter_map(|echo| pending.remove(echo))
+                .collect()
+        };
+        for p in expired {
+            TIMEOUT_STATS.entry(p.endpoint.clone()).or_default().1 += 1;
+            let elapsed = p.sent_at.elapsed().as_secs();
+            tracing::warn!(
+                "Onebot client ({}) API {} timed out after {}s",
+                p.endpoint,
+                p.action,
+                elapsed
+            );
+            let _ = p.ret.send(Err(ApiTimeout {
+                endpoint: p.endpoint,
+                action: p.action,
+                elapsed,
+            }
+            .into()));
         }
     }
 
+    // 端点断开后, 让等待该端点响应的API调用立即返回错误
+    async fn fail_pending(pending: &ResponsePendingChannal, endpoint: &Endpoint) {
+        let failed: Vec<PendingResponse> = {
+            let mut pending = pending.lock().await;
+            let echoes: Vec<String> = pending
+                .iter()
+                .filter(|(_, p)| &p.endpoint == endpoint)
+                .map(|(echo, _)| echo.clone())
+                .collect();
+            echoes
+                .iter()
+                .filter_map(|echo| pending.remove(echo))
+                .collect()
+        };
+        if !failed.is_empty() {
+            tracing::warn!(
+                "Onebot client ({}) disconnected with {} pending API calls",
+                endpoint,
+                failed.len()
+            );
+        }
+        for p in failed {
+            let _ = p.ret.send(Err(anyhow::anyhow!(
+                "Client({}) disconnected before responding to {}",
+                endpoint,
+                p.action
+            )));
+        }
+    }
+
+    // 端点等待响应的API调用数
+    pub fn pending_calls(endpoint: &Endpoint) -> usize {
+        PENDING_CALLS.get(endpoint).map_or(0, |count| *count)
+    }
+
     async fn accept_connection(&self, stream: TcpStream, event_sender: mpsc::Sender<OnebotEvent>) {
         let addr = stream
             .peer_addr()
@@ -233,15 +501,32 @@ impl OnebotPylon {
             }
         });
 
+        // 事件先放入端点自己的队列, 再逐个转发, 避免一个端点的积压影响其他端点
+        let queue = EventQueue::register(&endpoint, self.queue_size, self.overflow);
+        let forward_queue = queue.clone();
+        let forward_endpoint = endpoint.clone();
+        tokio::spawn(async move {
+            Self::forward_events(&forward_endpoint, &forward_queue, &event_sender).await;
+        });
+
         // 接收WebSocket消息
-        let sender = event_sender.clone();
         let endpoints_sender = self.endpoints_sender.clone();
         let pending = self.response_pending.clone();
+        let recent_events = self.recent_events.clone();
+        let permission = self.permissions.get(&endpoint.id).cloned();
         tokio::spawn(async move {
             while let Some(msg) = read.next().await {
                 match msg {
                     Ok(message) => {
-                        Self::handle_message(&endpoint, &message, &sender, &pending).await;
+                        Self::handle_message(
+                            &endpoint,
+                            &message,
+                            &queue,
+                            &pending,
+                            &recent_events,
+                            permission.as_ref(),
+                        )
+                        .await;
                     }
                     Err(e) => {
                         // 发送断开事件
@@ -250,15 +535,12 @@ impl OnebotPylon {
                             self_id: endpoint.id.clone(),
                             sub_type: "disconnect".to_string(),
                         }));
-                        if let Err(e) = sender
-                            .send(OnebotEvent {
+                        queue
+                            .push(OnebotEvent {
                                 endpoint: endpoint.clone(),
                                 raw: Arc::new(event),
                             })
-                            .await
-                        {
-                            tracing::warn!("Failed to send event: {}", e);
-                        }
+                            .await;
 
                         endpoints_sender.lock().await.remove(&endpoint);
                         tracing::warn!("Onebot client ({}) connection error: {}", endpoint, e);
@@ -266,14 +548,60 @@ impl OnebotPylon {
                     }
                 }
             }
+            Self::fail_pending(&pending, &endpoint).await;
+            EventQueue::unregister(&endpoint, &queue);
         });
     }
 
+    // 将端点队列中的事件转发给Telegram, Telegram处理慢时队列会积压
+    async fn forward_events(
+        endpoint: &Endpoint,
+        queue: &EventQueue,
+        event_sender: &mpsc::Sender<OnebotEvent>,
+    ) {
+        let mut last_notice: Option<tokio::time::Instant> = None;
+        while let Some(event) = queue.pop().await {
+            // 有事件被丢弃时提示, 限制提示频率
+            if last_notice.is_none_or(|t| t.elapsed() >= OVERFLOW_NOTICE_INTERVAL) {
+                let dropped = queue.take_dropped();
+                if dropped > 0 {
+                    last_notice = Some(tokio::time::Instant::now());
+                    tracing::warn!(
+                        "Onebot client ({}) event queue overflowed, dropped {} events",
+                        endpoint,
+                        dropped
+                    );
+                    let notice = Event::Meta(MetaEvent::QueueOverflow(QueueOverflowEvent {
+                        time: Utc::now().timestamp(),
+                        self_id: endpoint.id.clone(),
+                        dropped,
+                        depth: queue.depth(),
+                    }));
+                    if let Err(e) = event_sender
+                        .send(OnebotEvent {
+                            endpoint: endpoint.clone(),
+                            raw: Arc::new(notice),
+                        })
+                        .await
+                    {
+                        tracing::warn!("Failed to send event: {}", e);
+                    }
+                }
+            }
+
+            if let Err(e) = event_sender.send(event).await {
+                tracing::warn!("Failed to send event: {}", e);
+            }
+        }
+    }
+
     async fn handle_message(
         endpoint: &Endpoint,
         msg: &tungstenite::Message,
-        sender: &mpsc::Sender<OnebotEvent>,
+        queue: &EventQueue,
         pending: &ResponsePendingChannal,
+        recent_events: &RecentEvents,
+        permission: Option<&EndpointPermission>,
     ) {
         if let tungstenite::Message::Text(text) = msg {
             tracing::debug!("Received onebot message: {}", text);
@@ -281,20 +609,26 @@ impl OnebotPylon {
                 Ok(payload) => match payload {
                     // 上报Event
                     Payload::Event(event) => {
-                        if let Err(e) = sender
-                            .send(OnebotEvent {
+                        if permission.is_some_and(|p| !p.allow_event(event.get_post_type())) {
+                            tracing::debug!("Ignoring disallowed event: {}", text);
+                            return;
+                        }
+                        if Self::is_duplicated_event(endpoint, text, recent_events) {
+                            tracing::info!("Ignoring duplicated event: {}", text);
+                            return;
+                        }
+                        // 暂停策略下队列满时在此等待, 不再读取WebSocket (有API调用等待响应时除外)
+                        queue
+                            .push(OnebotEvent {
                                 endpoint: endpoint.clone(),
                                 raw: event,
                             })
-                            .await
-                        {
-                            tracing::warn!("Failed to send event: {}", e);
-                        }
+                            .await;
                     }
                     // 返回Response
                     Payload::Response(response) => {
                         if let Some(p) = pending.lock().await.remove(&response.echo) {
-                            if let Err(e) = p.send(Ok(response)) {
+                            if let Err(e) = p.ret.send(Ok(response)) {
                                 tracing::warn!("Failed to send response: {:?}", e);
                             }
                         }
@@ -311,6 +645,33 @@ impl OnebotPylon {
         }
     }
 
+    // 检查时间窗口内是否收到过相同的事件
+    fn is_duplicated_event(endpoint: &Endpoint, text: &str, recent_events: &RecentEvents) -> bool {
+        let now = Utc::now().timestamp();
+        let mut log = recent_events.lock().unwrap();
+
+        // 移除过期记录, 重复收到的事件以最近一次的记录为准
+        while let Some((received_at, _)) = log.order.front() {
+            if now - received_at < DEDUP_WINDOW {
+                break;
+            }
+            let (received_at, key) = log.order.pop_front().unwrap();
+            if log.received.get(&key) == Some(&received_at) {
+                log.received.remove(&key);
+            }
+        }
+
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        let key = (endpoint.clone(), hasher.finish());
+
+        log.order.push_back((now, key.clone()));
+        match log.received.insert(key, now) {
+            Some(received_at) => now - received_at < DEDUP_WINDOW,
+            None => false,
+        }
+    }
+
     async fn handle_request(
         req: Arc<Request>,
         write: &mut (
@@ -335,3 +696,29 @@ impl OnebotPylon {
         }
     }
 }
+
+// 记录等待响应的API调用, 调用结束或被取消时减少计数
+// 同时告知端点的事件队列, 等待响应时不能暂停读取WebSocket
+struct PendingCall(Endpoint, Option<Arc<EventQueue>>);
+
+impl PendingCall {
+    fn new(endpoint: &Endpoint) -> Self {
+        *PENDING_CALLS.entry(endpoint.clone()).or_default() += 1;
+        let queue = EventQueue::get(endpoint);
+        if let Some(queue) = &queue {
+            queue.call_started();
+        }
+        Self(endpoint.clone(), queue)
+    }
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        if let Some(mut count) = PENDING_CALLS.get_mut(&self.0) {
+            *count = count.saturating_sub(1);
+        }
+        if let Some(queue) = &self.1 {
+            queue.call_finished();
+        }
+    }
+}
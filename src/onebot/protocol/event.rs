@@ -32,6 +32,17 @@ pub enum Event {
 }
 
 impl Event {
+    // 上报类型, 与serde的tag一致
+    pub fn get_post_type(&self) -> &'static str {
+        match self {
+            Event::Message(_) => "message",
+            Event::MessageSent(_) => "message_sent",
+            Event::Meta(_) => "meta_event",
+            Event::Notice(_) => "notice",
+            Event::Request(_) => "request",
+        }
+    }
+
     pub fn get_chat_type(&self) -> ChatType {
         match self {
             Event::Message(event) => event.get_chat_type(),
@@ -61,7 +72,7 @@ pub struct MessageEvent {
     /// 收到事件的机器人ID
     #[serde(deserialize_with = "id_deserializer")]
     pub self_id: String,
-    /// 消息类型 (private/group)
+    /// 消息类型 (private/group/guild)
     pub message_type: String,
     /// 消息子类型 (private: friend/group/other, group: normal/anonymous/notice)
     pub sub_type: String,
@@ -72,6 +83,14 @@ pub struct MessageEvent {
     #[serde(deserialize_with = "option_id_deserializer")]
     #[serde(default)]
     pub group_id: Option<String>,
+    /// 频道ID
+    #[serde(deserialize_with = "option_id_deserializer")]
+    #[serde(default)]
+    pub guild_id: Option<String>,
+    /// 子频道ID
+    #[serde(deserialize_with = "option_id_deserializer")]
+    #[serde(default)]
+    pub channel_id: Option<String>,
     /// 发送者ID
     #[serde(deserialize_with = "id_deserializer")]
     pub user_id: String,
@@ -98,6 +117,11 @@ impl MessageEvent {
                 _ => self.user_id.clone(),
             },
             "group" => self.group_id.clone().unwrap(),
+            "guild" => format!(
+                "{}/{}",
+                self.guild_id.as_deref().unwrap_or_default(),
+                self.channel_id.as_deref().unwrap_or_default()
+            ),
             _ => String::new(),
         }
     }
@@ -175,6 +199,10 @@ pub enum MetaEvent {
     /// 心跳事件
     #[serde(rename = "heartbeat")]
     Heartbeat(HeartbeatEvent),
+
+    /// 事件队列溢出, 由OnebotPylon生成
+    #[serde(rename = "queue_overflow")]
+    QueueOverflow(QueueOverflowEvent),
 }
 
 /// 生命周期事件
@@ -203,6 +231,20 @@ pub struct HeartbeatEvent {
     pub interval: i64,
 }
 
+/// 事件队列溢出事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueOverflowEvent {
+    /// 事件发生的时间戳
+    pub time: i64,
+    /// 收到事件的机器人ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub self_id: String,
+    /// 上次提示后丢弃的事件数
+    pub dropped: u64,
+    /// 当前队列长度
+    pub depth: usize,
+}
+
 /// 运行状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Status {
@@ -247,6 +289,14 @@ pub enum NoticeEvent {
     /// 群名片事件
     #[serde(rename = "group_card")]
     GroupCard(GroupCardEvent),
+
+    /// 精华消息事件
+    #[serde(rename = "essence")]
+    Essence(EssenceEvent),
+
+    /// 消息编辑事件
+    #[serde(rename = "edit")]
+    Edit(EditEvent),
 }
 
 impl NoticeEvent {
@@ -263,6 +313,8 @@ impl NoticeEvent {
             NoticeEvent::GroupDecrease(_) => ChatType::Group,
             NoticeEvent::GroupIncrease(_) => ChatType::Group,
             NoticeEvent::GroupCard(_) => ChatType::Group,
+            NoticeEvent::Essence(_) => ChatType::Group,
+            NoticeEvent::Edit(e) => e.get_chat_type(),
         }
     }
 
@@ -279,6 +331,8 @@ impl NoticeEvent {
             NoticeEvent::GroupDecrease(e) => e.group_id.clone(),
             NoticeEvent::GroupIncrease(e) => e.group_id.clone(),
             NoticeEvent::GroupCard(event) => event.group_id.clone(),
+            NoticeEvent::Essence(event) => event.group_id.clone(),
+            NoticeEvent::Edit(event) => event.get_chat_id(),
         }
     }
 }
@@ -333,6 +387,43 @@ impl GroupRecallEvent {
     }
 }
 
+/// 消息编辑事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditEvent {
+    /// 事件发生的时间戳
+    pub time: i64,
+    /// 收到事件的机器人ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub self_id: String,
+    /// 被编辑的消息ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub message_id: String,
+    /// 发送者ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub user_id: String,
+    /// 群ID, 私聊时为空
+    #[serde(default, deserialize_with = "option_id_deserializer")]
+    pub group_id: Option<String>,
+    /// 编辑后的消息内容
+    pub message: Vec<Segment>,
+}
+
+impl EditEvent {
+    pub fn get_chat_type(&self) -> ChatType {
+        match &self.group_id {
+            Some(group_id) if group_id != "0" => ChatType::Group,
+            _ => ChatType::Private,
+        }
+    }
+
+    pub fn get_chat_id(&self) -> String {
+        match &self.group_id {
+            Some(group_id) if group_id != "0" => group_id.clone(),
+            _ => self.user_id.clone(),
+        }
+    }
+}
+
 /// 提示事件
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotifyEvent {
@@ -426,6 +517,13 @@ pub struct GroupDecreaseEvent {
     /// 离开者ID
     #[serde(deserialize_with = "id_deserializer")]
     pub user_id: String,
+    /// 事件子类型 (leave, kick, kick_me)
+    #[serde(default)]
+    pub sub_type: String,
+    /// 操作者ID
+    #[serde(deserialize_with = "option_id_deserializer")]
+    #[serde(default)]
+    pub operator_id: Option<String>,
     /// 其它字段
     #[serde(flatten)]
     pub extra_fields: HashMap<String, serde_json::Value>,
@@ -445,6 +543,40 @@ pub struct GroupIncreaseEvent {
     /// 加入者ID
     #[serde(deserialize_with = "id_deserializer")]
     pub user_id: String,
+    /// 事件子类型 (approve, invite)
+    #[serde(default)]
+    pub sub_type: String,
+    /// 操作者ID
+    #[serde(deserialize_with = "option_id_deserializer")]
+    #[serde(default)]
+    pub operator_id: Option<String>,
+    /// 其它字段
+    #[serde(flatten)]
+    pub extra_fields: HashMap<String, serde_json::Value>,
+}
+
+/// 精华消息事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EssenceEvent {
+    /// 事件发生的时间戳
+    pub time: i64,
+    /// 收到事件的机器人ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub self_id: String,
+    /// 事件子类型 (add/delete)
+    pub sub_type: String,
+    /// 群ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub group_id: String,
+    /// 消息发送者ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub sender_id: String,
+    /// 操作者ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub operator_id: String,
+    /// 消息ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub message_id: String,
     /// 其它字段
     #[serde(flatten)]
     pub extra_fields: HashMap<String, serde_json::Value>,
@@ -13,6 +13,14 @@ pub enum Request {
     #[serde(rename = "get_login_info")]
     GetLoginInfo { echo: String },
 
+    /// 获取运行状态
+    #[serde(rename = "get_status")]
+    GetStatus { echo: String },
+
+    /// 获取版本信息
+    #[serde(rename = "get_version_info")]
+    GetVersionInfo { echo: String },
+
     /// 获取陌生人信息
     #[serde(rename = "get_stranger_info")]
     GetStrangerInfo {
@@ -58,6 +66,10 @@ pub enum Request {
     #[serde(rename = "get_file")]
     GetFile { echo: String, params: GetFile },
 
+    /// 获取消息
+    #[serde(rename = "get_msg")]
+    GetMsg { echo: String, params: GetMsg },
+
     /// 获取合并转发消息
     #[serde(rename = "get_forward_msg")]
     GetForwardMsg { echo: String, params: GetForwardMsg },
@@ -69,6 +81,78 @@ pub enum Request {
     /// 发送消息
     #[serde(rename = "send_msg")]
     SendMsg { echo: String, params: SendMsg },
+
+    /// 群组踢人
+    #[serde(rename = "set_group_kick")]
+    SetGroupKick { echo: String, params: SetGroupKick },
+
+    /// 群组单人禁言
+    #[serde(rename = "set_group_ban")]
+    SetGroupBan { echo: String, params: SetGroupBan },
+
+    /// 设置群组专属头衔
+    #[serde(rename = "set_group_special_title")]
+    SetGroupSpecialTitle {
+        echo: String,
+        params: SetGroupSpecialTitle,
+    },
+
+    /// 设置精华消息
+    #[serde(rename = "set_essence_msg")]
+    SetEssenceMsg { echo: String, params: SetEssenceMsg },
+
+    /// 移出精华消息
+    #[serde(rename = "delete_essence_msg")]
+    DeleteEssenceMsg {
+        echo: String,
+        params: DeleteEssenceMsg,
+    },
+
+    /// 标记消息已读
+    #[serde(rename = "mark_msg_as_read")]
+    MarkMsgAsRead { echo: String, params: MarkMsgAsRead },
+
+    /// 获取群历史消息
+    #[serde(rename = "get_group_msg_history")]
+    GetGroupMsgHistory {
+        echo: String,
+        params: GetGroupMsgHistory,
+    },
+
+    /// 获取好友历史消息
+    #[serde(rename = "get_friend_msg_history")]
+    GetFriendMsgHistory {
+        echo: String,
+        params: GetFriendMsgHistory,
+    },
+
+    /// 获取群文件下载链接
+    #[serde(rename = "get_group_file_url")]
+    GetGroupFileUrl {
+        echo: String,
+        params: GetGroupFileUrl,
+    },
+
+    /// 获取频道的子频道列表
+    #[serde(rename = "get_guild_channel_list")]
+    GetGuildChannelList {
+        echo: String,
+        params: GetGuildChannelList,
+    },
+
+    /// 发送子频道消息
+    #[serde(rename = "send_guild_channel_msg")]
+    SendGuildChannelMsg {
+        echo: String,
+        params: SendGuildChannelMsg,
+    },
+
+    /// 分段上传文件 (NapCat 扩展)
+    #[serde(rename = "upload_file_stream")]
+    UploadFileStream {
+        echo: String,
+        params: UploadFileStream,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -134,6 +218,13 @@ pub struct GetFile {
     pub file_id: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetMsg {
+    /// 消息ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub message_id: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetForwardMsg {
     /// 消息ID
@@ -164,6 +255,139 @@ pub struct SendMsg {
     pub message: Vec<Segment>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetGroupKick {
+    /// 群ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub group_id: String,
+    /// 用户ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub user_id: String,
+    /// 是否拒绝此人的加群请求
+    pub reject_add_request: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetGroupBan {
+    /// 群ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub group_id: String,
+    /// 用户ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub user_id: String,
+    /// 禁言时长(秒), 0表示取消禁言
+    pub duration: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetGroupSpecialTitle {
+    /// 群ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub group_id: String,
+    /// 用户ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub user_id: String,
+    /// 专属头衔, 空字符串表示删除
+    pub special_title: String,
+    /// 有效期(秒), -1表示永久
+    pub duration: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetEssenceMsg {
+    /// 消息ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub message_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteEssenceMsg {
+    /// 消息ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub message_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkMsgAsRead {
+    /// 消息ID, 该消息及之前的消息都会被标记为已读
+    #[serde(deserialize_with = "id_deserializer")]
+    pub message_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetGroupMsgHistory {
+    /// 群ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub group_id: String,
+    /// 获取的消息数量
+    pub count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetFriendMsgHistory {
+    /// 用户ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub user_id: String,
+    /// 获取的消息数量
+    pub count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetGroupFileUrl {
+    /// 群ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub group_id: String,
+    /// 文件ID
+    pub file_id: String,
+    /// 文件类型
+    pub busid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetGuildChannelList {
+    /// 频道ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub guild_id: String,
+    /// 是否不使用缓存
+    pub no_cache: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendGuildChannelMsg {
+    /// 频道ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub guild_id: String,
+    /// 子频道ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub channel_id: String,
+    /// 消息内容
+    pub message: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadFileStream {
+    /// 上传流ID, 同一文件的分片使用相同的ID
+    pub stream_id: String,
+    /// Base64编码的分片内容
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk_data: Option<String>,
+    /// 分片序号, 从0开始
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk_index: Option<usize>,
+    /// 分片总数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_chunks: Option<usize>,
+    /// 文件大小
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    /// 文件名
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    /// 所有分片上传后置为true, 合并分片并返回文件路径
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_complete: Option<bool>,
+}
+
 macro_rules! echo {
     ($($x: tt),*) => {
         pub fn get_echo(&self) -> String {
@@ -200,8 +424,18 @@ macro_rules! params_builder {
 
 #[allow(dead_code)]
 impl Request {
+    // 请求的action名称, 与serde的tag一致
+    pub fn get_action(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|value| value["action"].as_str().map(|s| s.to_string()))
+            .unwrap_or_default()
+    }
+
     echo!(
         GetLoginInfo,
+        GetStatus,
+        GetVersionInfo,
         GetStrangerInfo,
         GetGroupInfo,
         GetFriendList,
@@ -211,13 +445,28 @@ impl Request {
         GetRecord,
         GetImage,
         GetFile,
+        GetMsg,
         GetForwardMsg,
         DeleteMsg,
-        SendMsg
+        SendMsg,
+        SetGroupKick,
+        SetGroupBan,
+        SetGroupSpecialTitle,
+        SetEssenceMsg,
+        DeleteEssenceMsg,
+        MarkMsgAsRead,
+        GetGroupMsgHistory,
+        GetFriendMsgHistory,
+        GetGroupFileUrl,
+        GetGuildChannelList,
+        SendGuildChannelMsg,
+        UploadFileStream
     );
 
     no_params_builder!(
         (get_login_info, GetLoginInfo),
+        (get_status, GetStatus),
+        (get_version_info, GetVersionInfo),
         (get_friend_list, GetFriendList),
         (get_group_list, GetGroupList)
     );
@@ -230,9 +479,22 @@ impl Request {
         (get_record, GetRecord),
         (get_image, GetImage),
         (get_file, GetFile),
+        (get_msg, GetMsg),
         (get_forward_msg, GetForwardMsg),
         (delete_msg, DeleteMsg),
-        (send_msg, SendMsg)
+        (send_msg, SendMsg),
+        (set_group_kick, SetGroupKick),
+        (set_group_ban, SetGroupBan),
+        (set_group_special_title, SetGroupSpecialTitle),
+        (set_essence_msg, SetEssenceMsg),
+        (delete_essence_msg, DeleteEssenceMsg),
+        (mark_msg_as_read, MarkMsgAsRead),
+        (get_group_msg_history, GetGroupMsgHistory),
+        (get_friend_msg_history, GetFriendMsgHistory),
+        (get_group_file_url, GetGroupFileUrl),
+        (get_guild_channel_list, GetGuildChannelList),
+        (send_guild_channel_msg, SendGuildChannelMsg),
+        (upload_file_stream, UploadFileStream)
     );
 }
 
@@ -2,7 +2,11 @@ use std::{collections::HashMap, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
-use super::{event::MessageEvent, id_deserializer};
+use super::{
+    event::{MessageEvent, Sender, Status},
+    id_deserializer,
+    segment::Segment,
+};
 
 /// Onebot API 响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +27,9 @@ pub enum ResponseData {
     /// delete_msg 响应数据
     None,
 
+    /// get_msg 响应数据
+    MessageDetail(Arc<MessageDetail>),
+
     /// send_msg 响应数据
     MessageId(Arc<MessageId>),
 
@@ -47,8 +54,23 @@ pub enum ResponseData {
     /// get_image, get_record, get_file 响应数据
     FileInfo(Arc<FileInfo>),
 
-    /// get_forward_msg 响应数据
+    /// get_forward_msg, get_group_msg_history, get_friend_msg_history 响应数据
     ForwardMessage(Arc<ForwardMessage>),
+
+    /// get_group_file_url 响应数据
+    FileUrl(Arc<FileUrl>),
+
+    /// get_guild_channel_list 响应数据
+    ChannelList(Arc<Vec<ChannelInfo>>),
+
+    /// get_version_info 响应数据
+    VersionInfo(Arc<VersionInfo>),
+
+    /// get_status 响应数据
+    Status(Arc<Status>),
+
+    /// upload_file_stream 响应数据
+    UploadStream(Arc<UploadStream>),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,6 +80,21 @@ pub struct MessageId {
     pub message_id: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageDetail {
+    /// 发送时间
+    pub time: i64,
+    /// 消息类型
+    pub message_type: String,
+    /// 消息ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub message_id: String,
+    /// 发送人
+    pub sender: Sender,
+    /// 消息内容
+    pub message: Vec<Segment>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserInfo {
     /// 用户ID
@@ -127,6 +164,29 @@ impl MemberInfo {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelInfo {
+    /// 所属频道ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub owner_guild_id: String,
+    /// 子频道ID
+    #[serde(deserialize_with = "id_deserializer")]
+    pub channel_id: String,
+    /// 子频道名称
+    pub channel_name: String,
+}
+
+impl ChannelInfo {
+    // 远端对话的目标ID: 频道ID/子频道ID
+    pub fn target_id(&self) -> String {
+        format!("{}/{}", self.owner_guild_id, self.channel_id)
+    }
+
+    pub fn display_name(&self) -> String {
+        self.channel_name.clone()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileInfo {
     /// 文件路径
@@ -141,6 +201,32 @@ pub struct FileInfo {
     pub base64: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionInfo {
+    /// 实现名称
+    pub app_name: String,
+    /// 实现版本
+    pub app_version: String,
+    /// OneBot 标准版本
+    pub protocol_version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadStream {
+    /// 上传状态
+    pub status: String,
+    /// 已接收的分片数
+    pub received_chunks: Option<usize>,
+    /// 合并后的文件路径, 上传完成时返回
+    pub file_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileUrl {
+    /// 文件下载URL
+    pub url: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ForwardMessage {
     /// 消息列表
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::option_id_deserializer;
+
 /// Onebot 消息段类型
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "type", content = "data")]
@@ -38,11 +40,11 @@ pub enum Segment {
 
     /// 猜拳魔法表情
     #[serde(rename = "rps")]
-    Rps,
+    Rps(Rps),
 
     /// 掷骰子魔法表情
     #[serde(rename = "dice")]
-    Dice,
+    Dice(Dice),
 
     /// 窗口抖动
     #[serde(rename = "shake")]
@@ -124,6 +126,9 @@ pub struct Image {
     pub summary: Option<String>,
     /// Emoji图片ID
     pub emoji_id: Option<String>,
+    /// 图片类型, flash为闪照
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -159,6 +164,28 @@ pub struct At {
     pub id: String,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Rps {
+    /// 猜拳结果 (1 布, 2 剪刀, 3 石头), 发送时不需要
+    #[serde(
+        default,
+        deserialize_with = "option_id_deserializer",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub result: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Dice {
+    /// 骰子点数 (1-6), 发送时不需要
+    #[serde(
+        default,
+        deserialize_with = "option_id_deserializer",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub result: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Poke {
     /// 类型
@@ -268,6 +295,9 @@ impl std::fmt::Display for Segment {
                 f.write_str("]")
             }
             Segment::MarketFace(_) => f.write_str("[表情]"),
+            Segment::Image(image) if image.type_.as_deref() == Some("flash") => {
+                f.write_str("[闪照]")
+            }
             Segment::Image(_) => f.write_str("[图片]"),
             Segment::Record(_) => f.write_str("[语音]"),
             Segment::Video(_) => f.write_str("[视频]"),
@@ -276,8 +306,8 @@ impl std::fmt::Display for Segment {
                 f.write_str("@")?;
                 f.write_str(&seg.id)
             }
-            Segment::Rps => f.write_str("[猜拳]"),
-            Segment::Dice => f.write_str("[掷骰子]"),
+            Segment::Rps(_) => f.write_str("[猜拳]"),
+            Segment::Dice(_) => f.write_str("[掷骰子]"),
             Segment::Shake => f.write_str("[窗口抖动]"),
             Segment::Poke(_) => f.write_str("[戳一戳]"),
             Segment::Anonymous => f.write_str("[匿名]"),
@@ -330,14 +360,15 @@ impl Segment {
         name: Option<String>,
         url: Option<String>,
         summary: Option<String>,
-        emoji_id: Option<String>
+        emoji_id: Option<String>,
+        type_: Option<String>
     );
     segment_builder!(record, Record, file: String, name: Option<String>);
     segment_builder!(video, Video, file: String, name: Option<String>, url: Option<String>);
     segment_builder!(file, File, file: String, name: Option<String>);
     segment_builder!(at, At, id: String);
-    segment_builder!(rps, Rps);
-    segment_builder!(dice, Dice);
+    segment_builder!(rps, Rps, result: Option<String>);
+    segment_builder!(dice, Dice, result: Option<String>);
     segment_builder!(shake, Shake);
     segment_builder!(poke, Poke, type_: String, id: String, name: Option<String>);
     segment_builder!(anonymous, Anonymous);
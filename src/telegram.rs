@@ -1,22 +1,26 @@
+mod bot_api;
 mod bridge;
+mod card_parser;
+mod chat_worker;
 mod command;
 mod entities;
+mod ffmpeg;
+mod file_transfer;
 mod from_onebot;
 mod from_telegram;
+mod i18n;
 mod index_service;
+mod media_cache;
 mod migration;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock_transport;
 mod onebot_helper;
+mod segment_converter;
+mod storage_maintenance;
 mod telegram_helper;
 pub mod telegram_pylon;
-
-#[macro_export]
-macro_rules! with_id_lock {
-    ($id_lock:expr, $id:expr, $block:expr) => {{
-        let arc_mutex = $id_lock
-            .entry($id)
-            .or_insert_with(|| std::sync::Arc::new(tokio::sync::Mutex::new(())))
-            .clone();
-        let _guard = arc_mutex.lock().await;
-        $block
-    }};
-}
+mod transcribe_service;
+mod translate_service;
+pub mod transport;
+pub mod web_service;
+mod wechat_cards;
@@ -0,0 +1,650 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+use grammers_client::Update;
+use grammers_client::types::{Media, PackedChat};
+use grammers_tl_types as tl;
+use reqwest::multipart::{Form, Part};
+use sea_orm::prelude::async_trait;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value, json};
+
+use super::telegram_helper as tg_helper;
+use super::transport::{
+    BotUser, ChatInfo, CommandScope, OutgoingButton, OutgoingMedia, OutgoingMessage, SentMessage,
+    TelegramTransport, UploadedFile,
+};
+
+// 默认的Bot API服务器
+const API_URL: &str = "https://api.telegram.org";
+// 长轮询等待更新的时间(秒)
+const POLL_TIMEOUT: u64 = 30;
+// 请求的超时时间, 需要大于长轮询的等待时间并留出上传文件的时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+// 获取更新失败后的重试间隔
+const POLL_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// Bot API返回的错误, description 如 "Bad Request: chat not found"
+#[derive(Debug)]
+pub struct BotApiError {
+    pub code: i64,
+    pub description: String,
+}
+
+impl std::fmt::Display for BotApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Bot API error {}: {}", self.code, self.description)
+    }
+}
+
+impl std::error::Error for BotApiError {}
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    error_code: Option<i64>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiMessage {
+    message_id: i32,
+    chat: ApiChat,
+    date: i64,
+    text: Option<String>,
+    caption: Option<String>,
+    message_thread_id: Option<i32>,
+    #[serde(default)]
+    is_topic_message: bool,
+    reply_to_message: Option<Box<ApiMessage>>,
+}
+
+impl From<ApiMessage> for SentMessage {
+    fn from(message: ApiMessage) -> Self {
+        Self {
+            chat_id: tg_helper::unpack_bot_api_id(message.chat.id).1,
+            id: message.message_id,
+            // 与MTProto一致, Topic中的消息取Topic ID, 否则取回复的消息
+            reply_to: match message.is_topic_message {
+                true => message.message_thread_id,
+                false => message.reply_to_message.map(|reply| reply.message_id),
+            },
+            date: message.date,
+            text: message.text.or(message.caption).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiChat {
+    id: i64,
+}
+
+#[derive(Deserialize)]
+struct ApiUser {
+    id: i64,
+    username: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiMessageId {
+    message_id: i32,
+}
+
+#[derive(Deserialize)]
+struct ApiForumTopic {
+    message_thread_id: i32,
+}
+
+#[derive(Deserialize)]
+struct ApiUpdate {
+    update_id: i64,
+}
+
+// 基于HTTP Bot API的实现, 不需要会话文件, 但无法使用MTProto独有的功能:
+// 不能下载收到的媒体, 不能检查Topic是否存在, 也不能管理贴纸包
+pub struct BotApiTransport {
+    http_client: reqwest::Client,
+    // 带有token的请求地址, 如 https://api.telegram.org/bot<token>
+    base_url: String,
+    // 下一次获取更新的起始ID
+    offset: AtomicI64,
+}
+
+impl BotApiTransport {
+    pub fn new(token: &str, api_url: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            http_client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            base_url: format!(
+                "{}/bot{}",
+                api_url.unwrap_or(API_URL).trim_end_matches('/'),
+                token
+            ),
+            offset: AtomicI64::new(0),
+        })
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let request = self
+            .http_client
+            .post(format!("{}/{}", self.base_url, method))
+            .json(&params);
+        Self::parse(request).await
+    }
+
+    // 带文件的请求以multipart发送, 其余参数作为文本字段
+    async fn call_with_files<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Map<String, Value>,
+        files: Vec<(String, String, Arc<Vec<u8>>)>,
+    ) -> Result<T> {
+        if files.is_empty() {
+            return self.call(method, Value::Object(params)).await;
+        }
+
+        let mut form = Form::new();
+        for (key, value) in params {
+            form = form.text(
+                key,
+                match value {
+                    Value::String(text) => text,
+                    value => value.to_string(),
+                },
+            );
+        }
+        for (field, name, data) in files {
+            form = form.part(field, Part::bytes(data.to_vec()).file_name(name));
+        }
+        let request = self
+            .http_client
+            .post(format!("{}/{}", self.base_url, method))
+            .multipart(form);
+        Self::parse(request).await
+    }
+
+    async fn parse<T: DeserializeOwned>(request: reqwest::RequestBuilder) -> Result<T> {
+        let response: ApiResponse<T> = request.send().await?.json().await?;
+        match (response.ok, response.result) {
+            (true, Some(result)) => Ok(result),
+            _ => Err(BotApiError {
+                code: response.error_code.unwrap_or_default(),
+                description: response.description.unwrap_or_default(),
+            }
+            .into()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TelegramTransport for BotApiTransport {
+    async fn send_message(
+        &self,
+        chat: PackedChat,
+        message: OutgoingMessage,
+    ) -> Result<SentMessage> {
+        let text_key = match &message.media {
+            None => "text",
+            Some(
+                OutgoingMedia::Dice(_) | OutgoingMedia::Venue(_) | OutgoingMedia::Sticker { .. },
+            ) => "",
+            Some(_) => "caption",
+        };
+        let mut params = message_params(chat, &message, text_key);
+        if let Some(reply_to) = message.reply_to {
+            params.insert("reply_parameters".into(), reply_parameters(reply_to));
+        }
+
+        let (method, file) = match message.media {
+            None => {
+                params.insert(
+                    "link_preview_options".into(),
+                    json!({ "is_disabled": !message.link_preview }),
+                );
+                ("sendMessage", None)
+            }
+            Some(OutgoingMedia::Photo { file, spoiler }) => {
+                if spoiler {
+                    params.insert("has_spoiler".into(), true.into());
+                }
+                let (name, data) = inline_file(file)?;
+                ("sendPhoto", Some(("photo", name, data)))
+            }
+            Some(OutgoingMedia::Document(file)) => {
+                let (name, data) = inline_file(file)?;
+                let (method, field) = document_method(&name);
+                (method, Some((field, name, data)))
+            }
+            Some(OutgoingMedia::File(file)) => {
+                params.insert("disable_content_type_detection".into(), true.into());
+                let (name, data) = inline_file(file)?;
+                ("sendDocument", Some(("document", name, data)))
+            }
+            Some(OutgoingMedia::Sticker { file, .. }) => {
+                let (name, data) = inline_file(file)?;
+                ("sendSticker", Some(("sticker", name, data)))
+            }
+            Some(OutgoingMedia::Dice(emoji)) => {
+                params.insert("emoji".into(), emoji.into());
+                ("sendDice", None)
+            }
+            Some(OutgoingMedia::Venue(venue)) => {
+                params.insert("latitude".into(), venue.latitude.into());
+                params.insert("longitude".into(), venue.longitude.into());
+                params.insert("title".into(), venue.title.into());
+                params.insert("address".into(), venue.address.into());
+                ("sendVenue", None)
+            }
+        };
+        let files = file
+            .map(|(field, name, data)| (field.to_string(), name, data))
+            .into_iter()
+            .collect();
+
+        let sent: ApiMessage = self.call_with_files(method, params, files).await?;
+        Ok(sent.into())
+    }
+
+    async fn send_album(
+        &self,
+        chat: PackedChat,
+        medias: Vec<OutgoingMessage>,
+    ) -> Result<Vec<Option<SentMessage>>> {
+        let mut params = Map::new();
+        params.insert("chat_id".into(), tg_helper::pack_bot_api_id(chat).into());
+        if let Some(reply_to) = medias.first().and_then(|media| media.reply_to) {
+            params.insert("reply_parameters".into(), reply_parameters(reply_to));
+        }
+
+        let mut items = Vec::with_capacity(medias.len());
+        let mut files = Vec::with_capacity(medias.len());
+        for (index, media) in medias.into_iter().enumerate() {
+            let (photo, file) = match media.media {
+                Some(OutgoingMedia::Photo { file, .. }) => (true, file),
+                Some(OutgoingMedia::Document(file)) => (false, file),
+                _ => return Err(anyhow::anyhow!("Unsupported album media")),
+            };
+            let (name, data) = inline_file(file)?;
+            let kind = match (photo, document_method(&name).1) {
+                (true, _) => "photo",
+                (false, "video") => "video",
+                _ => "document",
+            };
+            let field = format!("file{}", index);
+            let mut item = json!({ "type": kind, "media": format!("attach://{}", field) });
+            if !media.text.is_empty() {
+                item["caption"] = media.text.into();
+                if media.html {
+                    item["parse_mode"] = "HTML".into();
+                }
+            }
+            items.push(item);
+            files.push((field, name, data));
+        }
+        params.insert("media".into(), Value::Array(items));
+
+        let sent: Vec<ApiMessage> = self
+            .call_with_files("sendMediaGroup", params, files)
+            .await?;
+        Ok(sent
+            .into_iter()
+            .map(|message| Some(message.into()))
+            .collect())
+    }
+
+    // 媒体消息只能修改说明, 没有文字可修改时改为修改说明
+    async fn edit_message(
+        &self,
+        chat: PackedChat,
+        message_id: i32,
+        message: OutgoingMessage,
+    ) -> Result<()> {
+        if message.media.is_some() {
+            return Err(anyhow::anyhow!("Editing media is not supported by Bot API"));
+        }
+
+        let mut params = message_params(chat, &message, "text");
+        params.insert("message_id".into(), message_id.into());
+        params.insert(
+            "link_preview_options".into(),
+            json!({ "is_disabled": !message.link_preview }),
+        );
+        match self
+            .call::<Value>("editMessageText", Value::Object(params))
+            .await
+        {
+            Err(e) if tg_helper::is_rpc_error(&e, "there is no text in the message to edit") => {
+                let mut params = message_params(chat, &message, "caption");
+                params.insert("message_id".into(), message_id.into());
+                self.call::<Value>("editMessageCaption", Value::Object(params))
+                    .await?;
+                Ok(())
+            }
+            ret => ret.map(|_| ()),
+        }
+    }
+
+    async fn pin_message(&self, chat: PackedChat, message_id: i32) -> Result<()> {
+        self.call::<bool>(
+            "pinChatMessage",
+            json!({
+                "chat_id": tg_helper::pack_bot_api_id(chat),
+                "message_id": message_id,
+                "disable_notification": true,
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn unpin_message(&self, chat: PackedChat, message_id: i32) -> Result<()> {
+        self.call::<bool>(
+            "unpinChatMessage",
+            json!({
+                "chat_id": tg_helper::pack_bot_api_id(chat),
+                "message_id": message_id,
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn forward_messages(
+        &self,
+        destination: PackedChat,
+        message_ids: &[i32],
+        source: PackedChat,
+    ) -> Result<Vec<Option<SentMessage>>> {
+        let forwarded: Vec<ApiMessageId> = self
+            .call(
+                "forwardMessages",
+                json!({
+                    "chat_id": tg_helper::pack_bot_api_id(destination),
+                    "from_chat_id": tg_helper::pack_bot_api_id(source),
+                    "message_ids": message_ids,
+                }),
+            )
+            .await?;
+
+        // 转发只返回消息ID
+        Ok(forwarded
+            .into_iter()
+            .map(|forwarded| {
+                Some(SentMessage {
+                    chat_id: destination.id,
+                    id: forwarded.message_id,
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+
+    // 文件随发送的请求一起上传
+    async fn upload_bytes(&self, data: &[u8], name: String) -> Result<UploadedFile> {
+        if data.is_empty() {
+            return Err(anyhow::anyhow!("Cannot upload empty file {}", name));
+        }
+
+        Ok(UploadedFile::Inline {
+            name,
+            data: Arc::new(data.to_vec()),
+        })
+    }
+
+    // Bot API不需要access_hash, 能获取对话信息即表示Bot仍在对话中
+    async fn unpack_chat(&self, chat: PackedChat) -> Result<ChatInfo> {
+        self.call::<Value>(
+            "getChat",
+            json!({ "chat_id": tg_helper::pack_bot_api_id(chat) }),
+        )
+        .await?;
+
+        Ok(ChatInfo {
+            packed: chat,
+            available: true,
+        })
+    }
+
+    async fn get_me(&self) -> Result<BotUser> {
+        let me: ApiUser = self.call("getMe", json!({})).await?;
+        Ok(BotUser {
+            id: me.id,
+            username: me.username,
+        })
+    }
+
+    async fn set_commands(
+        &self,
+        scope: CommandScope,
+        commands: Vec<(String, String)>,
+    ) -> Result<()> {
+        self.call::<bool>(
+            "setMyCommands",
+            json!({
+                "commands": commands
+                    .into_iter()
+                    .map(|(command, description)| json!({
+                        "command": command,
+                        "description": description,
+                    }))
+                    .collect::<Vec<_>>(),
+                "scope": {
+                    "type": match scope {
+                        CommandScope::ChatAdmins => "all_chat_administrators",
+                        CommandScope::Users => "all_private_chats",
+                    },
+                },
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn create_forum_topic(
+        &self,
+        chat: PackedChat,
+        title: String,
+        icon_emoji_id: Option<i64>,
+    ) -> Result<i32> {
+        let mut params = json!({
+            "chat_id": tg_helper::pack_bot_api_id(chat),
+            "name": title,
+        });
+        if let Some(icon_emoji_id) = icon_emoji_id {
+            params["icon_custom_emoji_id"] = icon_emoji_id.to_string().into();
+        }
+
+        let topic: ApiForumTopic = self.call("createForumTopic", params).await?;
+        Ok(topic.message_thread_id)
+    }
+
+    async fn edit_forum_topic(
+        &self,
+        chat: PackedChat,
+        topic_id: i32,
+        title: Option<String>,
+        icon_emoji_id: Option<i64>,
+    ) -> Result<()> {
+        let mut params = json!({
+            "chat_id": tg_helper::pack_bot_api_id(chat),
+            "message_thread_id": topic_id,
+        });
+        if let Some(title) = title {
+            params["name"] = title.into();
+        }
+        // 空字符串表示移除图标
+        if let Some(icon_emoji_id) = icon_emoji_id {
+            params["icon_custom_emoji_id"] = match icon_emoji_id {
+                0 => String::new(),
+                id => id.to_string(),
+            }
+            .into();
+        }
+        self.call::<bool>("editForumTopic", params).await?;
+
+        Ok(())
+    }
+
+    async fn delete_forum_topic(&self, chat: PackedChat, topic_id: i32) -> Result<()> {
+        self.call::<bool>(
+            "deleteForumTopic",
+            json!({
+                "chat_id": tg_helper::pack_bot_api_id(chat),
+                "message_thread_id": topic_id,
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_forum_topics(
+        &self,
+        _chat: PackedChat,
+        _topic_ids: Vec<i32>,
+    ) -> Result<HashSet<i32>> {
+        Err(unsupported("Getting forum topics"))
+    }
+
+    async fn add_sticker_to_set(
+        &self,
+        _short_name: String,
+        _sticker: tl::enums::InputStickerSetItem,
+    ) -> Result<()> {
+        Err(unsupported("Adding MTProto stickers"))
+    }
+
+    async fn create_sticker_set(
+        &self,
+        _owner: PackedChat,
+        _title: String,
+        _short_name: String,
+        _sticker: tl::enums::InputStickerSetItem,
+    ) -> Result<()> {
+        Err(unsupported("Creating sticker sets from MTProto stickers"))
+    }
+
+    fn download<'a>(&'a self, _media: &Media) -> BoxStream<'a, Result<Vec<u8>>> {
+        futures_util::stream::once(async { Err(unsupported("Downloading MTProto media")) }).boxed()
+    }
+
+    // 长轮询确认收到的更新, 只负责发送的Bot不处理更新
+    async fn next_update(&self) -> Result<Option<Update>> {
+        let updates: Result<Vec<ApiUpdate>> = self
+            .call(
+                "getUpdates",
+                json!({
+                    "offset": self.offset.load(Ordering::Relaxed),
+                    "timeout": POLL_TIMEOUT,
+                }),
+            )
+            .await;
+        match updates {
+            Ok(updates) => {
+                if let Some(last) = updates.last() {
+                    self.offset.store(last.update_id + 1, Ordering::Relaxed);
+                }
+                Ok(None)
+            }
+            Err(e) => {
+                // 设置了Webhook或网络异常时避免立即重试
+                tokio::time::sleep(POLL_RETRY_DELAY).await;
+                Err(e)
+            }
+        }
+    }
+
+    fn dc_id(&self) -> Option<i32> {
+        None
+    }
+
+    fn session_file(&self) -> Option<&Path> {
+        None
+    }
+
+    async fn reset_session(&self) -> Result<()> {
+        Err(unsupported("Resetting session"))
+    }
+}
+
+// 消息的公共参数, text_key 为空时不发送文字
+fn message_params(
+    chat: PackedChat,
+    message: &OutgoingMessage,
+    text_key: &str,
+) -> Map<String, Value> {
+    let mut params = Map::new();
+    params.insert("chat_id".into(), tg_helper::pack_bot_api_id(chat).into());
+    if !text_key.is_empty() && !message.text.is_empty() {
+        params.insert(text_key.into(), message.text.clone().into());
+        if message.html {
+            params.insert("parse_mode".into(), "HTML".into());
+        }
+    }
+    if !message.buttons.is_empty() {
+        params.insert(
+            "reply_markup".into(),
+            json!({
+                "inline_keyboard": message
+                    .buttons
+                    .iter()
+                    .map(|row| row.iter().map(inline_button).collect::<Vec<_>>())
+                    .collect::<Vec<_>>(),
+            }),
+        );
+    }
+    params
+}
+
+// 回复的消息不存在时仍然发送, 与MTProto的行为一致
+fn reply_parameters(reply_to: i32) -> Value {
+    json!({
+        "message_id": reply_to,
+        "allow_sending_without_reply": true,
+    })
+}
+
+fn inline_button(button: &OutgoingButton) -> Value {
+    match button {
+        OutgoingButton::Url { text, url } => json!({ "text": text, "url": url }),
+        OutgoingButton::Callback { text, data } => json!({ "text": text, "callback_data": data }),
+    }
+}
+
+fn inline_file(file: UploadedFile) -> Result<(String, Arc<Vec<u8>>)> {
+    match file {
+        UploadedFile::Inline { name, data } => Ok((name, data)),
+        UploadedFile::Mtproto(_) => Err(anyhow::anyhow!(
+            "File uploaded by MTProto can't be sent by Bot API"
+        )),
+    }
+}
+
+// 按文件类型选择发送方法和文件字段, 与MTProto按类型发送文件一致
+fn document_method(name: &str) -> (&'static str, &'static str) {
+    let mime = mime_guess::from_path(name).first_or_octet_stream();
+    match (mime.type_().as_str(), mime.subtype().as_str()) {
+        ("video", _) => ("sendVideo", "video"),
+        ("audio", "ogg") => ("sendVoice", "voice"),
+        ("audio", _) => ("sendAudio", "audio"),
+        ("image", "gif") => ("sendAnimation", "animation"),
+        _ => ("sendDocument", "document"),
+    }
+}
+
+fn unsupported(feature: &str) -> anyhow::Error {
+    anyhow::anyhow!("{} is not supported by Bot API", feature)
+}
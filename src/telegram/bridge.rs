@@ -1,59 +1,125 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::num::NonZeroU32;
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 
 use anyhow::{Context, Result};
 use base64::Engine;
 use base64::prelude::BASE64_STANDARD;
-use chrono::Utc;
+use chrono::{Local, TimeZone, Timelike, Utc};
 use dashmap::DashMap;
+use futures_util::TryStreamExt;
 use governor::{Quota, RateLimiter};
-use grammers_client::Client;
 use grammers_client::session::PackedType;
-use grammers_client::types::media::{Document, Uploaded};
-use grammers_client::types::{Chat, Message, PackedChat};
+use grammers_client::types::media::Document;
+use grammers_client::types::{Message, PackedChat};
 use grammers_tl_types as tl;
 use regex::Regex;
 use reqwest::Url;
 use reqwest::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
 use sea_orm::ActiveValue::Set;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, sea_query,
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, TransactionTrait, sea_query,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{Mutex, OnceCell, Semaphore, SemaphorePermit, mpsc};
+use uuid::Uuid;
+
+use super::chat_worker::ChatWorkers;
+use super::ffmpeg;
+use super::file_transfer::FileTransfer;
+use super::i18n;
+use super::index_service::{IndexService, SearchQuery};
+use super::media_cache::MediaCache;
+use super::storage_maintenance::{self, StorageMaintenance};
+use super::transcribe_service::TranscribeService;
+use super::translate_service::TranslateService;
+use super::transport::{
+    BotUser, ChatInfo, OutgoingMessage, SentMessage, TelegramTransport, UploadedFile,
+};
+use super::{entities, onebot_helper as ob_helper, telegram_helper as tg_helper};
+use crate::common::{
+    ChatType, ConversationWindowConfig, DeliveryStatus, Endpoint, FilterAction, FilterDirection,
+    GifLimitConfig, Language, LogLevelReloader, MediaKind, MediaMode, MemberNotice, Platform,
+    ReactionAction, ReconnectNoticeConfig, RemoteChatKey, Signature, TelegramConfig,
+    TeleporterConfig, VoiceFormat,
 };
-use tokio::sync::{Mutex, mpsc};
-
-use super::index_service::IndexService;
-use super::{entities, onebot_helper as ob_helper};
-use crate::common::{ChatType, DeliveryStatus, Endpoint, Platform, RemoteChatKey};
 use crate::onebot::onebot_pylon::OnebotPylon;
 use crate::onebot::protocol::OnebotRequest;
+use crate::onebot::protocol::event::Status;
 use crate::onebot::protocol::request::{
-    DeleteMsg, GetFile, GetForwardMsg, GetGroupInfo, GetGroupMemberInfo, GetGroupMemberList,
-    GetImage, GetRecord, GetStrangerInfo, Request, SendMsg,
+    DeleteEssenceMsg, DeleteMsg, GetFile, GetForwardMsg, GetFriendMsgHistory, GetGroupFileUrl,
+    GetGroupInfo, GetGroupMemberInfo, GetGroupMemberList, GetGroupMsgHistory, GetGuildChannelList,
+    GetImage, GetMsg, GetRecord, GetStrangerInfo, MarkMsgAsRead, Request, SendGuildChannelMsg,
+    SendMsg, SetEssenceMsg, SetGroupBan, SetGroupKick, SetGroupSpecialTitle, UploadFileStream,
 };
 use crate::onebot::protocol::response::{
-    FileInfo, ForwardMessage, GroupInfo, MemberInfo, MessageId, ResponseData, UserInfo,
+    ChannelInfo, FileInfo, FileUrl, ForwardMessage, GroupInfo, MemberInfo, MessageDetail,
+    MessageId, ResponseData, UploadStream, UserInfo, VersionInfo,
 };
 use crate::onebot::protocol::segment::Segment;
 
 pub type RelayBridge = Arc<Bridge>;
 pub type ChatModel = entities::remote_chat::Model;
+pub type SettingsModel = entities::chat_settings::Model;
+pub type FilterModel = entities::filter::Model;
+pub type AutoReplyModel = entities::auto_reply::Model;
+
+// 内容过滤的结果, 打码在消息段上直接修改
+pub enum FilterVerdict {
+    Pass,
+    // 命中的规则
+    Drop(String),
+    Flag(String),
+}
 
-pub type RemoteIdLock = DashMap<RemoteChatKey, Arc<Mutex<()>>>;
-pub type TgIdLock = DashMap<i64, Arc<Mutex<()>>>;
+pub type RemoteWorkers = ChatWorkers<RemoteChatKey>;
+pub type TgWorkers = ChatWorkers<i64>;
 
 type GovernorStateMap = DashMap<i64, governor::state::InMemoryState>;
+type RemoteGovernorStateMap = DashMap<RemoteChatKey, governor::state::InMemoryState>;
 type GovernorClock = governor::clock::MonotonicClock;
 type GovernorMiddleware = governor::middleware::NoOpMiddleware<std::time::Instant>;
+type RemoteRateLimiter =
+    RateLimiter<RemoteChatKey, RemoteGovernorStateMap, GovernorClock, GovernorMiddleware>;
 
 const TG_RATE_LIMIT: u32 = 20;
+// 未配置时每个远端对话每分钟最多发送的消息数
+const REMOTE_RATE_LIMIT: u32 = 20;
+// 未配置时转换成GIF的最长时长(秒), 最多帧数和TGS贴纸转成GIF的最大字节数
+const GIF_MAX_DURATION: f64 = 6.0;
+const GIF_MAX_FRAMES: u64 = 180;
+const GIF_MAX_BYTES: usize = 1024 * 1024;
+// 未配置时远端重连提示的抑制窗口(秒)
+const RECONNECT_WINDOW: i64 = 10 * 60;
+// 回调数据的保留时间
+const CALLBACK_TTL: i64 = 7 * 24 * 60 * 60;
+// 等待确认发送的消息的保留时间
+const PENDING_SEND_TTL: i64 = 24 * 60 * 60;
+// 群成员列表的缓存时间
+const GROUP_MEMBER_TTL: i64 = 10 * 60;
+// 统计报告中列出的对话数量
+const STATS_TOP_CHATS: usize = 10;
+// 重建索引时每批读取的消息数
+const REINDEX_BATCH_SIZE: u64 = 500;
+// 同一条自动回复规则在同一对话中两次回复的最短间隔(秒)
+const AUTO_REPLY_COOLDOWN: i64 = 60;
+// 分段上传到Onebot实现时每段的大小, base64后仍在WebSocket消息限制内
+const STREAM_CHUNK_SIZE: usize = 1024 * 1024;
+// 对话窗口默认的不活跃天数
+const WINDOW_INACTIVE_DAYS: i64 = 7;
+// 通过URL下载文件的最大大小, 与Telegram允许上传的最大文件一致
+const FETCH_MAX_SIZE: usize = 2000 * 1024 * 1024;
 const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/87.0.4280.88 Safari/537.36 Edg/87.0.664.66";
 
 #[derive(Debug)]
 pub struct UploadedInfo {
-    pub uploaded: Uploaded,
+    pub uploaded: UploadedFile,
     pub file_name: String,
     pub file_size: usize,
     pub mime_type: String,
@@ -61,6 +127,297 @@ pub struct UploadedInfo {
     pub height: u32,
 }
 
+#[derive(Debug)]
+pub struct MediaTooLarge {
+    pub size: usize,
+    pub url: Option<String>,
+}
+
+impl std::fmt::Display for MediaTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "media too large: {} bytes", self.size)
+    }
+}
+
+impl std::error::Error for MediaTooLarge {}
+
+#[derive(Debug, Default)]
+pub struct DigestBuffer {
+    pub flush_at: i64,
+    pub lines: Vec<String>,
+}
+
+// 运行统计, 供管理页面展示
+pub struct BridgeStats {
+    pub started_at: i64,
+    // 转发到Telegram的消息数
+    pub to_telegram: AtomicU64,
+    // 转发到远端的消息数
+    pub to_remote: AtomicU64,
+    // 已连接的端点及连接时间
+    pub endpoints: DashMap<Endpoint, i64>,
+    // 各端点最近一次心跳的时间
+    pub heartbeats: DashMap<Endpoint, i64>,
+    // 统计报告周期的开始时间, 以下统计在发送报告后清空
+    pub period_start: AtomicI64,
+    // 各远端对话的消息数: (转发到Telegram, 转发到远端)
+    pub chats: DashMap<i64, (u64, u64)>,
+    // 转发的媒体字节数
+    pub media_bytes: AtomicU64,
+    // 发送失败的次数
+    pub failures: AtomicU64,
+}
+
+// 一个统计周期的数据
+pub struct StatsPeriod {
+    pub start: i64,
+    pub chats: Vec<(i64, (u64, u64))>,
+    pub media_bytes: u64,
+    pub failures: u64,
+}
+
+impl BridgeStats {
+    pub fn record_to_telegram(&self, remote_chat_id: i64) {
+        self.to_telegram.fetch_add(1, Ordering::Relaxed);
+        self.chats.entry(remote_chat_id).or_default().0 += 1;
+    }
+
+    pub fn record_to_remote(&self, remote_chat_id: i64) {
+        self.to_remote.fetch_add(1, Ordering::Relaxed);
+        self.chats.entry(remote_chat_id).or_default().1 += 1;
+    }
+
+    // 取出当前周期的统计并开始新的周期
+    pub fn take_period(&self) -> StatsPeriod {
+        let chats = self.chats.iter().map(|e| (*e.key(), *e.value())).collect();
+        self.chats.clear();
+
+        StatsPeriod {
+            start: self
+                .period_start
+                .swap(Utc::now().timestamp(), Ordering::Relaxed),
+            chats,
+            media_bytes: self.media_bytes.swap(0, Ordering::Relaxed),
+            failures: self.failures.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+// 单个Telegram Bot, 各自缓存对话并独立限制发送频率
+pub struct TelegramBot {
+    transport: Arc<dyn TelegramTransport>,
+    // 保存收到的access_hash, 重启后仍能解析对话
+    db: DatabaseConnection,
+    // access_hash只对获取它的Bot有效, 保存时按Bot区分
+    bot_id: OnceCell<i64>,
+    tg_chat_cache: DashMap<(PackedType, i64), Arc<ChatInfo>>,
+    tg_rate_limit: Arc<RateLimiter<i64, GovernorStateMap, GovernorClock, GovernorMiddleware>>,
+}
+
+impl TelegramBot {
+    pub fn new(transport: Arc<dyn TelegramTransport>, db: DatabaseConnection) -> Self {
+        Self {
+            transport,
+            db,
+            bot_id: OnceCell::new(),
+            tg_chat_cache: DashMap::new(),
+            tg_rate_limit: Arc::new(RateLimiter::keyed(Quota::per_minute(
+                NonZeroU32::new(TG_RATE_LIMIT - 1).unwrap(),
+            ))),
+        }
+    }
+
+    // 不经过频率限制和对话缓存的接口
+    pub fn transport(&self) -> &dyn TelegramTransport {
+        self.transport.as_ref()
+    }
+
+    pub async fn send_message<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        message: OutgoingMessage,
+    ) -> Result<SentMessage> {
+        // 限制发送频率
+        let chat: PackedChat = chat.into();
+        self.tg_rate_limit.until_key_ready(&chat.id).await;
+
+        let result = self.transport.send_message(chat, message).await;
+        self.check_peer(chat, result).await
+    }
+
+    pub async fn send_album<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        medias: Vec<OutgoingMessage>,
+    ) -> Result<Vec<Option<SentMessage>>> {
+        // 限制发送频率
+        let chat: PackedChat = chat.into();
+        self.tg_rate_limit.until_key_ready(&chat.id).await;
+
+        let result = self.transport.send_album(chat, medias).await;
+        self.check_peer(chat, result).await
+    }
+
+    pub async fn edit_message<C: Into<PackedChat>>(
+        &self,
+        chat: C,
+        message_id: i32,
+        message: OutgoingMessage,
+    ) -> Result<()> {
+        let chat: PackedChat = chat.into();
+        let result = self.transport.edit_message(chat, message_id, message).await;
+        self.check_peer(chat, result).await
+    }
+
+    pub async fn pin_message<C: Into<PackedChat>>(&self, chat: C, message_id: i32) -> Result<()> {
+        self.transport.pin_message(chat.into(), message_id).await
+    }
+
+    pub async fn unpin_message<C: Into<PackedChat>>(&self, chat: C, message_id: i32) -> Result<()> {
+        self.transport.unpin_message(chat.into(), message_id).await
+    }
+
+    pub async fn forward_messages<C: Into<PackedChat>, S: Into<PackedChat>>(
+        &self,
+        destination: C,
+        message_ids: &[i32],
+        source: S,
+    ) -> Result<Vec<Option<SentMessage>>> {
+        // 限制发送频率
+        let destination: PackedChat = destination.into();
+        self.tg_rate_limit.until_key_ready(&destination.id).await;
+
+        self.transport
+            .forward_messages(destination, message_ids, source.into())
+            .await
+    }
+
+    pub async fn upload_bytes(&self, data: &[u8], name: String) -> Result<UploadedFile> {
+        self.transport.upload_bytes(data, name).await
+    }
+
+    pub async fn get_me(&self) -> Result<BotUser> {
+        self.transport.get_me().await
+    }
+
+    // 首次使用时获取并缓存Bot的id
+    pub async fn bot_id(&self) -> Result<i64> {
+        self.bot_id
+            .get_or_try_init(|| async { self.get_me().await.map(|me| me.id) })
+            .await
+            .copied()
+    }
+
+    // 依次使用缓存, 保存的access_hash和0解析对话
+    // Bot不能获取对话列表, 没见过的对话只能等收到其消息后才能解析
+    pub async fn get_chat(&self, packed_type: PackedType, chat_id: i64) -> Result<Arc<ChatInfo>> {
+        if let Some(chat) = self.tg_chat_cache.get(&(packed_type, chat_id)) {
+            return Ok(chat.clone());
+        }
+
+        let stored = entities::tg_chat::Entity::find()
+            .filter(entities::tg_chat::Column::BotId.eq(self.bot_id().await?))
+            .filter(entities::tg_chat::Column::ChatId.eq(chat_id))
+            .filter(entities::tg_chat::Column::ChatType.eq(packed_type as i32))
+            .one(&self.db)
+            .await?
+            .map(|model| model.access_hash);
+        let unpack = |access_hash: i64| {
+            self.transport.unpack_chat(PackedChat {
+                ty: packed_type,
+                id: chat_id,
+                access_hash: Some(access_hash),
+            })
+        };
+        let chat = match stored {
+            Some(access_hash) => match unpack(access_hash).await {
+                Ok(chat) => chat,
+                Err(e) if tg_helper::is_peer_invalid(&e) => {
+                    tracing::warn!("Stored access hash of {} is invalid: {}", chat_id, e);
+                    self.forget_chat(packed_type, chat_id).await?;
+                    unpack(0).await?
+                }
+                Err(e) => return Err(e),
+            },
+            None => unpack(0).await?,
+        };
+
+        let chat = Arc::new(chat);
+        self.remember_chat(&chat).await?;
+        Ok(chat)
+    }
+
+    // 缓存并保存对话的access_hash, 与已缓存的相同时跳过
+    pub async fn remember_chat(&self, chat: &ChatInfo) -> Result<()> {
+        let packed = chat.pack();
+        let Some(access_hash) = packed.access_hash.filter(|hash| *hash != 0) else {
+            return Ok(());
+        };
+        let key = (packed.ty, packed.id);
+        if self
+            .tg_chat_cache
+            .get(&key)
+            .is_some_and(|cached| cached.pack().access_hash == Some(access_hash))
+        {
+            return Ok(());
+        }
+        self.tg_chat_cache.insert(key, Arc::new(chat.clone()));
+
+        let model = entities::tg_chat::ActiveModel {
+            bot_id: Set(self.bot_id().await?),
+            chat_id: Set(packed.id),
+            chat_type: Set(packed.ty as i32),
+            access_hash: Set(access_hash),
+            created_at: Set(Utc::now().timestamp()),
+            updated_at: Set(Utc::now().timestamp()),
+            ..Default::default()
+        };
+        entities::tg_chat::Entity::insert(model)
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    entities::tg_chat::Column::BotId,
+                    entities::tg_chat::Column::ChatId,
+                    entities::tg_chat::Column::ChatType,
+                ])
+                .update_columns([
+                    entities::tg_chat::Column::AccessHash,
+                    entities::tg_chat::Column::UpdatedAt,
+                ])
+                .to_owned(),
+            )
+            .exec(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    // 移除缓存和保存的access_hash, 下次使用时重新解析
+    async fn forget_chat(&self, packed_type: PackedType, chat_id: i64) -> Result<()> {
+        self.tg_chat_cache.remove(&(packed_type, chat_id));
+        entities::tg_chat::Entity::delete_many()
+            .filter(entities::tg_chat::Column::BotId.eq(self.bot_id().await?))
+            .filter(entities::tg_chat::Column::ChatId.eq(chat_id))
+            .filter(entities::tg_chat::Column::ChatType.eq(packed_type as i32))
+            .exec(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    // 对话无效时清除其缓存, 下次发送前重新解析
+    async fn check_peer<T>(&self, chat: PackedChat, result: Result<T>) -> Result<T> {
+        if let Err(e) = &result {
+            if tg_helper::is_peer_invalid(e) {
+                tracing::warn!("Peer {} is invalid, refreshing: {}", chat.id, e);
+                if let Err(e) = self.forget_chat(chat.ty, chat.id).await {
+                    tracing::warn!("Failed to forget peer {}: {}", chat.id, e);
+                }
+            }
+        }
+        result
+    }
+}
+
 #[derive(Debug, Clone, Hash)]
 pub struct CommandCallback {
     pub category: String,
@@ -82,18 +439,229 @@ impl CommandCallback {
     }
 }
 
+// 批量导入导出链接时CSV中的一行, tg_chat_type 为数据库中保存的对话类型
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkRecord {
+    pub endpoint: String,
+    pub chat_type: String,
+    pub target_id: String,
+    #[serde(default)]
+    pub name: String,
+    pub tg_chat_type: i32,
+    pub tg_chat_id: i64,
+    #[serde(default)]
+    pub tg_topic_id: i32,
+}
+
+// 批量导入链接的结果
+#[derive(Default)]
+pub struct LinkImportReport {
+    pub created: usize,
+    pub skipped: usize,
+    // 失败的行号及原因
+    pub failures: Vec<(usize, String)>,
+}
+
+// 端点在抑制窗口内的连接变化
+struct ReconnectState {
+    window_start: i64,
+    window: i64,
+    // 窗口内未提示的变化次数
+    suppressed: u32,
+    disconnects: u32,
+    connected: bool,
+}
+
+impl ReconnectState {
+    fn summary(&self, endpoint: &Endpoint, language: Language) -> Option<String> {
+        if self.suppressed == 0 {
+            return None;
+        }
+
+        Some(i18n::trf(
+            language,
+            "<b>[INFO] {} flapped {} times in {} min, currently {}</b>",
+            &[
+                endpoint,
+                &self.disconnects,
+                &((self.window + 59) / 60),
+                &i18n::tr(language, connection_state(self.connected)),
+            ],
+        ))
+    }
+}
+
+// 可以在运行时重新加载的设置
+#[derive(Clone)]
+pub struct DynamicSettings {
+    pub mark_as_read: bool,
+    // 各平台每个远端对话每分钟最多发送的消息数
+    pub remote_rate_limits: HashMap<Platform, u32>,
+    // 各平台GIF转换的限制, 未配置的平台使用默认值
+    pub gif_limits: HashMap<Platform, GifLimitConfig>,
+    // 各端点的重连提示策略, 未配置的端点使用默认策略
+    pub reconnect_notices: HashMap<Endpoint, ReconnectNoticeConfig>,
+    pub default_reconnect_notice: ReconnectNoticeConfig,
+    // 归档Topic的自定义表情图标
+    pub topic_icons: HashMap<String, i64>,
+    // 位置消息附带的静态地图地址模板
+    pub static_map: Option<String>,
+    // Bot回复和系统通知的语言
+    pub language: Language,
+    // 管理员ID -> 语言, 未配置的管理员使用默认语言
+    pub admin_languages: HashMap<i64, Language>,
+    // 命令别名 -> 命令, 都不带斜杠
+    pub command_aliases: HashMap<String, String>,
+    // 管理员的反应 -> 快捷操作
+    pub reaction_actions: HashMap<String, ReactionAction>,
+    // 收藏消息转发到的对话, 未配置时发给管理员
+    pub favorites_chat: Option<i64>,
+    // 语音格式, 按端点, 平台, default 查找
+    pub voice_formats: HashMap<String, VoiceFormat>,
+    // SILK编码器路径
+    pub silk_encoder: Option<String>,
+    // 私聊对话窗口, 未配置时发给管理员
+    pub conversation_window: Option<ConversationWindowConfig>,
+    // 发往远端的图片去除EXIF并按方向旋转
+    pub strip_exif: bool,
+    // 默认附加在发往远端的消息前的署名
+    pub signature: Option<String>,
+}
+
+impl DynamicSettings {
+    pub fn from_config(config: &TelegramConfig, rate_limit: &HashMap<String, u32>) -> Result<Self> {
+        let mut remote_rate_limits = HashMap::new();
+        for (platform, limit) in rate_limit {
+            let platform = platform
+                .parse::<Platform>()
+                .map_err(|e| anyhow::anyhow!(e))?;
+            remote_rate_limits.insert(platform, *limit);
+        }
+
+        let mut gif_limits = HashMap::new();
+        for (platform, limit) in config.gif_limit.clone().unwrap_or_default() {
+            let platform = platform
+                .parse::<Platform>()
+                .map_err(|e| anyhow::anyhow!(e))?;
+            gif_limits.insert(platform, limit);
+        }
+
+        let mut reconnect_notices = HashMap::new();
+        let mut default_reconnect_notice = ReconnectNoticeConfig::default();
+        for (endpoint, policy) in config.reconnect_notice.clone().unwrap_or_default() {
+            if endpoint == "default" {
+                default_reconnect_notice = policy;
+                continue;
+            }
+            let endpoint = endpoint
+                .parse::<Endpoint>()
+                .map_err(|e| anyhow::anyhow!(e))?;
+            reconnect_notices.insert(endpoint, policy);
+        }
+
+        let mut admin_languages = HashMap::new();
+        for (admin_id, language) in config.admin_language.clone().unwrap_or_default() {
+            let admin_id = admin_id
+                .parse::<i64>()
+                .with_context(|| format!("Invalid admin id in admin_language: {}", admin_id))?;
+            admin_languages.insert(admin_id, language);
+        }
+
+        Ok(Self {
+            mark_as_read: config.mark_as_read.unwrap_or(false),
+            remote_rate_limits,
+            gif_limits,
+            reconnect_notices,
+            default_reconnect_notice,
+            topic_icons: config.topic_icon.clone().unwrap_or_default(),
+            static_map: config.static_map.clone().filter(|url| !url.is_empty()),
+            language: config.language.unwrap_or_default(),
+            admin_languages,
+            command_aliases: config.command_aliases.clone().unwrap_or_default(),
+            reaction_actions: config.reaction_actions.clone().unwrap_or_default(),
+            favorites_chat: config.favorites_chat,
+            voice_formats: config.voice_format.clone().unwrap_or_default(),
+            silk_encoder: config.silk_encoder.clone().filter(|path| !path.is_empty()),
+            conversation_window: config.conversation_window,
+            strip_exif: config.strip_exif.unwrap_or(true),
+            signature: config.signature.clone().filter(|s| !s.is_empty()),
+        })
+    }
+}
+
+fn connection_state(connected: bool) -> &'static str {
+    match connected {
+        true => "connected",
+        false => "disconnected",
+    }
+}
+
+// 频率限制为0的平台不限制
+fn build_rate_limiters(
+    limits: &HashMap<Platform, u32>,
+) -> HashMap<Platform, Option<Arc<RemoteRateLimiter>>> {
+    limits
+        .iter()
+        .map(|(platform, limit)| {
+            (
+                platform.clone(),
+                NonZeroU32::new(*limit)
+                    .map(|limit| Arc::new(RateLimiter::keyed(Quota::per_minute(limit)))),
+            )
+        })
+        .collect()
+}
+
 pub struct Bridge {
     pub admin_id: i64,
-    pub bot_client: Client,
+    // 系统通知群, 未配置时发给管理员
+    notice_chat: Option<i64>,
+    // 所有Bot, 第一个为主Bot, 负责接收消息和处理命令
+    bots: Vec<TelegramBot>,
+    // 端点到Bot的路由表, 未配置的端点使用主Bot
+    bot_routes: HashMap<Endpoint, usize>,
     pub db: DatabaseConnection,
     index: Option<IndexService>,
+    pub translate: Option<TranslateService>,
+    pub transcribe: Option<TranscribeService>,
+    media_cache: MediaCache,
+    // 大文件以临时文件发送, 未配置时使用base64
+    file_transfer: Option<Arc<FileTransfer>>,
+    // 按保留策略清理存储, 未配置时不清理
+    maintenance: Option<Arc<StorageMaintenance>>,
+    max_media_size: Option<usize>,
+    // 限制同时下载, 转换和上传的媒体数量
+    media_workers: Semaphore,
+    // 可重新加载的设置
+    settings: RwLock<DynamicSettings>,
+    log_reloader: LogLevelReloader,
+    pub stats: BridgeStats,
     api_sender: mpsc::Sender<OnebotRequest>,
     http_client: reqwest::Client,
+    // 各平台往远端对话发送消息的频率限制, 未配置的平台使用默认值
+    remote_rate_limits: RwLock<HashMap<Platform, Option<Arc<RemoteRateLimiter>>>>,
+    default_rate_limit: Arc<RemoteRateLimiter>,
+    // 同一远端对话的消息排队依次发送, 保证顺序
+    remote_send_lock: DashMap<RemoteChatKey, Arc<Mutex<()>>>,
+    reconnect_states: DashMap<Endpoint, ReconnectState>,
 
     remote_chat_cache: DashMap<RemoteChatKey, Arc<ChatModel>>,
     callback_cache: DashMap<String, CommandCallback>,
-    tg_chat_cache: DashMap<(PackedType, i64), Arc<Chat>>,
-    tg_rate_limit: Arc<RateLimiter<i64, GovernorStateMap, GovernorClock, GovernorMiddleware>>,
+    chat_settings_cache: DashMap<i64, Option<Arc<SettingsModel>>>,
+    filter_cache: DashMap<i64, Arc<Vec<(FilterModel, Regex)>>>,
+    auto_reply_cache: DashMap<i64, Arc<Vec<(AutoReplyModel, Regex)>>>,
+    // 自动回复规则上次回复的时间
+    auto_reply_times: DashMap<i64, i64>,
+    // 群成员列表缓存: (端点, 群ID) -> (获取时间, 成员列表)
+    group_member_cache: DashMap<(Endpoint, String), (i64, Arc<Vec<MemberInfo>>)>,
+    digest_buffer: DashMap<RemoteChatKey, DigestBuffer>,
+    album_buffer: DashMap<i64, Vec<Message>>,
+    // 归档Topic中私聊回复群成员: (Telegram群ID, Topic ID) -> 私聊对话
+    dm_relays: DashMap<(i64, i32), Arc<ChatModel>>,
+    // 等待确认发送的消息: 预览ID -> (暂存时间, 远端对话ID, 消息)
+    pending_sends: DashMap<String, (i64, i64, Vec<Message>)>,
+    // 端点的显示名称
+    endpoint_aliases: DashMap<Endpoint, String>,
 }
 
 macro_rules! onebot_api {
@@ -216,6 +784,15 @@ macro_rules! download_seg{
             $($param: $type),*
         ) -> Result<(String, Vec<u8>)> {
             let file_info = self.$get_image_method(endpoint, $($param),*).await?;
+            // 优先通过URL分块下载, URL是本地路径或下载失败时使用base64
+            if let Some(url) = file_info.url.as_ref().filter(|url| url.starts_with("http")) {
+                match self.fetch_file(url).await {
+                    Ok((_, data)) => return Ok((file_info.file_name.clone(), data)),
+                    // 超出大小限制时不再通过base64获取
+                    Err(e) if e.is::<MediaTooLarge>() => return Err(e),
+                    Err(e) => tracing::warn!("Failed to fetch {}: {}", url, e),
+                }
+            }
             if let Some(base64_data) = file_info.base64.as_ref() {
                 return Ok((
                     file_info.file_name.clone(),
@@ -282,79 +859,197 @@ macro_rules! update_remote_chat {
 }
 
 impl Bridge {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         admin_id: i64,
-        bot_client: Client,
+        notice_chat: Option<i64>,
+        transports: Vec<Arc<dyn TelegramTransport>>,
+        bot_routes: HashMap<Endpoint, usize>,
         db: DatabaseConnection,
         index: Option<IndexService>,
+        translate: Option<TranslateService>,
+        transcribe: Option<TranscribeService>,
+        media_cache: MediaCache,
+        file_transfer: Option<Arc<FileTransfer>>,
+        maintenance: Option<Arc<StorageMaintenance>>,
+        max_media_size: Option<usize>,
+        media_workers: usize,
+        settings: DynamicSettings,
+        log_reloader: LogLevelReloader,
         api_sender: mpsc::Sender<OnebotRequest>,
     ) -> Self {
         Self {
             admin_id,
-            bot_client,
+            notice_chat,
+            bots: transports
+                .into_iter()
+                .map(|transport| TelegramBot::new(transport, db.clone()))
+                .collect(),
+            bot_routes,
             db,
             index,
+            translate,
+            transcribe,
+            media_cache,
+            file_transfer,
+            maintenance,
+            max_media_size,
+            media_workers: Semaphore::new(media_workers),
+            remote_rate_limits: RwLock::new(build_rate_limiters(&settings.remote_rate_limits)),
+            settings: RwLock::new(settings),
+            log_reloader,
+            stats: BridgeStats {
+                started_at: Utc::now().timestamp(),
+                to_telegram: AtomicU64::new(0),
+                to_remote: AtomicU64::new(0),
+                endpoints: DashMap::new(),
+                heartbeats: DashMap::new(),
+                period_start: AtomicI64::new(Utc::now().timestamp()),
+                chats: DashMap::new(),
+                media_bytes: AtomicU64::new(0),
+                failures: AtomicU64::new(0),
+            },
             api_sender,
             http_client: reqwest::Client::builder()
                 .user_agent(USER_AGENT)
                 .build()
                 .expect("Failed to create HTTP client"),
+            default_rate_limit: Arc::new(RateLimiter::keyed(Quota::per_minute(
+                NonZeroU32::new(REMOTE_RATE_LIMIT).unwrap(),
+            ))),
+            remote_send_lock: DashMap::new(),
+            reconnect_states: DashMap::new(),
             remote_chat_cache: DashMap::new(),
             callback_cache: DashMap::new(),
-            tg_chat_cache: DashMap::new(),
-            tg_rate_limit: Arc::new(RateLimiter::keyed(Quota::per_minute(
-                NonZeroU32::new(TG_RATE_LIMIT - 1).unwrap(),
-            ))),
+            chat_settings_cache: DashMap::new(),
+            filter_cache: DashMap::new(),
+            auto_reply_cache: DashMap::new(),
+            auto_reply_times: DashMap::new(),
+            group_member_cache: DashMap::new(),
+            digest_buffer: DashMap::new(),
+            album_buffer: DashMap::new(),
+            dm_relays: DashMap::new(),
+            pending_sends: DashMap::new(),
+            endpoint_aliases: DashMap::new(),
         }
     }
 
-    pub async fn send_telegram_message<
-        C: Into<PackedChat>,
-        M: Into<grammers_client::types::InputMessage>,
-    >(
-        &self,
-        chat: C,
-        message: M,
-    ) -> Result<Message> {
-        // 限制发送频率
-        let chat: PackedChat = chat.into();
-        self.tg_rate_limit.until_key_ready(&chat.id).await;
+    pub fn primary_bot(&self) -> &TelegramBot {
+        &self.bots[0]
+    }
 
-        Ok(self.bot_client.send_message(chat, message).await?)
+    // 按端点选择发送消息的Bot
+    pub fn get_bot(&self, endpoint: &Endpoint) -> &TelegramBot {
+        match self.bot_routes.get(endpoint) {
+            Some(index) => &self.bots[*index],
+            None => self.primary_bot(),
+        }
     }
 
-    pub async fn send_telegram_album<C: Into<PackedChat>>(
+    // 发送消息段到远端对话
+    #[tracing::instrument(skip_all, fields(remote_chat_id = remote_chat.id))]
+    pub async fn send_to_remote(
         &self,
-        chat: C,
-        medias: Vec<grammers_client::types::input_media::InputMedia>,
-    ) -> Result<Vec<Option<Message>>> {
-        // 限制发送频率
-        let chat: PackedChat = chat.into();
-        self.tg_rate_limit.until_key_ready(&chat.id).await;
+        remote_chat: &ChatModel,
+        segments: Vec<Segment>,
+    ) -> Result<Arc<MessageId>> {
+        // 排队并限制发送频率, 避免触发平台的风控
+        let lock = self
+            .remote_send_lock
+            .entry(remote_chat.to_id())
+            .or_default()
+            .clone();
+        let _guard = lock.lock().await;
+        let rate_limit = match self
+            .remote_rate_limits
+            .read()
+            .unwrap()
+            .get(&remote_chat.endpoint.platform)
+        {
+            Some(rate_limit) => rate_limit.clone(),
+            None => Some(self.default_rate_limit.clone()),
+        };
+        if let Some(rate_limit) = rate_limit {
+            rate_limit.until_key_ready(&remote_chat.to_id()).await;
+        }
+
+        let ret = match remote_chat.chat_type {
+            ChatType::Private => {
+                self.send_msg(
+                    &remote_chat.endpoint,
+                    "private".to_string(),
+                    None,
+                    Some(remote_chat.target_id.clone()),
+                    segments,
+                )
+                .await
+            }
+            ChatType::Group => {
+                self.send_msg(
+                    &remote_chat.endpoint,
+                    "group".to_string(),
+                    Some(remote_chat.target_id.clone()),
+                    None,
+                    segments,
+                )
+                .await
+            }
+            ChatType::Guild => match split_guild_target(&remote_chat.target_id) {
+                Ok((guild_id, channel_id)) => {
+                    self.send_guild_channel_msg(
+                        &remote_chat.endpoint,
+                        guild_id,
+                        channel_id,
+                        segments,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            },
+        };
+        match &ret {
+            Ok(_) => self.stats.record_to_remote(remote_chat.id),
+            Err(_) => {
+                self.stats.failures.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        ret
+    }
 
-        Ok(self.bot_client.send_album(chat, medias).await?)
+    // 等待空闲的媒体处理名额, 释放返回值后归还
+    pub async fn acquire_media_worker(&self) -> Result<SemaphorePermit<'_>> {
+        Ok(self.media_workers.acquire().await?)
     }
 
     // 将Onebot消息段的媒体下载到本地后上传到Telegram
+    #[tracing::instrument(skip_all, fields(endpoint = %endpoint))]
     pub async fn upload_segment(
         &self,
+        bot: &TelegramBot,
         endpoint: &Endpoint,
         segment: &Segment,
     ) -> Result<UploadedInfo> {
+        let _permit = self.acquire_media_worker().await?;
         let mut segment_data = self.download_segment(endpoint, segment).await?;
+        self.stats
+            .media_bytes
+            .fetch_add(segment_data.1.len() as u64, Ordering::Relaxed);
 
         let mut kind = infer::get(&segment_data.1);
 
         // TODO: 是不是所有的GIF都应该转成Sticker
         if ob_helper::is_sticker(segment) {
             if kind.filter(|i| i.mime_type() == "image/gif").is_some() {
-                match ob_helper::gif_to_webm(&segment_data.1).await {
-                    Ok(webm_data) => {
-                        kind = infer::get(&webm_data);
-                        segment_data.1 = webm_data;
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to convert gif to webm: {}", e);
+                // 没有ffmpeg时保持GIF原样发送
+                if ffmpeg::has_ffmpeg() {
+                    match ob_helper::gif_to_webm(&segment_data.1).await {
+                        Ok(webm_data) => {
+                            kind = infer::get(&webm_data);
+                            segment_data.1 = webm_data;
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to convert gif to webm: {}", e);
+                        }
                     }
                 }
             } else {
@@ -370,7 +1065,7 @@ impl Bridge {
             }
         } else if let Segment::Record(_) = segment {
             // QQ的目前是获取wav格式的, 需要转成opus ogg
-            if let Platform::QQ = endpoint.platform {
+            if endpoint.platform == Platform::QQ && ffmpeg::has_ffmpeg() {
                 match ob_helper::wav_to_ogg(&segment_data.1).await {
                     Ok(ogg_data) => {
                         kind = infer::get(&ogg_data);
@@ -383,6 +1078,32 @@ impl Bridge {
             }
         }
 
+        // 超出大小限制的视频尝试压缩, 仍然超出的放弃上传
+        if self.exceeds_media_size(segment_data.1.len()) && ffmpeg::has_ffmpeg() {
+            if let Segment::Video(_) = segment {
+                match ob_helper::compress_video(&segment_data.1).await {
+                    Ok(video_data) => {
+                        kind = infer::get(&video_data);
+                        segment_data.1 = video_data;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to compress video: {}", e);
+                    }
+                }
+            }
+            if self.exceeds_media_size(segment_data.1.len()) {
+                return Err(MediaTooLarge {
+                    size: segment_data.1.len(),
+                    url: match segment {
+                        Segment::Image(seg) => seg.url.clone(),
+                        Segment::Video(seg) => seg.url.clone(),
+                        _ => None,
+                    },
+                }
+                .into());
+            }
+        }
+
         let mut file_name = segment_data.0.clone();
         if let Some(info) = kind {
             if let Some(fixed_name) = fix_filename(&file_name, info.extension()) {
@@ -391,11 +1112,7 @@ impl Bridge {
         }
 
         let size = segment_data.1.len();
-        let mut stream = std::io::Cursor::new(&segment_data.1);
-        let uploaded = self
-            .bot_client
-            .upload_stream(&mut stream, size, file_name.clone())
-            .await?;
+        let uploaded = bot.upload_bytes(&segment_data.1, file_name.clone()).await?;
 
         // TODO: 针对图片返回width和height
         let (width, height) = match segment {
@@ -419,83 +1136,332 @@ impl Bridge {
         })
     }
 
-    pub async fn get_remote_chat(
-        &self,
-        endpoint: &Endpoint,
-        chat_type: &ChatType,
-        target_id: &str,
-    ) -> Result<Arc<ChatModel>> {
-        let key = (endpoint.clone(), chat_type.clone(), target_id.to_owned());
-
-        match self.remote_chat_cache.entry(key) {
-            dashmap::Entry::Occupied(entry) => Ok(entry.get().clone()),
-            dashmap::Entry::Vacant(entry) => {
-                let model = entities::remote_chat::Entity::find()
-                    .filter(entities::remote_chat::Column::Endpoint.eq(endpoint))
-                    .filter(entities::remote_chat::Column::ChatType.eq(chat_type))
-                    .filter(entities::remote_chat::Column::TargetId.eq(target_id))
-                    .one(&self.db)
-                    .await?;
+    // 下载网络图片后上传到Telegram, 用于头像和封面等
+    pub async fn upload_url(&self, bot: &TelegramBot, url: &str) -> Result<UploadedInfo> {
+        let (mut file_name, data) = self.fetch_file(url).await?;
 
-                match model {
-                    Some(model) => {
-                        let value = Arc::new(model);
-                        entry.insert(value.clone());
-                        Ok(value)
-                    }
-                    None => match &chat_type {
-                        ChatType::Private => {
-                            let info = self
-                                .get_stranger_info(endpoint, target_id.to_owned(), true)
-                                .await?;
-                            let model = self.save_remote_private_chat(endpoint, info).await?;
-                            let value = Arc::new(model);
-                            entry.insert(value.clone());
-                            Ok(value)
-                        }
-                        ChatType::Group => {
-                            let info = self
-                                .get_group_info(endpoint, target_id.to_owned(), true)
-                                .await?;
-                            let model = self.save_remote_group_chat(endpoint, info).await?;
-                            let value = Arc::new(model);
-                            entry.insert(value.clone());
-                            Ok(value)
-                        }
-                    },
-                }
+        let kind = infer::get(&data);
+        if let Some(info) = kind {
+            if let Some(fixed_name) = fix_filename(&file_name, info.extension()) {
+                file_name = fixed_name;
             }
         }
-    }
 
-    pub async fn get_tg_chat(&self, packed_type: PackedType, chat_id: i64) -> Result<Arc<Chat>> {
-        match self.tg_chat_cache.entry((packed_type, chat_id)) {
-            dashmap::Entry::Occupied(entry) => Ok(entry.get().clone()),
-            dashmap::Entry::Vacant(entry) => {
-                let packed_chat = PackedChat {
-                    ty: packed_type,
-                    id: chat_id,
-                    access_hash: Some(0),
-                };
-                let chat = Arc::new(self.bot_client.unpack_chat(packed_chat).await?);
-                entry.insert(chat.clone());
-                Ok(chat)
-            }
-        }
-    }
+        let size = data.len();
+        let uploaded = bot.upload_bytes(&data, file_name.clone()).await?;
 
-    pub async fn find_message_by_remote(
-        &self,
-        remote_chat_id: i64,
-        message_id: &str,
-    ) -> Result<Option<entities::message::Model>> {
-        Ok(entities::message::Entity::find()
-            .filter(entities::message::Column::RemoteChatId.eq(remote_chat_id))
+        let (mime_type, (width, height)) = match kind {
+            Some(info) => (
+                info.mime_type().to_string(),
+                ob_helper::image_size(&data, info.mime_type()),
+            ),
+            None => ("application/octet-stream".to_string(), (0, 0)),
+        };
+
+        Ok(UploadedInfo {
+            uploaded,
+            file_name,
+            file_size: size,
+            mime_type,
+            width,
+            height,
+        })
+    }
+
+    // 下载群文件后上传到Telegram, 超出大小限制时附带下载链接
+    pub async fn upload_group_file(
+        &self,
+        bot: &TelegramBot,
+        endpoint: &Endpoint,
+        group_id: &str,
+        file_id: &str,
+        busid: i64,
+        file_name: &str,
+    ) -> Result<UploadedInfo> {
+        let url = self
+            .get_group_file_url(endpoint, group_id.to_owned(), file_id.to_owned(), busid)
+            .await?
+            .url
+            .clone();
+        let (_, data) = self.fetch_file(&url).await?;
+        if self.exceeds_media_size(data.len()) {
+            return Err(MediaTooLarge {
+                size: data.len(),
+                url: Some(url),
+            }
+            .into());
+        }
+
+        let size = data.len();
+        let uploaded = bot.upload_bytes(&data, file_name.to_owned()).await?;
+
+        Ok(UploadedInfo {
+            uploaded,
+            file_name: file_name.to_owned(),
+            file_size: size,
+            mime_type: match infer::get(&data) {
+                Some(info) => info.mime_type().to_string(),
+                None => "application/octet-stream".to_string(),
+            },
+            width: 0,
+            height: 0,
+        })
+    }
+
+    pub fn bots(&self) -> &[TelegramBot] {
+        &self.bots
+    }
+
+    pub async fn reset_session(&self, index: usize) -> Result<()> {
+        let bot = self.bots.get(index).context("bot not found")?;
+        bot.transport().reset_session().await
+    }
+
+    pub fn exceeds_media_size(&self, size: usize) -> bool {
+        self.max_media_size.is_some_and(|max_size| size > max_size)
+    }
+
+    // 从数据库加载所有远端对话到缓存
+    pub async fn prime_remote_chat_cache(&self) -> Result<usize> {
+        let models = entities::remote_chat::Entity::find().all(&self.db).await?;
+        let count = models.len();
+        for model in models {
+            self.remote_chat_cache.insert(
+                (
+                    model.endpoint.clone(),
+                    model.chat_type.clone(),
+                    model.target_id.clone(),
+                ),
+                Arc::new(model),
+            );
+        }
+
+        Ok(count)
+    }
+
+    pub async fn load_endpoint_aliases(&self) -> Result<usize> {
+        let models = entities::endpoint_alias::Entity::find()
+            .all(&self.db)
+            .await?;
+        let count = models.len();
+        for model in models {
+            self.endpoint_aliases.insert(model.endpoint, model.alias);
+        }
+
+        Ok(count)
+    }
+
+    pub fn endpoint_alias(&self, endpoint: &Endpoint) -> Option<String> {
+        self.endpoint_aliases
+            .get(endpoint)
+            .map(|alias| alias.clone())
+    }
+
+    // 有别名时显示为 别名 (端点)
+    pub fn endpoint_name(&self, endpoint: &Endpoint) -> String {
+        match self.endpoint_aliases.get(endpoint) {
+            Some(alias) => format!("{} ({})", *alias, endpoint),
+            None => endpoint.to_string(),
+        }
+    }
+
+    // 设置端点的别名, 为空时删除
+    pub async fn set_endpoint_alias(&self, endpoint: &Endpoint, alias: &str) -> Result<()> {
+        if alias.is_empty() {
+            entities::endpoint_alias::Entity::delete_many()
+                .filter(entities::endpoint_alias::Column::Endpoint.eq(endpoint))
+                .exec(&self.db)
+                .await?;
+            self.endpoint_aliases.remove(endpoint);
+            return Ok(());
+        }
+
+        let model = entities::endpoint_alias::ActiveModel {
+            endpoint: Set(endpoint.clone()),
+            alias: Set(alias.to_owned()),
+            created_at: Set(Utc::now().timestamp()),
+            updated_at: Set(Utc::now().timestamp()),
+            ..Default::default()
+        };
+        entities::endpoint_alias::Entity::insert(model)
+            .on_conflict(
+                sea_query::OnConflict::column(entities::endpoint_alias::Column::Endpoint)
+                    .update_columns([
+                        entities::endpoint_alias::Column::Alias,
+                        entities::endpoint_alias::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await?;
+        self.endpoint_aliases
+            .insert(endpoint.clone(), alias.to_owned());
+
+        Ok(())
+    }
+
+    pub async fn list_canned_replies(&self) -> Result<Vec<entities::canned_reply::Model>> {
+        Ok(entities::canned_reply::Entity::find()
+            .order_by_asc(entities::canned_reply::Column::Name)
+            .all(&self.db)
+            .await?)
+    }
+
+    pub async fn find_canned_reply(
+        &self,
+        name: &str,
+    ) -> Result<Option<entities::canned_reply::Model>> {
+        Ok(entities::canned_reply::Entity::find()
+            .filter(entities::canned_reply::Column::Name.eq(name))
+            .one(&self.db)
+            .await?)
+    }
+
+    // 保存快捷回复, 同名的覆盖原有内容
+    pub async fn save_canned_reply(&self, name: &str, content: &str) -> Result<()> {
+        let model = entities::canned_reply::ActiveModel {
+            name: Set(name.to_owned()),
+            content: Set(content.to_owned()),
+            created_at: Set(Utc::now().timestamp()),
+            updated_at: Set(Utc::now().timestamp()),
+            ..Default::default()
+        };
+        entities::canned_reply::Entity::insert(model)
+            .on_conflict(
+                sea_query::OnConflict::column(entities::canned_reply::Column::Name)
+                    .update_columns([
+                        entities::canned_reply::Column::Content,
+                        entities::canned_reply::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_canned_reply(&self, name: &str) -> Result<bool> {
+        let result = entities::canned_reply::Entity::delete_many()
+            .filter(entities::canned_reply::Column::Name.eq(name))
+            .exec(&self.db)
+            .await?;
+
+        Ok(result.rows_affected > 0)
+    }
+
+    pub async fn get_remote_chat(
+        &self,
+        endpoint: &Endpoint,
+        chat_type: &ChatType,
+        target_id: &str,
+    ) -> Result<Arc<ChatModel>> {
+        let key = (endpoint.clone(), chat_type.clone(), target_id.to_owned());
+
+        match self.remote_chat_cache.entry(key) {
+            dashmap::Entry::Occupied(entry) => Ok(entry.get().clone()),
+            dashmap::Entry::Vacant(entry) => {
+                let model = entities::remote_chat::Entity::find()
+                    .filter(entities::remote_chat::Column::Endpoint.eq(endpoint))
+                    .filter(entities::remote_chat::Column::ChatType.eq(chat_type))
+                    .filter(entities::remote_chat::Column::TargetId.eq(target_id))
+                    .one(&self.db)
+                    .await?;
+
+                match model {
+                    Some(model) => {
+                        let value = Arc::new(model);
+                        entry.insert(value.clone());
+                        Ok(value)
+                    }
+                    None => match &chat_type {
+                        ChatType::Private => {
+                            let info = self
+                                .get_stranger_info(endpoint, target_id.to_owned(), true)
+                                .await?;
+                            let model = self.save_remote_private_chat(endpoint, info).await?;
+                            let value = Arc::new(model);
+                            entry.insert(value.clone());
+                            Ok(value)
+                        }
+                        ChatType::Group => {
+                            let info = self
+                                .get_group_info(endpoint, target_id.to_owned(), true)
+                                .await?;
+                            let model = self.save_remote_group_chat(endpoint, info).await?;
+                            let value = Arc::new(model);
+                            entry.insert(value.clone());
+                            Ok(value)
+                        }
+                        ChatType::Guild => {
+                            let model = self.save_remote_guild_chat(endpoint, target_id).await?;
+                            let value = Arc::new(model);
+                            entry.insert(value.clone());
+                            Ok(value)
+                        }
+                    },
+                }
+            }
+        }
+    }
+
+    // 子频道名称从所属频道的子频道列表中查找, 找不到时使用ID
+    async fn save_remote_guild_chat(
+        &self,
+        endpoint: &Endpoint,
+        target_id: &str,
+    ) -> Result<ChatModel> {
+        let (guild_id, channel_id) = split_guild_target(target_id)?;
+        let name = match self.get_guild_channel_list(endpoint, guild_id, true).await {
+            Ok(channels) => channels
+                .iter()
+                .find(|channel| channel.channel_id == channel_id)
+                .map(|channel| channel.display_name()),
+            Err(e) => {
+                tracing::warn!("Failed to get guild channel list: {}", e);
+                None
+            }
+        };
+
+        let model = entities::remote_chat::ActiveModel {
+            endpoint: Set(endpoint.to_owned()),
+            chat_type: Set(ChatType::Guild),
+            target_id: Set(target_id.to_owned()),
+            name: Set(name.unwrap_or_else(|| target_id.to_owned())),
+            ..Default::default()
+        };
+        Ok(model.insert(&self.db).await?)
+    }
+
+    pub async fn find_message_by_remote(
+        &self,
+        remote_chat_id: i64,
+        message_id: &str,
+    ) -> Result<Option<entities::message::Model>> {
+        Ok(entities::message::Entity::find()
+            .filter(entities::message::Column::RemoteChatId.eq(remote_chat_id))
             .filter(entities::message::Column::RemoteMsgId.eq(message_id))
             .one(&self.db)
             .await?)
     }
 
+    // 查找远端对话最新的消息, 跳过本地生成的提示以及待发送和发送失败的消息
+    pub async fn find_latest_message_by_remote(
+        &self,
+        remote_chat_id: i64,
+    ) -> Result<Option<entities::message::Model>> {
+        let mut query = entities::message::Entity::find()
+            .filter(entities::message::Column::RemoteChatId.eq(remote_chat_id));
+        for prefix in entities::message::SYNTHETIC_MSG_ID_PREFIXES {
+            query = query
+                .filter(entities::message::Column::RemoteMsgId.not_like(format!("{}%", prefix)));
+        }
+        Ok(query
+            .order_by_desc(entities::message::Column::Id)
+            .one(&self.db)
+            .await?)
+    }
+
     pub async fn find_message_by_tg(
         &self,
         tg_chat_id: i64,
@@ -527,22 +1493,34 @@ impl Bridge {
     pub async fn find_link_by_tg(
         &self,
         tg_chat_id: i64,
+        tg_topic_id: i32,
     ) -> Result<Option<(entities::link::Model, Option<entities::remote_chat::Model>)>> {
         Ok(entities::link::Entity::find()
             .filter(entities::link::Column::TgChatId.eq(tg_chat_id))
+            .filter(entities::link::Column::TgTopicId.eq(tg_topic_id))
             .find_also_related(entities::remote_chat::Entity)
             .one(&self.db)
             .await?)
     }
 
-    pub async fn find_archive_by_endpoint(
+    // 按归档规则查找远端对话所在的归档群, 指定了规则的优先于匹配所有对话的
+    pub async fn find_archive(
         &self,
         endpoint: &Endpoint,
+        remote_chat: &ChatModel,
     ) -> Result<Option<entities::archive::Model>> {
-        Ok(entities::archive::Entity::find()
+        let archives = entities::archive::Entity::find()
             .filter(entities::archive::Column::Endpoint.eq(endpoint))
-            .one(&self.db)
-            .await?)
+            .order_by_asc(entities::archive::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        Ok(archives
+            .iter()
+            .filter(|archive| !archive.rule.is_empty())
+            .chain(archives.iter().filter(|archive| archive.rule.is_empty()))
+            .find(|archive| archive.matches(remote_chat))
+            .cloned())
     }
 
     pub async fn find_archive_by_tg(
@@ -563,10 +1541,1292 @@ impl Bridge {
         }
     }
 
-    pub async fn create_archive(&self, endpoint: &Endpoint, tg_chat_id: i64) -> Result<()> {
+    // 查找Telegram消息所在的链接群或归档Topic对应的远端对话
+    pub async fn find_remote_chat_by_tg(&self, message: &Message) -> Result<Option<ChatModel>> {
+        let tg_chat_id = message.chat().id();
+        let tg_topic_id = tg_helper::get_forum_topic_id(message);
+        if let Some((_, remote_chat)) = self.find_link_by_tg(tg_chat_id, tg_topic_id).await? {
+            return Ok(remote_chat);
+        }
+
+        if let Some(tl::enums::MessageReplyHeader::Header(header)) = message.reply_header() {
+            if header.forum_topic {
+                if let Some(tg_topic_id) = header.reply_to_top_id.or(header.reply_to_msg_id) {
+                    return self.find_archive_by_tg(tg_chat_id, tg_topic_id).await;
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    // 开启或关闭Topic的私聊中转, 返回之前的私聊对话
+    pub fn set_dm_relay(
+        &self,
+        tg_chat_id: i64,
+        tg_topic_id: i32,
+        remote_chat: Option<Arc<ChatModel>>,
+    ) -> Option<Arc<ChatModel>> {
+        match remote_chat {
+            Some(remote_chat) => self
+                .dm_relays
+                .insert((tg_chat_id, tg_topic_id), remote_chat),
+            None => self
+                .dm_relays
+                .remove(&(tg_chat_id, tg_topic_id))
+                .map(|(_, remote_chat)| remote_chat),
+        }
+    }
+
+    pub fn get_dm_relay(&self, tg_chat_id: i64, tg_topic_id: i32) -> Option<Arc<ChatModel>> {
+        self.dm_relays
+            .get(&(tg_chat_id, tg_topic_id))
+            .map(|remote_chat| remote_chat.clone())
+    }
+
+    // 暂存等待确认的消息, 返回预览ID
+    pub fn put_pending_send(&self, remote_chat_id: i64, messages: Vec<Message>) -> String {
+        let key = Uuid::new_v4().simple().to_string();
+        self.pending_sends.insert(
+            key.clone(),
+            (Utc::now().timestamp(), remote_chat_id, messages),
+        );
+        key
+    }
+
+    pub fn take_pending_send(&self, key: &str) -> Option<(i64, Vec<Message>)> {
+        self.pending_sends
+            .remove(key)
+            .map(|(_, (_, remote_chat_id, messages))| (remote_chat_id, messages))
+    }
+
+    pub async fn create_archive(
+        &self,
+        endpoint: &Endpoint,
+        tg_chat_id: i64,
+        tg_chat_type: PackedType,
+        rule: &str,
+    ) -> Result<()> {
         let entity = entities::archive::ActiveModel {
             endpoint: Set(endpoint.to_owned()),
             tg_chat_id: Set(tg_chat_id),
+            tg_chat_type: Set(tg_chat_type as i32),
+            rule: Set(rule.to_owned()),
+            ..Default::default()
+        };
+        entity.insert(&self.db).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_archive(&self, id: i64) -> Result<()> {
+        // 删除关联的Topic
+        entities::topic::Entity::delete_many()
+            .filter(entities::topic::Column::ArchiveId.eq(id))
+            .exec(&self.db)
+            .await?;
+
+        // 删除Archive
+        entities::archive::Entity::delete_by_id(id)
+            .exec(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_topic(&self, id: i64) -> Result<()> {
+        entities::topic::Entity::delete_by_id(id)
+            .exec(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    // 移除远端对话已失效的Topic, 下次发送时重新创建
+    pub async fn delete_topic_by_remote(&self, remote_chat_id: i64) -> Result<()> {
+        entities::topic::Entity::delete_many()
+            .filter(entities::topic::Column::RemoteChatId.eq(remote_chat_id))
+            .exec(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_or_create_topic(
+        &self,
+        archive: &entities::archive::Model,
+        remote_chat: &entities::remote_chat::Model,
+    ) -> Result<i32> {
+        // 查找已有的Topic
+        if let Some(topic) = entities::topic::Entity::find()
+            .filter(entities::topic::Column::ArchiveId.eq(archive.id))
+            .filter(entities::topic::Column::RemoteChatId.eq(remote_chat.id))
+            .one(&self.db)
+            .await?
+        {
+            return Ok(topic.tg_topic_id);
+        }
+
+        let tg_topic_id = self
+            .create_forum_topic(&archive.endpoint, archive.tg_chat_id, remote_chat)
+            .await?;
+        self.create_topic(archive.id, tg_topic_id, remote_chat.id)
+            .await?;
+
+        Ok(tg_topic_id)
+    }
+
+    // 由端点对应的Bot在话题群中创建远端对话的Topic, 返回Topic ID
+    async fn create_forum_topic(
+        &self,
+        endpoint: &Endpoint,
+        tg_chat_id: i64,
+        remote_chat: &ChatModel,
+    ) -> Result<i32> {
+        let bot = self.get_bot(endpoint);
+        let tg_chat = bot.get_chat(PackedType::Megagroup, tg_chat_id).await?;
+
+        bot.transport()
+            .create_forum_topic(
+                tg_chat.pack(),
+                remote_chat.topic_title(),
+                self.topic_icon(remote_chat),
+            )
+            .await
+    }
+
+    // 为没有链接和归档的私聊在话题群中创建对话窗口并链接, 未配置时返回None
+    pub async fn open_conversation_window(
+        &self,
+        remote_chat: &ChatModel,
+    ) -> Result<Option<(i64, i32)>> {
+        let Some(config) = self.conversation_window() else {
+            return Ok(None);
+        };
+        let (packed_type, tg_chat_id) = tg_helper::unpack_bot_api_id(config.chat);
+        if packed_type != PackedType::Megagroup {
+            return Err(anyhow::anyhow!(
+                "Conversation window chat {} is not a forum group",
+                config.chat
+            ));
+        }
+
+        let tg_topic_id = self
+            .create_forum_topic(&remote_chat.endpoint, tg_chat_id, remote_chat)
+            .await?;
+        self.create_link(
+            PackedType::Megagroup,
+            tg_chat_id,
+            tg_topic_id,
+            remote_chat.id,
+        )
+        .await?;
+
+        // 链接被手动删除后留下的旧记录
+        entities::conversation_window::Entity::delete_many()
+            .filter(entities::conversation_window::Column::RemoteChatId.eq(remote_chat.id))
+            .exec(&self.db)
+            .await?;
+        let entity = entities::conversation_window::ActiveModel {
+            remote_chat_id: Set(remote_chat.id),
+            tg_chat_id: Set(tg_chat_id),
+            tg_topic_id: Set(tg_topic_id),
+            ..Default::default()
+        };
+        entity.insert(&self.db).await?;
+
+        Ok(Some((tg_chat_id, tg_topic_id)))
+    }
+
+    // 删除超过期限没有消息的对话窗口及其Topic和链接, 返回删除的数量
+    pub async fn cleanup_conversation_windows(&self) -> Result<usize> {
+        let Some(config) = self.conversation_window() else {
+            return Ok(0);
+        };
+        let inactive_days = config.inactive_days.unwrap_or(WINDOW_INACTIVE_DAYS);
+        let cutoff = Utc::now().timestamp() - inactive_days * 24 * 60 * 60;
+
+        let mut count = 0;
+        for (window, remote_chat) in entities::conversation_window::Entity::find()
+            .find_also_related(entities::remote_chat::Entity)
+            .filter(entities::conversation_window::Column::CreatedAt.lt(cutoff))
+            .all(&self.db)
+            .await?
+        {
+            // 期限内仍有消息的保留
+            if entities::message::Entity::find()
+                .filter(entities::message::Column::RemoteChatId.eq(window.remote_chat_id))
+                .filter(entities::message::Column::CreatedAt.gte(cutoff))
+                .one(&self.db)
+                .await?
+                .is_some()
+            {
+                continue;
+            }
+
+            // 链接已被手动删除或修改的只删除记录
+            let link = self
+                .find_link_by_remote(window.remote_chat_id)
+                .await?
+                .filter(|link| {
+                    link.tg_chat_id == window.tg_chat_id && link.tg_topic_id == window.tg_topic_id
+                });
+            if let (Some(link), Some(remote_chat)) = (link, remote_chat) {
+                self.delete_link(link.id).await?;
+                let bot = self.get_bot(&remote_chat.endpoint);
+                let tg_chat = bot
+                    .get_chat(PackedType::Megagroup, window.tg_chat_id)
+                    .await?;
+                if let Err(e) = bot
+                    .transport()
+                    .delete_forum_topic(tg_chat.pack(), window.tg_topic_id)
+                    .await
+                {
+                    tracing::warn!("Failed to delete conversation window topic: {}", e);
+                }
+            }
+
+            entities::conversation_window::Entity::delete_by_id(window.id)
+                .exec(&self.db)
+                .await?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    pub async fn rename_remote_chat(&self, remote_chat: &ChatModel, alias: &str) -> Result<()> {
+        let model = entities::remote_chat::ActiveModel {
+            id: Set(remote_chat.id),
+            alias: Set(alias.to_owned()),
+            ..Default::default()
+        };
+        let model = model.update(&self.db).await?;
+
+        self.remote_chat_cache.remove(&model.to_id());
+
+        // 同步修改已有的Topic标题
+        if let Some((topic, Some(archive))) = entities::topic::Entity::find()
+            .find_also_related(entities::archive::Entity)
+            .filter(entities::topic::Column::RemoteChatId.eq(remote_chat.id))
+            .one(&self.db)
+            .await?
+        {
+            self.edit_topic(&archive, topic.tg_topic_id, Some(model.topic_title()), None)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // 按平台和对话类型配置的Topic图标
+    pub fn topic_icon(&self, remote_chat: &entities::remote_chat::Model) -> Option<i64> {
+        let platform = remote_chat.endpoint.platform.to_string();
+        let chat_type = remote_chat.chat_type.to_string();
+        let settings = self.settings.read().unwrap();
+        [format!("{}.{}", platform, chat_type), platform, chat_type]
+            .iter()
+            .find_map(|key| settings.topic_icons.get(key).copied())
+    }
+
+    // 修改远端对话所在Topic的图标, 0为默认图标, 没有Topic时返回false
+    pub async fn set_topic_icon(&self, remote_chat_id: i64, icon_emoji_id: i64) -> Result<bool> {
+        let Some((topic, Some(archive))) = entities::topic::Entity::find()
+            .find_also_related(entities::archive::Entity)
+            .filter(entities::topic::Column::RemoteChatId.eq(remote_chat_id))
+            .one(&self.db)
+            .await?
+        else {
+            return Ok(false);
+        };
+
+        self.edit_topic(&archive, topic.tg_topic_id, None, Some(icon_emoji_id))
+            .await?;
+
+        Ok(true)
+    }
+
+    async fn edit_topic(
+        &self,
+        archive: &entities::archive::Model,
+        tg_topic_id: i32,
+        title: Option<String>,
+        icon_emoji_id: Option<i64>,
+    ) -> Result<()> {
+        let bot = self.get_bot(&archive.endpoint);
+        let tg_chat = bot
+            .get_chat(PackedType::Megagroup, archive.tg_chat_id)
+            .await?;
+
+        bot.transport()
+            .edit_forum_topic(tg_chat.pack(), tg_topic_id, title, icon_emoji_id)
+            .await
+    }
+
+    // 导出全部链接为CSV
+    pub async fn export_links(&self) -> Result<Vec<u8>> {
+        let links = entities::link::Entity::find()
+            .find_also_related(entities::remote_chat::Entity)
+            .order_by_asc(entities::link::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for (link, remote_chat) in links {
+            let Some(remote_chat) = remote_chat else {
+                continue;
+            };
+            writer.serialize(LinkRecord {
+                endpoint: remote_chat.endpoint.to_string(),
+                chat_type: remote_chat.chat_type.to_string(),
+                target_id: remote_chat.target_id.clone(),
+                name: remote_chat.display_name().to_owned(),
+                tg_chat_type: link.tg_chat_type,
+                tg_chat_id: link.tg_chat_id,
+                tg_topic_id: link.tg_topic_id,
+            })?;
+        }
+
+        Ok(writer.into_inner()?)
+    }
+
+    // 从CSV批量创建链接, 已存在的相同链接跳过, 其余错误按行记录后继续
+    pub async fn import_links(&self, data: &[u8]) -> Result<LinkImportReport> {
+        let mut report = LinkImportReport::default();
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(data);
+        for (index, record) in reader.deserialize::<LinkRecord>().enumerate() {
+            // 第一行为表头
+            let line = index + 2;
+            match self.import_link(record).await {
+                Ok(true) => report.created += 1,
+                Ok(false) => report.skipped += 1,
+                Err(e) => report.failures.push((line, e.to_string())),
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn import_link(&self, record: csv::Result<LinkRecord>) -> Result<bool> {
+        let record = record?;
+        let endpoint = record
+            .endpoint
+            .parse::<Endpoint>()
+            .map_err(|e| anyhow::anyhow!(e))?;
+        let chat_type = record
+            .chat_type
+            .parse::<ChatType>()
+            .map_err(|e| anyhow::anyhow!(e))?;
+        let tg_chat_type = tg_helper::unpack_chat_type(record.tg_chat_type);
+        if tg_chat_type as i32 != record.tg_chat_type {
+            return Err(anyhow::anyhow!(
+                "invalid tg_chat_type: {}",
+                record.tg_chat_type
+            ));
+        }
+
+        let remote_chat = self
+            .get_remote_chat(&endpoint, &chat_type, &record.target_id)
+            .await
+            .with_context(|| format!("remote chat {} not found", record.target_id))?;
+
+        if let Some(link) = self.find_link_by_remote(remote_chat.id).await? {
+            if link.tg_chat_id == record.tg_chat_id && link.tg_topic_id == record.tg_topic_id {
+                return Ok(false);
+            }
+            return Err(anyhow::anyhow!(
+                "{} is already linked to {}",
+                remote_chat.display_name(),
+                link.tg_chat_id
+            ));
+        }
+        if self
+            .find_link_by_tg(record.tg_chat_id, record.tg_topic_id)
+            .await?
+            .is_some()
+        {
+            return Err(anyhow::anyhow!(
+                "telegram chat {} is already linked",
+                record.tg_chat_id
+            ));
+        }
+
+        // 确认Bot仍在Telegram群中
+        let tg_chat = self
+            .get_bot(&endpoint)
+            .get_chat(tg_chat_type, record.tg_chat_id)
+            .await?;
+        if !tg_chat.available {
+            return Err(anyhow::anyhow!(
+                "telegram chat {} is not available",
+                record.tg_chat_id
+            ));
+        }
+
+        self.create_link(
+            tg_chat_type,
+            record.tg_chat_id,
+            record.tg_topic_id,
+            remote_chat.id,
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    pub async fn create_link(
+        &self,
+        tg_chat_type: PackedType,
+        tg_chat_id: i64,
+        tg_topic_id: i32,
+        remote_chat_id: i64,
+    ) -> Result<()> {
+        let entity = entities::link::ActiveModel {
+            tg_chat_type: Set(tg_chat_type as i32),
+            tg_chat_id: Set(tg_chat_id),
+            tg_topic_id: Set(tg_topic_id),
+            remote_chat_id: Set(remote_chat_id),
+            ..Default::default()
+        };
+        entity.insert(&self.db).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_link(&self, id: i64) -> Result<()> {
+        entities::link::Entity::delete_by_id(id)
+            .exec(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    // Bot被移出Telegram群后删除该群的链接和归档, 返回被删除记录的说明
+    pub async fn remove_tg_chat(&self, tg_chat_id: i64) -> Result<Vec<String>> {
+        let mut removed = Vec::new();
+
+        for (link, remote_chat) in entities::link::Entity::find()
+            .filter(entities::link::Column::TgChatId.eq(tg_chat_id))
+            .find_also_related(entities::remote_chat::Entity)
+            .all(&self.db)
+            .await?
+        {
+            removed.push(format!(
+                "🔗 {}",
+                remote_chat.map_or(link.remote_chat_id.to_string(), |c| c.topic_title())
+            ));
+            self.delete_link(link.id).await?;
+        }
+
+        for archive in entities::archive::Entity::find()
+            .filter(entities::archive::Column::TgChatId.eq(tg_chat_id))
+            .all(&self.db)
+            .await?
+        {
+            removed.push(format!(
+                "🗄 {} ({})",
+                archive.endpoint,
+                entities::archive::Model::rule_name(&archive.rule)
+            ));
+            self.delete_archive(archive.id).await?;
+        }
+
+        // 清除缓存的对话信息
+        for bot in &self.bots {
+            bot.tg_chat_cache.retain(|(_, id), _| *id != tg_chat_id);
+        }
+
+        Ok(removed)
+    }
+
+    pub async fn get_chat_settings(
+        &self,
+        remote_chat_id: i64,
+    ) -> Result<Option<Arc<SettingsModel>>> {
+        match self.chat_settings_cache.entry(remote_chat_id) {
+            dashmap::Entry::Occupied(entry) => Ok(entry.get().clone()),
+            dashmap::Entry::Vacant(entry) => {
+                let settings = entities::chat_settings::Entity::find()
+                    .filter(entities::chat_settings::Column::RemoteChatId.eq(remote_chat_id))
+                    .one(&self.db)
+                    .await?
+                    .map(Arc::new);
+                entry.insert(settings.clone());
+                Ok(settings)
+            }
+        }
+    }
+
+    // 更新对话设置的指定字段, 不存在时创建
+    pub async fn update_chat_settings(
+        &self,
+        remote_chat_id: i64,
+        mut model: entities::chat_settings::ActiveModel,
+        mut columns: Vec<entities::chat_settings::Column>,
+    ) -> Result<()> {
+        let timestamp = Utc::now().timestamp();
+        model.remote_chat_id = Set(remote_chat_id);
+        model.created_at = Set(timestamp);
+        model.updated_at = Set(timestamp);
+        columns.push(entities::chat_settings::Column::UpdatedAt);
+
+        entities::chat_settings::Entity::insert(model)
+            .on_conflict(
+                sea_query::OnConflict::column(entities::chat_settings::Column::RemoteChatId)
+                    .update_columns(columns)
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await?;
+
+        self.chat_settings_cache.remove(&remote_chat_id);
+
+        Ok(())
+    }
+
+    pub async fn mute_chat(&self, remote_chat_id: i64, muted_until: i64) -> Result<()> {
+        self.update_chat_settings(
+            remote_chat_id,
+            entities::chat_settings::ActiveModel {
+                muted_until: Set(muted_until),
+                ..Default::default()
+            },
+            vec![entities::chat_settings::Column::MutedUntil],
+        )
+        .await
+    }
+
+    pub async fn is_muted(&self, remote_chat_id: i64) -> Result<bool> {
+        Ok(self
+            .get_chat_settings(remote_chat_id)
+            .await?
+            .is_some_and(|settings| settings.muted_until > Utc::now().timestamp()))
+    }
+
+    pub async fn set_translate(&self, remote_chat_id: i64, translate: &str) -> Result<()> {
+        self.update_chat_settings(
+            remote_chat_id,
+            entities::chat_settings::ActiveModel {
+                translate: Set(translate.to_owned()),
+                ..Default::default()
+            },
+            vec![entities::chat_settings::Column::Translate],
+        )
+        .await
+    }
+
+    pub async fn set_transcribe(&self, remote_chat_id: i64, transcribe: &str) -> Result<()> {
+        self.update_chat_settings(
+            remote_chat_id,
+            entities::chat_settings::ActiveModel {
+                transcribe: Set(transcribe.to_owned()),
+                ..Default::default()
+            },
+            vec![entities::chat_settings::Column::Transcribe],
+        )
+        .await
+    }
+
+    // 下载语音片段并转写为文字
+    pub async fn transcribe_record(
+        &self,
+        endpoint: &Endpoint,
+        segment: &Segment,
+        language: &str,
+    ) -> Result<String> {
+        let service = self
+            .transcribe
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Transcription provider is not configured"))?;
+        let (_, data) = self.download_segment(endpoint, segment).await?;
+
+        service.transcribe(&data, language).await
+    }
+
+    pub async fn set_media_policy(&self, remote_chat_id: i64, media_policy: &str) -> Result<()> {
+        self.update_chat_settings(
+            remote_chat_id,
+            entities::chat_settings::ActiveModel {
+                media_policy: Set(media_policy.to_owned()),
+                ..Default::default()
+            },
+            vec![entities::chat_settings::Column::MediaPolicy],
+        )
+        .await
+    }
+
+    // 对话中一类媒体的转发方式, 未设置或读取失败时上传
+    pub async fn get_media_mode(&self, remote_chat_id: i64, kind: MediaKind) -> MediaMode {
+        match self.get_chat_settings(remote_chat_id).await {
+            Ok(Some(settings)) => settings.media_mode(kind),
+            _ => MediaMode::Upload,
+        }
+    }
+
+    pub async fn set_member_notice(&self, remote_chat_id: i64, notice: MemberNotice) -> Result<()> {
+        self.update_chat_settings(
+            remote_chat_id,
+            entities::chat_settings::ActiveModel {
+                member_notice: Set(notice.to_string()),
+                ..Default::default()
+            },
+            vec![entities::chat_settings::Column::MemberNotice],
+        )
+        .await
+    }
+
+    // 群成员变动的提示级别, 未设置或读取失败时全部提示
+    pub async fn get_member_notice(&self, remote_chat_id: i64) -> MemberNotice {
+        match self.get_chat_settings(remote_chat_id).await {
+            Ok(Some(settings)) => settings.member_notice(),
+            _ => MemberNotice::All,
+        }
+    }
+
+    pub async fn set_confirm_send(&self, remote_chat_id: i64, confirm_send: bool) -> Result<()> {
+        self.update_chat_settings(
+            remote_chat_id,
+            entities::chat_settings::ActiveModel {
+                confirm_send: Set(confirm_send),
+                ..Default::default()
+            },
+            vec![entities::chat_settings::Column::ConfirmSend],
+        )
+        .await
+    }
+
+    // 发送前是否需要确认, 未设置或读取失败时直接发送
+    pub async fn is_confirm_send(&self, remote_chat_id: i64) -> bool {
+        match self.get_chat_settings(remote_chat_id).await {
+            Ok(Some(settings)) => settings.confirm_send,
+            _ => false,
+        }
+    }
+
+    pub async fn set_honor_notice(&self, remote_chat_id: i64, honor_notice: bool) -> Result<()> {
+        self.update_chat_settings(
+            remote_chat_id,
+            entities::chat_settings::ActiveModel {
+                honor_notice: Set(honor_notice),
+                ..Default::default()
+            },
+            vec![entities::chat_settings::Column::HonorNotice],
+        )
+        .await
+    }
+
+    // 是否转发群荣誉和运气王提示, 未设置或读取失败时转发
+    pub async fn is_honor_notice(&self, remote_chat_id: i64) -> bool {
+        match self.get_chat_settings(remote_chat_id).await {
+            Ok(Some(settings)) => settings.honor_notice,
+            _ => true,
+        }
+    }
+
+    pub async fn set_source_link(&self, remote_chat_id: i64, source_link: bool) -> Result<()> {
+        self.update_chat_settings(
+            remote_chat_id,
+            entities::chat_settings::ActiveModel {
+                source_link: Set(source_link),
+                ..Default::default()
+            },
+            vec![entities::chat_settings::Column::SourceLink],
+        )
+        .await
+    }
+
+    // 是否附上原消息的标识, 未设置或读取失败时不附上
+    pub async fn is_source_link(&self, remote_chat_id: i64) -> bool {
+        match self.get_chat_settings(remote_chat_id).await {
+            Ok(Some(settings)) => settings.source_link,
+            _ => false,
+        }
+    }
+
+    pub async fn set_signature(&self, remote_chat_id: i64, signature: Signature) -> Result<()> {
+        self.update_chat_settings(
+            remote_chat_id,
+            entities::chat_settings::ActiveModel {
+                signature: Set(signature.to_string()),
+                ..Default::default()
+            },
+            vec![entities::chat_settings::Column::Signature],
+        )
+        .await
+    }
+
+    // 发往远端的消息的署名, 未设置或读取失败时保持原样
+    pub async fn get_signature(&self, remote_chat_id: i64) -> Signature {
+        match self.get_chat_settings(remote_chat_id).await {
+            Ok(Some(settings)) => settings.signature(),
+            _ => Signature::Default,
+        }
+    }
+
+    // 对话的内容过滤规则, 无效的正则表达式被忽略
+    pub async fn get_filters(&self, remote_chat_id: i64) -> Result<Arc<Vec<(FilterModel, Regex)>>> {
+        if let Some(filters) = self.filter_cache.get(&remote_chat_id) {
+            return Ok(filters.clone());
+        }
+
+        let filters = Arc::new(
+            entities::filter::Entity::find()
+                .filter(entities::filter::Column::RemoteChatId.eq(remote_chat_id))
+                .order_by_asc(entities::filter::Column::Id)
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .filter_map(|filter| match FilterModel::compile(&filter.pattern) {
+                    Ok(regex) => Some((filter, regex)),
+                    Err(e) => {
+                        tracing::warn!("Invalid filter pattern {}: {}", filter.pattern, e);
+                        None
+                    }
+                })
+                .collect::<Vec<_>>(),
+        );
+        self.filter_cache.insert(remote_chat_id, filters.clone());
+
+        Ok(filters)
+    }
+
+    pub async fn create_filter(
+        &self,
+        remote_chat_id: i64,
+        direction: FilterDirection,
+        action: FilterAction,
+        pattern: &str,
+    ) -> Result<()> {
+        let entity = entities::filter::ActiveModel {
+            remote_chat_id: Set(remote_chat_id),
+            direction: Set(direction.to_string()),
+            action: Set(action.to_string()),
+            pattern: Set(pattern.to_owned()),
+            ..Default::default()
+        };
+        entity.insert(&self.db).await?;
+        self.filter_cache.remove(&remote_chat_id);
+
+        Ok(())
+    }
+
+    // 只删除属于该对话的规则, 返回是否删除成功
+    pub async fn delete_filter(&self, remote_chat_id: i64, id: i64) -> Result<bool> {
+        let result = entities::filter::Entity::delete_many()
+            .filter(entities::filter::Column::Id.eq(id))
+            .filter(entities::filter::Column::RemoteChatId.eq(remote_chat_id))
+            .exec(&self.db)
+            .await?;
+        self.filter_cache.remove(&remote_chat_id);
+
+        Ok(result.rows_affected > 0)
+    }
+
+    // 按顺序检查文本消息段, 命中drop规则时立即返回, 命中redact规则的内容替换为***
+    pub async fn apply_filters(
+        &self,
+        remote_chat_id: i64,
+        direction: FilterDirection,
+        segments: &mut [Segment],
+    ) -> Result<FilterVerdict> {
+        let mut verdict = FilterVerdict::Pass;
+        for (filter, regex) in self.get_filters(remote_chat_id).await?.iter() {
+            if !filter.direction().contains(direction) {
+                continue;
+            }
+
+            for segment in segments.iter_mut() {
+                let Segment::Text(text) = segment else {
+                    continue;
+                };
+                if !regex.is_match(&text.text) {
+                    continue;
+                }
+
+                match filter.action() {
+                    FilterAction::Drop => return Ok(FilterVerdict::Drop(filter.pattern.clone())),
+                    FilterAction::Redact => {
+                        text.text = regex.replace_all(&text.text, "***").into_owned();
+                    }
+                    FilterAction::Flag => verdict = FilterVerdict::Flag(filter.pattern.clone()),
+                }
+            }
+        }
+
+        Ok(verdict)
+    }
+
+    // 对话的自动回复规则, 无效的正则表达式被忽略
+    pub async fn get_auto_replies(
+        &self,
+        remote_chat_id: i64,
+    ) -> Result<Arc<Vec<(AutoReplyModel, Regex)>>> {
+        if let Some(rules) = self.auto_reply_cache.get(&remote_chat_id) {
+            return Ok(rules.clone());
+        }
+
+        let rules = Arc::new(
+            entities::auto_reply::Entity::find()
+                .filter(entities::auto_reply::Column::RemoteChatId.eq(remote_chat_id))
+                .order_by_asc(entities::auto_reply::Column::Id)
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .filter_map(|rule| match FilterModel::compile(&rule.pattern) {
+                    Ok(regex) => Some((rule, regex)),
+                    Err(e) => {
+                        tracing::warn!("Invalid auto reply pattern {}: {}", rule.pattern, e);
+                        None
+                    }
+                })
+                .collect::<Vec<_>>(),
+        );
+        self.auto_reply_cache.insert(remote_chat_id, rules.clone());
+
+        Ok(rules)
+    }
+
+    pub async fn create_auto_reply(
+        &self,
+        remote_chat_id: i64,
+        pattern: &str,
+        reply: &str,
+        quiet_hours: &str,
+    ) -> Result<()> {
+        let entity = entities::auto_reply::ActiveModel {
+            remote_chat_id: Set(remote_chat_id),
+            pattern: Set(pattern.to_owned()),
+            reply: Set(reply.to_owned()),
+            quiet_hours: Set(quiet_hours.to_owned()),
+            ..Default::default()
+        };
+        entity.insert(&self.db).await?;
+        self.auto_reply_cache.remove(&remote_chat_id);
+
+        Ok(())
+    }
+
+    // 只删除属于该对话的规则, 返回是否删除成功
+    pub async fn delete_auto_reply(&self, remote_chat_id: i64, id: i64) -> Result<bool> {
+        let result = entities::auto_reply::Entity::delete_many()
+            .filter(entities::auto_reply::Column::Id.eq(id))
+            .filter(entities::auto_reply::Column::RemoteChatId.eq(remote_chat_id))
+            .exec(&self.db)
+            .await?;
+        self.auto_reply_cache.remove(&remote_chat_id);
+
+        Ok(result.rows_affected > 0)
+    }
+
+    // 查找第一条命中文本消息段的自动回复, 跳过不回复时段和冷却中的规则
+    pub async fn match_auto_reply(
+        &self,
+        remote_chat_id: i64,
+        segments: &[Segment],
+    ) -> Result<Option<AutoReplyModel>> {
+        let rules = self.get_auto_replies(remote_chat_id).await?;
+        if rules.is_empty() {
+            return Ok(None);
+        }
+
+        let now = Local::now();
+        let text: String = segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect();
+        for (rule, regex) in rules.iter() {
+            if rule.is_quiet(now.hour()) || !regex.is_match(&text) {
+                continue;
+            }
+            let timestamp = now.timestamp();
+            if self
+                .auto_reply_times
+                .get(&rule.id)
+                .is_some_and(|last| timestamp - *last < AUTO_REPLY_COOLDOWN)
+            {
+                continue;
+            }
+            self.auto_reply_times.insert(rule.id, timestamp);
+            return Ok(Some(rule.clone()));
+        }
+
+        Ok(None)
+    }
+
+    // 发送连接状态, 错误和统计等系统通知, 通知群发送失败时改发给管理员
+    pub async fn send_notice(&self, html: String) -> Result<()> {
+        let bot = self.primary_bot();
+        if let Some(notice_chat) = self.notice_chat {
+            let (packed_type, chat_id) = tg_helper::unpack_bot_api_id(notice_chat);
+            let result = match bot.get_chat(packed_type, chat_id).await {
+                Ok(chat) => bot
+                    .send_message(&*chat, OutgoingMessage::html(html.clone()))
+                    .await
+                    .map(|_| ()),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(_) => return Ok(()),
+                Err(e) => tracing::warn!("Failed to send notice to {}: {}", notice_chat, e),
+            }
+        }
+
+        let chat = bot.get_chat(PackedType::User, self.admin_id).await?;
+        bot.send_message(&*chat, OutgoingMessage::html(html))
+            .await?;
+
+        Ok(())
+    }
+
+    // 通知管理员有消息命中了flag规则
+    pub async fn notify_flagged(
+        &self,
+        remote_chat: &ChatModel,
+        direction: FilterDirection,
+        pattern: &str,
+        content: &str,
+    ) -> Result<()> {
+        let bot = self.primary_bot();
+        let chat = bot.get_chat(PackedType::User, self.admin_id).await?;
+        let language = self.language();
+        let html = i18n::trf(
+            language,
+            "<b>[FLAG] Message {} {} matched filter {}</b>\n{}",
+            &[
+                &i18n::tr(
+                    language,
+                    match direction {
+                        FilterDirection::Out => "to",
+                        _ => "from",
+                    },
+                ),
+                &html_escape::encode_text(&remote_chat.topic_title()),
+                &html_escape::encode_text(pattern),
+                &html_escape::encode_text(content),
+            ],
+        );
+        bot.send_message(&*chat, OutgoingMessage::html(html))
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_digest_interval(&self, remote_chat_id: i64, interval: i64) -> Result<()> {
+        self.update_chat_settings(
+            remote_chat_id,
+            entities::chat_settings::ActiveModel {
+                digest_interval: Set(interval),
+                ..Default::default()
+            },
+            vec![entities::chat_settings::Column::DigestInterval],
+        )
+        .await
+    }
+
+    // 重新读取配置文件, 应用日志级别和可重新加载的设置, 其他配置需要重启后生效
+    pub fn reload_config(&self) -> Result<()> {
+        let config = TeleporterConfig::try_load()?;
+        let settings = DynamicSettings::from_config(&config.telegram, &config.rate_limit())?;
+        (self.log_reloader)(&config.general.log_level).map_err(|e| anyhow::anyhow!(e))?;
+
+        // 频率限制未变化的平台保留原有的限制器状态
+        {
+            let old_settings = self.settings.read().unwrap();
+            let mut rate_limiters = self.remote_rate_limits.write().unwrap();
+            rate_limiters.retain(|platform, _| {
+                settings.remote_rate_limits.get(platform)
+                    == old_settings.remote_rate_limits.get(platform)
+            });
+            for (platform, limiter) in build_rate_limiters(&settings.remote_rate_limits) {
+                rate_limiters.entry(platform).or_insert(limiter);
+            }
+        }
+        *self.settings.write().unwrap() = settings;
+
+        Ok(())
+    }
+
+    pub fn mark_as_read(&self) -> bool {
+        self.settings.read().unwrap().mark_as_read
+    }
+
+    // 管理员的语言, Bot回复和系统通知都发给管理员
+    pub fn language(&self) -> Language {
+        self.language_for(self.admin_id)
+    }
+
+    // 按用户查找语言, 没有单独配置时使用默认语言
+    pub fn language_for(&self, user_id: i64) -> Language {
+        let settings = self.settings.read().unwrap();
+        settings
+            .admin_languages
+            .get(&user_id)
+            .copied()
+            .unwrap_or(settings.language)
+    }
+
+    pub fn command_aliases(&self) -> HashMap<String, String> {
+        self.settings.read().unwrap().command_aliases.clone()
+    }
+
+    pub fn reaction_action(&self, reaction: &str) -> Option<ReactionAction> {
+        self.settings
+            .read()
+            .unwrap()
+            .reaction_actions
+            .get(reaction)
+            .copied()
+    }
+
+    pub fn favorites_chat(&self) -> Option<i64> {
+        self.settings.read().unwrap().favorites_chat
+    }
+
+    // 发往端点的语音格式, 未配置时不转码
+    pub fn voice_format(&self, endpoint: &Endpoint) -> VoiceFormat {
+        let settings = self.settings.read().unwrap();
+        [
+            endpoint.to_string(),
+            endpoint.platform.to_string(),
+            "default".to_string(),
+        ]
+        .iter()
+        .find_map(|key| settings.voice_formats.get(key).copied())
+        .unwrap_or_default()
+    }
+
+    pub fn silk_encoder(&self) -> Option<String> {
+        self.settings.read().unwrap().silk_encoder.clone()
+    }
+
+    pub fn strip_exif(&self) -> bool {
+        self.settings.read().unwrap().strip_exif
+    }
+
+    pub fn default_signature(&self) -> Option<String> {
+        self.settings.read().unwrap().signature.clone()
+    }
+
+    pub fn conversation_window(&self) -> Option<ConversationWindowConfig> {
+        self.settings.read().unwrap().conversation_window
+    }
+
+    // 取出消息中的命令, 别名替换为对应的命令
+    // 非ASCII的别名不会被Telegram识别为命令, 按消息开头的文字匹配
+    pub fn get_command(&self, message: &Message) -> Option<String> {
+        let entity = tg_helper::get_command(message);
+        let command = entity.clone().or_else(|| {
+            message
+                .text()
+                .split_whitespace()
+                .next()
+                .filter(|word| word.starts_with('/'))
+                .map(|word| word.to_string())
+        })?;
+        let name = command.trim_start_matches('/');
+        let settings = self.settings.read().unwrap();
+        match settings.command_aliases.get(name) {
+            Some(target) => Some(format!("/{}", target.trim_start_matches('/'))),
+            None => entity,
+        }
+    }
+
+    // 位置对应的静态地图地址, 未配置时返回None
+    pub fn static_map_url(&self, lat: f64, lon: f64) -> Option<String> {
+        self.settings
+            .read()
+            .unwrap()
+            .static_map
+            .as_ref()
+            .map(|url| {
+                url.replace("{lat}", &format!("{:.6}", lat))
+                    .replace("{lon}", &format!("{:.6}", lon))
+            })
+    }
+
+    pub fn gif_limit(&self, platform: &Platform) -> GifLimitConfig {
+        self.settings
+            .read()
+            .unwrap()
+            .gif_limits
+            .get(platform)
+            .copied()
+            .map(|limit| GifLimitConfig {
+                max_bytes: limit.max_bytes.or(Some(GIF_MAX_BYTES)),
+                ..limit
+            })
+            .unwrap_or(GifLimitConfig {
+                max_duration: GIF_MAX_DURATION,
+                max_frames: GIF_MAX_FRAMES,
+                max_bytes: Some(GIF_MAX_BYTES),
+            })
+    }
+
+    // 记录端点的连接或断开, 返回需要立即发送给管理员的提示
+    pub fn record_reconnect(&self, endpoint: &Endpoint, connected: bool) -> Option<String> {
+        let policy = {
+            let settings = self.settings.read().unwrap();
+            settings
+                .reconnect_notices
+                .get(endpoint)
+                .copied()
+                .unwrap_or(settings.default_reconnect_notice)
+        };
+        if policy.mute {
+            return None;
+        }
+
+        let language = self.language();
+        let notice = format!(
+            "<b>[INFO] {} {}</b>",
+            endpoint,
+            i18n::tr(language, connection_state(connected))
+        );
+        let window = policy.window.unwrap_or(RECONNECT_WINDOW);
+        if window <= 0 {
+            return Some(notice);
+        }
+
+        let now = Utc::now().timestamp();
+        match self.reconnect_states.entry(endpoint.clone()) {
+            // 窗口内的变化只计数
+            dashmap::Entry::Occupied(mut entry) if now < entry.get().window_start + window => {
+                let state = entry.get_mut();
+                state.suppressed += 1;
+                state.disconnects += u32::from(!connected);
+                state.connected = connected;
+                None
+            }
+            entry => {
+                // 上个窗口还未发送的汇总与本次提示一起发送
+                let summary = match &entry {
+                    dashmap::Entry::Occupied(entry) => entry.get().summary(endpoint, language),
+                    dashmap::Entry::Vacant(_) => None,
+                };
+                entry.insert(ReconnectState {
+                    window_start: now,
+                    window,
+                    suppressed: 0,
+                    disconnects: u32::from(!connected),
+                    connected,
+                });
+                match summary {
+                    Some(summary) => Some(format!("{}\n{}", summary, notice)),
+                    None => Some(notice),
+                }
+            }
+        }
+    }
+
+    // 取出抑制窗口已结束的端点的汇总提示
+    pub fn take_reconnect_summaries(&self) -> Vec<String> {
+        let now = Utc::now().timestamp();
+        let language = self.language();
+        let mut summaries = Vec::new();
+        self.reconnect_states.retain(|endpoint, state| {
+            if now < state.window_start + state.window {
+                return true;
+            }
+            summaries.extend(state.summary(endpoint, language));
+            false
+        });
+
+        summaries
+    }
+
+    // 获取群成员列表, 在缓存时间内复用上次的结果
+    pub async fn get_cached_group_members(
+        &self,
+        endpoint: &Endpoint,
+        group_id: &str,
+    ) -> Result<Arc<Vec<MemberInfo>>> {
+        let key = (endpoint.clone(), group_id.to_owned());
+        let now = Utc::now().timestamp();
+        if let Some(entry) = self.group_member_cache.get(&key) {
+            if now - entry.0 < GROUP_MEMBER_TTL {
+                return Ok(entry.1.clone());
+            }
+        }
+
+        let members = self
+            .get_group_member_list(endpoint, group_id.to_owned())
+            .await?;
+        self.group_member_cache.insert(key, (now, members.clone()));
+
+        Ok(members)
+    }
+
+    // 缓存摘要消息, interval为汇总间隔(分钟)
+    pub fn push_digest(&self, key: RemoteChatKey, interval: i64, line: String) {
+        let mut buffer = self
+            .digest_buffer
+            .entry(key)
+            .or_insert_with(|| DigestBuffer {
+                flush_at: Utc::now().timestamp() + interval * 60,
+                lines: Vec::new(),
+            });
+        buffer.lines.push(line);
+    }
+
+    // 取出到期的摘要消息, force为true时取出全部
+    pub fn take_digests(&self, force: bool) -> Vec<(RemoteChatKey, Vec<String>)> {
+        let now = Utc::now().timestamp();
+        let keys: Vec<RemoteChatKey> = self
+            .digest_buffer
+            .iter()
+            .filter(|entry| force || entry.flush_at <= now)
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        keys.into_iter()
+            .filter_map(|key| self.digest_buffer.remove(&key))
+            .map(|(key, buffer)| (key, buffer.lines))
+            .collect()
+    }
+
+    // 缓存相册消息, 返回是否是该相册的第一条消息
+    pub fn push_album(&self, grouped_id: i64, message: Message) -> bool {
+        let mut messages = self.album_buffer.entry(grouped_id).or_default();
+        messages.push(message);
+        messages.len() == 1
+    }
+
+    // 取出相册的全部消息, 按消息ID排序
+    pub fn take_album(&self, grouped_id: i64) -> Vec<Message> {
+        let mut messages = self
+            .album_buffer
+            .remove(&grouped_id)
+            .map(|(_, v)| v)
+            .unwrap_or_default();
+        messages.sort_by_key(|message| message.id());
+        messages
+    }
+
+    #[tracing::instrument(
+        skip_all,
+        fields(remote_chat_id = remote_chat_id, remote_message_id = remote_message_id)
+    )]
+    pub async fn save_message_by_remote(
+        &self,
+        remote_chat_id: i64,
+        remote_message_id: &str,
+        remote_sender_id: &str,
+        telegram_message: &SentMessage,
+        content: &str,
+    ) -> Result<()> {
+        let entity = entities::message::ActiveModel {
+            tg_chat_id: Set(telegram_message.chat_id),
+            tg_msg_id: Set(telegram_message.id),
+            remote_chat_id: Set(remote_chat_id),
+            remote_msg_id: Set(remote_message_id.to_owned()),
+            remote_sender_id: Set(remote_sender_id.to_owned()),
+            content: Set(content.to_owned()),
+            delivery_status: Set(DeliveryStatus::Sent),
             ..Default::default()
         };
         entity.insert(&self.db).await?;
@@ -574,142 +2834,324 @@ impl Bridge {
         Ok(())
     }
 
-    pub async fn delete_archive(&self, id: i64) -> Result<()> {
-        // 删除关联的Topic
-        entities::topic::Entity::delete_many()
-            .filter(entities::topic::Column::ArchiveId.eq(id))
+    // 发送到远端前记录为待发送, remote_message_id 为 pending: 开头的临时ID, 同一组消息在一个事务中写入,
+    // 消息段只在发送失败时记录
+    #[tracing::instrument(
+        skip_all,
+        fields(remote_chat_id = remote_chat_id, remote_message_id = pending_id)
+    )]
+    pub async fn save_pending_messages(
+        &self,
+        remote_chat_id: i64,
+        pending_id: &str,
+        telegram_messages: &[Message],
+        content: &str,
+    ) -> Result<()> {
+        let txn = self.db.begin().await?;
+        for telegram_message in telegram_messages {
+            let entity = entities::message::ActiveModel {
+                tg_chat_id: Set(telegram_message.chat().id()),
+                tg_msg_id: Set(telegram_message.id()),
+                remote_chat_id: Set(remote_chat_id),
+                remote_msg_id: Set(pending_id.to_owned()),
+                remote_sender_id: Set(String::new()),
+                content: Set(content.to_owned()),
+                delivery_status: Set(DeliveryStatus::Pending),
+                ..Default::default()
+            };
+            entity.insert(&txn).await?;
+        }
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    // 上次退出时仍在发送中的消息无法确认结果, 启动时标记为失败, 返回标记的数量
+    pub async fn fail_stale_pending_messages(&self) -> Result<u64> {
+        let result = entities::message::Entity::update_many()
+            .col_expr(
+                entities::message::Column::DeliveryStatus,
+                sea_query::Expr::value(DeliveryStatus::Failed),
+            )
+            .col_expr(
+                entities::message::Column::Error,
+                sea_query::Expr::value("Interrupted by restart"),
+            )
+            .col_expr(
+                entities::message::Column::UpdatedAt,
+                sea_query::Expr::value(Utc::now().timestamp()),
+            )
+            .filter(entities::message::Column::DeliveryStatus.eq(DeliveryStatus::Pending))
+            .filter(entities::message::Column::RemoteMsgId.starts_with("pending:"))
             .exec(&self.db)
             .await?;
 
-        // 删除Archive
-        entities::archive::Entity::delete_by_id(id)
+        Ok(result.rows_affected)
+    }
+
+    // 远端接受后更新为远端的消息ID, 不再保留消息段
+    pub async fn mark_messages_sent(
+        &self,
+        remote_chat_id: i64,
+        temp_id: &str,
+        remote_message_id: &str,
+    ) -> Result<()> {
+        entities::message::Entity::update_many()
+            .col_expr(
+                entities::message::Column::RemoteMsgId,
+                sea_query::Expr::value(remote_message_id),
+            )
+            .col_expr(
+                entities::message::Column::DeliveryStatus,
+                sea_query::Expr::value(DeliveryStatus::Sent),
+            )
+            .col_expr(
+                entities::message::Column::Payload,
+                sea_query::Expr::value(""),
+            )
+            .col_expr(entities::message::Column::Error, sea_query::Expr::value(""))
+            .col_expr(
+                entities::message::Column::UpdatedAt,
+                sea_query::Expr::value(Utc::now().timestamp()),
+            )
+            .filter(entities::message::Column::RemoteChatId.eq(remote_chat_id))
+            .filter(entities::message::Column::RemoteMsgId.eq(temp_id))
             .exec(&self.db)
             .await?;
 
         Ok(())
     }
 
-    pub async fn get_or_create_topic(
+    // 发送失败时记录原因和消息段, remote_message_id 改为 failed: 开头的临时ID, 之后可以重新发送
+    pub async fn mark_messages_failed(
         &self,
-        archive: &entities::archive::Model,
-        remote_chat: &entities::remote_chat::Model,
-    ) -> Result<i32> {
-        // 查找已有的Topic
-        if let Some(topic) = entities::topic::Entity::find()
-            .filter(entities::topic::Column::RemoteChatId.eq(remote_chat.id))
+        remote_chat_id: i64,
+        temp_id: &str,
+        failed_id: &str,
+        error: &str,
+        payload: &str,
+    ) -> Result<()> {
+        entities::message::Entity::update_many()
+            .col_expr(
+                entities::message::Column::RemoteMsgId,
+                sea_query::Expr::value(failed_id),
+            )
+            .col_expr(
+                entities::message::Column::DeliveryStatus,
+                sea_query::Expr::value(DeliveryStatus::Failed),
+            )
+            .col_expr(
+                entities::message::Column::Error,
+                sea_query::Expr::value(error),
+            )
+            .col_expr(
+                entities::message::Column::Payload,
+                sea_query::Expr::value(payload),
+            )
+            .col_expr(
+                entities::message::Column::UpdatedAt,
+                sea_query::Expr::value(Utc::now().timestamp()),
+            )
+            .filter(entities::message::Column::RemoteChatId.eq(remote_chat_id))
+            .filter(entities::message::Column::RemoteMsgId.eq(temp_id))
+            .exec(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    // 按记录的消息段重新发送, 成功后更新为远端的消息ID, 失败时更新失败原因
+    pub async fn resend_failed_message(&self, remote_message_id: &str) -> Result<()> {
+        let (message, remote_chat) = entities::message::Entity::find()
+            .find_also_related(entities::remote_chat::Entity)
+            .filter(entities::message::Column::RemoteMsgId.eq(remote_message_id))
+            .filter(entities::message::Column::DeliveryStatus.eq(DeliveryStatus::Failed))
             .one(&self.db)
             .await?
-        {
-            return Ok(topic.tg_topic_id);
-        }
-
-        let tg_chat = self
-            .get_tg_chat(PackedType::Megagroup, archive.tg_chat_id)
-            .await?;
-
-        // 创建Topic
-        let req = tl::functions::channels::CreateForumTopic {
-            channel: tl::enums::InputChannel::Channel(tl::types::InputChannel {
-                channel_id: archive.tg_chat_id,
-                access_hash: tg_chat.pack().access_hash.unwrap_or(0),
-            }),
-            title: match remote_chat.chat_type {
-                ChatType::Private => format!("👤 {}", remote_chat.name.clone()),
-                ChatType::Group => format!("👥 {}", remote_chat.name.clone()),
-            },
-            icon_color: None,
-            icon_emoji_id: None,
-            random_id: rand::random::<i64>(),
-            send_as: None,
-        };
-        match self.bot_client.invoke(&req).await? {
-            grammers_tl_types::enums::Updates::Updates(updates) => {
-                for update in &updates.updates {
-                    if let tl::enums::Update::NewChannelMessage(message) = update {
-                        if let tl::enums::Message::Service(service) = &message.message {
-                            if let tl::enums::MessageAction::TopicCreate(_) = service.action {
-                                self.create_topic(archive.id, service.id, remote_chat.id)
-                                    .await?;
-                                return Ok(service.id);
-                            }
-                        }
-                    }
-                }
+            .context("failed message not found")?;
+        let remote_chat = remote_chat.context("remote chat not found")?;
+
+        let payload = message
+            .payload
+            .filter(|payload| !payload.is_empty())
+            .context("message has no payload")?;
+        let segments: Vec<Segment> = serde_json::from_str(&payload)?;
+        match self.send_to_remote(&remote_chat, segments).await {
+            Ok(message_id) => {
+                self.mark_messages_sent(remote_chat.id, remote_message_id, &message_id.message_id)
+                    .await
+            }
+            Err(e) => {
+                self.mark_messages_failed(
+                    remote_chat.id,
+                    remote_message_id,
+                    remote_message_id,
+                    &e.to_string(),
+                    &payload,
+                )
+                .await?;
+                Err(e)
             }
-            _ => return Err(anyhow::anyhow!("Unsupported update type")),
         }
-
-        Err(anyhow::anyhow!("Failed to get or create topic"))
     }
 
-    pub async fn create_link(
+    // 远端对话在该时间之后发送的消息按状态统计的数量
+    pub async fn delivery_stats(
         &self,
-        tg_chat_type: PackedType,
-        tg_chat_id: i64,
         remote_chat_id: i64,
-    ) -> Result<()> {
-        let entity = entities::link::ActiveModel {
-            tg_chat_type: Set(tg_chat_type as u8),
-            tg_chat_id: Set(tg_chat_id),
-            remote_chat_id: Set(remote_chat_id),
-            ..Default::default()
-        };
-        entity.insert(&self.db).await?;
-
-        Ok(())
+        since: i64,
+    ) -> Result<Vec<(DeliveryStatus, i64)>> {
+        Ok(entities::message::Entity::find()
+            .select_only()
+            .column(entities::message::Column::DeliveryStatus)
+            .column_as(
+                sea_query::Expr::col(entities::message::Column::Id).count(),
+                "count",
+            )
+            .filter(entities::message::Column::RemoteChatId.eq(remote_chat_id))
+            .filter(entities::message::Column::CreatedAt.gte(since))
+            .group_by(entities::message::Column::DeliveryStatus)
+            .into_tuple::<(DeliveryStatus, i64)>()
+            .all(&self.db)
+            .await?)
     }
 
-    pub async fn delete_link(&self, id: i64) -> Result<()> {
-        entities::link::Entity::delete_by_id(id)
-            .exec(&self.db)
-            .await?;
-
-        Ok(())
+    // 该时间之后最近一次发送失败的消息
+    pub async fn last_delivery_failure(
+        &self,
+        remote_chat_id: i64,
+        since: i64,
+    ) -> Result<Option<entities::message::Model>> {
+        Ok(entities::message::Entity::find()
+            .filter(entities::message::Column::RemoteChatId.eq(remote_chat_id))
+            .filter(entities::message::Column::CreatedAt.gte(since))
+            .filter(entities::message::Column::DeliveryStatus.eq(DeliveryStatus::Failed))
+            .order_by_desc(entities::message::Column::UpdatedAt)
+            .one(&self.db)
+            .await?)
     }
 
-    pub async fn save_message_by_remote(
+    // 记录消息中转换失败的片段
+    pub async fn save_message_failures(
         &self,
         remote_chat_id: i64,
         remote_message_id: &str,
-        telegram_message: &Message,
-        content: &str,
+        failures: &str,
     ) -> Result<()> {
-        let entity = entities::message::ActiveModel {
-            tg_chat_id: Set(telegram_message.chat().id()),
-            tg_msg_id: Set(telegram_message.id()),
-            remote_chat_id: Set(remote_chat_id),
-            remote_msg_id: Set(remote_message_id.to_owned()),
-            content: Set(content.to_owned()),
-            delivery_status: Set(DeliveryStatus::Sent),
-            ..Default::default()
-        };
-        entity.insert(&self.db).await?;
+        entities::message::Entity::update_many()
+            .col_expr(
+                entities::message::Column::Failures,
+                sea_query::Expr::value(failures),
+            )
+            .filter(entities::message::Column::RemoteChatId.eq(remote_chat_id))
+            .filter(entities::message::Column::RemoteMsgId.eq(remote_message_id))
+            .exec(&self.db)
+            .await?;
 
         Ok(())
     }
 
-    pub fn put_callback(&self, callback: &CommandCallback) -> String {
+    pub async fn put_callback(&self, callback: &CommandCallback) -> Result<String> {
         let mut hasher = DefaultHasher::new();
         callback.hash(&mut hasher);
         let hash = hasher.finish().to_string();
+
+        // 持久化回调数据, 重启后按钮仍然可用
+        let timestamp = Utc::now().timestamp();
+        let model = entities::callback::ActiveModel {
+            hash: Set(hash.clone()),
+            category: Set(callback.category.clone()),
+            action: Set(callback.action.clone()),
+            page: Set(callback.page as i64),
+            keyword: Set(callback.keyword.clone()),
+            data: Set(callback.data.clone()),
+            created_at: Set(timestamp),
+            updated_at: Set(timestamp),
+            ..Default::default()
+        };
+        entities::callback::Entity::insert(model)
+            .on_conflict(
+                sea_query::OnConflict::column(entities::callback::Column::Hash)
+                    .update_column(entities::callback::Column::UpdatedAt)
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await?;
+
         self.callback_cache.insert(hash.clone(), callback.clone());
-        hash
+        Ok(hash)
+    }
+
+    pub async fn get_callback(&self, hash: &str) -> Result<Option<CommandCallback>> {
+        // 缓存中没有则从数据库中查找
+        let callback = match self.callback_cache.remove(hash) {
+            Some((_, callback)) => Some(callback),
+            None => entities::callback::Entity::find()
+                .filter(entities::callback::Column::Hash.eq(hash))
+                .one(&self.db)
+                .await?
+                .map(|model| CommandCallback {
+                    category: model.category,
+                    action: model.action,
+                    page: model.page as u64,
+                    keyword: model.keyword,
+                    data: model.data,
+                }),
+        };
+
+        entities::callback::Entity::delete_many()
+            .filter(entities::callback::Column::Hash.eq(hash))
+            .exec(&self.db)
+            .await?;
+
+        Ok(callback)
     }
 
-    pub fn get_callback(&self, hash: &str) -> Option<CommandCallback> {
-        self.callback_cache.remove(hash).map(|(_, v)| v)
+    // 清理过期的回调数据
+    pub async fn cleanup_callbacks(&self) -> Result<()> {
+        let expired_at = Utc::now().timestamp() - CALLBACK_TTL;
+        entities::callback::Entity::delete_many()
+            .filter(entities::callback::Column::UpdatedAt.lt(expired_at))
+            .exec(&self.db)
+            .await?;
+
+        // 内存缓存只是数据库的副本, 直接清空
+        self.callback_cache.clear();
+
+        // 管理员未确认的消息过期后丢弃
+        let expired_at = Utc::now().timestamp() - PENDING_SEND_TTL;
+        self.pending_sends
+            .retain(|_, (created_at, _, _)| *created_at >= expired_at);
+
+        Ok(())
     }
 
     // 下载Telegram的媒体文件
+    #[tracing::instrument(skip_all)]
     pub async fn download_media(
         &self,
         media: &grammers_client::types::Media,
     ) -> Result<(String, Vec<u8>)> {
-        let mut file_bytes = Vec::new();
-        let mut download = self.bot_client.iter_download(media);
-        while let Some(chunk) = download.next().await? {
-            file_bytes.extend(chunk);
-        }
+        // 同一媒体转发到多个对话时复用已下载的数据
+        let cache_key = media_cache_key(media);
+        let cached = match &cache_key {
+            Some(key) => self.media_cache.get(key).await,
+            None => None,
+        };
+        let file_bytes = match cached {
+            Some(data) => data.as_ref().clone(),
+            None => {
+                let mut file_bytes = Vec::new();
+                let mut download = self.primary_bot().transport().download(media);
+                while let Some(chunk) = download.try_next().await? {
+                    file_bytes.extend(chunk);
+                }
+                if let Some(key) = &cache_key {
+                    self.media_cache.put(key, file_bytes.clone()).await;
+                }
+                file_bytes
+            }
+        };
 
         let file_name = match media {
             grammers_client::types::Media::Photo(photo) => photo.id().to_string() + ".jpg",
@@ -721,13 +3163,389 @@ impl Bridge {
             }
             _ => Default::default(),
         };
+        self.stats
+            .media_bytes
+            .fetch_add(file_bytes.len() as u64, Ordering::Relaxed);
 
         Ok((file_name, file_bytes))
     }
 
-    pub async fn index_message(&self, message: &Message) -> Result<()> {
+    // 超过大小的文件是否保存为临时文件发送
+    pub fn should_transfer(&self, size: usize) -> bool {
+        self.file_transfer
+            .as_ref()
+            .is_some_and(|file_transfer| file_transfer.should_transfer(size))
+    }
+
+    // 将Telegram的媒体分块写入临时文件, 返回文件名和Onebot实现读取的地址
+    #[tracing::instrument(skip_all)]
+    pub async fn download_media_to_transfer(
+        &self,
+        endpoint: &Endpoint,
+        media: &grammers_client::types::Media,
+    ) -> Result<(String, String)> {
+        let Some(file_transfer) = &self.file_transfer else {
+            return Err(anyhow::anyhow!("file transfer is not configured"));
+        };
+
+        let mut download = self.primary_bot().transport().download(media);
+        let first_chunk = download.try_next().await?.unwrap_or_default();
+        let file_name = match media {
+            grammers_client::types::Media::Document(document) => {
+                get_tg_doc_file_name(document, &first_chunk)
+            }
+            _ => Default::default(),
+        };
+
+        let (name, path) = file_transfer.create(&file_name);
+        let mut file = tokio::fs::File::create(&path).await?;
+        let mut size = first_chunk.len();
+        file.write_all(&first_chunk).await?;
+        while let Some(chunk) = download.try_next().await? {
+            size += chunk.len();
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        self.stats
+            .media_bytes
+            .fetch_add(size as u64, Ordering::Relaxed);
+
+        // 不能共享目录时分段上传到Onebot实现, 使用其返回的路径
+        if file_transfer.stream() {
+            let result = self
+                .upload_transfer_file(endpoint, &path, &file_name, size)
+                .await;
+            tokio::fs::remove_file(&path).await?;
+            return Ok((file_name, result?));
+        }
+
+        Ok((file_name, file_transfer.uri(&name)))
+    }
+
+    // 通过upload_file_stream逐段上传临时文件, 返回Onebot实现上的文件地址
+    async fn upload_transfer_file(
+        &self,
+        endpoint: &Endpoint,
+        path: &Path,
+        file_name: &str,
+        size: usize,
+    ) -> Result<String> {
+        let stream_id = Uuid::new_v4().to_string();
+        let total_chunks = size.div_ceil(STREAM_CHUNK_SIZE);
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut buf = vec![0; STREAM_CHUNK_SIZE];
+        for chunk_index in 0..total_chunks {
+            let len = STREAM_CHUNK_SIZE.min(size - chunk_index * STREAM_CHUNK_SIZE);
+            file.read_exact(&mut buf[..len]).await?;
+            self.upload_file_stream(
+                endpoint,
+                UploadFileStream {
+                    stream_id: stream_id.clone(),
+                    chunk_data: Some(BASE64_STANDARD.encode(&buf[..len])),
+                    chunk_index: Some(chunk_index),
+                    total_chunks: Some(total_chunks),
+                    file_size: Some(size as u64),
+                    filename: Some(file_name.to_string()),
+                    is_complete: None,
+                },
+            )
+            .await?;
+            // 每完成约10%记录一次进度
+            let done = chunk_index + 1;
+            if (done * 10 / total_chunks) != (chunk_index * 10 / total_chunks) {
+                tracing::info!(
+                    "Streaming {} to {}: {}/{} chunks",
+                    file_name,
+                    endpoint,
+                    done,
+                    total_chunks
+                );
+            }
+        }
+
+        let result = self
+            .upload_file_stream(
+                endpoint,
+                UploadFileStream {
+                    stream_id,
+                    chunk_data: None,
+                    chunk_index: None,
+                    total_chunks: None,
+                    file_size: None,
+                    filename: None,
+                    is_complete: Some(true),
+                },
+            )
+            .await?;
+        match &result.file_path {
+            Some(file_path) => Ok(format!("file://{}", file_path)),
+            None => Err(anyhow::anyhow!(
+                "upload_file_stream returned no file path, status: {}",
+                result.status
+            )),
+        }
+    }
+
+    // 分段上传的参数较多, 不使用onebot_api宏
+    async fn upload_file_stream(
+        &self,
+        endpoint: &Endpoint,
+        params: UploadFileStream,
+    ) -> Result<Arc<UploadStream>> {
+        let request = Request::upload_file_stream(params);
+        match OnebotPylon::call_api(self.api_sender.clone(), endpoint.clone(), request).await {
+            Ok(response) => {
+                if response.status.as_str() != "ok" {
+                    return Err(anyhow::anyhow!(
+                        "failed to upload_file_stream, retcode: {}",
+                        response.retcode
+                    ));
+                }
+
+                match response.data.clone() {
+                    ResponseData::UploadStream(data) => Ok(data),
+                    _ => Err(anyhow::anyhow!("invalid return data")),
+                }
+            }
+            Err(e) => Err(anyhow::anyhow!("failed to upload_file_stream: {}", e)),
+        }
+    }
+
+    pub fn transfer_path(&self, name: &str) -> Option<PathBuf> {
+        self.file_transfer.as_ref()?.path(name)
+    }
+
+    // 清理过期的临时文件
+    pub async fn cleanup_transfers(&self) -> Result<()> {
+        if let Some(file_transfer) = &self.file_transfer {
+            let count = file_transfer.cleanup().await?;
+            if count > 0 {
+                tracing::info!("Removed {} expired transfer files", count);
+            }
+        }
+
+        Ok(())
+    }
+
+    // 按保留策略清理日志, 索引和临时文件, 进入新的月份时向管理员报告上月回收的空间
+    pub async fn run_maintenance(&self) -> Result<()> {
+        let Some(maintenance) = &self.maintenance else {
+            return Ok(());
+        };
+
+        let report = maintenance.run(self.index.as_ref()).await?;
+        if report.bytes() > 0 {
+            tracing::info!(
+                "Storage maintenance removed {} logs ({:.1}MB), {} temp files ({:.1}MB), reclaimed {:.1}MB from index",
+                report.logs.0,
+                report.logs.1 as f64 / 1024.0 / 1024.0,
+                report.temp_files.0,
+                report.temp_files.1 as f64 / 1024.0 / 1024.0,
+                report.index_bytes as f64 / 1024.0 / 1024.0
+            );
+        }
+
+        if let Some((files, bytes)) = maintenance.take_monthly()? {
+            let html = i18n::trf(
+                self.language(),
+                "<b>[INFO] Storage maintenance last month</b>\nRemoved {} files, reclaimed {}MB",
+                &[&files, &format!("{:.1}", bytes as f64 / 1024.0 / 1024.0)],
+            );
+            self.send_notice(html).await?;
+        }
+
+        Ok(())
+    }
+
+    // 生成上一周期的统计报告并开始新的周期
+    pub async fn take_stats_report(&self) -> Result<String> {
+        let mut period = self.stats.take_period();
+        let (to_telegram, to_remote) = period
+            .chats
+            .iter()
+            .fold((0, 0), |acc, (_, (t, r))| (acc.0 + t, acc.1 + r));
+        period.chats.sort_by_key(|(_, (to_telegram, to_remote))| {
+            std::cmp::Reverse(to_telegram + to_remote)
+        });
+        period.chats.truncate(STATS_TOP_CHATS);
+
+        let language = self.language();
+        let mut html = i18n::trf(
+            language,
+            "<b>📊 Statistics since {}</b>\nTo Telegram: {}\nTo remote: {}\nMedia: {}MB\nFailures: {}",
+            &[
+                &Local
+                    .timestamp_opt(period.start, 0)
+                    .single()
+                    .unwrap_or_else(Local::now)
+                    .format("%Y-%m-%d %H:%M"),
+                &to_telegram,
+                &to_remote,
+                &format!("{:.1}", period.media_bytes as f64 / 1024.0 / 1024.0),
+                &period.failures,
+            ],
+        );
+        if let Some(size) = self.index_size() {
+            html.push('\n');
+            html.push_str(&i18n::trf(language, "Search index: {} messages", &[&size]));
+        }
+
+        if !period.chats.is_empty() {
+            let remote_chats: HashMap<i64, ChatModel> = entities::remote_chat::Entity::find()
+                .filter(
+                    entities::remote_chat::Column::Id.is_in(period.chats.iter().map(|(id, _)| *id)),
+                )
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|chat| (chat.id, chat))
+                .collect();
+
+            html.push_str("\n\n");
+            html.push_str(i18n::tr(
+                language,
+                "<b>Top chats (to Telegram / to remote):</b>",
+            ));
+            for (index, (id, (to_telegram, to_remote))) in period.chats.iter().enumerate() {
+                html.push_str(&format!(
+                    "\n{}. {} {} / {}",
+                    index + 1,
+                    html_escape::encode_text(
+                        &remote_chats
+                            .get(id)
+                            .map_or(id.to_string(), |chat| chat.topic_title())
+                    ),
+                    to_telegram,
+                    to_remote
+                ));
+            }
+        }
+
+        Ok(html)
+    }
+
+    // 搜索索引中的消息数, 未启用搜索时为None
+    pub fn index_size(&self) -> Option<u64> {
+        self.index.as_ref().map(|index| index.num_docs())
+    }
+
+    // 从消息表重建搜索索引, 只包含远端发来的消息, 每批写入后回调进度(已完成, 总数)
+    pub async fn reindex_messages<F, Fut>(&self, mut on_progress: F) -> Result<u64>
+    where
+        F: FnMut(u64, u64) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let Some(index) = &self.index else {
+            return Err(anyhow::anyhow!("Search is not enabled"));
+        };
+
+        let total = self.count_indexable_messages().await?;
+        index.clear().await?;
+
+        let mut done = 0;
+        let mut last_id = 0;
+        let mut remote_chats: HashMap<i64, Option<entities::remote_chat::Model>> = HashMap::new();
+        let mut topics: HashMap<(i64, i64), i32> = HashMap::new();
+        loop {
+            let records = entities::message::Entity::find()
+                .filter(entities::message::Column::Id.gt(last_id))
+                .filter(entities::message::Column::RemoteSenderId.ne(""))
+                .order_by_asc(entities::message::Column::Id)
+                .limit(REINDEX_BATCH_SIZE)
+                .all(&self.db)
+                .await?;
+            let Some(last) = records.last() else {
+                break;
+            };
+            last_id = last.id;
+
+            for record in &records {
+                if !remote_chats.contains_key(&record.remote_chat_id) {
+                    let remote_chat =
+                        entities::remote_chat::Entity::find_by_id(record.remote_chat_id)
+                            .one(&self.db)
+                            .await?;
+                    remote_chats.insert(record.remote_chat_id, remote_chat);
+                }
+                // 远端对话已删除的消息跳过
+                let Some(Some(remote_chat)) = remote_chats.get(&record.remote_chat_id) else {
+                    continue;
+                };
+
+                let key = (record.remote_chat_id, record.tg_chat_id);
+                let tg_topic_id = match topics.get(&key) {
+                    Some(tg_topic_id) => *tg_topic_id,
+                    None => {
+                        let tg_topic_id = self
+                            .find_topic_id(record.remote_chat_id, record.tg_chat_id)
+                            .await?;
+                        topics.insert(key, tg_topic_id);
+                        tg_topic_id
+                    }
+                };
+
+                index.index_record(record, tg_topic_id, remote_chat).await?;
+                done += 1;
+            }
+
+            on_progress(done, total).await;
+        }
+        index.flush().await?;
+
+        Ok(done)
+    }
+
+    // 数据库中可建立索引的消息数与索引中的文档数, 未启用搜索时为None
+    pub async fn check_index(&self) -> Result<Option<(u64, u64)>> {
+        let Some(index) = &self.index else {
+            return Ok(None);
+        };
+        index.flush().await?;
+
+        Ok(Some((
+            self.count_indexable_messages().await?,
+            index.num_docs(),
+        )))
+    }
+
+    async fn count_indexable_messages(&self) -> Result<u64> {
+        Ok(entities::message::Entity::find()
+            .filter(entities::message::Column::RemoteSenderId.ne(""))
+            .count(&self.db)
+            .await?)
+    }
+
+    // 远端对话在Telegram群中对应的Topic, 没有时为0
+    async fn find_topic_id(&self, remote_chat_id: i64, tg_chat_id: i64) -> Result<i32> {
+        if let Some(link) = entities::link::Entity::find()
+            .filter(entities::link::Column::RemoteChatId.eq(remote_chat_id))
+            .filter(entities::link::Column::TgChatId.eq(tg_chat_id))
+            .one(&self.db)
+            .await?
+        {
+            return Ok(link.tg_topic_id);
+        }
+
+        Ok(entities::topic::Entity::find()
+            .find_also_related(entities::archive::Entity)
+            .filter(entities::topic::Column::RemoteChatId.eq(remote_chat_id))
+            .filter(entities::archive::Column::TgChatId.eq(tg_chat_id))
+            .one(&self.db)
+            .await?
+            .map_or(0, |(topic, _)| topic.tg_topic_id))
+    }
+
+    pub async fn index_message(
+        &self,
+        message: &SentMessage,
+        sender_id: &str,
+        sender_name: &str,
+        remote_chat: &ChatModel,
+    ) -> Result<()> {
         if let Some(index) = &self.index {
-            index.index_message(message).await?;
+            index
+                .index_message(message, sender_id, sender_name, remote_chat)
+                .await?;
         }
 
         Ok(())
@@ -737,14 +3555,14 @@ impl Bridge {
         &self,
         chat_id: i64,
         reply_to: Option<i32>,
-        keyword: &str,
+        search: &SearchQuery,
         last_id: Option<i32>,
         page_size: u64,
     ) -> Result<Vec<(i32, i64, String)>> {
         match &self.index {
             Some(index) => {
                 index
-                    .search_messages(chat_id, reply_to, keyword, last_id, page_size)
+                    .search_messages(chat_id, reply_to, search, last_id, page_size)
                     .await
             }
             None => Ok(Vec::new()),
@@ -777,6 +3595,7 @@ impl Bridge {
     }
 
     // 下载Onebot的消息段里的媒体
+    #[tracing::instrument(skip_all, fields(endpoint = %endpoint))]
     async fn download_segment(
         &self,
         endpoint: &Endpoint,
@@ -784,9 +3603,14 @@ impl Bridge {
     ) -> Result<(String, Vec<u8>)> {
         match segment {
             Segment::Image(seg) => {
-                if seg.emoji_id.is_some() {
-                    if let Some(url) = seg.url.as_ref().filter(|s| s.starts_with("http")) {
-                        return self.fetch_file(url).await;
+                // 片段带有URL时直接下载, 失败再通过API获取
+                if let Some(url) = seg.url.as_ref().filter(|s| s.starts_with("http")) {
+                    match self.fetch_file(url).await {
+                        Ok(data) => return Ok(data),
+                        Err(e) if seg.emoji_id.is_some() || e.is::<MediaTooLarge>() => {
+                            return Err(e);
+                        }
+                        Err(e) => tracing::warn!("Failed to fetch image {}: {}", url, e),
                     }
                 }
                 self.download_image(
@@ -820,6 +3644,13 @@ impl Bridge {
                     .await
             }
             Segment::Video(seg) => {
+                if let Some(url) = seg.url.as_ref().filter(|s| s.starts_with("http")) {
+                    match self.fetch_file(url).await {
+                        Ok(data) => return Ok(data),
+                        Err(e) if e.is::<MediaTooLarge>() => return Err(e),
+                        Err(e) => tracing::warn!("Failed to fetch video {}: {}", url, e),
+                    }
+                }
                 self.download_video(endpoint, seg.file.clone(), seg.file.clone())
                     .await
             }
@@ -831,12 +3662,46 @@ impl Bridge {
         }
     }
 
+    // 分块写入临时文件, 超过大小限制时放弃, 完成后按实际大小一次读取
+    // 不信任Content-Length预先分配内存, 只用于提前判断是否超出限制
     async fn fetch_file(&self, url: &str) -> Result<(String, Vec<u8>)> {
         let url = Url::parse(url)?;
-        let response = self.http_client.get(url.as_str()).send().await?;
+        let mut response = self
+            .http_client
+            .get(url.as_str())
+            .send()
+            .await?
+            .error_for_status()?;
         let filename = get_final_filename(response.headers(), &url);
 
-        Ok((filename, response.bytes().await?.to_vec()))
+        let limit = self
+            .max_media_size
+            .map_or(FETCH_MAX_SIZE, |size| size.min(FETCH_MAX_SIZE));
+        let too_large = |size: usize| MediaTooLarge {
+            size,
+            url: Some(url.to_string()),
+        };
+        if let Some(size) = response.content_length() {
+            if size > limit as u64 {
+                return Err(too_large(size as usize).into());
+            }
+        }
+
+        let temp_file = storage_maintenance::temp_file()?;
+        let mut file = tokio::fs::File::create(temp_file.path()).await?;
+        let mut size = 0;
+        while let Some(chunk) = response.chunk().await? {
+            size += chunk.len();
+            if size > limit {
+                return Err(too_large(size).into());
+            }
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        drop(file);
+
+        let data = tokio::fs::read(temp_file.path()).await?;
+        Ok((filename, data))
     }
 }
 
@@ -849,6 +3714,8 @@ impl Bridge {
     download_seg!(download_file, get_file, file: String, file_id: String);
 
     onebot_api!(get_login_info, UserInfo, UserInfo);
+    onebot_api!(get_status, Status, Status);
+    onebot_api!(get_version_info, VersionInfo, VersionInfo);
     onebot_api!(get_stranger_info, UserInfo, UserInfo, GetStrangerInfo, user_id: String, no_cache: bool);
     onebot_api!(get_group_info, GroupInfo, GroupInfo, GetGroupInfo, group_id: String, no_cache: bool);
     onebot_api!(get_friend_list, FriendList, Vec<UserInfo>);
@@ -858,9 +3725,21 @@ impl Bridge {
     onebot_api!(get_record, FileInfo, FileInfo, GetRecord, file: String, out_format: String);
     onebot_api!(get_image, FileInfo, FileInfo, GetImage, file: String, file_id: String, emoji_id: Option<String>);
     onebot_api!(get_file, FileInfo, FileInfo, GetFile, file: String, file_id: String);
+    onebot_api!(get_msg, MessageDetail, MessageDetail, GetMsg, message_id: String);
     onebot_api!(get_forward_msg, ForwardMessage, ForwardMessage, GetForwardMsg, message_id: String);
+    onebot_api!(get_group_msg_history, ForwardMessage, ForwardMessage, GetGroupMsgHistory, group_id: String, count: i32);
+    onebot_api!(get_friend_msg_history, ForwardMessage, ForwardMessage, GetFriendMsgHistory, user_id: String, count: i32);
+    onebot_api!(get_group_file_url, FileUrl, FileUrl, GetGroupFileUrl, group_id: String, file_id: String, busid: i64);
     onebot_api!(send_msg, MessageId, MessageId, SendMsg, message_type: String, group_id: Option<String>, user_id: Option<String>, message: Vec<Segment>);
+    onebot_api!(get_guild_channel_list, ChannelList, Vec<ChannelInfo>, GetGuildChannelList, guild_id: String, no_cache: bool);
+    onebot_api!(send_guild_channel_msg, MessageId, MessageId, SendGuildChannelMsg, guild_id: String, channel_id: String, message: Vec<Segment>);
     onebot_api_no_resp!(delete_msg, DeleteMsg, message_id: String);
+    onebot_api_no_resp!(set_group_kick, SetGroupKick, group_id: String, user_id: String, reject_add_request: bool);
+    onebot_api_no_resp!(set_group_ban, SetGroupBan, group_id: String, user_id: String, duration: i64);
+    onebot_api_no_resp!(set_group_special_title, SetGroupSpecialTitle, group_id: String, user_id: String, special_title: String, duration: i64);
+    onebot_api_no_resp!(set_essence_msg, SetEssenceMsg, message_id: String);
+    onebot_api_no_resp!(delete_essence_msg, DeleteEssenceMsg, message_id: String);
+    onebot_api_no_resp!(mark_msg_as_read, MarkMsgAsRead, message_id: String);
 
     save_remote_chat!(save_remote_private_chat, UserInfo, Private, user_id);
     save_remote_chat!(save_remote_group_chat, GroupInfo, Group, group_id);
@@ -868,6 +3747,16 @@ impl Bridge {
     update_remote_chat!(update_remote_group_chat, GroupInfo, Group, group_id);
 }
 
+// 拆分子频道对话的目标ID: 频道ID/子频道ID
+fn split_guild_target(target_id: &str) -> Result<(String, String)> {
+    match target_id.split_once('/') {
+        Some((guild_id, channel_id)) if !guild_id.is_empty() && !channel_id.is_empty() => {
+            Ok((guild_id.to_owned(), channel_id.to_owned()))
+        }
+        _ => Err(anyhow::anyhow!("invalid guild target: {}", target_id)),
+    }
+}
+
 pub fn fix_filename(filename: &str, ext: &str) -> Option<String> {
     let path = Path::new(filename);
     let mut new_path = path.to_path_buf();
@@ -932,6 +3821,30 @@ fn generate_default_filename(headers: &reqwest::header::HeaderMap) -> String {
     format!("data.{}", ext)
 }
 
+// 媒体在缓存中的键, 由ID和access hash组成
+fn media_cache_key(media: &grammers_client::types::Media) -> Option<String> {
+    let document_key = |document: &Option<tl::enums::Document>| match document {
+        Some(tl::enums::Document::Document(document)) => {
+            Some(format!("doc_{}_{}", document.id, document.access_hash))
+        }
+        _ => None,
+    };
+
+    match media {
+        grammers_client::types::Media::Photo(photo) => match &photo.raw.photo {
+            Some(tl::enums::Photo::Photo(photo)) => {
+                Some(format!("photo_{}_{}", photo.id, photo.access_hash))
+            }
+            _ => None,
+        },
+        grammers_client::types::Media::Document(document) => document_key(&document.raw.document),
+        grammers_client::types::Media::Sticker(sticker) => {
+            document_key(&sticker.document.raw.document)
+        }
+        _ => None,
+    }
+}
+
 fn get_tg_doc_file_name(document: &Document, data: &[u8]) -> String {
     let mut file_name = document.name().to_string();
     if file_name.is_empty() {
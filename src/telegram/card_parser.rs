@@ -0,0 +1,283 @@
+use lazy_static::lazy_static;
+use serde_json::Value;
+
+use super::onebot_helper::{xml_attr, xml_tag};
+use super::wechat_cards::{
+    FinderFeedMatcher, FinderLiveMatcher, FinderNameCardMatcher, TimelineMatcher,
+};
+
+// 从卡片消息中提取的统一内容
+#[derive(Debug, Default)]
+pub struct Card {
+    pub icon: &'static str,
+    pub kind: &'static str,
+    pub title: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub thumbnail: Option<String>,
+    pub source: String,
+}
+
+impl Card {
+    pub fn to_html(&self) -> String {
+        let mut html = format!(
+            "{} <b>[{}]</b> {}",
+            self.icon,
+            self.kind,
+            html_escape::encode_text(&self.title)
+        );
+        if !self.description.is_empty() && self.description != self.title {
+            html.push('\n');
+            html.push_str(&html_escape::encode_text(&self.description));
+        }
+        match (&self.url, self.source.as_str()) {
+            (Some(url), source) => html.push_str(&format!(
+                "\n\nvia <a href=\"{}\">{}</a>",
+                html_escape::encode_double_quoted_attribute(url),
+                html_escape::encode_text(if source.is_empty() { "链接" } else { source })
+            )),
+            (None, "") => {}
+            (None, source) => {
+                html.push_str(&format!("\n\nvia {}", html_escape::encode_text(source)))
+            }
+        }
+
+        html
+    }
+}
+
+// 一种卡片格式的识别规则, 不认识的返回None
+pub trait CardMatcher: Send + Sync {
+    fn parse_json(&self, _json: &Value) -> Option<Card> {
+        None
+    }
+
+    fn parse_xml(&self, _xml: &str) -> Option<Card> {
+        None
+    }
+}
+
+lazy_static! {
+    // 按顺序匹配, 通用的规则放在最后
+    static ref MATCHERS: Vec<Box<dyn CardMatcher>> = vec![
+        Box::new(GroupInviteMatcher),
+        Box::new(MiniProgramMatcher),
+        Box::new(FileMatcher),
+        Box::new(QQXmlMatcher),
+        Box::new(FinderFeedMatcher),
+        Box::new(FinderLiveMatcher),
+        Box::new(FinderNameCardMatcher),
+        Box::new(TimelineMatcher),
+        Box::new(WeChatAppMatcher),
+        Box::new(GenericJsonMatcher),
+    ];
+}
+
+pub fn parse_json(json: &Value) -> Option<Card> {
+    MATCHERS.iter().find_map(|m| m.parse_json(json))
+}
+
+pub fn parse_xml(xml: &str) -> Option<Card> {
+    MATCHERS.iter().find_map(|m| m.parse_xml(xml))
+}
+
+// QQ卡片的内容在 meta 下唯一的对象里, 如 meta.news, meta.detail_1, 依次取第一个非空的字段
+fn meta_field(json: &Value, keys: &[&str]) -> Option<String> {
+    let meta = json.get("meta")?.as_object()?;
+    meta.values().find_map(|item| {
+        keys.iter().find_map(|key| {
+            item.get(*key)
+                .and_then(Value::as_str)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        })
+    })
+}
+
+fn json_str(json: &Value, key: &str) -> String {
+    json.get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+// 去掉 prompt 开头的 [QQ小程序] 等类型标记
+fn trim_prompt(prompt: &str) -> String {
+    match prompt.strip_prefix('[').and_then(|p| p.split_once(']')) {
+        Some((_, rest)) => rest.trim().to_string(),
+        None => prompt.trim().to_string(),
+    }
+}
+
+// 部分卡片的图片地址缺少协议
+fn fix_url(url: String) -> String {
+    match url.starts_with("//") {
+        true => format!("https:{}", url),
+        false => url,
+    }
+}
+
+struct GroupInviteMatcher;
+
+impl CardMatcher for GroupInviteMatcher {
+    fn parse_json(&self, json: &Value) -> Option<Card> {
+        let app = json_str(json, "app");
+        if app != "com.tencent.troopsharecard" && app != "com.tencent.qun.invite" {
+            return None;
+        }
+
+        Some(Card {
+            icon: "👥",
+            kind: "群邀请",
+            title: meta_field(json, &["nickname", "title"])
+                .unwrap_or_else(|| trim_prompt(&json_str(json, "prompt"))),
+            description: meta_field(json, &["contact", "desc"]).unwrap_or_default(),
+            url: meta_field(json, &["jumpUrl", "url"]),
+            thumbnail: meta_field(json, &["avatar", "preview"]).map(fix_url),
+            source: meta_field(json, &["tag"]).unwrap_or_default(),
+        })
+    }
+}
+
+struct MiniProgramMatcher;
+
+impl CardMatcher for MiniProgramMatcher {
+    fn parse_json(&self, json: &Value) -> Option<Card> {
+        if !json_str(json, "app").starts_with("com.tencent.miniapp") {
+            return None;
+        }
+
+        // 小程序的 title 为小程序名称, desc 为分享的内容
+        Some(Card {
+            icon: "🧩",
+            kind: "小程序",
+            title: meta_field(json, &["desc"])
+                .unwrap_or_else(|| trim_prompt(&json_str(json, "prompt"))),
+            description: String::new(),
+            url: meta_field(json, &["qqdocurl", "url", "jumpUrl"]),
+            thumbnail: meta_field(json, &["preview"]).map(fix_url),
+            source: meta_field(json, &["title"]).unwrap_or_default(),
+        })
+    }
+}
+
+struct FileMatcher;
+
+impl CardMatcher for FileMatcher {
+    fn parse_json(&self, json: &Value) -> Option<Card> {
+        let app = json_str(json, "app");
+        if !app.contains("file") {
+            return None;
+        }
+
+        Some(Card {
+            icon: "📄",
+            kind: "文件",
+            title: meta_field(json, &["filename", "title"])
+                .unwrap_or_else(|| trim_prompt(&json_str(json, "prompt"))),
+            description: meta_field(json, &["filesize", "desc"]).unwrap_or_default(),
+            url: meta_field(json, &["jumpUrl", "url"]),
+            thumbnail: None,
+            source: String::new(),
+        })
+    }
+}
+
+// QQ的XML卡片: <msg serviceID=".." url=".." brief=".."><item><title/><summary/><picture cover=".."/></item><source name=".."/></msg>
+struct QQXmlMatcher;
+
+impl CardMatcher for QQXmlMatcher {
+    fn parse_xml(&self, xml: &str) -> Option<Card> {
+        if !xml.contains("serviceID") {
+            return None;
+        }
+
+        let brief = xml_attr(xml, "msg", "brief").unwrap_or_default();
+        let title = xml_tag(xml, "title").unwrap_or_else(|| trim_prompt(&brief));
+        if title.is_empty() {
+            return None;
+        }
+
+        Some(Card {
+            icon: "🔗",
+            kind: "分享",
+            title,
+            description: xml_tag(xml, "summary").unwrap_or_default(),
+            url: xml_attr(xml, "msg", "url").filter(|u| u.starts_with("http")),
+            thumbnail: xml_attr(xml, "picture", "cover").map(fix_url),
+            source: xml_attr(xml, "source", "name").unwrap_or_default(),
+        })
+    }
+}
+
+// 微信的appmsg: type 5为链接, 6为文件, 33/36为小程序, 视频号和朋友圈见 wechat_cards
+struct WeChatAppMatcher;
+
+impl CardMatcher for WeChatAppMatcher {
+    fn parse_xml(&self, xml: &str) -> Option<Card> {
+        if !xml.contains("<appmsg") {
+            return None;
+        }
+
+        let title = xml_tag(xml, "title").unwrap_or_default();
+        let description = xml_tag(xml, "des").unwrap_or_default();
+        let url = xml_tag(xml, "url").filter(|u| u.starts_with("http"));
+        let thumbnail = xml_tag(xml, "thumburl").filter(|u| u.starts_with("http"));
+        let source = xml_tag(xml, "sourcedisplayname")
+            .or_else(|| xml_tag(xml, "appname"))
+            .unwrap_or_default();
+
+        let card = match xml_tag(xml, "type")?.as_str() {
+            "6" => Card {
+                icon: "📄",
+                kind: "文件",
+                title,
+                description: xml_tag(xml, "totallen")
+                    .and_then(|len| len.parse::<f64>().ok())
+                    .map(|len| format!("{:.1}MB", len / 1024.0 / 1024.0))
+                    .unwrap_or_default(),
+                ..Default::default()
+            },
+            "33" | "36" => Card {
+                icon: "🧩",
+                kind: "小程序",
+                title,
+                description,
+                url,
+                thumbnail,
+                source,
+            },
+            _ if url.is_some() => Card {
+                icon: "🔗",
+                kind: "分享",
+                title,
+                description,
+                url,
+                thumbnail,
+                source,
+            },
+            _ => return None,
+        };
+
+        Some(card)
+    }
+}
+
+// 其他带跳转链接的QQ卡片, 如新闻, 文档等
+struct GenericJsonMatcher;
+
+impl CardMatcher for GenericJsonMatcher {
+    fn parse_json(&self, json: &Value) -> Option<Card> {
+        let url = meta_field(json, &["qqdocurl", "jumpUrl", "url"])?;
+
+        Some(Card {
+            icon: "🔗",
+            kind: "分享",
+            title: trim_prompt(&json_str(json, "prompt")),
+            description: meta_field(json, &["desc"]).unwrap_or_default(),
+            url: Some(url),
+            thumbnail: meta_field(json, &["preview"]).map(fix_url),
+            source: meta_field(json, &["tag", "title"]).unwrap_or_default(),
+        })
+    }
+}
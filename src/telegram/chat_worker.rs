@@ -0,0 +1,129 @@
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use tokio::sync::{Mutex, OwnedMutexGuard, Semaphore, mpsc};
+
+// 工作任务空闲多久后退出
+const WORKER_IDLE: Duration = Duration::from_secs(60);
+
+type Job = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+struct ChatQueue {
+    // 工作任务的队列, 没有工作任务时为None
+    sender: Option<mpsc::UnboundedSender<Job>>,
+    // 执行任务时持有, 其他需要与该对话互斥的操作也使用
+    lock: Arc<Mutex<()>>,
+}
+
+impl ChatQueue {
+    fn new() -> Self {
+        Self {
+            sender: None,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+}
+
+// 每个对话一个工作任务, 按顺序执行该对话的任务, 不同对话之间并发执行
+// 所有对话同时执行的任务数受全局上限限制, 慢的对话只占用一个名额
+pub struct ChatWorkers<K> {
+    queues: Arc<DashMap<K, ChatQueue>>,
+    permits: Arc<Semaphore>,
+}
+
+impl<K: Eq + Hash + Clone + Send + Sync + 'static> ChatWorkers<K> {
+    // 多组工作任务可以共用同一个名额上限
+    pub fn new(permits: Arc<Semaphore>) -> Self {
+        Self {
+            queues: Arc::new(DashMap::new()),
+            permits,
+        }
+    }
+
+    // 将任务放入对话的队列, 没有工作任务时启动一个
+    pub fn spawn<F>(&self, key: K, job: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let mut queue = self
+            .queues
+            .entry(key.clone())
+            .or_insert_with(ChatQueue::new);
+        let job: Job = Box::pin(job);
+        // 工作任务已退出(如任务panic)时发送失败, 重新启动
+        let job = match &queue.sender {
+            Some(sender) => match sender.send(job) {
+                Ok(()) => return,
+                Err(e) => e.0,
+            },
+            None => job,
+        };
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let _ = sender.send(job);
+        queue.sender = Some(sender);
+        let lock = queue.lock.clone();
+        drop(queue);
+
+        tokio::spawn(Self::work(
+            self.queues.clone(),
+            self.permits.clone(),
+            key,
+            lock,
+            receiver,
+        ));
+    }
+
+    // 等待对话当前的任务执行完, 持有期间该对话的任务不会执行
+    pub async fn lock(&self, key: K) -> OwnedMutexGuard<()> {
+        let lock = self
+            .queues
+            .entry(key)
+            .or_insert_with(ChatQueue::new)
+            .lock
+            .clone();
+        lock.lock_owned().await
+    }
+
+    async fn work(
+        queues: Arc<DashMap<K, ChatQueue>>,
+        permits: Arc<Semaphore>,
+        key: K,
+        lock: Arc<Mutex<()>>,
+        mut receiver: mpsc::UnboundedReceiver<Job>,
+    ) {
+        loop {
+            match tokio::time::timeout(WORKER_IDLE, receiver.recv()).await {
+                Ok(Some(job)) => {
+                    // 先取得名额再加锁, 等待名额时不阻塞该对话的其他操作
+                    let Ok(_permit) = permits.acquire().await else {
+                        break;
+                    };
+                    let _guard = lock.lock().await;
+                    job.await;
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    // 在分片锁内检查队列是否为空, 不会漏掉同时放入的任务
+                    let Some(mut queue) = queues.get_mut(&key) else {
+                        break;
+                    };
+                    if receiver.is_empty() {
+                        queue.sender = None;
+                        break;
+                    }
+                }
+            }
+        }
+
+        // 没有其他操作持有锁时移除对话
+        drop(lock);
+        queues.remove_if(&key, |_, queue| {
+            queue.sender.is_none() && Arc::strong_count(&queue.lock) == 1
+        });
+    }
+}
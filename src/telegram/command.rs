@@ -1,33 +1,171 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use chrono::{Local, TimeZone};
-use grammers_client::types::{CallbackQuery, Chat, Message};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use grammers_client::session::PackedType;
+use grammers_client::types::{CallbackQuery, Chat, Message, media};
 use grammers_client::{InputMessage, button, reply_markup};
 use grammers_tl_types as tl;
-use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect};
+use sea_orm::{
+    ColumnTrait, Condition, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+};
 
 use super::bridge::{Bridge, CommandCallback};
+use super::i18n;
+use super::index_service::SearchQuery;
+use super::transcribe_service::TranscribeService;
+use super::translate_service::TranslateService;
+use super::transport::{CommandScope, OutgoingButton, OutgoingMessage, SentMessage, inline_button};
 use super::{entities, telegram_helper as tg_helper};
 use crate::TelegramPylon;
-use crate::common::{ChatType, Endpoint};
+use crate::common::{
+    ChatType, DeliveryStatus, Endpoint, FilterAction, FilterDirection, MediaKind, MediaMode,
+    MemberNotice, Platform, Signature,
+};
+use crate::onebot::event_queue::EventQueue;
+use crate::onebot::onebot_pylon::OnebotPylon;
+use crate::onebot::protocol::segment::Segment;
 
 // 分页大小
 const PAGE_SIZE: u64 = 10;
 // 占位符
 const PLACE_HOLDER: &str = "porter";
+// Bot管理的贴纸包名称前缀, 完整名称需以 _by_<bot用户名> 结尾
+const STICKER_SET_PREFIX: &str = "porter_stickers";
+// 贴纸包标题
+const STICKER_SET_TITLE: &str = "Teleporter Stickers";
+// 单次导入历史消息的最大数量
+const IMPORT_MAX_COUNT: i32 = 200;
+// 命令列表: (命令, 说明)
+const COMMANDS: &[(&str, &str)] = &[
+    ("help", "Show command list."),
+    ("link", "Manage remote chat link."),
+    ("archive", "Archive remote chat."),
+    ("search", "Search messages."),
+    ("mute", "Mute remote chat."),
+    ("unmute", "Unmute remote chat."),
+    ("digest", "Set digest interval of remote chat."),
+    ("translate", "Set translation languages of remote chat."),
+    (
+        "transcribe",
+        "Set voice transcription language of remote chat.",
+    ),
+    ("info", "Show remote chat of current group or topic."),
+    ("rename", "Set display name of remote chat."),
+    ("topicicon", "Set custom emoji icon of archive topic."),
+    ("settings", "Set media policy of remote chat."),
+    ("read", "Mark remote chat as read."),
+    ("share_contact", "Share a friend or group to remote chat."),
+    ("import", "Import recent history of remote chat."),
+    ("kick", "Kick the sender of replied message."),
+    ("ban", "Ban the sender of replied message."),
+    (
+        "title",
+        "Set special title for the sender of replied message.",
+    ),
+    ("steal", "Add replied sticker to sticker pack."),
+    ("failed", "List messages failed to send to remote."),
+    ("filter", "Manage content filters of remote chat."),
+    ("dm", "Reply privately to the sender of replied message."),
+    ("reindex", "Rebuild search index from stored messages."),
+    ("reload", "Reload config file."),
+    (
+        "status",
+        "Show status of endpoints or delivery of remote chat.",
+    ),
+    ("endpoint", "Manage endpoint aliases."),
+    ("chats", "Browse remote chats."),
+    ("autoreply", "Manage auto replies of remote chat."),
+    ("canned", "Manage canned replies."),
+    ("c", "Send a canned reply to remote chat."),
+    ("session", "Show or reset bot sessions."),
+];
+// 内容过滤命令的用法
+const FILTER_USAGE: &str = "<b>Usage: /filter add drop|redact|flag [both|in|out] keyword|re:regex, \
+/filter remove id or /filter list</b>";
+// 单次查询Topic的最大数量
+const TOPIC_CHECK_SIZE: usize = 100;
+// 私聊中可用的命令
+const PRIVATE_COMMANDS: &[&str] = &[
+    "help", "failed", "reindex", "reload", "status", "endpoint", "chats", "canned",
+];
+// 对话浏览命令的用法
+const CHATS_USAGE: &str = "<b>No remote chats found, filter by platform, endpoint, type, \
+linked, unlinked or name, e.g. /chats qq group unlinked</b>";
+// 自动回复命令的用法
+const AUTOREPLY_USAGE: &str = "<b>Usage: /autoreply add [quiet:22-8] keyword|re:regex =&gt; reply, \
+/autoreply remove id or /autoreply list</b>";
+// 快捷回复命令的用法
+const CANNED_USAGE: &str = "<b>Usage: /canned add name content, /canned remove name or /canned list, \
+send with /c name, content supports {name}, {id}, {endpoint}, {date} and {time}</b>";
+// 快捷回复列表中预览的字数
+const CANNED_PREVIEW_LEN: usize = 30;
+// 对话发送状态命令的用法
+const LINK_STATUS_USAGE: &str = "<b>Usage: /status link in linked groups or archive topics, \
+or /status link remote_chat_id</b>";
+// 会话命令的用法
+const SESSION_USAGE: &str =
+    "<b>Usage: /session info or /session reset [bot index], the main bot is 0</b>";
+// 端点别名命令的用法
+const ENDPOINT_USAGE: &str = "<b>Usage: /endpoint, /endpoint rename platform:self_id alias or /endpoint rename platform:self_id</b>";
+// 重建索引时更新进度的最短间隔
+const REINDEX_PROGRESS_INTERVAL: Duration = Duration::from_secs(3);
+// 查询端点状态的超时时间
+const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
 
 impl TelegramPylon {
+    // 注册命令列表, 群组中仅对管理员显示, 私聊中只显示可用的命令
+    pub async fn register_commands(bridge: &Bridge) -> Result<()> {
+        let language = bridge.language();
+        let to_bot_commands = |commands: Vec<&(&str, &str)>| {
+            commands
+                .into_iter()
+                .map(|(command, description)| {
+                    (
+                        command.to_string(),
+                        i18n::tr(language, description).to_string(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let transport = bridge.primary_bot().transport();
+        transport
+            .set_commands(
+                CommandScope::ChatAdmins,
+                to_bot_commands(COMMANDS.iter().collect()),
+            )
+            .await?;
+        transport
+            .set_commands(
+                CommandScope::Users,
+                to_bot_commands(
+                    COMMANDS
+                        .iter()
+                        .filter(|(command, _)| PRIVATE_COMMANDS.contains(command))
+                        .collect(),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn process_callback(bridge: &Bridge, callback: &CallbackQuery) -> Result<()> {
         let message = callback.load_message().await?;
-        if let Some(command_callback) =
-            bridge.get_callback(std::str::from_utf8(callback.data()).unwrap_or(""))
+        if let Some(command_callback) = bridge
+            .get_callback(std::str::from_utf8(callback.data()).unwrap_or(""))
+            .await?
         {
             match command_callback.category.as_str() {
                 "archive" => match command_callback.action.as_str() {
                     "create" => Self::create_archive(bridge, &message, &command_callback).await?,
                     "delete" => Self::delete_archive(bridge, &message, &command_callback).await?,
+                    "rule" => {
+                        Self::select_archive_rule(bridge, &message, &command_callback).await?
+                    }
                     "cancel" => Self::cancel(bridge, &message, &command_callback).await?,
                     _ => {}
                 },
@@ -38,6 +176,65 @@ impl TelegramPylon {
                     "cancel" => Self::cancel(bridge, &message, &command_callback).await?,
                     _ => {}
                 },
+                "check" => match command_callback.action.as_str() {
+                    "delete_link" | "delete_archive" | "delete_topic" => {
+                        Self::clean_stale(bridge, &message, &command_callback).await?
+                    }
+                    "cancel" => Self::cancel(bridge, &message, &command_callback).await?,
+                    _ => {}
+                },
+                "settings" => match command_callback.action.as_str() {
+                    "media" => Self::toggle_media_mode(bridge, &message, &command_callback).await?,
+                    "member" => {
+                        Self::toggle_member_notice(bridge, &message, &command_callback).await?
+                    }
+                    "confirm" => {
+                        Self::toggle_confirm_send(bridge, &message, &command_callback).await?
+                    }
+                    "honor" => {
+                        Self::toggle_honor_notice(bridge, &message, &command_callback).await?
+                    }
+                    "source" => {
+                        Self::toggle_source_link(bridge, &message, &command_callback).await?
+                    }
+                    "signature" => {
+                        Self::toggle_signature(bridge, &message, &command_callback).await?
+                    }
+                    "cancel" => Self::cancel(bridge, &message, &command_callback).await?,
+                    _ => {}
+                },
+                "failed" => match command_callback.action.as_str() {
+                    "retry" => Self::retry_failed(bridge, &message, &command_callback).await?,
+                    "cancel" => Self::cancel(bridge, &message, &command_callback).await?,
+                    _ => {}
+                },
+                "confirm" => match command_callback.action.as_str() {
+                    "send" => {
+                        Self::process_pending_send(bridge, &message, &command_callback, true)
+                            .await?
+                    }
+                    "discard" => {
+                        Self::process_pending_send(bridge, &message, &command_callback, false)
+                            .await?
+                    }
+                    _ => {}
+                },
+                "chats" => match command_callback.action.as_str() {
+                    "list" => Self::list_chats(bridge, &message, &command_callback).await?,
+                    "open" => Self::open_chat(bridge, &message, &command_callback).await?,
+                    "link" | "unlink" | "archive" => {
+                        Self::chat_action(bridge, &message, &command_callback).await?
+                    }
+                    "cancel" => Self::cancel(bridge, &message, &command_callback).await?,
+                    _ => {}
+                },
+                "canned" => match command_callback.action.as_str() {
+                    "send" => {
+                        Self::send_canned_callback(bridge, &message, &command_callback).await?
+                    }
+                    "cancel" => Self::cancel(bridge, &message, &command_callback).await?,
+                    _ => {}
+                },
                 "search" => match command_callback.action.as_str() {
                     "list" => Self::list_search(bridge, &message, &command_callback).await?,
                     "cancel" => Self::cancel(bridge, &message, &command_callback).await?,
@@ -57,14 +254,26 @@ impl TelegramPylon {
 
         match command {
             "/help" => {
-                message
-                    .respond(InputMessage::html(
-                        "help - Show command list.\n\
-                        link - Manage remote chat link.\n\
-                        archive - Archive remote chat.\n\
-                        search - Search messages.",
-                    ))
-                    .await?;
+                let language = bridge.language();
+                let aliases = bridge.command_aliases();
+                let help = COMMANDS
+                    .iter()
+                    .map(|(command, description)| {
+                        let mut line = format!("{} - {}", command, i18n::tr(language, description));
+                        // 附上命令的别名
+                        let names: Vec<String> = aliases
+                            .iter()
+                            .filter(|(_, target)| target.trim_start_matches('/') == *command)
+                            .map(|(alias, _)| format!("/{}", alias))
+                            .collect();
+                        if !names.is_empty() {
+                            write!(&mut line, " ({})", names.join(", ")).unwrap();
+                        }
+                        line
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                message.respond(InputMessage::html(help)).await?;
             }
             "/archive" => {
                 if let Chat::Group(group) = message.chat() {
@@ -76,9 +285,10 @@ impl TelegramPylon {
                 }
                 message
                     .respond(
-                        InputMessage::html(
+                        InputMessage::html(i18n::tr(
+                            bridge.language(),
                             "<b>Currently, archive is only supported in forum groups</b>",
-                        )
+                        ))
                         .reply_to(tg_helper::get_topic_id(message)),
                     )
                     .await?;
@@ -90,8 +300,8 @@ impl TelegramPylon {
                             return Self::process_link(bridge, message).await;
                         }
                         tl::enums::Chat::Channel(channel) => {
-                            // 目前不支持绑定在有Topic的群
-                            if channel.megagroup && !channel.forum {
+                            // 有Topic的群链接到命令所在的Topic
+                            if channel.megagroup {
                                 return Self::process_link(bridge, message).await;
                             }
                         }
@@ -99,9 +309,10 @@ impl TelegramPylon {
                     }
                 }
                 message
-                    .respond(InputMessage::html(
-                        "<b>Currently, link creation is only supported in regular groups</b>",
-                    ))
+                    .respond(InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Currently, link creation is only supported in groups</b>",
+                    )))
                     .await?;
             }
             "/search" => {
@@ -114,16 +325,101 @@ impl TelegramPylon {
                 }
                 message
                     .respond(
-                        InputMessage::html(
+                        InputMessage::html(i18n::tr(
+                            bridge.language(),
                             "<b>Currently, search is only supported in mega groups</b>",
-                        )
+                        ))
                         .reply_to(tg_helper::get_topic_id(message)),
                     )
                     .await?;
             }
+            "/mute" => {
+                return Self::process_mute(bridge, message).await;
+            }
+            "/unmute" => {
+                return Self::process_unmute(bridge, message).await;
+            }
+            "/digest" => {
+                return Self::process_digest(bridge, message).await;
+            }
+            "/translate" => {
+                return Self::process_translate(bridge, message).await;
+            }
+            "/transcribe" => {
+                return Self::process_transcribe(bridge, message).await;
+            }
+            "/settings" => {
+                return Self::process_settings(bridge, message).await;
+            }
+            "/info" => {
+                return Self::process_info(bridge, message).await;
+            }
+            "/rename" => {
+                return Self::process_rename(bridge, message).await;
+            }
+            "/topicicon" => {
+                return Self::process_topic_icon(bridge, message).await;
+            }
+            "/read" => {
+                return Self::process_read(bridge, message).await;
+            }
+            "/share_contact" => {
+                return Self::process_share_contact(bridge, message).await;
+            }
+            "/import" => {
+                return Self::process_import(bridge, message).await;
+            }
+            "/kick" | "/ban" | "/title" => {
+                return Self::process_group_admin(bridge, message, command).await;
+            }
+            "/steal" => {
+                return Self::process_steal(bridge, message).await;
+            }
+            "/failed" => {
+                return Self::list_failed(bridge, message).await;
+            }
+            "/filter" => {
+                return Self::process_filter(bridge, message).await;
+            }
+            "/dm" => {
+                return Self::process_dm(bridge, message).await;
+            }
+            "/reindex" => {
+                return Self::process_reindex(bridge, message).await;
+            }
+            "/status" => {
+                return Self::process_status(bridge, message).await;
+            }
+            "/endpoint" => {
+                return Self::process_endpoint(bridge, message).await;
+            }
+            "/chats" => {
+                return Self::process_chats(bridge, message).await;
+            }
+            "/autoreply" => {
+                return Self::process_autoreply(bridge, message).await;
+            }
+            "/canned" => {
+                return Self::process_canned(bridge, message).await;
+            }
+            "/c" => {
+                return Self::process_send_canned(bridge, message).await;
+            }
+            "/session" => {
+                return Self::process_session(bridge, message).await;
+            }
+            "/reload" => {
+                let html = Self::reload_config(bridge);
+                message
+                    .respond(InputMessage::html(html).reply_to(tg_helper::get_topic_id(message)))
+                    .await?;
+            }
             _ => {
                 message
-                    .respond(InputMessage::html("<b>Command not supported</b>"))
+                    .respond(InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Command not supported</b>",
+                    )))
                     .await?;
             }
         }
@@ -132,7 +428,22 @@ impl TelegramPylon {
     }
 
     async fn process_archive(bridge: &Bridge, message: &Message) -> Result<()> {
-        Self::list_archive(bridge, message).await
+        // 可以带上自定义的归档规则, 如 /archive id:100-200 或 /archive keyword:工作
+        let rule = tg_helper::get_command_args(message);
+        if !entities::archive::Model::is_valid_rule(rule) {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Invalid rule, e.g. private, group, id:1,100-200 or keyword:name</b>",
+                    ))
+                    .reply_to(tg_helper::get_topic_id(message)),
+                )
+                .await?;
+            return Ok(());
+        }
+
+        Self::list_archive(bridge, message, rule).await
     }
 
     async fn create_archive(
@@ -141,15 +452,22 @@ impl TelegramPylon {
         callback: &CommandCallback,
     ) -> Result<()> {
         match callback.data.parse::<Endpoint>() {
-            // TODO: 是否把原先的解绑然后重新绑定到当前的?还是仅仅提示绑定失败
-            Ok(endpoint) => match bridge.create_archive(&endpoint, message.chat().id()).await {
+            Ok(endpoint) => match bridge
+                .create_archive(
+                    &endpoint,
+                    message.chat().id(),
+                    tg_helper::get_packed_type(message),
+                    &callback.keyword,
+                )
+                .await
+            {
                 Ok(_) => tracing::info!("Created archive successfully"),
                 Err(e) => tracing::warn!("Failed to create archive: {:?}", e),
             },
             Err(_) => tracing::warn!("Invalid endpoint: {:?}", callback.data),
         }
 
-        Self::list_archive(bridge, message).await
+        Self::list_archive(bridge, message, "").await
     }
 
     async fn delete_archive(
@@ -165,20 +483,33 @@ impl TelegramPylon {
             Err(_) => tracing::warn!("Invalid archive id: {:?}", callback.data),
         }
 
-        Self::list_archive(bridge, message).await
+        Self::list_archive(bridge, message, &callback.keyword).await
     }
 
-    async fn list_archive(bridge: &Bridge, message: &Message) -> Result<()> {
+    // rule为命令中指定的自定义规则, 在选择规则时作为候选
+    async fn list_archive(bridge: &Bridge, message: &Message, rule: &str) -> Result<()> {
         let tg_chat_id = message.chat().id();
 
-        let mut content = "Archive: ".to_string();
+        let mut content = i18n::tr(bridge.language(), "Archive: ").to_string();
 
-        let mut archives: HashMap<Endpoint, entities::archive::Model> = HashMap::new();
-        for archive in entities::archive::Entity::find().all(&bridge.db).await? {
+        let mut archives: HashMap<Endpoint, Vec<entities::archive::Model>> = HashMap::new();
+        for archive in entities::archive::Entity::find()
+            .order_by_asc(entities::archive::Column::Id)
+            .all(&bridge.db)
+            .await?
+        {
             if archive.tg_chat_id == tg_chat_id {
-                content.push_str(archive.endpoint.to_string().as_str());
+                write!(
+                    &mut content,
+                    "\n{} ({})",
+                    bridge.endpoint_name(&archive.endpoint),
+                    entities::archive::Model::rule_name(&archive.rule)
+                )?;
             }
-            archives.insert(archive.endpoint.clone(), archive);
+            archives
+                .entry(archive.endpoint.clone())
+                .or_default()
+                .push(archive);
         }
 
         let endpoints = entities::remote_chat::Entity::find()
@@ -191,38 +522,40 @@ impl TelegramPylon {
 
         let mut markup = Vec::new();
 
-        // 构建 endpoint 的列表
+        // 构建 endpoint 的列表, 已有的归档可以删除, 点击 endpoint 选择规则新建归档
         for enpoint in &endpoints {
-            let text = format!(
-                "{}{}",
-                match archives.get(enpoint) {
-                    Some(_) => "🗃",
-                    None => "",
-                },
-                enpoint
-            );
-            let cb = match archives.get(enpoint) {
-                Some(archive) => CommandCallback::new(
+            for archive in archives.get(enpoint).into_iter().flatten() {
+                let cb = CommandCallback::new(
                     "archive",
                     "delete",
                     0,
-                    String::new(),
+                    rule.to_string(),
                     archive.id.to_string(),
-                ),
-                None => {
-                    CommandCallback::new("archive", "create", 0, String::new(), enpoint.to_string())
-                }
-            };
+                );
+                markup.push(vec![button::inline(
+                    format!(
+                        "🗃{} ({})",
+                        bridge.endpoint_name(enpoint),
+                        entities::archive::Model::rule_name(&archive.rule)
+                    ),
+                    bridge.put_callback(&cb).await?,
+                )]);
+            }
 
-            markup.push(vec![button::inline(text, bridge.put_callback(&cb))]);
+            let cb =
+                CommandCallback::new("archive", "rule", 0, rule.to_string(), enpoint.to_string());
+            markup.push(vec![button::inline(
+                bridge.endpoint_name(enpoint),
+                bridge.put_callback(&cb).await?,
+            )]);
         }
 
         // 构造取消按钮
         {
             let cb = CommandCallback::new("archive", "cancel", 0, String::new(), String::new());
             markup.push(vec![button::inline(
-                "cancel".to_string(),
-                bridge.put_callback(&cb),
+                i18n::tr(bridge.language(), "cancel").to_string(),
+                bridge.put_callback(&cb).await?,
             )]);
         }
 
@@ -244,18 +577,155 @@ impl TelegramPylon {
         Ok(())
     }
 
+    // 选择新建归档的规则, 同一端点已使用的规则不再列出
+    async fn select_archive_rule(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        let used_rules: HashSet<String> = entities::archive::Entity::find()
+            .filter(entities::archive::Column::Endpoint.eq(callback.data.as_str()))
+            .all(&bridge.db)
+            .await?
+            .into_iter()
+            .map(|archive| archive.rule)
+            .collect();
+
+        let mut rules = vec!["", "private", "group", "guild"];
+        if !rules.contains(&callback.keyword.as_str()) {
+            rules.push(&callback.keyword);
+        }
+
+        let mut markup = Vec::new();
+        for rule in rules.into_iter().filter(|rule| !used_rules.contains(*rule)) {
+            let cb = CommandCallback::new(
+                "archive",
+                "create",
+                0,
+                rule.to_string(),
+                callback.data.clone(),
+            );
+            markup.push(vec![button::inline(
+                entities::archive::Model::rule_name(rule),
+                bridge.put_callback(&cb).await?,
+            )]);
+        }
+        {
+            let cb = CommandCallback::new("archive", "cancel", 0, String::new(), String::new());
+            markup.push(vec![button::inline(
+                i18n::tr(bridge.language(), "cancel").to_string(),
+                bridge.put_callback(&cb).await?,
+            )]);
+        }
+
+        message
+            .edit(
+                InputMessage::text(i18n::trf(
+                    bridge.language(),
+                    "Archive rule of {}:",
+                    &[&callback.data],
+                ))
+                .reply_markup(&reply_markup::inline(markup)),
+            )
+            .await?;
+
+        Ok(())
+    }
+
     async fn process_link(bridge: &Bridge, message: &Message) -> Result<()> {
-        let callback = CommandCallback::new(
-            "link",
-            "list",
-            0,
-            message.text()[5..].trim().to_owned(),
-            String::new(),
-        );
+        let keyword = tg_helper::get_command_args(message);
+        match keyword {
+            "export" => return Self::export_links(bridge, message).await,
+            "import" => return Self::import_links(bridge, message).await,
+            _ => {}
+        }
+
+        let callback = CommandCallback::new("link", "list", 0, keyword.to_owned(), String::new());
 
         Self::list_link(bridge, message, &callback).await
     }
 
+    // 以CSV文件导出全部链接
+    async fn export_links(bridge: &Bridge, message: &Message) -> Result<()> {
+        let data = bridge.export_links().await?;
+        let bot = bridge.primary_bot();
+        let uploaded = bot.upload_bytes(&data, "links.csv".to_string()).await?;
+
+        bot.send_message(
+            message.chat().pack(),
+            OutgoingMessage::html(i18n::tr(
+                bridge.language(),
+                "<b>Columns: endpoint, chat_type, target_id, name, tg_chat_type, tg_chat_id, tg_topic_id</b>",
+            ))
+            .document(uploaded)
+            .reply_to(tg_helper::get_topic_id(message)),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    // 以 /link import 为标题发送CSV文件, 或回复CSV文件, 批量创建链接
+    async fn import_links(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let document = match message.media() {
+            Some(media::Media::Document(document)) => Some(document),
+            _ => match message.get_reply().await? {
+                Some(reply) => match reply.media() {
+                    Some(media::Media::Document(document)) => Some(document),
+                    _ => None,
+                },
+                None => None,
+            },
+        };
+        let Some(document) = document else {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Please send or reply to a CSV file with /link import</b>",
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        };
+
+        let (_, data) = bridge
+            .download_media(&media::Media::Document(document))
+            .await?;
+        let language = bridge.language();
+        let content = match bridge.import_links(&data).await {
+            Ok(report) => {
+                let mut content = i18n::trf(
+                    language,
+                    "<b>Imported links: {} created, {} skipped, {} failed</b>",
+                    &[&report.created, &report.skipped, &report.failures.len()],
+                );
+                for (line, error) in report.failures.iter().take(PAGE_SIZE as usize) {
+                    content.push('\n');
+                    content.push_str(&i18n::trf(
+                        language,
+                        "Line {}: {}",
+                        &[line, &html_escape::encode_text(error)],
+                    ));
+                }
+                content
+            }
+            Err(e) => i18n::trf(
+                language,
+                "<b>Failed to import links:</b> {}",
+                &[&html_escape::encode_text(&e.to_string())],
+            ),
+        };
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
     async fn create_link(
         bridge: &Bridge,
         message: &Message,
@@ -267,6 +737,7 @@ impl TelegramPylon {
                 .create_link(
                     tg_helper::get_packed_type(message),
                     message.chat().id(),
+                    tg_helper::get_forum_topic_id(message),
                     remote_chat_id,
                 )
                 .await
@@ -308,8 +779,13 @@ impl TelegramPylon {
             entities::remote_chat::Entity::find().find_also_related(entities::link::Entity);
         // 添加过滤条件
         if !callback.keyword.is_empty() {
-            query = query
-                .filter(entities::remote_chat::Column::Name.like(format!("%{}%", keyword.clone())));
+            query = query.filter(
+                Condition::any()
+                    .add(entities::remote_chat::Column::Name.like(format!("%{}%", keyword.clone())))
+                    .add(
+                        entities::remote_chat::Column::Alias.like(format!("%{}%", keyword.clone())),
+                    ),
+            );
         }
 
         let chat_pages = query
@@ -319,47 +795,59 @@ impl TelegramPylon {
         // 获取分页信息
         let pagination_info = chat_pages.num_items_and_pages().await?;
         if pagination_info.number_of_items == 0 {
-            let msg = InputMessage::html("<b>There are no remote chats avaiable</b>");
+            let msg = InputMessage::html(i18n::tr(
+                bridge.language(),
+                "<b>There are no remote chats available</b>",
+            ));
             // 如果源消息是Bot发送的，直接编辑源消息, 否则回复一条新消息
             if message.outgoing() {
                 message.edit(msg).await?;
             } else {
-                message.respond(msg).await?;
+                message
+                    .respond(msg.reply_to(tg_helper::get_topic_id(message)))
+                    .await?;
             }
             return Ok(());
         }
 
         // 获取当前链接信息
-        let content = match entities::link::Entity::find()
-            .find_also_related(entities::remote_chat::Entity)
-            .filter(entities::link::Column::TgChatId.eq(message.chat().id()))
-            .one(&bridge.db)
+        let content = match bridge
+            .find_link_by_tg(message.chat().id(), tg_helper::get_forum_topic_id(message))
             .await?
         {
-            Some((_, Some(remote_chat))) => format!(
+            Some((_, Some(remote_chat))) => i18n::trf(
+                bridge.language(),
                 "Link: 🔗{}({}) from ({})",
-                remote_chat.name, remote_chat.target_id, remote_chat.endpoint
+                &[
+                    &remote_chat.display_name(),
+                    &remote_chat.target_id,
+                    &bridge.endpoint_name(&remote_chat.endpoint),
+                ],
             ),
-            _ => "Link:".to_string(),
+            _ => i18n::tr(bridge.language(), "Link:").to_string(),
         };
 
         let mut markup = Vec::new();
 
         // 构建 remote chat 的列表
         for (chat, link) in &chat_pages.fetch_page(page).await? {
-            let text = format!(
+            let text = i18n::trf(
+                bridge.language(),
                 "{}{}{}({}) from ({})",
-                match link {
-                    Some(_) => "🔗",
-                    None => "",
-                },
-                match chat.chat_type {
-                    ChatType::Private => "👤",
-                    ChatType::Group => "👥",
-                },
-                chat.name,
-                chat.target_id,
-                chat.endpoint
+                &[
+                    &match link {
+                        Some(_) => "🔗",
+                        None => "",
+                    },
+                    &match chat.chat_type {
+                        ChatType::Private => "👤",
+                        ChatType::Group => "👥",
+                        ChatType::Guild => "#️⃣",
+                    },
+                    &chat.display_name(),
+                    &chat.target_id,
+                    &bridge.endpoint_name(&chat.endpoint),
+                ],
             );
             let cb = match link {
                 Some(link) => CommandCallback::new(
@@ -377,7 +865,7 @@ impl TelegramPylon {
                     chat.id.to_string(),
                 ),
             };
-            markup.push(vec![button::inline(text, bridge.put_callback(&cb))]);
+            markup.push(vec![button::inline(text, bridge.put_callback(&cb).await?)]);
         }
 
         // 构建分页按钮
@@ -390,14 +878,21 @@ impl TelegramPylon {
                 keyword.clone(),
                 callback.data.clone(),
             );
-            bottom.push(button::inline("< Prev", bridge.put_callback(&cb)));
+            bottom.push(button::inline(
+                i18n::tr(bridge.language(), "< Prev"),
+                bridge.put_callback(&cb).await?,
+            ));
         } else {
             bottom.push(button::inline(" ", PLACE_HOLDER));
         }
         {
-            let text = format!("{}/{} | Cancel", page + 1, pagination_info.number_of_pages);
+            let text = i18n::trf(
+                bridge.language(),
+                "{}/{} | Cancel",
+                &[&(page + 1), &pagination_info.number_of_pages],
+            );
             let cb = CommandCallback::new("link", "cancel", page, keyword.clone(), String::new());
-            bottom.push(button::inline(text, bridge.put_callback(&cb)));
+            bottom.push(button::inline(text, bridge.put_callback(&cb).await?));
         }
         if page < pagination_info.number_of_pages - 1 {
             let cb = CommandCallback::new(
@@ -407,7 +902,10 @@ impl TelegramPylon {
                 keyword.clone(),
                 callback.data.clone(),
             );
-            bottom.push(button::inline("Next >", bridge.put_callback(&cb)));
+            bottom.push(button::inline(
+                i18n::tr(bridge.language(), "Next >"),
+                bridge.put_callback(&cb).await?,
+            ));
         } else {
             bottom.push(button::inline(" ", PLACE_HOLDER));
         }
@@ -420,100 +918,489 @@ impl TelegramPylon {
                 .await?;
         } else {
             message
-                .respond(InputMessage::text(content).reply_markup(&reply_markup::inline(markup)))
+                .respond(
+                    InputMessage::text(content)
+                        .reply_to(tg_helper::get_topic_id(message))
+                        .reply_markup(&reply_markup::inline(markup)),
+                )
                 .await?;
         }
 
         Ok(())
     }
 
-    async fn process_search(bridge: &Bridge, message: &Message) -> Result<()> {
+    async fn process_chats(bridge: &Bridge, message: &Message) -> Result<()> {
         let callback = CommandCallback::new(
-            "search",
+            "chats",
             "list",
             0,
-            message.text()[7..].trim().to_owned(),
+            tg_helper::get_command_args(message).to_owned(),
             String::new(),
         );
 
-        Self::list_search(bridge, message, &callback).await
+        Self::list_chats(bridge, message, &callback).await
     }
 
-    async fn list_search(
+    // 按平台, 端点, 对话类型, linked/unlinked 过滤, 其他词按名称匹配
+    fn chats_condition(filters: &str) -> Condition {
+        let mut condition = Condition::all();
+        for word in filters.split_whitespace() {
+            condition = match word {
+                "linked" => condition.add(entities::link::Column::Id.is_not_null()),
+                "unlinked" => condition.add(entities::link::Column::Id.is_null()),
+                _ => {
+                    if word.parse::<Platform>().is_ok() {
+                        condition.add(
+                            entities::remote_chat::Column::Endpoint.like(format!("{}:%", word)),
+                        )
+                    } else if let Ok(endpoint) = word.parse::<Endpoint>() {
+                        condition.add(entities::remote_chat::Column::Endpoint.eq(endpoint))
+                    } else if let Ok(chat_type) = word.parse::<ChatType>() {
+                        condition.add(entities::remote_chat::Column::ChatType.eq(chat_type))
+                    } else {
+                        condition.add(
+                            Condition::any()
+                                .add(
+                                    entities::remote_chat::Column::Name.like(format!("%{}%", word)),
+                                )
+                                .add(
+                                    entities::remote_chat::Column::Alias
+                                        .like(format!("%{}%", word)),
+                                ),
+                        )
+                    }
+                }
+            };
+        }
+        condition
+    }
+
+    async fn list_chats(
         bridge: &Bridge,
         message: &Message,
         callback: &CommandCallback,
     ) -> Result<()> {
         let page = callback.page;
-        let keyword = callback.keyword.clone();
+        let filters = callback.keyword.clone();
 
-        // 检查关键词是否为空
-        if callback.keyword.is_empty() {
-            message
-                .respond(
-                    InputMessage::html("<b>Please input a keyword</b>")
-                        .reply_to(tg_helper::get_topic_id(message)),
-                )
-                .await?;
+        let chat_pages = entities::remote_chat::Entity::find()
+            .find_also_related(entities::link::Entity)
+            .filter(Self::chats_condition(&filters))
+            .order_by_asc(entities::remote_chat::Column::Endpoint)
+            .order_by_asc(entities::remote_chat::Column::Id)
+            .paginate(&bridge.db, PAGE_SIZE);
+
+        let pagination_info = chat_pages.num_items_and_pages().await?;
+        if pagination_info.number_of_items == 0 {
+            let msg = InputMessage::html(i18n::tr(bridge.language(), CHATS_USAGE));
+            // 如果源消息是Bot发送的，直接编辑源消息, 否则回复一条新消息
+            if message.outgoing() {
+                message.edit(msg).await?;
+            } else {
+                message
+                    .respond(msg.reply_to(tg_helper::get_topic_id(message)))
+                    .await?;
+            }
             return Ok(());
         }
 
-        let chat_id = message.chat().id();
-        let last_id = match callback.data.is_empty() {
-            true => None,
-            false => match callback.data.parse::<i32>() {
-                Ok(id) => Some(id),
-                Err(_) => None,
-            },
-        };
-        let reply_to = tg_helper::get_topic_id(message);
-        let result = bridge
-            .search_messages(
-                message.chat().id(),
-                reply_to,
-                &callback.keyword,
-                last_id,
-                PAGE_SIZE,
-            )
-            .await?;
-
-        let mut content = String::new();
-        for (message_id, timestamp, sinppet) in &result {
-            let link = match reply_to {
-                Some(reply_to) => format!("https://t.me/c/{}/{}/{}", chat_id, reply_to, message_id),
-                None => format!("https://t.me/c/{}/{}", chat_id, message_id),
-            };
+        let language = bridge.language();
+        let content = i18n::trf(
+            language,
+            "Chats: {} ({})",
+            &[
+                &if filters.is_empty() {
+                    i18n::tr(language, "all")
+                } else {
+                    filters.as_str()
+                },
+                &pagination_info.number_of_items,
+            ],
+        );
 
-            write!(
-                &mut content,
-                "{}\n<blockquote>[{}]\n{}</blockquote>",
-                link,
-                Local.timestamp_opt(*timestamp, 0).unwrap(),
-                sinppet
-            )?;
-        }
+        let mut markup = Vec::new();
 
-        // 如果无返回, 填充文本 (Telegram无法发送空消息)
-        if content.is_empty() {
-            content = "<blockquote>Have reached the edge of the world.</blockquote>".to_string();
+        // 每个对话一行, 点击查看详情和快捷操作
+        for (chat, link) in &chat_pages.fetch_page(page).await? {
+            let text = i18n::trf(
+                bridge.language(),
+                "{}{}{}({}) from ({})",
+                &[
+                    &match link {
+                        Some(_) => "🔗",
+                        None => "",
+                    },
+                    &match chat.chat_type {
+                        ChatType::Private => "👤",
+                        ChatType::Group => "👥",
+                        ChatType::Guild => "#️⃣",
+                    },
+                    &chat.display_name(),
+                    &chat.target_id,
+                    &bridge.endpoint_name(&chat.endpoint),
+                ],
+            );
+            let cb =
+                CommandCallback::new("chats", "open", page, filters.clone(), chat.id.to_string());
+            markup.push(vec![button::inline(text, bridge.put_callback(&cb).await?)]);
         }
 
         // 构建分页按钮
-        let mut markup = Vec::new();
         let mut bottom = Vec::new();
-        {
-            let cb = CommandCallback::new("search", "cancel", page, keyword.clone(), String::new());
-            bottom.push(button::inline("Cancel", bridge.put_callback(&cb)));
+        if page > 0 {
+            let cb =
+                CommandCallback::new("chats", "list", page - 1, filters.clone(), String::new());
+            bottom.push(button::inline(
+                i18n::tr(bridge.language(), "< Prev"),
+                bridge.put_callback(&cb).await?,
+            ));
+        } else {
+            bottom.push(button::inline(" ", PLACE_HOLDER));
         }
-        if result.len() == (PAGE_SIZE as usize) {
-            let cb = CommandCallback::new(
-                "search",
-                "list",
-                page,
-                keyword.clone(),
-                result.last().unwrap().0.to_string(),
+        {
+            let text = i18n::trf(
+                bridge.language(),
+                "{}/{} | Cancel",
+                &[&(page + 1), &pagination_info.number_of_pages],
             );
-            bottom.push(button::inline("Next >", bridge.put_callback(&cb)));
+            let cb = CommandCallback::new("chats", "cancel", page, filters.clone(), String::new());
+            bottom.push(button::inline(text, bridge.put_callback(&cb).await?));
+        }
+        if page < pagination_info.number_of_pages - 1 {
+            let cb =
+                CommandCallback::new("chats", "list", page + 1, filters.clone(), String::new());
+            bottom.push(button::inline(
+                i18n::tr(bridge.language(), "Next >"),
+                bridge.put_callback(&cb).await?,
+            ));
+        } else {
+            bottom.push(button::inline(" ", PLACE_HOLDER));
+        }
+        markup.push(bottom);
+
+        // 如果源消息是Bot发送的，直接编辑源消息, 否则回复一条新消息
+        if message.outgoing() {
+            message
+                .edit(InputMessage::text(content).reply_markup(&reply_markup::inline(markup)))
+                .await?;
+        } else {
+            message
+                .respond(
+                    InputMessage::text(content)
+                        .reply_to(tg_helper::get_topic_id(message))
+                        .reply_markup(&reply_markup::inline(markup)),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // 显示对话的链接和归档情况, 以及链接到此处, 归档, 打开Topic的按钮
+    async fn open_chat(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        let Some((remote_chat, link)) = entities::remote_chat::Entity::find_by_id(
+            callback.data.parse::<i64>().unwrap_or_default(),
+        )
+        .find_also_related(entities::link::Entity)
+        .one(&bridge.db)
+        .await?
+        else {
+            return Self::list_chats(bridge, message, callback).await;
+        };
+        let page = callback.page;
+        let filters = callback.keyword.clone();
+        let data = remote_chat.id.to_string();
+
+        let language = bridge.language();
+        let mut content = i18n::trf(
+            language,
+            "<b>{}</b>\nEndpoint: <code>{}</code>\nType: {}\nTarget ID: <code>{}</code>",
+            &[
+                &html_escape::encode_text(remote_chat.display_name()),
+                &html_escape::encode_text(&bridge.endpoint_name(&remote_chat.endpoint)),
+                &remote_chat.chat_type,
+                &html_escape::encode_text(&remote_chat.target_id),
+            ],
+        );
+        let mut markup = Vec::new();
+
+        match &link {
+            Some(link) => {
+                content.push_str(&i18n::trf(
+                    language,
+                    "\nLinked: <code>{}</code>",
+                    &[&link.tg_chat_id],
+                ));
+                if link.tg_topic_id > 0 {
+                    content.push_str(&i18n::trf(language, " (topic {})", &[&link.tg_topic_id]));
+                }
+                let cb =
+                    CommandCallback::new("chats", "unlink", page, filters.clone(), data.clone());
+                markup.push(vec![button::inline(
+                    i18n::tr(language, "Unlink"),
+                    bridge.put_callback(&cb).await?,
+                )]);
+            }
+            None => {
+                // 只能在群组中创建链接
+                if matches!(
+                    tg_helper::get_packed_type(message),
+                    PackedType::Chat | PackedType::Megagroup
+                ) {
+                    let cb =
+                        CommandCallback::new("chats", "link", page, filters.clone(), data.clone());
+                    markup.push(vec![button::inline(
+                        i18n::tr(language, "🔗 Link here"),
+                        bridge.put_callback(&cb).await?,
+                    )]);
+                }
+            }
+        }
+
+        let topic = entities::topic::Entity::find()
+            .find_also_related(entities::archive::Entity)
+            .filter(entities::topic::Column::RemoteChatId.eq(remote_chat.id))
+            .one(&bridge.db)
+            .await?;
+        match topic {
+            Some((topic, Some(archive))) => {
+                content.push_str(&i18n::trf(
+                    language,
+                    "\nArchived: <code>{}</code> (topic {})",
+                    &[&archive.tg_chat_id, &topic.tg_topic_id],
+                ));
+                markup.push(vec![button::url(
+                    i18n::tr(language, "🗃 Open topic"),
+                    format!(
+                        "https://t.me/c/{}/{}",
+                        archive.tg_chat_id, topic.tg_topic_id
+                    ),
+                )]);
+            }
+            _ => {
+                // 频道归档没有Topic
+                if let Some(archive) = bridge
+                    .find_archive(&remote_chat.endpoint, &remote_chat)
+                    .await?
+                    .filter(|archive| {
+                        tg_helper::unpack_chat_type(archive.tg_chat_type) != PackedType::Broadcast
+                    })
+                {
+                    content.push_str(&i18n::trf(
+                        language,
+                        "\nArchive: <code>{}</code>",
+                        &[&archive.tg_chat_id],
+                    ));
+                    let cb = CommandCallback::new(
+                        "chats",
+                        "archive",
+                        page,
+                        filters.clone(),
+                        data.clone(),
+                    );
+                    markup.push(vec![button::inline(
+                        i18n::tr(language, "🗃 Archive"),
+                        bridge.put_callback(&cb).await?,
+                    )]);
+                }
+            }
+        }
+
+        let cb = CommandCallback::new("chats", "list", page, filters.clone(), String::new());
+        markup.push(vec![button::inline(
+            i18n::tr(language, "< Back"),
+            bridge.put_callback(&cb).await?,
+        )]);
+
+        message
+            .edit(InputMessage::html(content).reply_markup(&reply_markup::inline(markup)))
+            .await?;
+
+        Ok(())
+    }
+
+    // 执行对话详情中的快捷操作, 完成后刷新详情
+    async fn chat_action(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        let Ok(remote_chat_id) = callback.data.parse::<i64>() else {
+            tracing::warn!("Invalid remote chat id: {:?}", callback.data);
+            return Ok(());
+        };
+
+        let result = match callback.action.as_str() {
+            "link" => {
+                bridge
+                    .create_link(
+                        tg_helper::get_packed_type(message),
+                        message.chat().id(),
+                        tg_helper::get_forum_topic_id(message),
+                        remote_chat_id,
+                    )
+                    .await
+            }
+            "unlink" => match entities::link::Entity::find()
+                .filter(entities::link::Column::RemoteChatId.eq(remote_chat_id))
+                .one(&bridge.db)
+                .await?
+            {
+                Some(link) => bridge.delete_link(link.id).await,
+                None => Ok(()),
+            },
+            "archive" => {
+                match entities::remote_chat::Entity::find_by_id(remote_chat_id)
+                    .one(&bridge.db)
+                    .await?
+                {
+                    Some(remote_chat) => {
+                        match bridge
+                            .find_archive(&remote_chat.endpoint, &remote_chat)
+                            .await?
+                        {
+                            Some(archive) => bridge
+                                .get_or_create_topic(&archive, &remote_chat)
+                                .await
+                                .map(|_| ()),
+                            None => Ok(()),
+                        }
+                    }
+                    None => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to {} chat {}: {:?}",
+                callback.action,
+                remote_chat_id,
+                e
+            );
+        }
+
+        Self::open_chat(bridge, message, callback).await
+    }
+
+    async fn process_search(bridge: &Bridge, message: &Message) -> Result<()> {
+        let callback = CommandCallback::new(
+            "search",
+            "list",
+            0,
+            tg_helper::get_command_args(message).to_owned(),
+            String::new(),
+        );
+
+        Self::list_search(bridge, message, &callback).await
+    }
+
+    async fn list_search(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        let page = callback.page;
+        let keyword = callback.keyword.clone();
+
+        // 解析过滤条件, 如 from:张三 after:2024-01-01
+        let search = match SearchQuery::parse(&keyword) {
+            Ok(search) => search,
+            Err(e) => {
+                message
+                    .respond(
+                        InputMessage::html(i18n::trf(
+                            bridge.language(),
+                            "<b>Invalid search filter:</b> {}",
+                            &[&html_escape::encode_text(&e.to_string())],
+                        ))
+                        .reply_to(tg_helper::get_topic_id(message)),
+                    )
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        // 检查关键词和过滤条件是否为空
+        if search.is_empty() {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Please input a keyword or filters: from:name in:chat type:private|group|guild after:YYYY-MM-DD before:YYYY-MM-DD</b>",
+                    ))
+                    .reply_to(tg_helper::get_topic_id(message)),
+                )
+                .await?;
+            return Ok(());
+        }
+
+        let chat_id = message.chat().id();
+        let last_id = match callback.data.is_empty() {
+            true => None,
+            false => match callback.data.parse::<i32>() {
+                Ok(id) => Some(id),
+                Err(_) => None,
+            },
+        };
+        let reply_to = tg_helper::get_topic_id(message);
+        // 在Topic中只搜索该Topic, 回复普通消息时不限制范围
+        let topic_id = Some(tg_helper::get_forum_topic_id(message)).filter(|id| *id > 0);
+        let result = bridge
+            .search_messages(message.chat().id(), topic_id, &search, last_id, PAGE_SIZE)
+            .await?;
+
+        let mut content = String::new();
+        for (message_id, timestamp, sinppet) in &result {
+            let link = match topic_id {
+                Some(topic_id) => format!("https://t.me/c/{}/{}/{}", chat_id, topic_id, message_id),
+                None => format!("https://t.me/c/{}/{}", chat_id, message_id),
+            };
+
+            write!(
+                &mut content,
+                "{}\n<blockquote>[{}]\n{}</blockquote>",
+                link,
+                Local.timestamp_opt(*timestamp, 0).unwrap(),
+                sinppet
+            )?;
+        }
+
+        // 如果无返回, 填充文本 (Telegram无法发送空消息)
+        if content.is_empty() {
+            content = i18n::tr(
+                bridge.language(),
+                "<blockquote>Have reached the edge of the world.</blockquote>",
+            )
+            .to_string();
+        }
+
+        // 构建分页按钮
+        let mut markup = Vec::new();
+        let mut bottom = Vec::new();
+        {
+            let cb = CommandCallback::new("search", "cancel", page, keyword.clone(), String::new());
+            bottom.push(button::inline(
+                i18n::tr(bridge.language(), "Cancel"),
+                bridge.put_callback(&cb).await?,
+            ));
+        }
+        if result.len() == (PAGE_SIZE as usize) {
+            let cb = CommandCallback::new(
+                "search",
+                "list",
+                page,
+                keyword.clone(),
+                result.last().unwrap().0.to_string(),
+            );
+            bottom.push(button::inline(
+                i18n::tr(bridge.language(), "Next >"),
+                bridge.put_callback(&cb).await?,
+            ));
         }
         markup.push(bottom);
 
@@ -535,9 +1422,2382 @@ impl TelegramPylon {
         Ok(())
     }
 
-    async fn cancel(_: &Bridge, message: &Message, _: &CommandCallback) -> Result<()> {
+    async fn process_mute(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let remote_chat = match bridge.find_remote_chat_by_tg(message).await? {
+            Some(remote_chat) => remote_chat,
+            None => {
+                message
+                    .respond(
+                        InputMessage::html(i18n::tr(
+                            bridge.language(),
+                            "<b>Mute is only supported in linked groups or archive topics</b>",
+                        ))
+                        .reply_to(reply_to),
+                    )
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        // 不指定时长则一直静音
+        let duration = tg_helper::get_command_args(message);
+        let (muted_until, content) = if duration.is_empty() {
+            (
+                i64::MAX,
+                i18n::trf(
+                    bridge.language(),
+                    "<b>Muted {} until unmuted</b>",
+                    &[&html_escape::encode_text(remote_chat.display_name())],
+                ),
+            )
+        } else {
+            let until = tg_helper::parse_duration(duration).and_then(|seconds| {
+                let muted_until = Utc::now().timestamp().checked_add(seconds)?;
+                Some((muted_until, Local.timestamp_opt(muted_until, 0).single()?))
+            });
+            match until {
+                Some((muted_until, until)) => (
+                    muted_until,
+                    i18n::trf(
+                        bridge.language(),
+                        "<b>Muted {} until {}</b>",
+                        &[
+                            &html_escape::encode_text(remote_chat.display_name()),
+                            &until,
+                        ],
+                    ),
+                ),
+                None => {
+                    message
+                        .respond(
+                            InputMessage::html(i18n::tr(
+                                bridge.language(),
+                                "<b>Invalid duration, e.g. 30m, 2h, 1d (up to 30d)</b>",
+                            ))
+                            .reply_to(reply_to),
+                        )
+                        .await?;
+                    return Ok(());
+                }
+            }
+        };
+
+        bridge.mute_chat(remote_chat.id, muted_until).await?;
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    // 列出已连接端点的实现, 在线状态, 心跳和等待中的API调用, 或当前对话的发送状态
+    async fn process_status(bridge: &Bridge, message: &Message) -> Result<()> {
+        let args = tg_helper::get_command_args(message);
+        if let Some(args) = args.strip_prefix("link") {
+            return Self::process_link_status(bridge, message, args.trim()).await;
+        }
+
+        let mut endpoints = bridge
+            .stats
+            .endpoints
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect::<Vec<_>>();
+        endpoints.sort_by_key(|(endpoint, _)| endpoint.to_string());
+        let queues = EventQueue::stats();
+
+        let language = bridge.language();
+        let mut content = String::new();
+        for (endpoint, connected_at) in &endpoints {
+            let (status, version) = tokio::join!(
+                tokio::time::timeout(STATUS_TIMEOUT, bridge.get_status(endpoint)),
+                tokio::time::timeout(STATUS_TIMEOUT, bridge.get_version_info(endpoint)),
+            );
+
+            write!(
+                &mut content,
+                "<b>{}</b> ({})\n",
+                html_escape::encode_text(&bridge.endpoint_name(endpoint)),
+                endpoint.platform
+            )?;
+            match version {
+                Ok(Ok(version)) => content.push_str(&i18n::trf(
+                    language,
+                    "Impl: {} {} (OneBot {})\n",
+                    &[
+                        &html_escape::encode_text(&version.app_name),
+                        &html_escape::encode_text(&version.app_version),
+                        &html_escape::encode_text(&version.protocol_version),
+                    ],
+                )),
+                _ => content.push_str(i18n::tr(language, "Impl: unknown\n")),
+            }
+            match status {
+                Ok(Ok(status)) => content.push_str(&i18n::trf(
+                    language,
+                    "Online: {}, {}\n",
+                    &[
+                        &i18n::tr(
+                            language,
+                            match status.online {
+                                Some(true) => "yes",
+                                Some(false) => "no",
+                                None => "unknown",
+                            },
+                        ),
+                        &i18n::tr(language, if status.good { "good" } else { "not good" }),
+                    ],
+                )),
+                Ok(Err(e)) => content.push_str(&i18n::trf(
+                    language,
+                    "Online: unknown ({})\n",
+                    &[&html_escape::encode_text(&e.to_string())],
+                )),
+                Err(_) => content.push_str(i18n::tr(language, "Online: unknown (timeout)\n")),
+            }
+            content.push_str(&i18n::trf(
+                language,
+                "Connected at: {}\n",
+                &[&Local.timestamp_opt(*connected_at, 0).unwrap()],
+            ));
+            match bridge.stats.heartbeats.get(endpoint) {
+                Some(heartbeat) => content.push_str(&i18n::trf(
+                    language,
+                    "Last heartbeat: {}\n",
+                    &[&Local.timestamp_opt(*heartbeat, 0).unwrap()],
+                )),
+                None => content.push_str(i18n::tr(language, "Last heartbeat: none\n")),
+            }
+            content.push_str(&i18n::trf(
+                language,
+                "Pending API calls: {}\n",
+                &[&OnebotPylon::pending_calls(endpoint)],
+            ));
+            if let Some(queue) = queues.iter().find(|q| &q.endpoint == endpoint) {
+                content.push_str(&i18n::trf(
+                    language,
+                    "Event queue: {}/{}, dropped: {}\n",
+                    &[&queue.depth, &queue.capacity, &queue.total_dropped],
+                ));
+            }
+            content.push('\n');
+        }
+
+        if content.is_empty() {
+            content = i18n::tr(language, "<b>No endpoint connected</b>").to_string();
+        }
+        message
+            .respond(
+                InputMessage::html(content.trim_end()).reply_to(tg_helper::get_topic_id(message)),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    // 统计远端对话最近24小时发往远端的消息状态, 不指定对话ID时使用当前群组或话题的对话
+    async fn process_link_status(bridge: &Bridge, message: &Message, args: &str) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let remote_chat = match args.parse::<i64>() {
+            Ok(id) => {
+                entities::remote_chat::Entity::find_by_id(id)
+                    .one(&bridge.db)
+                    .await?
+            }
+            Err(_) if args.is_empty() => bridge.find_remote_chat_by_tg(message).await?,
+            Err(_) => None,
+        };
+        let Some(remote_chat) = remote_chat else {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(bridge.language(), LINK_STATUS_USAGE))
+                        .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        };
+
+        let since = Utc::now().timestamp() - 24 * 60 * 60;
+        let stats = bridge.delivery_stats(remote_chat.id, since).await?;
+        let count = |status: DeliveryStatus| {
+            stats
+                .iter()
+                .find(|(s, _)| *s == status)
+                .map_or(0, |(_, count)| *count)
+        };
+
+        let language = bridge.language();
+        let mut content = i18n::trf(
+            language,
+            "<b>Delivery status of {} (24h)</b>\nSent: {}\nPending: {}\nFailed: {}\nRecalled: {}",
+            &[
+                &html_escape::encode_text(remote_chat.display_name()),
+                &count(DeliveryStatus::Sent),
+                &count(DeliveryStatus::Pending),
+                &count(DeliveryStatus::Failed),
+                &count(DeliveryStatus::Recalled),
+            ],
+        );
+        if let Some(failed) = bridge.last_delivery_failure(remote_chat.id, since).await? {
+            content.push_str(&i18n::trf(
+                language,
+                "\nLast error ({}): {}",
+                &[
+                    &Local
+                        .timestamp_opt(failed.updated_at, 0)
+                        .unwrap()
+                        .format("%m-%d %H:%M"),
+                    &html_escape::encode_text(failed.error.as_deref().unwrap_or_default()),
+                ],
+            ));
+        }
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    // 查看各Bot的会话状态, 或重新登录指定的Bot
+    async fn process_session(bridge: &Bridge, message: &Message) -> Result<()> {
+        let args = tg_helper::get_command_args(message);
+        let content = match args.split_once(char::is_whitespace).unwrap_or((args, "")) {
+            ("", _) | ("info", _) => {
+                let language = bridge.language();
+                let health = i18n::tr(language, "Health");
+                let dc_label = i18n::tr(language, "DC");
+                let key_age = i18n::tr(language, "Auth key age");
+                let unknown = i18n::tr(language, "unknown");
+                let mut content = String::new();
+                for (index, bot) in bridge.bots().iter().enumerate() {
+                    let me = tokio::time::timeout(STATUS_TIMEOUT, bot.get_me()).await;
+                    write!(
+                        &mut content,
+                        "<b>{}. {}</b>\n",
+                        index,
+                        match &me {
+                            Ok(Ok(me)) =>
+                                html_escape::encode_text(me.username.as_deref().unwrap_or_default())
+                                    .to_string(),
+                            _ => unknown.to_string(),
+                        }
+                    )?;
+                    match me {
+                        Ok(Ok(_)) => {
+                            write!(&mut content, "{}: {}\n", health, i18n::tr(language, "good"))?
+                        }
+                        Ok(Err(e)) => write!(
+                            &mut content,
+                            "{}: {}\n",
+                            health,
+                            html_escape::encode_text(&e.to_string())
+                        )?,
+                        Err(_) => write!(
+                            &mut content,
+                            "{}: {}\n",
+                            health,
+                            i18n::tr(language, "timeout")
+                        )?,
+                    }
+                    match bot.transport().dc_id() {
+                        Some(dc) => write!(&mut content, "{}: {}\n", dc_label, dc)?,
+                        None => write!(&mut content, "{}: {}\n", dc_label, unknown)?,
+                    }
+                    // 会话文件只在登录时写入, 以修改时间作为授权密钥的创建时间
+                    let modified = match bot.transport().session_file() {
+                        Some(file) => tokio::fs::metadata(file)
+                            .await
+                            .and_then(|metadata| metadata.modified())
+                            .ok(),
+                        None => None,
+                    };
+                    match modified {
+                        Some(modified) => {
+                            let modified = DateTime::<Local>::from(modified);
+                            write!(
+                                &mut content,
+                                "{}: {}\n",
+                                key_age,
+                                i18n::trf(
+                                    language,
+                                    "{} days (since {})",
+                                    &[
+                                        &(Local::now() - modified).num_days(),
+                                        &modified.format("%Y-%m-%d %H:%M"),
+                                    ],
+                                )
+                            )?;
+                        }
+                        None => write!(&mut content, "{}: {}\n", key_age, unknown)?,
+                    }
+                    content.push('\n');
+                }
+                content.trim_end().to_string()
+            }
+            ("reset", rest) => match rest.trim() {
+                "" => Self::reset_session(bridge, 0).await,
+                index => match index.parse::<usize>() {
+                    Ok(index) => Self::reset_session(bridge, index).await,
+                    Err(_) => i18n::tr(bridge.language(), SESSION_USAGE).to_string(),
+                },
+            },
+            _ => i18n::tr(bridge.language(), SESSION_USAGE).to_string(),
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(tg_helper::get_topic_id(message)))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn reset_session(bridge: &Bridge, index: usize) -> String {
+        match bridge.reset_session(index).await {
+            Ok(()) => i18n::trf(
+                bridge.language(),
+                "<b>Bot {} signed in again</b>",
+                &[&index],
+            ),
+            Err(e) => {
+                tracing::warn!("Failed to reset session of bot {}: {}", index, e);
+                i18n::trf(
+                    bridge.language(),
+                    "<b>Failed to reset session of bot {}:</b> {}",
+                    &[&index, &html_escape::encode_text(&e.to_string())],
+                )
+            }
+        }
+    }
+
+    // 列出端点及其别名, 或设置/清除端点的别名
+    async fn process_endpoint(bridge: &Bridge, message: &Message) -> Result<()> {
+        let args = tg_helper::get_command_args(message);
+        let content = match args.split_once(char::is_whitespace).unwrap_or((args, "")) {
+            ("", _) | ("list", _) => {
+                // 已保存对话的端点加上当前连接的端点
+                let mut endpoints = entities::remote_chat::Entity::find()
+                    .select_only()
+                    .column(entities::remote_chat::Column::Endpoint)
+                    .distinct()
+                    .into_tuple::<Endpoint>()
+                    .all(&bridge.db)
+                    .await?;
+                for entry in bridge.stats.endpoints.iter() {
+                    if !endpoints.contains(entry.key()) {
+                        endpoints.push(entry.key().clone());
+                    }
+                }
+                endpoints.sort_by_key(|endpoint| endpoint.to_string());
+
+                let mut content = i18n::tr(bridge.language(), "<b>Endpoints:</b>").to_string();
+                for endpoint in &endpoints {
+                    write!(
+                        &mut content,
+                        "\n{} <code>{}</code>{}",
+                        if bridge.stats.endpoints.contains_key(endpoint) {
+                            "🟢"
+                        } else {
+                            "⚪️"
+                        },
+                        html_escape::encode_text(&endpoint.to_string()),
+                        match bridge.endpoint_alias(endpoint) {
+                            Some(alias) => format!(" {}", html_escape::encode_text(&alias)),
+                            None => String::new(),
+                        }
+                    )?;
+                }
+                content
+            }
+            ("rename", rest) => {
+                let rest = rest.trim();
+                let (endpoint, alias) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                match endpoint.parse::<Endpoint>() {
+                    Ok(endpoint) => {
+                        let alias = alias.trim();
+                        bridge.set_endpoint_alias(&endpoint, alias).await?;
+                        if alias.is_empty() {
+                            i18n::trf(
+                                bridge.language(),
+                                "<b>Cleared alias of {}</b>",
+                                &[&html_escape::encode_text(&endpoint.to_string())],
+                            )
+                        } else {
+                            i18n::trf(
+                                bridge.language(),
+                                "<b>Renamed {} to {}</b>",
+                                &[
+                                    &html_escape::encode_text(&endpoint.to_string()),
+                                    &html_escape::encode_text(alias),
+                                ],
+                            )
+                        }
+                    }
+                    Err(_) => i18n::tr(bridge.language(), ENDPOINT_USAGE).to_string(),
+                }
+            }
+            _ => i18n::tr(bridge.language(), ENDPOINT_USAGE).to_string(),
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(tg_helper::get_topic_id(message)))
+            .await?;
+
+        Ok(())
+    }
+
+    // 显示当前链接群或归档Topic对应的远端对话
+    async fn process_info(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let remote_chat = match bridge.find_remote_chat_by_tg(message).await? {
+            Some(remote_chat) => remote_chat,
+            None => {
+                message
+                    .respond(
+                        InputMessage::html(i18n::tr(
+                            bridge.language(),
+                            "<b>No remote chat is linked or archived here</b>",
+                        ))
+                        .reply_to(reply_to),
+                    )
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        let language = bridge.language();
+        let mut content = i18n::trf(
+            language,
+            "<b>{}</b>\nEndpoint: <code>{}</code>\nType: {}\nTarget ID: <code>{}</code>\nName: {}",
+            &[
+                &html_escape::encode_text(remote_chat.display_name()),
+                &html_escape::encode_text(&bridge.endpoint_name(&remote_chat.endpoint)),
+                &remote_chat.chat_type,
+                &html_escape::encode_text(&remote_chat.target_id),
+                &html_escape::encode_text(&remote_chat.name),
+            ],
+        );
+        if !remote_chat.alias.is_empty() {
+            content.push_str(&i18n::trf(
+                language,
+                "\nAlias: {}",
+                &[&html_escape::encode_text(&remote_chat.alias)],
+            ));
+        }
+
+        match bridge.find_link_by_remote(remote_chat.id).await? {
+            Some(link) if link.tg_topic_id > 0 => content.push_str(&i18n::trf(
+                language,
+                "\nLink: #{} ({} topic {})",
+                &[&link.id, &link.tg_chat_id, &link.tg_topic_id],
+            )),
+            Some(link) => content.push_str(&i18n::trf(
+                language,
+                "\nLink: #{} ({})",
+                &[&link.id, &link.tg_chat_id],
+            )),
+            None => content.push_str(i18n::tr(language, "\nLink: none")),
+        }
+        match entities::topic::Entity::find()
+            .find_also_related(entities::archive::Entity)
+            .filter(entities::topic::Column::RemoteChatId.eq(remote_chat.id))
+            .one(&bridge.db)
+            .await?
+        {
+            Some((topic, Some(archive))) => content.push_str(&i18n::trf(
+                language,
+                "\nArchive: #{} ({} topic {})",
+                &[&archive.id, &archive.tg_chat_id, &topic.tg_topic_id],
+            )),
+            _ => content.push_str(i18n::tr(language, "\nArchive: none")),
+        }
+
+        if let Some(settings) = bridge.get_chat_settings(remote_chat.id).await? {
+            if settings.muted_until > Utc::now().timestamp() {
+                match settings.muted_until {
+                    i64::MAX => content.push_str(i18n::tr(language, "\nMuted: until unmuted")),
+                    muted_until => match Local.timestamp_opt(muted_until, 0).single() {
+                        Some(until) => {
+                            content.push_str(&i18n::trf(language, "\nMuted: until {}", &[&until]))
+                        }
+                        None => content.push_str(i18n::tr(language, "\nMuted: until unmuted")),
+                    },
+                }
+            }
+        }
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn process_unmute(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let content = match bridge.find_remote_chat_by_tg(message).await? {
+            Some(remote_chat) => {
+                bridge.mute_chat(remote_chat.id, 0).await?;
+                i18n::trf(
+                    bridge.language(),
+                    "<b>Unmuted {}</b>",
+                    &[&html_escape::encode_text(remote_chat.display_name())],
+                )
+            }
+            None => i18n::tr(
+                bridge.language(),
+                "<b>Unmute is only supported in linked groups or archive topics</b>",
+            )
+            .to_string(),
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn process_digest(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let remote_chat = match bridge.find_remote_chat_by_tg(message).await? {
+            Some(remote_chat) => remote_chat,
+            None => {
+                message
+                    .respond(
+                        InputMessage::html(i18n::tr(
+                            bridge.language(),
+                            "<b>Digest is only supported in linked groups or archive topics</b>",
+                        ))
+                        .reply_to(reply_to),
+                    )
+                    .await?;
+                return Ok(());
+            }
+        };
+        let name = html_escape::encode_text(remote_chat.display_name()).to_string();
+
+        // 不带参数时显示当前设置
+        let language = bridge.language();
+        let interval = tg_helper::get_command_args(message);
+        let content = match interval {
+            "" => match bridge.get_chat_settings(remote_chat.id).await? {
+                Some(settings) if settings.digest_interval > 0 => i18n::trf(
+                    language,
+                    "<b>Digest of {} is sent every {} minutes</b>",
+                    &[&name, &settings.digest_interval],
+                ),
+                _ => i18n::trf(language, "<b>Digest of {} is disabled</b>", &[&name]),
+            },
+            "off" | "0" => {
+                bridge.set_digest_interval(remote_chat.id, 0).await?;
+                i18n::trf(language, "<b>Disabled digest of {}</b>", &[&name])
+            }
+            _ => match interval.parse::<i64>() {
+                Ok(minutes) if minutes > 0 => {
+                    bridge.set_digest_interval(remote_chat.id, minutes).await?;
+                    i18n::trf(
+                        language,
+                        "<b>Digest of {} will be sent every {} minutes</b>",
+                        &[&name, &minutes],
+                    )
+                }
+                _ => i18n::tr(
+                    language,
+                    "<b>Invalid interval, e.g. /digest 30 or /digest off</b>",
+                )
+                .to_string(),
+            },
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn process_translate(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        if bridge.translate.is_none() {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Translation provider is not configured</b>",
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        }
+
+        let remote_chat = match bridge.find_remote_chat_by_tg(message).await? {
+            Some(remote_chat) => remote_chat,
+            None => {
+                message
+                    .respond(
+                        InputMessage::html(i18n::tr(
+                            bridge.language(),
+                            "<b>Translation is only supported in linked groups or archive topics</b>",
+                        ))
+                        .reply_to(reply_to),
+                    )
+                    .await?;
+                return Ok(());
+            }
+        };
+        let name = html_escape::encode_text(remote_chat.display_name()).to_string();
+
+        // 不带参数时显示当前设置
+        let languages = tg_helper::get_command_args(message).to_lowercase();
+        let content = match languages.as_str() {
+            "" => match bridge.get_chat_settings(remote_chat.id).await? {
+                Some(settings) if !settings.translate.is_empty() => i18n::trf(
+                    bridge.language(),
+                    "<b>Messages of {} are translated as {}</b>",
+                    &[&name, &settings.translate],
+                ),
+                _ => i18n::trf(
+                    bridge.language(),
+                    "<b>Translation of {} is disabled</b>",
+                    &[&name],
+                ),
+            },
+            "off" => {
+                bridge.set_translate(remote_chat.id, "").await?;
+                i18n::trf(
+                    bridge.language(),
+                    "<b>Disabled translation of {}</b>",
+                    &[&name],
+                )
+            }
+            _ => match TranslateService::parse_languages(&languages) {
+                Some(_) => {
+                    bridge.set_translate(remote_chat.id, &languages).await?;
+                    i18n::trf(
+                        bridge.language(),
+                        "<b>Messages of {} will be translated as {}</b>",
+                        &[&name, &html_escape::encode_text(&languages)],
+                    )
+                }
+                None => i18n::tr(
+                    bridge.language(),
+                    "<b>Invalid languages, e.g. /translate zh-en or /translate off</b>",
+                )
+                .to_string(),
+            },
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn process_transcribe(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        if bridge.transcribe.is_none() {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Transcription provider is not configured</b>",
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        }
+
+        let remote_chat = match bridge.find_remote_chat_by_tg(message).await? {
+            Some(remote_chat) => remote_chat,
+            None => {
+                message
+                    .respond(
+                        InputMessage::html(i18n::tr(
+                            bridge.language(),
+                            "<b>Transcription is only supported in linked groups or archive topics</b>",
+                        ))
+                        .reply_to(reply_to),
+                    )
+                    .await?;
+                return Ok(());
+            }
+        };
+        let name = html_escape::encode_text(remote_chat.display_name()).to_string();
+
+        // 不带参数时显示当前设置
+        let language = tg_helper::get_command_args(message).to_lowercase();
+        let content = match language.as_str() {
+            "" => match bridge.get_chat_settings(remote_chat.id).await? {
+                Some(settings) if !settings.transcribe.is_empty() => i18n::trf(
+                    bridge.language(),
+                    "<b>Voice messages of {} are transcribed as {}</b>",
+                    &[&name, &settings.transcribe],
+                ),
+                _ => i18n::trf(
+                    bridge.language(),
+                    "<b>Transcription of {} is disabled</b>",
+                    &[&name],
+                ),
+            },
+            "off" => {
+                bridge.set_transcribe(remote_chat.id, "").await?;
+                i18n::trf(
+                    bridge.language(),
+                    "<b>Disabled transcription of {}</b>",
+                    &[&name],
+                )
+            }
+            _ if TranscribeService::is_valid_language(&language) => {
+                bridge.set_transcribe(remote_chat.id, &language).await?;
+                i18n::trf(
+                    bridge.language(),
+                    "<b>Voice messages of {} will be transcribed as {}</b>",
+                    &[&name, &html_escape::encode_text(&language)],
+                )
+            }
+            _ => i18n::tr(
+                bridge.language(),
+                "<b>Invalid language, e.g. /transcribe zh, /transcribe auto or /transcribe off</b>",
+            )
+            .to_string(),
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn process_settings(bridge: &Bridge, message: &Message) -> Result<()> {
+        match bridge.find_remote_chat_by_tg(message).await? {
+            Some(remote_chat) => Self::list_settings(bridge, message, &remote_chat).await,
+            None => {
+                message
+                    .respond(
+                        InputMessage::html(i18n::tr(
+                            bridge.language(),
+                            "<b>Settings are only supported in linked groups or archive topics</b>",
+                        ))
+                        .reply_to(tg_helper::get_topic_id(message)),
+                    )
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+
+    // 点击媒体类型依次切换转发方式: upload -> link -> drop
+    async fn toggle_media_mode(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        let Some((remote_chat_id, kind)) = callback.data.split_once(':').and_then(|(id, kind)| {
+            Some((id.parse::<i64>().ok()?, kind.parse::<MediaKind>().ok()?))
+        }) else {
+            tracing::warn!("Invalid media setting: {:?}", callback.data);
+            return Ok(());
+        };
+        let Some(remote_chat) = entities::remote_chat::Entity::find_by_id(remote_chat_id)
+            .one(&bridge.db)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let policy = bridge
+            .get_chat_settings(remote_chat_id)
+            .await?
+            .map(|settings| settings.media_policy.clone())
+            .unwrap_or_default();
+        let mode = entities::chat_settings::Model::parse_media_mode(&policy, kind).next();
+        bridge
+            .set_media_policy(
+                remote_chat_id,
+                &entities::chat_settings::Model::with_media_mode(&policy, kind, mode),
+            )
+            .await?;
+
+        Self::list_settings(bridge, message, &remote_chat).await
+    }
+
+    // 点击依次切换群成员变动的提示级别: all -> kick -> off
+    async fn toggle_member_notice(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        let Ok(remote_chat_id) = callback.data.parse::<i64>() else {
+            tracing::warn!("Invalid member notice setting: {:?}", callback.data);
+            return Ok(());
+        };
+        let Some(remote_chat) = entities::remote_chat::Entity::find_by_id(remote_chat_id)
+            .one(&bridge.db)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let notice = bridge.get_member_notice(remote_chat_id).await.next();
+        bridge.set_member_notice(remote_chat_id, notice).await?;
+
+        Self::list_settings(bridge, message, &remote_chat).await
+    }
+
+    // 开启或关闭发送前的预览确认
+    async fn toggle_confirm_send(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        let Ok(remote_chat_id) = callback.data.parse::<i64>() else {
+            tracing::warn!("Invalid confirm setting: {:?}", callback.data);
+            return Ok(());
+        };
+        let Some(remote_chat) = entities::remote_chat::Entity::find_by_id(remote_chat_id)
+            .one(&bridge.db)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let confirm_send = !bridge.is_confirm_send(remote_chat_id).await;
+        bridge
+            .set_confirm_send(remote_chat_id, confirm_send)
+            .await?;
+
+        Self::list_settings(bridge, message, &remote_chat).await
+    }
+
+    // 开启或关闭群荣誉和运气王提示
+    async fn toggle_honor_notice(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        let Ok(remote_chat_id) = callback.data.parse::<i64>() else {
+            tracing::warn!("Invalid honor notice setting: {:?}", callback.data);
+            return Ok(());
+        };
+        let Some(remote_chat) = entities::remote_chat::Entity::find_by_id(remote_chat_id)
+            .one(&bridge.db)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let honor_notice = !bridge.is_honor_notice(remote_chat_id).await;
+        bridge
+            .set_honor_notice(remote_chat_id, honor_notice)
+            .await?;
+
+        Self::list_settings(bridge, message, &remote_chat).await
+    }
+
+    // 开启或关闭转发消息附带的原消息标识
+    async fn toggle_source_link(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        let Ok(remote_chat_id) = callback.data.parse::<i64>() else {
+            tracing::warn!("Invalid source link setting: {:?}", callback.data);
+            return Ok(());
+        };
+        let Some(remote_chat) = entities::remote_chat::Entity::find_by_id(remote_chat_id)
+            .one(&bridge.db)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let source_link = !bridge.is_source_link(remote_chat_id).await;
+        bridge.set_source_link(remote_chat_id, source_link).await?;
+
+        Self::list_settings(bridge, message, &remote_chat).await
+    }
+
+    // 切换发往远端的消息的署名
+    async fn toggle_signature(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        let Ok(remote_chat_id) = callback.data.parse::<i64>() else {
+            tracing::warn!("Invalid signature setting: {:?}", callback.data);
+            return Ok(());
+        };
+        let Some(remote_chat) = entities::remote_chat::Entity::find_by_id(remote_chat_id)
+            .one(&bridge.db)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let signature = bridge.get_signature(remote_chat_id).await.next();
+        bridge.set_signature(remote_chat_id, signature).await?;
+
+        Self::list_settings(bridge, message, &remote_chat).await
+    }
+
+    async fn list_settings(
+        bridge: &Bridge,
+        message: &Message,
+        remote_chat: &entities::remote_chat::Model,
+    ) -> Result<()> {
+        let language = bridge.language();
+        let on_off = |on: bool| i18n::tr(language, if on { "on" } else { "off" });
+        let content = i18n::trf(language, "Settings of {}:", &[&remote_chat.display_name()]);
+        let settings = bridge.get_chat_settings(remote_chat.id).await?;
+
+        let mut markup = Vec::new();
+        for kind in MediaKind::ALL {
+            let mode = settings
+                .as_ref()
+                .map_or(MediaMode::Upload, |settings| settings.media_mode(kind));
+            let cb = CommandCallback::new(
+                "settings",
+                "media",
+                0,
+                String::new(),
+                format!("{}:{}", remote_chat.id, kind),
+            );
+            markup.push(vec![button::inline(
+                format!("{}: {}", kind, mode),
+                bridge.put_callback(&cb).await?,
+            )]);
+        }
+
+        // 群成员变动提示, 只对群组有效
+        if remote_chat.chat_type == ChatType::Group {
+            let notice = settings
+                .as_ref()
+                .map_or(MemberNotice::All, |settings| settings.member_notice());
+            let cb = CommandCallback::new(
+                "settings",
+                "member",
+                0,
+                String::new(),
+                remote_chat.id.to_string(),
+            );
+            markup.push(vec![button::inline(
+                i18n::trf(language, "member: {}", &[&notice]),
+                bridge.put_callback(&cb).await?,
+            )]);
+
+            // 群荣誉和运气王提示
+            let honor_notice = settings
+                .as_ref()
+                .is_none_or(|settings| settings.honor_notice);
+            let cb = CommandCallback::new(
+                "settings",
+                "honor",
+                0,
+                String::new(),
+                remote_chat.id.to_string(),
+            );
+            markup.push(vec![button::inline(
+                i18n::trf(language, "honor: {}", &[&on_off(honor_notice)]),
+                bridge.put_callback(&cb).await?,
+            )]);
+        }
+
+        // 发送前预览确认
+        {
+            let confirm_send = settings
+                .as_ref()
+                .is_some_and(|settings| settings.confirm_send);
+            let cb = CommandCallback::new(
+                "settings",
+                "confirm",
+                0,
+                String::new(),
+                remote_chat.id.to_string(),
+            );
+            markup.push(vec![button::inline(
+                i18n::trf(language, "confirm: {}", &[&on_off(confirm_send)]),
+                bridge.put_callback(&cb).await?,
+            )]);
+        }
+
+        // 发往远端的消息的署名
+        {
+            let signature = settings
+                .as_ref()
+                .map_or(Signature::Default, |settings| settings.signature());
+            let cb = CommandCallback::new(
+                "settings",
+                "signature",
+                0,
+                String::new(),
+                remote_chat.id.to_string(),
+            );
+            markup.push(vec![button::inline(
+                i18n::trf(language, "signature: {}", &[&signature]),
+                bridge.put_callback(&cb).await?,
+            )]);
+        }
+
+        // 转发的消息附带原消息标识
+        {
+            let source_link = settings
+                .as_ref()
+                .is_some_and(|settings| settings.source_link);
+            let cb = CommandCallback::new(
+                "settings",
+                "source",
+                0,
+                String::new(),
+                remote_chat.id.to_string(),
+            );
+            markup.push(vec![button::inline(
+                i18n::trf(language, "source: {}", &[&on_off(source_link)]),
+                bridge.put_callback(&cb).await?,
+            )]);
+        }
+
+        // 构造取消按钮
+        {
+            let cb = CommandCallback::new("settings", "cancel", 0, String::new(), String::new());
+            markup.push(vec![button::inline(
+                i18n::tr(bridge.language(), "cancel").to_string(),
+                bridge.put_callback(&cb).await?,
+            )]);
+        }
+
+        // 如果源消息是Bot发送的，直接编辑源消息, 否则回复一条新消息
+        if message.outgoing() {
+            message
+                .edit(InputMessage::text(content).reply_markup(&reply_markup::inline(markup)))
+                .await?;
+        } else {
+            message
+                .respond(
+                    InputMessage::text(content)
+                        .reply_to(tg_helper::get_topic_id(message))
+                        .reply_markup(&reply_markup::inline(markup)),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn process_rename(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let content = match bridge.find_remote_chat_by_tg(message).await? {
+            Some(remote_chat) => {
+                // 不带参数时恢复为平台提供的名称
+                let alias = tg_helper::get_command_args(message);
+                bridge.rename_remote_chat(&remote_chat, alias).await?;
+                if alias.is_empty() {
+                    i18n::trf(
+                        bridge.language(),
+                        "<b>Reset name of {}</b>",
+                        &[&html_escape::encode_text(&remote_chat.name)],
+                    )
+                } else {
+                    i18n::trf(
+                        bridge.language(),
+                        "<b>Renamed {} to {}</b>",
+                        &[
+                            &html_escape::encode_text(remote_chat.display_name()),
+                            &html_escape::encode_text(alias),
+                        ],
+                    )
+                }
+            }
+            None => i18n::tr(
+                bridge.language(),
+                "<b>Rename is only supported in linked groups or archive topics</b>",
+            )
+            .to_string(),
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    // /topicicon <自定义表情或ID>, /topicicon default 恢复为配置的图标
+    async fn process_topic_icon(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let Some(remote_chat) = bridge.find_remote_chat_by_tg(message).await? else {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Topic icon is only supported in archive topics</b>",
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        };
+
+        let arg = tg_helper::get_command_args(message);
+        let icon_emoji_id = match arg {
+            "default" => Some(bridge.topic_icon(&remote_chat).unwrap_or(0)),
+            _ => tg_helper::get_custom_emoji_id(message).or_else(|| arg.parse::<i64>().ok()),
+        };
+        let Some(icon_emoji_id) = icon_emoji_id else {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Usage: /topicicon custom_emoji|emoji_id or /topicicon default</b>",
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        };
+
+        let content = match bridge.set_topic_icon(remote_chat.id, icon_emoji_id).await {
+            Ok(true) => i18n::trf(
+                bridge.language(),
+                "<b>Changed topic icon of {}</b>",
+                &[&html_escape::encode_text(remote_chat.display_name())],
+            ),
+            Ok(false) => i18n::tr(
+                bridge.language(),
+                "<b>Topic icon is only supported in archive topics</b>",
+            )
+            .to_string(),
+            Err(e) => i18n::trf(
+                bridge.language(),
+                "<b>Failed to change topic icon:</b> {}",
+                &[&html_escape::encode_text(&e.to_string())],
+            ),
+        };
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    // /filter add <drop|redact|flag> [both|in|out] <关键字或re:正则>, /filter remove <id>, /filter list
+    async fn process_filter(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let Some(remote_chat) = bridge.find_remote_chat_by_tg(message).await? else {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Filter is only supported in linked groups or archive topics</b>",
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        };
+        let name = html_escape::encode_text(remote_chat.display_name()).to_string();
+
+        let args = tg_helper::get_command_args(message);
+        let (sub_command, args) = args.split_once(' ').unwrap_or((args, ""));
+        let content = match sub_command {
+            "" | "list" => {
+                let filters = bridge.get_filters(remote_chat.id).await?;
+                if filters.is_empty() {
+                    i18n::trf(bridge.language(), "<b>No filters of {}</b>", &[&name])
+                } else {
+                    let mut content =
+                        i18n::trf(bridge.language(), "<b>Filters of {}:</b>", &[&name]);
+                    for (filter, _) in filters.iter() {
+                        write!(
+                            &mut content,
+                            "\n#{} {} {} <code>{}</code>",
+                            filter.id,
+                            filter.action,
+                            filter.direction,
+                            html_escape::encode_text(&filter.pattern)
+                        )?;
+                    }
+                    content
+                }
+            }
+            "add" => {
+                let (action, rest) = args.trim().split_once(' ').unwrap_or((args.trim(), ""));
+                let rest = rest.trim();
+                // 方向可以省略, 默认双向
+                let (direction, pattern) = match rest.split_once(' ') {
+                    Some((direction, pattern)) => match direction.parse::<FilterDirection>() {
+                        Ok(direction) => (direction, pattern.trim()),
+                        Err(_) => (FilterDirection::Both, rest),
+                    },
+                    None => (FilterDirection::Both, rest),
+                };
+                match action.parse::<FilterAction>() {
+                    Ok(_) if pattern.is_empty() => {
+                        i18n::tr(bridge.language(), FILTER_USAGE).to_string()
+                    }
+                    Ok(action) => match entities::filter::Model::compile(pattern) {
+                        Ok(_) => {
+                            bridge
+                                .create_filter(remote_chat.id, direction, action, pattern)
+                                .await?;
+                            i18n::trf(
+                                bridge.language(),
+                                "<b>Added {} filter of {}:</b> <code>{}</code>",
+                                &[&action, &name, &html_escape::encode_text(pattern)],
+                            )
+                        }
+                        Err(e) => i18n::trf(
+                            bridge.language(),
+                            "<b>Invalid regex:</b> {}",
+                            &[&html_escape::encode_text(&e.to_string())],
+                        ),
+                    },
+                    Err(_) => i18n::tr(bridge.language(), FILTER_USAGE).to_string(),
+                }
+            }
+            "remove" => match args.trim().trim_start_matches('#').parse::<i64>() {
+                Ok(id) => match bridge.delete_filter(remote_chat.id, id).await? {
+                    true => i18n::trf(
+                        bridge.language(),
+                        "<b>Removed filter #{} of {}</b>",
+                        &[&id, &name],
+                    ),
+                    false => i18n::trf(bridge.language(), "<b>Filter #{} not found</b>", &[&id]),
+                },
+                Err(_) => i18n::tr(bridge.language(), FILTER_USAGE).to_string(),
+            },
+            _ => i18n::tr(bridge.language(), FILTER_USAGE).to_string(),
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    // /autoreply add [quiet:22-8] 关键字 => 回复, /autoreply remove id 或 /autoreply list
+    async fn process_autoreply(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let Some(remote_chat) = bridge.find_remote_chat_by_tg(message).await? else {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Auto reply is only supported in linked groups or archive topics</b>",
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        };
+        let name = html_escape::encode_text(remote_chat.display_name()).to_string();
+
+        let args = tg_helper::get_command_args(message);
+        let (sub_command, args) = args.split_once(' ').unwrap_or((args, ""));
+        let content = match sub_command {
+            "" | "list" => {
+                let rules = bridge.get_auto_replies(remote_chat.id).await?;
+                if rules.is_empty() {
+                    i18n::trf(bridge.language(), "<b>No auto replies of {}</b>", &[&name])
+                } else {
+                    let mut content =
+                        i18n::trf(bridge.language(), "<b>Auto replies of {}:</b>", &[&name]);
+                    for (rule, _) in rules.iter() {
+                        write!(
+                            &mut content,
+                            "\n#{} <code>{}</code> => {}",
+                            rule.id,
+                            html_escape::encode_text(&rule.pattern),
+                            html_escape::encode_text(&rule.reply)
+                        )?;
+                        if !rule.quiet_hours.is_empty() {
+                            content.push_str(&i18n::trf(
+                                bridge.language(),
+                                " (quiet {})",
+                                &[&rule.quiet_hours],
+                            ));
+                        }
+                    }
+                    content
+                }
+            }
+            "add" => {
+                let args = args.trim();
+                // 不回复的时段可以省略
+                let (quiet_hours, rest) = match args.strip_prefix("quiet:") {
+                    Some(rest) => rest.split_once(' ').unwrap_or((rest, "")),
+                    None => ("", args),
+                };
+                match rest.split_once("=>") {
+                    Some((pattern, reply))
+                        if !pattern.trim().is_empty()
+                            && !reply.trim().is_empty()
+                            && (quiet_hours.is_empty()
+                                || entities::auto_reply::Model::is_valid_quiet_hours(
+                                    quiet_hours,
+                                )) =>
+                    {
+                        let pattern = pattern.trim();
+                        match entities::filter::Model::compile(pattern) {
+                            Ok(_) => {
+                                bridge
+                                    .create_auto_reply(
+                                        remote_chat.id,
+                                        pattern,
+                                        reply.trim(),
+                                        quiet_hours,
+                                    )
+                                    .await?;
+                                i18n::trf(
+                                    bridge.language(),
+                                    "<b>Added auto reply of {}:</b> <code>{}</code>",
+                                    &[&name, &html_escape::encode_text(pattern)],
+                                )
+                            }
+                            Err(e) => i18n::trf(
+                                bridge.language(),
+                                "<b>Invalid regex:</b> {}",
+                                &[&html_escape::encode_text(&e.to_string())],
+                            ),
+                        }
+                    }
+                    _ => i18n::tr(bridge.language(), AUTOREPLY_USAGE).to_string(),
+                }
+            }
+            "remove" => match args.trim().trim_start_matches('#').parse::<i64>() {
+                Ok(id) => match bridge.delete_auto_reply(remote_chat.id, id).await? {
+                    true => i18n::trf(
+                        bridge.language(),
+                        "<b>Removed auto reply #{} of {}</b>",
+                        &[&id, &name],
+                    ),
+                    false => {
+                        i18n::trf(bridge.language(), "<b>Auto reply #{} not found</b>", &[&id])
+                    }
+                },
+                Err(_) => i18n::tr(bridge.language(), AUTOREPLY_USAGE).to_string(),
+            },
+            _ => i18n::tr(bridge.language(), AUTOREPLY_USAGE).to_string(),
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn process_read(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let content = match bridge.find_remote_chat_by_tg(message).await? {
+            Some(remote_chat) => {
+                // 标记最新的消息即可将整个对话标记为已读
+                match bridge.find_latest_message_by_remote(remote_chat.id).await? {
+                    Some(msg) => {
+                        bridge
+                            .mark_msg_as_read(&remote_chat.endpoint, msg.remote_msg_id)
+                            .await?;
+                        i18n::trf(
+                            bridge.language(),
+                            "<b>Marked {} as read</b>",
+                            &[&html_escape::encode_text(remote_chat.display_name())],
+                        )
+                    }
+                    None => i18n::trf(
+                        bridge.language(),
+                        "<b>No messages of {} to mark</b>",
+                        &[&html_escape::encode_text(remote_chat.display_name())],
+                    ),
+                }
+            }
+            None => i18n::tr(
+                bridge.language(),
+                "<b>Read is only supported in linked groups or archive topics</b>",
+            )
+            .to_string(),
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn process_share_contact(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let remote_chat = match bridge.find_remote_chat_by_tg(message).await? {
+            Some(remote_chat) => remote_chat,
+            None => {
+                message
+                    .respond(
+                        InputMessage::html(i18n::tr(
+                            bridge.language(),
+                            "<b>Share contact is only supported in linked groups or archive topics</b>",
+                        ))
+                        .reply_to(reply_to),
+                    )
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        // 格式: /share_contact [group] <id>
+        let args: Vec<&str> = tg_helper::get_command_args(message)
+            .split_whitespace()
+            .collect();
+        let (contact_type, id) = match args.as_slice() {
+            [id] => ("qq", *id),
+            ["group", id] => ("group", *id),
+            _ => {
+                message
+                    .respond(
+                        InputMessage::html(i18n::tr(
+                            bridge.language(),
+                            "<b>Usage: /share_contact [group] &lt;id&gt;</b>",
+                        ))
+                        .reply_to(reply_to),
+                    )
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        let segment = Segment::Contact(Segment::contact(contact_type.to_string(), id.to_string()));
+        let content = segment.to_string();
+        let message_id = bridge.send_to_remote(&remote_chat, vec![segment]).await?;
+        bridge
+            .save_message_by_remote(
+                remote_chat.id,
+                &message_id.message_id,
+                "",
+                &SentMessage::from(message),
+                &content,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    // /canned 列出快捷回复, /canned add 名称 内容 保存, /canned remove 名称 删除
+    async fn process_canned(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+        let args = tg_helper::get_command_args(message);
+        let (action, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        let rest = rest.trim_start();
+        let content = match action {
+            "" | "list" => return Self::list_canned(bridge, message).await,
+            "add" => match rest.split_once(char::is_whitespace) {
+                Some((name, content)) if !content.trim().is_empty() => {
+                    bridge.save_canned_reply(name, content.trim()).await?;
+                    i18n::trf(
+                        bridge.language(),
+                        "<b>Saved canned reply {}</b>",
+                        &[&html_escape::encode_text(name)],
+                    )
+                }
+                _ => i18n::tr(bridge.language(), CANNED_USAGE).to_string(),
+            },
+            "remove" if !rest.is_empty() => match bridge.delete_canned_reply(rest).await? {
+                true => i18n::trf(
+                    bridge.language(),
+                    "<b>Removed canned reply {}</b>",
+                    &[&html_escape::encode_text(rest)],
+                ),
+                false => i18n::trf(
+                    bridge.language(),
+                    "<b>Canned reply {} not found</b>",
+                    &[&html_escape::encode_text(rest)],
+                ),
+            },
+            _ => i18n::tr(bridge.language(), CANNED_USAGE).to_string(),
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    // 列出快捷回复, 在链接群或归档Topic中附带发送按钮
+    async fn list_canned(bridge: &Bridge, message: &Message) -> Result<()> {
+        let replies = bridge.list_canned_replies().await?;
+        if replies.is_empty() {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(bridge.language(), CANNED_USAGE))
+                        .reply_to(tg_helper::get_topic_id(message)),
+                )
+                .await?;
+            return Ok(());
+        }
+
+        let mapped = bridge.find_remote_chat_by_tg(message).await?.is_some();
+        let mut content = i18n::tr(bridge.language(), "<b>Canned replies:</b>").to_string();
+        let mut markup = Vec::new();
+        for reply in &replies {
+            let preview: String = reply.content.chars().take(CANNED_PREVIEW_LEN).collect();
+            write!(
+                &mut content,
+                "\n<code>{}</code> - {}",
+                html_escape::encode_text(&reply.name),
+                html_escape::encode_text(&preview)
+            )?;
+            if mapped {
+                let cb =
+                    CommandCallback::new("canned", "send", 0, String::new(), reply.name.clone());
+                markup.push(vec![button::inline(
+                    reply.name.clone(),
+                    bridge.put_callback(&cb).await?,
+                )]);
+            }
+        }
+
+        let mut msg = InputMessage::html(content).reply_to(tg_helper::get_topic_id(message));
+        if mapped {
+            let cb = CommandCallback::new("canned", "cancel", 0, String::new(), String::new());
+            markup.push(vec![button::inline(
+                i18n::tr(bridge.language(), "cancel").to_string(),
+                bridge.put_callback(&cb).await?,
+            )]);
+            msg = msg.reply_markup(&reply_markup::inline(markup));
+        }
+        message.respond(msg).await?;
+
+        Ok(())
+    }
+
+    // /c 名称 发送快捷回复到当前对应的远端对话
+    async fn process_send_canned(bridge: &Bridge, message: &Message) -> Result<()> {
+        let name = tg_helper::get_command_args(message);
+        if name.is_empty() {
+            return Self::list_canned(bridge, message).await;
+        }
+
+        Self::send_canned(bridge, message, name, false).await
+    }
+
+    async fn send_canned_callback(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        Self::send_canned(bridge, message, &callback.data, true).await
+    }
+
+    // 替换占位符后发送, 按钮发送时另发一条消息显示内容并作为映射的Telegram消息
+    async fn send_canned(
+        bridge: &Bridge,
+        message: &Message,
+        name: &str,
+        from_button: bool,
+    ) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let Some(remote_chat) = bridge.find_remote_chat_by_tg(message).await? else {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Canned replies can only be sent in linked groups or archive topics</b>",
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        };
+        let Some(reply) = bridge.find_canned_reply(name).await? else {
+            message
+                .respond(
+                    InputMessage::html(i18n::trf(
+                        bridge.language(),
+                        "<b>Canned reply {} not found</b>",
+                        &[&html_escape::encode_text(name)],
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        };
+
+        let content = reply.render(&remote_chat);
+        let message_id = bridge
+            .send_to_remote(
+                &remote_chat,
+                vec![Segment::Text(Segment::text(content.clone()))],
+            )
+            .await?;
+        let tg_message = match from_button {
+            true => {
+                message
+                    .respond(
+                        InputMessage::html(format!("📝 {}", html_escape::encode_text(&content)))
+                            .reply_to(reply_to),
+                    )
+                    .await?
+            }
+            false => message.clone(),
+        };
+        bridge
+            .save_message_by_remote(
+                remote_chat.id,
+                &message_id.message_id,
+                "",
+                &SentMessage::from(&tg_message),
+                &content,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn process_import(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let content = match bridge.find_remote_chat_by_tg(message).await? {
+            Some(remote_chat) => match tg_helper::get_command_args(message).parse::<i32>() {
+                Ok(count) if count > 0 && count <= IMPORT_MAX_COUNT => {
+                    // 导入需要逐条拉取和发送, 先告知已开始
+                    message
+                        .respond(
+                            InputMessage::html(i18n::trf(
+                                bridge.language(),
+                                "<b>Importing up to {} messages of {}...</b>",
+                                &[
+                                    &count,
+                                    &html_escape::encode_text(remote_chat.display_name()),
+                                ],
+                            ))
+                            .reply_to(reply_to),
+                        )
+                        .await?;
+                    let imported =
+                        TelegramPylon::import_onebot_history(bridge, &remote_chat, count).await?;
+                    i18n::trf(
+                        bridge.language(),
+                        "<b>Imported {} messages of {}</b>",
+                        &[
+                            &imported,
+                            &html_escape::encode_text(remote_chat.display_name()),
+                        ],
+                    )
+                }
+                _ => i18n::trf(
+                    bridge.language(),
+                    "<b>Invalid count, e.g. /import 50 (at most {})</b>",
+                    &[&IMPORT_MAX_COUNT],
+                ),
+            },
+            None => i18n::tr(
+                bridge.language(),
+                "<b>Import is only supported in linked groups or archive topics</b>",
+            )
+            .to_string(),
+        };
+
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn process_group_admin(bridge: &Bridge, message: &Message, command: &str) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        // 通过消息映射找到源消息的发送者
+        let target = match tg_helper::get_reply_to_msg_id(message) {
+            Some(msg_id) => {
+                bridge
+                    .find_message_by_tg(message.chat().id(), msg_id)
+                    .await?
+            }
+            None => None,
+        };
+        let (target_msg, remote_chat) = match target {
+            Some((msg, Some(remote_chat)))
+                if remote_chat.chat_type == ChatType::Group && !msg.remote_sender_id.is_empty() =>
+            {
+                (msg, remote_chat)
+            }
+            _ => {
+                message
+                    .respond(
+                        InputMessage::html(i18n::tr(
+                            bridge.language(),
+                            "<b>Please reply to a message from remote group</b>",
+                        ))
+                        .reply_to(reply_to),
+                    )
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        let endpoint = &remote_chat.endpoint;
+        let group_id = remote_chat.target_id.clone();
+        let user_id = target_msg.remote_sender_id.clone();
+        let args = tg_helper::get_command_args(message);
+        let language = bridge.language();
+        let result = match command {
+            "/kick" => bridge
+                .set_group_kick(endpoint, group_id, user_id.clone(), false)
+                .await
+                .map(|_| i18n::trf(language, "<b>Kicked {}</b>", &[&user_id])),
+            "/ban" => match tg_helper::parse_duration(args) {
+                Some(duration) => bridge
+                    .set_group_ban(endpoint, group_id, user_id.clone(), duration)
+                    .await
+                    .map(|_| match duration {
+                        0 => i18n::trf(language, "<b>Unbanned {}</b>", &[&user_id]),
+                        _ => i18n::trf(language, "<b>Banned {} for {}</b>", &[&user_id, &args]),
+                    }),
+                None => Ok(i18n::tr(
+                    language,
+                    "<b>Invalid duration, e.g. 10m, 2h, 1d (up to 30d) or 0 to unban</b>",
+                )
+                .to_string()),
+            },
+            "/title" => bridge
+                .set_group_special_title(endpoint, group_id, user_id.clone(), args.to_string(), -1)
+                .await
+                .map(|_| match args.is_empty() {
+                    true => i18n::trf(language, "<b>Removed special title of {}</b>", &[&user_id]),
+                    false => i18n::trf(
+                        language,
+                        "<b>Set special title of {} to {}</b>",
+                        &[&user_id, &html_escape::encode_text(args)],
+                    ),
+                }),
+            _ => return Ok(()),
+        };
+
+        let content = match result {
+            Ok(content) => content,
+            Err(e) => {
+                tracing::warn!("Failed to process {}: {}", command, e);
+                i18n::trf(language, "<b>Failed to process {}</b>", &[&command])
+            }
+        };
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    // 在群的归档Topic中私聊回复群成员, 之后Topic中的消息都发给该成员, 不回复消息再次/dm时关闭
+    async fn process_dm(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+        let tg_chat_id = message.chat().id();
+        let tg_topic_id = tg_helper::get_forum_topic_id(message);
+
+        let is_group_topic = match tg_topic_id {
+            0 => false,
+            _ => matches!(
+                bridge.find_archive_by_tg(tg_chat_id, tg_topic_id).await?,
+                Some(remote_chat) if remote_chat.chat_type == ChatType::Group
+            ),
+        };
+        if !is_group_topic {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Private reply is only supported in archive topics of groups</b>",
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        }
+
+        let Some(msg_id) = tg_helper::get_reply_to_msg_id(message) else {
+            let content = match bridge.set_dm_relay(tg_chat_id, tg_topic_id, None) {
+                Some(private_chat) => i18n::trf(
+                    bridge.language(),
+                    "<b>Stopped private reply to {}</b>",
+                    &[&html_escape::encode_text(private_chat.display_name())],
+                ),
+                None => i18n::tr(
+                    bridge.language(),
+                    "<b>Please reply to a message from remote group</b>",
+                )
+                .to_string(),
+            };
+            message
+                .respond(InputMessage::html(content).reply_to(reply_to))
+                .await?;
+            return Ok(());
+        };
+
+        // 通过消息映射找到源消息的发送者
+        let target_msg = match bridge.find_message_by_tg(tg_chat_id, msg_id).await? {
+            Some((msg, Some(remote_chat)))
+                if remote_chat.chat_type == ChatType::Group && !msg.remote_sender_id.is_empty() =>
+            {
+                Some((msg, remote_chat))
+            }
+            _ => None,
+        };
+        let Some((target_msg, remote_chat)) = target_msg else {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Please reply to a message from remote group</b>",
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        };
+
+        let content = match bridge
+            .get_remote_chat(
+                &remote_chat.endpoint,
+                &ChatType::Private,
+                &target_msg.remote_sender_id,
+            )
+            .await
+        {
+            Ok(private_chat) => {
+                let content = i18n::trf(
+                    bridge.language(),
+                    "<b>Messages in this topic will be sent privately to {}, send /dm without reply to stop</b>",
+                    &[&html_escape::encode_text(private_chat.display_name())],
+                );
+                bridge.set_dm_relay(tg_chat_id, tg_topic_id, Some(private_chat));
+                content
+            }
+            Err(e) => {
+                tracing::warn!("Failed to get private chat: {}", e);
+                i18n::tr(bridge.language(), "<b>Failed to open private chat</b>").to_string()
+            }
+        };
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    // 将回复的贴纸加入Bot管理的贴纸包, 贴纸包不存在时以管理员身份创建
+    async fn process_steal(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let item = match message.get_reply().await? {
+            Some(reply) => match reply.media() {
+                Some(media::Media::Sticker(sticker)) => {
+                    tg_helper::get_sticker_item(&sticker.document)
+                }
+                Some(media::Media::Document(document)) => tg_helper::get_sticker_item(&document),
+                _ => None,
+            },
+            None => None,
+        };
+        let Some(item) = item else {
+            message
+                .respond(
+                    InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Please reply to a sticker</b>",
+                    ))
+                    .reply_to(reply_to),
+                )
+                .await?;
+            return Ok(());
+        };
+
+        let bot = bridge.primary_bot();
+        let me = bot.get_me().await?;
+        let short_name = format!(
+            "{}_by_{}",
+            STICKER_SET_PREFIX,
+            me.username.as_deref().unwrap_or_default()
+        );
+
+        let result = match bot
+            .transport()
+            .add_sticker_to_set(short_name.clone(), item.clone())
+            .await
+        {
+            Err(e) if tg_helper::is_rpc_error(&e, "STICKERSET_INVALID") => {
+                let admin = bot.get_chat(PackedType::User, bridge.admin_id).await?;
+                bot.transport()
+                    .create_sticker_set(
+                        admin.pack(),
+                        STICKER_SET_TITLE.to_string(),
+                        short_name.clone(),
+                        item,
+                    )
+                    .await
+            }
+            ret => ret,
+        };
+
+        let content = match result {
+            Ok(_) => i18n::trf(
+                bridge.language(),
+                "<b>Added to <a href=\"https://t.me/addstickers/{}\">{}</a></b>",
+                &[&short_name, &STICKER_SET_TITLE],
+            ),
+            Err(e) => {
+                tracing::warn!("Failed to add sticker to set: {}", e);
+                i18n::tr(
+                    bridge.language(),
+                    "<b>Failed to add sticker to sticker pack</b>",
+                )
+                .to_string()
+            }
+        };
+        message
+            .respond(InputMessage::html(content).reply_to(reply_to))
+            .await?;
+
+        Ok(())
+    }
+
+    // 检查链接群, 归档群及其Topic是否仍然有效, 将失效的记录报告给管理员
+    // message不为空时表示在清理后更新原有的报告
+    pub async fn check_consistency(bridge: &Bridge, message: Option<&Message>) -> Result<()> {
+        let remote_chats: HashMap<i64, entities::remote_chat::Model> =
+            entities::remote_chat::Entity::find()
+                .all(&bridge.db)
+                .await?
+                .into_iter()
+                .map(|chat| (chat.id, chat))
+                .collect();
+        let remote_chat_name = |id: i64| {
+            remote_chats
+                .get(&id)
+                .map_or(format!("#{}", id), |chat| chat.display_name().to_owned())
+        };
+
+        // (描述, 清理的回调)
+        let language = bridge.language();
+        let mut problems = Vec::new();
+        let stale = |action: &str, id: i64| {
+            CommandCallback::new("check", action, 0, String::new(), id.to_string())
+        };
+
+        // 检查链接群
+        for link in entities::link::Entity::find().all(&bridge.db).await? {
+            let Some(remote_chat) = remote_chats.get(&link.remote_chat_id) else {
+                problems.push((
+                    i18n::trf(
+                        language,
+                        "🔗 #{} → {} (remote chat missing)",
+                        &[&link.id, &link.tg_chat_id],
+                    ),
+                    stale("delete_link", link.id),
+                ));
+                continue;
+            };
+            let available = bridge
+                .get_bot(&remote_chat.endpoint)
+                .get_chat(
+                    tg_helper::unpack_chat_type(link.tg_chat_type),
+                    link.tg_chat_id,
+                )
+                .await
+                .is_ok_and(|chat| chat.available);
+            if !available {
+                problems.push((
+                    i18n::trf(
+                        language,
+                        "🔗 {} → {} (group unavailable)",
+                        &[&remote_chat.display_name(), &link.tg_chat_id],
+                    ),
+                    stale("delete_link", link.id),
+                ));
+            }
+        }
+
+        // 检查归档群以及其中的Topic
+        for archive in entities::archive::Entity::find().all(&bridge.db).await? {
+            let bot = bridge.get_bot(&archive.endpoint);
+            let tg_chat = match bot
+                .get_chat(
+                    tg_helper::unpack_chat_type(archive.tg_chat_type),
+                    archive.tg_chat_id,
+                )
+                .await
+            {
+                Ok(chat) if chat.available => chat,
+                _ => {
+                    problems.push((
+                        i18n::trf(
+                            language,
+                            "🗃 {} → {} (group unavailable)",
+                            &[&archive.endpoint, &archive.tg_chat_id],
+                        ),
+                        stale("delete_archive", archive.id),
+                    ));
+                    continue;
+                }
+            };
+
+            let topics = entities::topic::Entity::find()
+                .filter(entities::topic::Column::ArchiveId.eq(archive.id))
+                .all(&bridge.db)
+                .await?;
+            for chunk in topics.chunks(TOPIC_CHECK_SIZE) {
+                let existing = match bot
+                    .transport()
+                    .get_forum_topics(
+                        tg_chat.pack(),
+                        chunk.iter().map(|topic| topic.tg_topic_id).collect(),
+                    )
+                    .await
+                {
+                    Ok(existing) => existing,
+                    Err(e) => {
+                        tracing::warn!("Failed to get forum topics: {}", e);
+                        continue;
+                    }
+                };
+                for topic in chunk {
+                    if !existing.contains(&topic.tg_topic_id) {
+                        problems.push((
+                            i18n::trf(
+                                language,
+                                "🗂 {} in {} (topic deleted)",
+                                &[&remote_chat_name(topic.remote_chat_id), &archive.tg_chat_id],
+                            ),
+                            stale("delete_topic", topic.id),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            if let Some(message) = message {
+                message
+                    .edit(InputMessage::html(i18n::tr(
+                        language,
+                        "<b>All stale records are cleaned up</b>",
+                    )))
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        let content = i18n::trf(
+            language,
+            "Found {} stale records, tap to clean up:",
+            &[&problems.len()],
+        );
+        let mut buttons = Vec::new();
+        for (text, cb) in &problems {
+            buttons.push(vec![OutgoingButton::inline(
+                text,
+                bridge.put_callback(cb).await?,
+            )]);
+        }
+        {
+            let cb = CommandCallback::new("check", "cancel", 0, String::new(), String::new());
+            buttons.push(vec![OutgoingButton::inline(
+                i18n::tr(language, "ignore"),
+                bridge.put_callback(&cb).await?,
+            )]);
+        }
+
+        match message {
+            Some(message) => {
+                let markup = buttons
+                    .into_iter()
+                    .map(|row| row.into_iter().map(inline_button).collect())
+                    .collect();
+                message
+                    .edit(InputMessage::text(content).reply_markup(&reply_markup::inline(markup)))
+                    .await?
+            }
+            None => {
+                let bot = bridge.primary_bot();
+                let chat = bot.get_chat(PackedType::User, bridge.admin_id).await?;
+                bot.send_message(&*chat, OutgoingMessage::text(content).buttons(buttons))
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn clean_stale(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        match callback.data.parse::<i64>() {
+            Ok(id) => {
+                let result = match callback.action.as_str() {
+                    "delete_link" => bridge.delete_link(id).await,
+                    "delete_archive" => bridge.delete_archive(id).await,
+                    _ => bridge.delete_topic(id).await,
+                };
+                match result {
+                    Ok(_) => tracing::info!("Cleaned up stale record: {:?}", callback),
+                    Err(e) => tracing::warn!("Failed to clean up stale record: {:?}", e),
+                }
+            }
+            Err(_) => tracing::warn!("Invalid record id: {:?}", callback.data),
+        }
+
+        Self::check_consistency(bridge, Some(message)).await
+    }
+
+    // 从消息表重建搜索索引, /reindex check 只比较数据库与索引中的消息数
+    async fn process_reindex(bridge: &Bridge, message: &Message) -> Result<()> {
+        let reply_to = tg_helper::get_topic_id(message);
+
+        let language = bridge.language();
+        if tg_helper::get_command_args(message) == "check" {
+            let content = match bridge.check_index().await? {
+                Some((stored, indexed)) if stored == indexed => i18n::trf(
+                    language,
+                    "<b>Index is complete: {} messages</b>",
+                    &[&indexed],
+                ),
+                Some((stored, indexed)) => i18n::trf(
+                    language,
+                    "<b>Index is incomplete: {} stored, {} indexed, run /reindex to rebuild</b>",
+                    &[&stored, &indexed],
+                ),
+                None => i18n::tr(language, "<b>Search is not enabled</b>").to_string(),
+            };
+            message
+                .respond(InputMessage::html(content).reply_to(reply_to))
+                .await?;
+            return Ok(());
+        }
+
+        let status = message
+            .respond(
+                InputMessage::html(i18n::tr(language, "<b>Reindexing messages...</b>"))
+                    .reply_to(reply_to),
+            )
+            .await?;
+
+        let mut last_update = Instant::now();
+        let result = bridge
+            .reindex_messages(|done, total| {
+                let update = last_update.elapsed() >= REINDEX_PROGRESS_INTERVAL;
+                if update {
+                    last_update = Instant::now();
+                }
+                let status = &status;
+                async move {
+                    if !update {
+                        return;
+                    }
+                    if let Err(e) = status
+                        .edit(InputMessage::html(i18n::trf(
+                            language,
+                            "<b>Reindexing messages: {}/{}</b>",
+                            &[&done, &total],
+                        )))
+                        .await
+                    {
+                        tracing::warn!("Failed to update reindex progress: {}", e);
+                    }
+                }
+            })
+            .await;
+
+        let content = match result {
+            Ok(done) => match bridge.check_index().await? {
+                Some((stored, indexed)) => i18n::trf(
+                    language,
+                    "<b>Reindexed {} messages</b>\nStored: {}\nIndexed: {}{}",
+                    &[
+                        &done,
+                        &stored,
+                        &indexed,
+                        &if stored == indexed {
+                            ""
+                        } else {
+                            i18n::tr(
+                                language,
+                                "\n⚠️ Counts don't match, new messages may have arrived during reindex",
+                            )
+                        },
+                    ],
+                ),
+                None => i18n::tr(language, "<b>Search is not enabled</b>").to_string(),
+            },
+            Err(e) => {
+                tracing::warn!("Failed to reindex messages: {}", e);
+                i18n::trf(
+                    language,
+                    "<b>Failed to reindex messages:</b> {}",
+                    &[&html_escape::encode_text(&e.to_string())],
+                )
+            }
+        };
+        status.edit(InputMessage::html(content)).await?;
+
+        Ok(())
+    }
+
+    // 列出当前对话中最近发送失败的消息, 点击重新发送
+    async fn list_failed(bridge: &Bridge, message: &Message) -> Result<()> {
+        let mut failed: Vec<(
+            entities::message::Model,
+            Option<entities::remote_chat::Model>,
+        )> = Vec::new();
+        for (msg, remote_chat) in entities::message::Entity::find()
+            .find_also_related(entities::remote_chat::Entity)
+            .filter(entities::message::Column::TgChatId.eq(message.chat().id()))
+            .filter(entities::message::Column::DeliveryStatus.eq(DeliveryStatus::Failed))
+            .order_by_desc(entities::message::Column::Id)
+            .all(&bridge.db)
+            .await?
+        {
+            // 相册的多条消息共用同一个失败记录
+            if failed
+                .iter()
+                .any(|(m, _)| m.remote_msg_id == msg.remote_msg_id)
+            {
+                continue;
+            }
+            failed.push((msg, remote_chat));
+            if failed.len() as u64 >= PAGE_SIZE {
+                break;
+            }
+        }
+
+        let language = bridge.language();
+        let mut content = i18n::tr(language, "Failed messages:").to_string();
+        let mut markup = Vec::new();
+        for (index, (msg, remote_chat)) in failed.iter().enumerate() {
+            write!(
+                &mut content,
+                "\n{}. [{}] {} {}",
+                index + 1,
+                Local
+                    .timestamp_opt(msg.created_at, 0)
+                    .unwrap()
+                    .format("%m-%d %H:%M"),
+                remote_chat.as_ref().map_or("", |c| c.display_name()),
+                msg.content.chars().take(50).collect::<String>()
+            )?;
+            let cb = CommandCallback::new(
+                "failed",
+                "retry",
+                0,
+                "list".to_string(),
+                msg.remote_msg_id.clone(),
+            );
+            markup.push(vec![button::inline(
+                i18n::trf(language, "Retry {}", &[&(index + 1)]),
+                bridge.put_callback(&cb).await?,
+            )]);
+        }
+        if failed.is_empty() {
+            content.push_str(i18n::tr(language, "\nNone"));
+        }
+
+        // 构造取消按钮
+        {
+            let cb = CommandCallback::new("failed", "cancel", 0, String::new(), String::new());
+            markup.push(vec![button::inline(
+                i18n::tr(bridge.language(), "cancel").to_string(),
+                bridge.put_callback(&cb).await?,
+            )]);
+        }
+
+        // 如果源消息是Bot发送的，直接编辑源消息, 否则回复一条新消息
+        if message.outgoing() {
+            message
+                .edit(InputMessage::text(content).reply_markup(&reply_markup::inline(markup)))
+                .await?;
+        } else {
+            message
+                .respond(
+                    InputMessage::text(content)
+                        .reply_to(tg_helper::get_topic_id(message))
+                        .reply_markup(&reply_markup::inline(markup)),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn retry_failed(
+        bridge: &Bridge,
+        message: &Message,
+        callback: &CommandCallback,
+    ) -> Result<()> {
+        let result = bridge.resend_failed_message(&callback.data).await;
+        if let Err(e) = &result {
+            tracing::warn!("Failed to resend message: {}", e);
+        }
+
+        // 从列表中重试时刷新列表
+        if callback.keyword == "list" {
+            return Self::list_failed(bridge, message).await;
+        }
+
+        match result {
+            Ok(_) => {
+                message
+                    .edit(InputMessage::html(i18n::tr(
+                        bridge.language(),
+                        "<b>Message resent to remote</b>",
+                    )))
+                    .await?;
+            }
+            Err(e) => {
+                // 保留按钮以便再次重试
+                let markup = vec![vec![button::inline(
+                    i18n::tr(bridge.language(), "Retry").to_string(),
+                    bridge.put_callback(callback).await?,
+                )]];
+                message
+                    .edit(
+                        InputMessage::html(i18n::trf(
+                            bridge.language(),
+                            "<b>Failed to send message to remote</b>\n{}",
+                            &[&html_escape::encode_text(&e.to_string())],
+                        ))
+                        .reply_markup(&reply_markup::inline(markup)),
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn cancel(bridge: &Bridge, message: &Message, _: &CommandCallback) -> Result<()> {
         Ok(message
-            .edit(InputMessage::html("<del>Cancelled by the user</del>"))
+            .edit(InputMessage::html(i18n::tr(
+                bridge.language(),
+                "<del>Cancelled by the user</del>",
+            )))
             .await?)
     }
 }
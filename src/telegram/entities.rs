@@ -1,5 +1,7 @@
 use std::str::FromStr;
 
+use chrono::Local;
+use regex::Regex;
 use sea_orm::{
     ColIdx, DbErr, QueryResult, TryGetError, TryGetable, Value,
     prelude::StringLen,
@@ -7,12 +9,23 @@ use sea_orm::{
 };
 
 use crate::common::Endpoint;
-use crate::common::{ChatType, DeliveryStatus};
+use crate::common::{
+    ChatType, DeliveryStatus, FilterAction, FilterDirection, MediaKind, MediaMode, MemberNotice,
+    Signature,
+};
 
 pub mod archive;
+pub mod auto_reply;
+pub mod callback;
+pub mod canned_reply;
+pub mod chat_settings;
+pub mod conversation_window;
+pub mod endpoint_alias;
+pub mod filter;
 pub mod link;
 pub mod message;
 pub mod remote_chat;
+pub mod tg_chat;
 pub mod topic;
 
 impl remote_chat::Model {
@@ -23,6 +36,153 @@ impl remote_chat::Model {
             self.target_id.clone(),
         )
     }
+
+    // 优先使用自定义别名
+    pub fn display_name(&self) -> &str {
+        if self.alias.is_empty() {
+            &self.name
+        } else {
+            &self.alias
+        }
+    }
+
+    pub fn topic_title(&self) -> String {
+        match self.chat_type {
+            ChatType::Private => format!("👤 {}", self.display_name()),
+            ChatType::Group => format!("👥 {}", self.display_name()),
+            ChatType::Guild => format!("#️⃣ {}", self.display_name()),
+        }
+    }
+}
+
+impl chat_settings::Model {
+    pub fn member_notice(&self) -> MemberNotice {
+        self.member_notice.parse().unwrap_or(MemberNotice::All)
+    }
+
+    pub fn signature(&self) -> Signature {
+        self.signature.parse().unwrap_or(Signature::Default)
+    }
+
+    pub fn media_mode(&self, kind: MediaKind) -> MediaMode {
+        Self::parse_media_mode(&self.media_policy, kind)
+    }
+
+    pub fn parse_media_mode(policy: &str, kind: MediaKind) -> MediaMode {
+        policy
+            .split(',')
+            .filter_map(|item| item.split_once('='))
+            .find(|(k, _)| *k == kind.to_string())
+            .and_then(|(_, mode)| mode.parse().ok())
+            .unwrap_or(MediaMode::Upload)
+    }
+
+    // 修改一类媒体的转发方式, 返回新的设置
+    pub fn with_media_mode(policy: &str, kind: MediaKind, mode: MediaMode) -> String {
+        MediaKind::ALL
+            .iter()
+            .map(|k| match *k == kind {
+                true => (*k, mode),
+                false => (*k, Self::parse_media_mode(policy, *k)),
+            })
+            .filter(|(_, mode)| *mode != MediaMode::Upload)
+            .map(|(k, mode)| format!("{}={}", k, mode))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl auto_reply::Model {
+    // 当前时间是否在不回复的时段内, 支持跨零点, 如 22-8
+    pub fn is_quiet(&self, hour: u32) -> bool {
+        let Some((start, end)) = self.quiet_hours.split_once('-') else {
+            return false;
+        };
+        match (start.trim().parse::<u32>(), end.trim().parse::<u32>()) {
+            (Ok(start), Ok(end)) if start <= end => (start..end).contains(&hour),
+            (Ok(start), Ok(end)) => hour >= start || hour < end,
+            _ => false,
+        }
+    }
+
+    pub fn is_valid_quiet_hours(hours: &str) -> bool {
+        match hours.split_once('-') {
+            Some((start, end)) => [start, end]
+                .iter()
+                .all(|h| h.parse::<u32>().is_ok_and(|h| h < 24)),
+            None => false,
+        }
+    }
+}
+
+impl canned_reply::Model {
+    // 替换占位符: {name} 对话名称, {id} 对话ID, {endpoint} 端点, {date} 日期, {time} 时间
+    pub fn render(&self, remote_chat: &remote_chat::Model) -> String {
+        let now = Local::now();
+        self.content
+            .replace("{name}", remote_chat.display_name())
+            .replace("{id}", &remote_chat.target_id)
+            .replace("{endpoint}", &remote_chat.endpoint.to_string())
+            .replace("{date}", &now.format("%Y-%m-%d").to_string())
+            .replace("{time}", &now.format("%H:%M").to_string())
+    }
+}
+
+impl filter::Model {
+    pub fn action(&self) -> FilterAction {
+        self.action.parse().unwrap_or(FilterAction::Flag)
+    }
+
+    pub fn direction(&self) -> FilterDirection {
+        self.direction.parse().unwrap_or(FilterDirection::Both)
+    }
+
+    // 关键字忽略大小写匹配, re: 开头的按正则表达式匹配
+    pub fn compile(pattern: &str) -> Result<Regex, regex::Error> {
+        match pattern.strip_prefix("re:") {
+            Some(re) => Regex::new(re),
+            None => Regex::new(&format!("(?i){}", regex::escape(pattern))),
+        }
+    }
+}
+
+impl archive::Model {
+    // 规则: private, group, guild, id:1,2,100-200 或 keyword:名称关键字
+    pub fn is_valid_rule(rule: &str) -> bool {
+        match rule.split_once(':') {
+            Some(("id", ids)) => ids.split(',').all(|id| !id.is_empty()),
+            Some(("keyword", keyword)) => !keyword.is_empty(),
+            _ => matches!(rule, "" | "private" | "group" | "guild"),
+        }
+    }
+
+    pub fn rule_name(rule: &str) -> &str {
+        if rule.is_empty() { "all" } else { rule }
+    }
+
+    // 判断远端对话是否符合归档规则
+    pub fn matches(&self, remote_chat: &remote_chat::Model) -> bool {
+        match self.rule.split_once(':') {
+            Some(("id", ids)) => ids.split(',').any(|id| match id.split_once('-') {
+                Some((start, end)) => match (
+                    start.parse::<i64>(),
+                    end.parse::<i64>(),
+                    remote_chat.target_id.parse::<i64>(),
+                ) {
+                    (Ok(start), Ok(end), Ok(target_id)) => (start..=end).contains(&target_id),
+                    _ => false,
+                },
+                None => id == remote_chat.target_id,
+            }),
+            Some(("keyword", keyword)) => remote_chat.display_name().contains(keyword),
+            _ => match self.rule.as_str() {
+                "private" => remote_chat.chat_type == ChatType::Private,
+                "group" => remote_chat.chat_type == ChatType::Group,
+                "guild" => remote_chat.chat_type == ChatType::Guild,
+                _ => true,
+            },
+        }
+    }
 }
 
 impl ValueType for Endpoint {
@@ -71,6 +231,7 @@ impl ValueType for ChatType {
             Value::Int(Some(n)) => match n {
                 0 => Ok(ChatType::Private),
                 1 => Ok(ChatType::Group),
+                2 => Ok(ChatType::Guild),
                 _ => Err(ValueTypeErr),
             },
             _ => Err(ValueTypeErr),
@@ -96,6 +257,7 @@ impl TryGetable for ChatType {
         match value {
             0 => Ok(ChatType::Private),
             1 => Ok(ChatType::Group),
+            2 => Ok(ChatType::Guild),
             _ => Err(TryGetError::DbErr(DbErr::Type(format!(
                 "Invalid ChatType: {}",
                 value
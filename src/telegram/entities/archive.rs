@@ -14,6 +14,10 @@ pub struct Model {
     pub id: i64,
     pub endpoint: Endpoint,
     pub tg_chat_id: i64,
+    // 归档目标的类型, 超级群组按Topic归档, 频道以频道消息归档
+    pub tg_chat_type: i32,
+    // 归档规则, 为空时匹配所有对话
+    pub rule: String,
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -0,0 +1,45 @@
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelBehavior, ActiveValue::Set, ConnectionTrait, DbErr, DerivePrimaryKey,
+    DeriveRelation, EntityTrait, EnumIter, PrimaryKeyTrait, entity::prelude::DeriveEntityModel,
+    prelude::async_trait,
+};
+
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "callback")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub hash: String,
+    pub category: String,
+    pub action: String,
+    pub page: i64,
+    pub keyword: String,
+    #[sea_orm(column_type = "Text")]
+    pub data: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let timestamp = Utc::now().timestamp();
+
+        if insert {
+            self.created_at = Set(timestamp);
+        }
+
+        self.updated_at = Set(timestamp);
+
+        Ok(self)
+    }
+}
+
+impl Entity {}
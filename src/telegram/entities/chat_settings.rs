@@ -0,0 +1,70 @@
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelBehavior, ActiveValue::Set, ConnectionTrait, DbErr, DerivePrimaryKey,
+    DeriveRelation, EntityTrait, EnumIter, PrimaryKeyTrait, Related, RelationDef, RelationTrait,
+    entity::prelude::DeriveEntityModel, prelude::async_trait,
+};
+
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "chat_settings")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub remote_chat_id: i64,
+    pub muted_until: i64,
+    pub digest_interval: i64,
+    // 翻译的语言对, 如 zh-en, 为空时不翻译
+    pub translate: String,
+    // 语音转写的语言, auto 为自动检测, 为空时不转写
+    pub transcribe: String,
+    // 各类媒体的转发方式, 如 video=drop,image=link, 未设置的为上传
+    pub media_policy: String,
+    // 群成员变动的提示级别: all, kick 或 off
+    pub member_notice: String,
+    // 发送到远端前先预览, 确认后再发送
+    pub confirm_send: bool,
+    // 发往远端的消息的署名: default, name 或 none
+    pub signature: String,
+    // 是否转发群荣誉和运气王等提示
+    pub honor_notice: bool,
+    // 转发到Telegram的消息附上原消息的标识, 便于在原平台中查找
+    pub source_link: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::remote_chat::Entity",
+        from = "Column::RemoteChatId",
+        to = "super::remote_chat::Column::Id"
+    )]
+    RemoteChat,
+}
+
+impl Related<super::remote_chat::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RemoteChat.def()
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let timestamp = Utc::now().timestamp();
+
+        if insert {
+            self.created_at = Set(timestamp);
+        }
+
+        self.updated_at = Set(timestamp);
+
+        Ok(self)
+    }
+}
+
+impl Entity {}
@@ -10,8 +10,10 @@ use sea_orm::{
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
-    pub tg_chat_type: u8,
+    pub tg_chat_type: i32,
     pub tg_chat_id: i64,
+    // 链接到论坛群的Topic, 0表示整个群或者General
+    pub tg_topic_id: i32,
     pub remote_chat_id: i64,
     pub created_at: i64,
     pub updated_at: i64,
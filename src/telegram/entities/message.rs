@@ -7,6 +7,9 @@ use sea_orm::{
 
 use crate::common::DeliveryStatus;
 
+// 没有对应远端消息的临时ID前缀: 本地生成的提示, 待发送和发送失败的消息
+pub const SYNTHETIC_MSG_ID_PREFIXES: [&str; 3] = ["fake:", "pending:", "failed:"];
+
 #[derive(Clone, Debug, DeriveEntityModel)]
 #[sea_orm(table_name = "message")]
 pub struct Model {
@@ -16,8 +19,19 @@ pub struct Model {
     pub tg_msg_id: i32,
     pub remote_chat_id: i64,
     pub remote_msg_id: String,
+    pub remote_sender_id: String,
+    #[sea_orm(column_type = "Text")]
     pub content: String,
     pub delivery_status: DeliveryStatus,
+    // 转换失败的片段(JSON), 用于之后重试
+    #[sea_orm(column_type = "Text", nullable)]
+    pub failures: Option<String>,
+    // 发送到远端失败的消息段(JSON), 用于重新发送
+    #[sea_orm(column_type = "Text", nullable)]
+    pub payload: Option<String>,
+    // 发送失败的原因
+    #[sea_orm(column_type = "Text", nullable)]
+    pub error: Option<String>,
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -57,3 +71,12 @@ impl ActiveModelBehavior for ActiveModel {
 }
 
 impl Entity {}
+
+impl Model {
+    // 是否对应远端的真实消息, 只有这样的ID可以用于回复, 撤回和设置精华等
+    pub fn has_remote_msg(&self) -> bool {
+        !SYNTHETIC_MSG_ID_PREFIXES
+            .iter()
+            .any(|prefix| self.remote_msg_id.starts_with(prefix))
+    }
+}
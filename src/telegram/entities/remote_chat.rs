@@ -16,6 +16,7 @@ pub struct Model {
     pub chat_type: ChatType,
     pub target_id: String,
     pub name: String,
+    pub alias: String,
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -28,6 +29,8 @@ pub enum Relation {
     Topic,
     #[sea_orm(has_many = "super::message::Entity")]
     Message,
+    #[sea_orm(has_one = "super::chat_settings::Entity")]
+    ChatSettings,
 }
 
 impl Related<super::link::Entity> for Entity {
@@ -48,6 +51,12 @@ impl Related<super::message::Entity> for Entity {
     }
 }
 
+impl Related<super::chat_settings::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatSettings.def()
+    }
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
@@ -0,0 +1,47 @@
+use std::sync::OnceLock;
+
+use tokio::process::Command;
+
+// 启动时检测到的版本, 未安装时为None
+static FFMPEG_VERSION: OnceLock<Option<String>> = OnceLock::new();
+static FFPROBE_VERSION: OnceLock<Option<String>> = OnceLock::new();
+
+// 检测ffmpeg和ffprobe是否可用, 返回各自的版本
+pub async fn detect() -> (Option<String>, Option<String>) {
+    let ffmpeg = version("ffmpeg").await;
+    let ffprobe = version("ffprobe").await;
+    let _ = FFMPEG_VERSION.set(ffmpeg.clone());
+    let _ = FFPROBE_VERSION.set(ffprobe.clone());
+
+    (ffmpeg, ffprobe)
+}
+
+async fn version(binary: &str) -> Option<String> {
+    let output = Command::new(binary)
+        .arg("-version")
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // 第一行形如: ffmpeg version 6.1.1 Copyright (c) ...
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()?
+        .split_whitespace()
+        .nth(2)
+        .map(str::to_string)
+}
+
+// 检测之前按可用处理
+pub fn has_ffmpeg() -> bool {
+    FFMPEG_VERSION.get().is_none_or(Option::is_some)
+}
+
+pub fn has_ffprobe() -> bool {
+    FFPROBE_VERSION.get().is_none_or(Option::is_some)
+}
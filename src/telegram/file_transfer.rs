@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::common::FileTransferConfig;
+
+// 临时文件目录
+const TRANSFER_DIR: &str = "transfer";
+// 默认超过该大小(MB)的文件保存为临时文件发送
+const DEFAULT_THRESHOLD: usize = 8;
+// 临时文件的保留时间, 失败重发时仍然可用
+const FILE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// 大文件下载到临时目录, 以路径或URL发给Onebot实现, 避免base64占用双倍内存和超过WebSocket帧大小
+pub struct FileTransfer {
+    dir: PathBuf,
+    threshold: usize,
+    // Onebot实现访问临时目录的路径
+    remote_dir: String,
+    // 通过管理页面下载临时文件的地址
+    url: Option<String>,
+    // 分段上传到Onebot实现, 而不是提供路径或地址
+    stream: bool,
+}
+
+impl FileTransfer {
+    pub async fn new(data_dir: &Path, config: FileTransferConfig) -> Result<Self> {
+        let dir = data_dir.join(TRANSFER_DIR);
+        tokio::fs::create_dir_all(&dir).await?;
+        let dir = tokio::fs::canonicalize(&dir).await?;
+
+        Ok(Self {
+            remote_dir: config
+                .path
+                .unwrap_or_else(|| dir.to_string_lossy().to_string()),
+            dir,
+            threshold: config.threshold.unwrap_or(DEFAULT_THRESHOLD) * 1024 * 1024,
+            url: config.url,
+            stream: config.stream.unwrap_or(false),
+        })
+    }
+
+    pub fn should_transfer(&self, size: usize) -> bool {
+        size > self.threshold
+    }
+
+    pub fn stream(&self) -> bool {
+        self.stream
+    }
+
+    // 生成新的临时文件名, 保留原文件的扩展名
+    pub fn create(&self, file_name: &str) -> (String, PathBuf) {
+        let name = match Path::new(file_name).extension().and_then(|e| e.to_str()) {
+            Some(ext) => format!("{}.{}", Uuid::new_v4().simple(), ext),
+            None => Uuid::new_v4().simple().to_string(),
+        };
+        let path = self.dir.join(&name);
+        (name, path)
+    }
+
+    // Onebot实现读取临时文件的地址
+    pub fn uri(&self, name: &str) -> String {
+        match &self.url {
+            Some(url) => format!("{}/{}", url.trim_end_matches('/'), name),
+            None => format!("file://{}/{}", self.remote_dir.trim_end_matches('/'), name),
+        }
+    }
+
+    // 按文件名查找临时文件, 拒绝可能跳出临时目录的名称
+    pub fn path(&self, name: &str) -> Option<PathBuf> {
+        let valid =
+            !name.starts_with('.') && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.');
+        valid
+            .then(|| self.dir.join(name))
+            .filter(|path| path.is_file())
+    }
+
+    // 删除过期的临时文件
+    pub async fn cleanup(&self) -> Result<usize> {
+        let mut count = 0;
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            let expired = SystemTime::now()
+                .duration_since(metadata.modified()?)
+                .is_ok_and(|age| age > FILE_TTL);
+            if metadata.is_file() && expired {
+                tokio::fs::remove_file(entry.path()).await?;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+}
@@ -1,50 +1,51 @@
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 
 use anyhow::Result;
+use chrono::{Local, TimeZone, Utc};
 use grammers_client::session::PackedType;
-use grammers_client::types::{Chat, InputMedia};
-use grammers_client::{InputMessage, button, reply_markup};
-use grammers_tl_types::enums::{InputGeoPoint, InputStickerSet};
-use grammers_tl_types::types::{
-    DocumentAttributeFilename, DocumentAttributeSticker, InputMediaUploadedDocument,
-    InputMediaVenue,
-};
 use sea_orm::ActiveValue::Set;
 use sea_orm::{ActiveModelTrait, IntoActiveModel};
 use serde_json::Value;
 use uuid::Uuid;
 
-use super::bridge::RelayBridge;
-use super::{entities, onebot_helper as ob_helper};
+use super::bridge::{Bridge, ChatModel, FilterVerdict, RelayBridge, TelegramBot, UploadedInfo};
+use super::i18n;
+use super::segment_converter::{ConvertContext, TgMsgType, upload_failed_text};
+use super::translate_service::TranslateService;
+use super::transport::{ChatInfo, OutgoingButton, OutgoingMedia, OutgoingMessage, SentMessage};
+use super::{entities, telegram_helper as tg_helper};
 use crate::TelegramPylon;
-use crate::common::{ChatType, DeliveryStatus, Endpoint, Platform};
+use crate::common::{
+    ChatType, DeliveryStatus, Endpoint, FilterDirection, Language, MemberNotice, RemoteChatKey,
+};
 use crate::onebot::protocol::OnebotEvent;
-use crate::onebot::protocol::event::{Event, MessageEvent, MetaEvent, NoticeEvent};
+use crate::onebot::protocol::event::{
+    EditEvent, EssenceEvent, Event, GroupDecreaseEvent, GroupIncreaseEvent, GroupUploadEvent,
+    MessageEvent, MetaEvent, NoticeEvent, NotifyEvent,
+};
 use crate::onebot::protocol::segment::Segment;
 
 const BIG_FILE_SIZE: usize = 10 * 1024 * 1024;
 const IMAGE_SLIDE_LIMIT: u32 = 2560;
-
-enum TgMsgType {
-    Text,
-    Html,
-    Photo,
-    Sticker,
-    Voice,
-    Video,
-    Document,
-    Location,
-}
+const DIGEST_MAX_LENGTH: usize = 4000;
+// Telegram单条消息的最大长度(UTF-16)
+const MESSAGE_MAX_LENGTH: usize = 4096;
+// Telegram媒体说明的最大长度(UTF-16)
+const CAPTION_MAX_LENGTH: usize = 1024;
+// Telegram相册的最大媒体数
+const ALBUM_MAX_SIZE: usize = 10;
 
 impl TelegramPylon {
     pub async fn handle_event(bridge: &RelayBridge, event: OnebotEvent) -> Result<()> {
         match &*event.raw {
             Event::Message(message) => {
-                Self::process_onebot_message(bridge, &event.endpoint, message).await?
+                Self::relay_onebot_message(bridge, &event.endpoint, message, false).await?
             }
             Event::MessageSent(message) => {
-                Self::process_onebot_message(bridge, &event.endpoint, message).await?
+                Self::relay_onebot_message(bridge, &event.endpoint, message, false).await?
             }
             Event::Meta(meta) => Self::process_onebot_meta(bridge, &event.endpoint, meta).await?,
             Event::Notice(notice) => {
@@ -56,10 +57,94 @@ impl TelegramPylon {
         Ok(())
     }
 
+    // 拉取远端对话的历史消息, 按时间顺序转发到Telegram
+    pub async fn import_onebot_history(
+        bridge: &Bridge,
+        remote_chat: &ChatModel,
+        count: i32,
+    ) -> Result<usize> {
+        let history = match remote_chat.chat_type {
+            ChatType::Private => {
+                bridge
+                    .get_friend_msg_history(
+                        &remote_chat.endpoint,
+                        remote_chat.target_id.clone(),
+                        count,
+                    )
+                    .await?
+            }
+            ChatType::Group => {
+                bridge
+                    .get_group_msg_history(
+                        &remote_chat.endpoint,
+                        remote_chat.target_id.clone(),
+                        count,
+                    )
+                    .await?
+            }
+            ChatType::Guild => {
+                return Err(anyhow::anyhow!(
+                    "history of guild channels is not supported"
+                ));
+            }
+        };
+
+        let mut messages: Vec<&MessageEvent> = history.messages.iter().collect();
+        messages.sort_by_key(|message| message.time);
+
+        let mut imported = 0;
+        for message in messages {
+            // 已经转发过的消息会在处理时跳过
+            if bridge
+                .find_message_by_remote(remote_chat.id, &message.message_id)
+                .await?
+                .is_some()
+            {
+                continue;
+            }
+            match Self::relay_onebot_message(bridge, &remote_chat.endpoint, message, true).await {
+                Ok(_) => imported += 1,
+                Err(e) => tracing::warn!("Failed to import message {}: {}", message.message_id, e),
+            }
+        }
+
+        Ok(imported)
+    }
+
+    // 归档群里的Topic被管理员删除时, 移除失效的Topic记录并重新创建后再发送一次
+    async fn relay_onebot_message(
+        bridge: &Bridge,
+        endpoint: &Endpoint,
+        message: &MessageEvent,
+        imported: bool,
+    ) -> Result<()> {
+        match Self::process_onebot_message(bridge, endpoint, message, imported).await {
+            Err(e) if tg_helper::is_topic_missing(&e) => {
+                let remote_chat = bridge
+                    .get_remote_chat(endpoint, &message.get_chat_type(), &message.get_chat_id())
+                    .await?;
+                // 链接到Topic的对话由管理员自行处理
+                if bridge.find_link_by_remote(remote_chat.id).await?.is_some() {
+                    return Err(e);
+                }
+
+                tracing::warn!(
+                    "Topic of {} was deleted, recreating",
+                    remote_chat.topic_title()
+                );
+                bridge.delete_topic_by_remote(remote_chat.id).await?;
+                Self::process_onebot_message(bridge, endpoint, message, imported).await
+            }
+            ret => ret,
+        }
+    }
+
+    // imported为true时表示导入的历史消息, 忽略静音和摘要设置并在标题中附带原始时间
     async fn process_onebot_message(
-        bridge: &RelayBridge,
+        bridge: &Bridge,
         endpoint: &Endpoint,
         message: &MessageEvent,
+        imported: bool,
     ) -> Result<()> {
         tracing::info!("Received Onebot message: {}", message);
 
@@ -72,7 +157,7 @@ impl TelegramPylon {
             .get_remote_chat(endpoint, &message.get_chat_type(), &message.get_chat_id())
             .await?;
 
-        // 检查消息是否处理过
+        // 检查消息是否处理过, 需要在自动回复和摘要缓存之前
         if (bridge
             .find_message_by_remote(remote_chat.id, &message.message_id)
             .await?)
@@ -82,321 +167,410 @@ impl TelegramPylon {
             return Ok(());
         }
 
-        let (chat, mut reply_to, mut title) = Self::fetch_chat_and_title(
-            bridge,
-            endpoint,
-            remote_chat.clone(),
-            &message.sender.display_name(),
-        )
-        .await?;
+        // 跳过已静音的对话
+        if !imported && bridge.is_muted(remote_chat.id).await? {
+            tracing::debug!(
+                "Ignoring message from muted chat: {}",
+                remote_chat.display_name()
+            );
+            return Ok(());
+        }
 
-        // 遍历消息里的各片段进行转换处理
-        let mut msg_type = TgMsgType::Text;
-        let mut content = String::new();
-        let mut media_uploaded = Vec::new();
-        let mut location = None;
-        for segment in &(message.message) {
-            match segment {
-                Segment::Text(seg) => match endpoint.platform {
-                    Platform::WeChat => {
-                        content.push_str(&ob_helper::replace_wechat_emoji(&seg.text));
-                    }
-                    _ => {
-                        content.push_str(&seg.text);
-                    }
-                },
-                Segment::Face(seg) => match endpoint.platform {
-                    Platform::QQ => {
-                        content.push_str(ob_helper::replace_qq_face(&seg.id).as_str());
-                    }
-                    _ => {
-                        content.push_str("/[Face");
-                        content.push_str(&seg.id);
-                        content.push(']');
-                    }
-                },
-                Segment::At(seg) => {
-                    match bridge
-                        .get_group_member_info(
-                            endpoint,
-                            message.group_id.as_ref().unwrap().clone(),
-                            seg.id.clone(),
-                            true,
-                        )
-                        .await
-                    {
-                        Ok(member) => {
-                            content.push('@');
-                            content.push_str(&member.display_name());
-                        }
-                        Err(_) => {
-                            content.push('@');
-                            content.push_str(&seg.id);
-                        }
-                    }
-                }
-                Segment::Image(_) => match bridge.upload_segment(endpoint, segment).await {
-                    Ok(uploaded) => {
-                        media_uploaded.push(uploaded);
-                        content.push_str("[图片]");
-                        if ob_helper::is_sticker(segment) {
-                            msg_type = TgMsgType::Sticker;
-                        } else {
-                            msg_type = TgMsgType::Photo;
-                        }
-                    }
-                    Err(e) => {
-                        content.push_str("[图片上传失败]");
-                        tracing::warn!("Failed to upload photo: {}", e)
-                    }
-                },
-                Segment::MarketFace(_) => match bridge.upload_segment(endpoint, segment).await {
-                    Ok(uploaded) => {
-                        media_uploaded.push(uploaded);
-                        content.push_str("[表情]");
-                        msg_type = TgMsgType::Sticker;
-                    }
-                    Err(e) => {
-                        content.push_str("[表情上传失败]");
-                        tracing::warn!("Failed to upload sticker: {}", e)
-                    }
-                },
-                Segment::Record(_) => match bridge.upload_segment(endpoint, segment).await {
-                    Ok(uploaded) => {
-                        media_uploaded.push(uploaded);
-                        content.push_str("[语音]");
-                        msg_type = TgMsgType::Voice;
-                    }
-                    Err(e) => {
-                        content.push_str("[语音上传失败]");
-                        tracing::warn!("Failed to upload record: {}", e)
-                    }
-                },
-                Segment::Video(_) => match bridge.upload_segment(endpoint, segment).await {
-                    Ok(uploaded) => {
-                        media_uploaded.push(uploaded);
-                        content.push_str("[视频]");
-                        msg_type = TgMsgType::Video;
-                    }
-                    Err(e) => {
-                        content.push_str("[视频上传失败]");
-                        tracing::warn!("Failed to upload video: {}", e)
-                    }
-                },
-                Segment::File(_) => match bridge.upload_segment(endpoint, segment).await {
-                    Ok(uploaded) => {
-                        media_uploaded.push(uploaded);
-                        content.push_str("[文件]");
-                        msg_type = TgMsgType::Document;
-                    }
-                    Err(e) => {
-                        content.push_str("[文件上传失败]");
-                        tracing::warn!("Failed to upload file: {}", e)
-                    }
-                },
-                Segment::Reply(seg) => {
-                    if let Some(entity) = bridge
-                        .find_message_by_remote(remote_chat.id, &seg.id)
-                        .await?
-                    {
-                        reply_to = Some(entity.tg_msg_id);
-                    }
-                }
-                Segment::Forward(seg) => {
-                    content.push_str("[合并消息]\n");
-                    for msg in &bridge
-                        .get_forward_msg(endpoint, seg.id.clone())
-                        .await?
-                        .messages
-                    {
-                        writeln!(&mut content, "{}", msg).unwrap();
-                    }
-                    content.pop();
-                }
-                Segment::Location(seg) => {
-                    location = Some(InputMediaVenue {
-                        geo_point: InputGeoPoint::Point(grammers_tl_types::types::InputGeoPoint {
-                            lat: seg.lat,
-                            long: seg.lon,
-                            accuracy_radius: None,
-                        }),
-                        title: seg.title.as_deref().unwrap_or("").to_string(),
-                        address: seg.content.as_deref().unwrap_or("").to_string(),
-                        provider: String::new(),
-                        venue_id: String::new(),
-                        venue_type: String::new(),
-                    });
-                    msg_type = TgMsgType::Location;
-                }
-                Segment::Share(seg) => {
-                    write!(
-                        &mut content,
-                        "<u>{}</u>\n\n{}\n\nvia <a href=\"{}\">{}</a>",
-                        html_escape::encode_text(&seg.title),
-                        html_escape::encode_text(seg.content.as_deref().unwrap_or("")),
-                        html_escape::encode_text(&seg.url),
-                        html_escape::encode_text(&seg.title),
-                    )
-                    .unwrap();
-                    msg_type = TgMsgType::Html;
+        // 内容过滤, 之后使用打码后的消息
+        let mut filtered = message.clone();
+        match bridge
+            .apply_filters(remote_chat.id, FilterDirection::In, &mut filtered.message)
+            .await?
+        {
+            FilterVerdict::Drop(pattern) => {
+                tracing::info!(
+                    "Dropping message {} matching filter: {}",
+                    message.message_id,
+                    pattern
+                );
+                return Ok(());
+            }
+            FilterVerdict::Flag(pattern) => {
+                let content: String = filtered.message.iter().map(|s| s.to_string()).collect();
+                if let Err(e) = bridge
+                    .notify_flagged(&remote_chat, FilterDirection::In, &pattern, &content)
+                    .await
+                {
+                    tracing::warn!("Failed to notify flagged message: {}", e);
                 }
-                Segment::Json(seg) => {
-                    if let Ok(v) = serde_json::from_str::<Value>(&seg.data) {
-                        let view = v.get("view").and_then(Value::as_str).unwrap_or("");
-                        if view == "LocationShare" {
-                            location = Some(ob_helper::extract_location_from_json(&v)?);
-                            msg_type = TgMsgType::Location;
-                            break;
-                        } else {
-                            let share = ob_helper::extract_share_from_json(&v)?;
-                            if !share.is_empty() {
-                                content.push_str(&share);
-                                msg_type = TgMsgType::Html;
-                                break;
-                            }
-                        }
-                    }
+            }
+            FilterVerdict::Pass => {}
+        }
+        let message = &filtered;
 
-                    content.push_str(&seg.data);
+        // 自动回复常见问题, 原消息仍照常转发
+        if !imported && message.user_id != endpoint.id {
+            if let Some(rule) = bridge
+                .match_auto_reply(remote_chat.id, &message.message)
+                .await?
+            {
+                tracing::info!(
+                    "Auto replying {} with rule #{}",
+                    remote_chat.display_name(),
+                    rule.id
+                );
+                let reply = vec![Segment::Text(Segment::text(rule.reply))];
+                if let Err(e) = bridge.send_to_remote(&remote_chat, reply).await {
+                    tracing::warn!("Failed to send auto reply: {}", e);
                 }
-                _ => {}
             }
         }
 
+        // 摘要模式下先缓存消息, 由定时任务汇总发送
+        if let Some(settings) = bridge.get_chat_settings(remote_chat.id).await? {
+            if !imported && settings.digest_interval > 0 {
+                let content: String = message
+                    .message
+                    .iter()
+                    .map(|segment| segment.to_string())
+                    .collect();
+                bridge.push_digest(
+                    remote_chat.to_id(),
+                    settings.digest_interval,
+                    format!(
+                        "[{}] {}: {}",
+                        Local
+                            .timestamp_opt(message.time, 0)
+                            .single()
+                            .unwrap_or_else(Local::now)
+                            .format("%H:%M"),
+                        message.sender.display_name(),
+                        content
+                    ),
+                );
+                return Ok(());
+            }
+        }
+
+        // 私聊对方设置了别名时使用别名
+        let sender_name = match remote_chat.chat_type {
+            ChatType::Private if message.user_id == remote_chat.target_id => {
+                remote_chat.display_name().to_owned()
+            }
+            _ => message.sender.display_name(),
+        };
+        let (bot, chat, reply_to, mut title) =
+            Self::fetch_chat_and_title(bridge, endpoint, remote_chat.clone(), &sender_name).await?;
+        if imported {
+            title = format!(
+                "[{}] {}",
+                Local
+                    .timestamp_opt(message.time, 0)
+                    .single()
+                    .unwrap_or_else(Local::now)
+                    .format("%Y-%m-%d %H:%M"),
+                title
+            );
+        }
+
+        // 遍历消息里的各片段进行转换处理
+        let mut ctx = ConvertContext {
+            bridge,
+            bot,
+            endpoint,
+            remote_chat: remote_chat.clone(),
+            message,
+            msg_type: TgMsgType::Text,
+            content: String::new(),
+            media_uploaded: Vec::new(),
+            followups: Vec::new(),
+            location: None,
+            mentions: Vec::new(),
+            spoiler: false,
+            reply_to,
+            quote: None,
+            failures: Vec::new(),
+            prefetched: HashMap::new(),
+            poll_options: None,
+            position: 0,
+        };
+        ctx.convert_all().await?;
+        let failure_report = ctx.failure_report();
+        bridge
+            .stats
+            .failures
+            .fetch_add(ctx.failures.len() as u64, Ordering::Relaxed);
+        let failures = match ctx.failures.is_empty() {
+            true => None,
+            false => Some(serde_json::to_string(&ctx.failures)?),
+        };
+        let ConvertContext {
+            msg_type,
+            content,
+            mut media_uploaded,
+            followups,
+            location,
+            mentions,
+            spoiler,
+            reply_to,
+            quote,
+            ..
+        } = ctx;
+
+        // 按对话设置附上原消息的标识, 便于在原平台中查找
+        let footer = match bridge.is_source_link(remote_chat.id).await {
+            true => source_footer(&remote_chat, message),
+            false => String::new(),
+        };
+        // 媒体说明放不下标识时另外回复发送
+        let mut footer_overflow = None;
+
         // 发送转换后的消息到Telegram
-        let ret;
+        let mut ret;
         match msg_type {
             TgMsgType::Text => {
                 title.push('\n');
                 title.push_str(&content);
-                let message = InputMessage::text(title).reply_to(reply_to);
-                ret = vec![Some(bridge.send_telegram_message(&*chat, message).await?)];
+                if !imported {
+                    if let Some(translation) =
+                        Self::translate_content(bridge, remote_chat.id, &content).await
+                    {
+                        title.push_str("\n\n🌐 ");
+                        title.push_str(&translation);
+                    }
+                }
+                title.push_str(&footer);
+                if mentions.is_empty() && quote.is_none() {
+                    ret = Self::send_long_message(bot, &chat, &title, false, reply_to).await?;
+                } else {
+                    let mut html = linkify_mentions(&title, &mentions, bridge.admin_id);
+                    if let Some(quote) = &quote {
+                        html.insert_str(0, &quote_html(quote));
+                    }
+                    ret = Self::send_long_message(bot, &chat, &html, true, reply_to).await?;
+                }
             }
             TgMsgType::Html => {
                 title.push('\n');
                 title.push_str(&content);
-                let message = InputMessage::html(title)
-                    .reply_to(reply_to)
-                    .link_preview(true);
-                ret = vec![Some(bridge.send_telegram_message(&*chat, message).await?)];
+                title.push_str(&footer);
+                if let Some(quote) = &quote {
+                    title.insert_str(0, &quote_html(quote));
+                }
+                ret = Self::send_long_message(bot, &chat, &title, true, reply_to).await?;
             }
             TgMsgType::Photo => {
                 if media_uploaded.len() == 1 {
-                    // 也是图文混合
+                    // 也是图文混合, 文字超过说明长度限制时另外发送
+                    let mut overflow = None;
                     if message.message.len() > 1 {
-                        title.push('\n');
-                        title.push_str(&content);
+                        if utf16_len(&title) + 1 + utf16_len(&content) > CAPTION_MAX_LENGTH {
+                            overflow = Some(content.clone());
+                        } else {
+                            title.push('\n');
+                            title.push_str(&content);
+                        }
                     }
+                    footer_overflow = push_footer(&mut title, &footer);
                     // TODO: 判断图片大小和尺寸决定发送图片还是文件
                     let media = media_uploaded.pop().unwrap();
-                    let mut message = InputMessage::text(&title).reply_to(reply_to);
+                    let mut message = OutgoingMessage::text(&title).reply_to(reply_to);
                     if media.file_size > BIG_FILE_SIZE
                         || media.width > IMAGE_SLIDE_LIMIT
                         || media.height > IMAGE_SLIDE_LIMIT
                     {
                         message = message.document(media.uploaded);
+                    } else if spoiler {
+                        message = message.media(OutgoingMedia::Photo {
+                            file: media.uploaded,
+                            spoiler: true,
+                        });
                     } else {
                         message = message.photo(media.uploaded);
                         /*
-                        match bridge.bot_client.send_message(&*chat, message).await {
+                        match bot.send_message(&*chat, message).await {
                             Ok(message) => ret = vec![Some(message)],
                             Err(_) => {
                                 // 失败则发送原图
-                                let message = InputMessage::text(&title)
+                                let message = OutgoingMessage::text(&title)
                                     .document(media.uploaded)
                                     .reply_to(reply_to);
                                 ret = vec![
-                                    bridge.bot_client.send_message(&*chat, message).await.ok(),
+                                    bot.send_message(&*chat, message).await.ok(),
                                 ];
                             }
                         }
                         */
                     }
-                    ret = vec![Some(bridge.send_telegram_message(&*chat, message).await?)];
+                    let sent = bot.send_message(&*chat, message).await?;
+                    let sent_id = sent.id;
+                    ret = vec![Some(sent)];
+                    if let Some(overflow) = overflow {
+                        ret.extend(
+                            Self::send_long_message(bot, &chat, &overflow, false, Some(sent_id))
+                                .await?,
+                        );
+                    }
                 } else {
                     title.push('\n');
                     title.push_str(&content);
-                    ret = bridge
-                        .send_telegram_album(
-                            &*chat,
-                            media_uploaded
-                                .iter()
-                                .map(|u| {
-                                    InputMedia::caption(&title)
-                                        .photo(u.uploaded.clone())
-                                        .reply_to(reply_to)
-                                })
-                                .collect(),
-                        )
-                        .await?;
+                    footer_overflow = push_footer(&mut title, &footer);
+                    // 相册最多10个媒体, 超出的部分回复上一组发送
+                    ret = Vec::new();
+                    let mut album_reply_to = reply_to;
+                    for chunk in media_uploaded.chunks(ALBUM_MAX_SIZE) {
+                        let sent = bot
+                            .send_album(
+                                &*chat,
+                                chunk
+                                    .iter()
+                                    .map(|u| {
+                                        let media = OutgoingMessage::text(&title);
+                                        match u.mime_type.starts_with("video/") {
+                                            true => media.document(u.uploaded.clone()),
+                                            false => media.photo(u.uploaded.clone()),
+                                        }
+                                        .reply_to(album_reply_to)
+                                    })
+                                    .collect(),
+                            )
+                            .await?;
+                        album_reply_to = sent.iter().flatten().next().map(|m| m.id);
+                        ret.extend(sent);
+                    }
                 }
             }
             TgMsgType::Sticker => {
                 let upload_info = media_uploaded.pop().unwrap();
 
                 // TODO: QQ里魔法表情可以和文字混合, 目前这逻辑会忽略掉文字内容了...
-                let message = InputMessage::text(&title)
-                    .media(InputMediaUploadedDocument {
-                        nosound_video: false,
-                        force_file: false,
-                        spoiler: false,
-                        file: upload_info.uploaded.raw,
-                        thumb: None,
-                        mime_type: upload_info.mime_type,
-                        attributes: vec![
-                            (DocumentAttributeFilename {
-                                file_name: upload_info.file_name,
-                            })
-                            .into(),
-                            (DocumentAttributeSticker {
-                                mask: false,
-                                alt: "😊".to_string(),
-                                stickerset: InputStickerSet::Empty,
-                                mask_coords: None,
-                            })
-                            .into(),
-                        ],
-                        stickers: None,
-                        ttl_seconds: None,
-                        video_cover: None,
-                        video_timestamp: None,
-                    })
-                    .reply_markup(&reply_markup::inline(vec![vec![button::url(
-                        &title,
-                        "tg://sticker",
-                    )]]))
+                let message = OutgoingMessage::text(&title)
+                    .media(sticker_media(upload_info))
+                    .buttons(vec![vec![OutgoingButton::url(&title, "tg://sticker")]])
                     .reply_to(reply_to);
 
-                ret = vec![Some(bridge.send_telegram_message(&*chat, message).await?)];
+                ret = vec![Some(bot.send_message(&*chat, message).await?)];
             }
             TgMsgType::Voice => {
-                let message = InputMessage::text(title)
+                footer_overflow = push_footer(&mut title, &footer);
+                let voice = OutgoingMessage::text(&title)
                     .document(media_uploaded.pop().unwrap().uploaded)
                     .reply_to(reply_to);
                 // TODO: 增加语音持续时间
-                ret = vec![Some(bridge.send_telegram_message(&*chat, message).await?)];
+                let sent = bot.send_message(&*chat, voice).await?;
+
+                // 开启转写时将文字附在语音下方
+                if let Some(transcript) =
+                    Self::transcribe_voice(bridge, endpoint, remote_chat.id, message).await
+                {
+                    let caption = format!("{}\n📝 {}", title, transcript);
+                    if let Err(e) = bot
+                        .edit_message(&*chat, sent.id, OutgoingMessage::text(caption))
+                        .await
+                    {
+                        tracing::warn!("Failed to append transcript: {}", e);
+                    }
+                }
+                ret = vec![Some(sent)];
             }
             TgMsgType::Video => {
-                let message = InputMessage::text(title)
+                footer_overflow = push_footer(&mut title, &footer);
+                let message = OutgoingMessage::text(title)
                     .document(media_uploaded.pop().unwrap().uploaded)
                     .reply_to(reply_to);
-                ret = vec![Some(bridge.send_telegram_message(&*chat, message).await?)];
+                ret = vec![Some(bot.send_message(&*chat, message).await?)];
             }
             TgMsgType::Document => {
-                let message = InputMessage::text(title)
+                footer_overflow = push_footer(&mut title, &footer);
+                let message = OutgoingMessage::text(title)
                     .file(media_uploaded.pop().unwrap().uploaded)
                     .reply_to(reply_to);
-                ret = vec![Some(bridge.send_telegram_message(&*chat, message).await?)];
+                ret = vec![Some(bot.send_message(&*chat, message).await?)];
+            }
+            TgMsgType::Card => {
+                let mut title = html_escape::encode_text(&title).to_string();
+                title.push('\n');
+                title.push_str(&content);
+                title.push_str(&footer);
+                if let Some(quote) = &quote {
+                    title.insert_str(0, &quote_html(quote));
+                }
+                let mut message = OutgoingMessage::html(title).reply_to(reply_to);
+                if let Some(media) = media_uploaded.pop() {
+                    message = message.photo(media.uploaded);
+                }
+                ret = vec![Some(bot.send_message(&*chat, message).await?)];
+            }
+            TgMsgType::Dice(emoticon) => {
+                // Bot无法指定骰子的点数, 远端的点数以文字回复在骰子下方
+                let dice = OutgoingMessage::text("")
+                    .media(OutgoingMedia::Dice(emoticon))
+                    .reply_to(reply_to);
+                let dice = bot.send_message(&*chat, dice).await?;
+                title.push('\n');
+                title.push_str(&content);
+                title.push_str(&footer);
+                let result = OutgoingMessage::text(&title).reply_to(Some(dice.id));
+                let result = bot.send_message(&*chat, result).await?;
+                ret = vec![Some(dice), Some(result)];
             }
             TgMsgType::Location => {
-                let message = InputMessage::text(&title)
-                    .media(location.unwrap())
+                title.push_str(&footer);
+                let message = OutgoingMessage::text(&title)
+                    .media(OutgoingMedia::Venue(location.unwrap()))
                     .reply_to(reply_to);
-                ret = vec![Some(bridge.send_telegram_message(&*chat, message).await?)];
+                let venue = bot.send_message(&*chat, message).await?;
+                // 静态地图以图片回复位置消息
+                let map = match media_uploaded.pop() {
+                    Some(media) => {
+                        let message = OutgoingMessage::text("")
+                            .photo(media.uploaded)
+                            .reply_to(Some(venue.id));
+                        match bot.send_message(&*chat, message).await {
+                            Ok(msg) => Some(msg),
+                            Err(e) => {
+                                tracing::warn!("Failed to send static map: {}", e);
+                                None
+                            }
+                        }
+                    }
+                    None => None,
+                };
+                ret = vec![Some(venue), map];
+            }
+        }
+
+        // 无法合并的媒体依次回复上一条消息发送
+        let mut followup_reply_to = ret.iter().flatten().last().map(|m| m.id);
+        for (msg_type, upload_info) in followups {
+            let message = OutgoingMessage::text("").reply_to(followup_reply_to.or(reply_to));
+            let message = match msg_type {
+                TgMsgType::Photo
+                    if upload_info.file_size <= BIG_FILE_SIZE
+                        && upload_info.width <= IMAGE_SLIDE_LIMIT
+                        && upload_info.height <= IMAGE_SLIDE_LIMIT =>
+                {
+                    message.photo(upload_info.uploaded)
+                }
+                TgMsgType::Sticker => message.media(sticker_media(upload_info)),
+                TgMsgType::Document => message.file(upload_info.uploaded),
+                _ => message.document(upload_info.uploaded),
+            };
+            match bot.send_message(&*chat, message).await {
+                Ok(sent) => {
+                    followup_reply_to = Some(sent.id);
+                    ret.push(Some(sent));
+                }
+                Err(e) => tracing::warn!("Failed to send follow-up media: {}", e),
+            }
+        }
+
+        if let Some(footer) = footer_overflow {
+            let footer_reply_to = ret.iter().flatten().next().map(|m| m.id).or(reply_to);
+            let message = OutgoingMessage::text(footer).reply_to(footer_reply_to);
+            match bot.send_message(&*chat, message).await {
+                Ok(sent) => ret.push(Some(sent)),
+                Err(e) => tracing::warn!("Failed to send source footer: {}", e),
             }
         }
 
         tracing::debug!("Send to telegram return: {:?}", ret);
+        bridge.stats.record_to_telegram(remote_chat.id);
 
         let content: String = message
             .message
@@ -406,17 +580,52 @@ impl TelegramPylon {
 
         // 保存消息映射关系以及建立消息索引
         for msg in ret.iter().flatten() {
-            if let Err(e) = bridge.index_message(msg).await {
+            if let Err(e) = bridge
+                .index_message(msg, &message.user_id, &sender_name, &remote_chat)
+                .await
+            {
                 tracing::warn!("Failed to index message: {}", e);
             }
             if let Err(e) = bridge
-                .save_message_by_remote(remote_chat.id, &message.message_id, msg, &content)
+                .save_message_by_remote(
+                    remote_chat.id,
+                    &message.message_id,
+                    &message.user_id,
+                    msg,
+                    &content,
+                )
                 .await
             {
                 tracing::warn!("Failed to insert message mapping: {}", e);
             }
         }
 
+        // 部分片段转换失败时, 附上失败汇总并记录失败的片段以便重试
+        if let Some(failures) = failures {
+            if let Err(e) = bridge
+                .save_message_failures(remote_chat.id, &message.message_id, &failures)
+                .await
+            {
+                tracing::warn!("Failed to save message failures: {}", e);
+            }
+        }
+        if let (Some(report), Some(sent)) = (failure_report, ret.iter().flatten().next()) {
+            let report = OutgoingMessage::html(report).reply_to(Some(sent.id));
+            if let Err(e) = bot.send_message(&*chat, report).await {
+                tracing::warn!("Failed to send failure report: {}", e);
+            }
+        }
+
+        // 成功转发到Telegram后标记远端消息为已读
+        if bridge.mark_as_read() && !imported && ret.iter().any(|msg| msg.is_some()) {
+            if let Err(e) = bridge
+                .mark_msg_as_read(endpoint, message.message_id.clone())
+                .await
+            {
+                tracing::warn!("Failed to mark message as read: {}", e);
+            }
+        }
+
         Ok(())
     }
 
@@ -426,9 +635,34 @@ impl TelegramPylon {
         meta: &MetaEvent,
     ) -> Result<()> {
         tracing::debug!("Received meta: {:?}", meta);
+        // 事件积压导致丢弃时提示管理员
+        if let MetaEvent::QueueOverflow(overflow) = meta {
+            let notice = i18n::trf(
+                bridge.language(),
+                "<b>[WARN] Onebot client ({}) is sending events faster than they can be relayed, {} events dropped</b> (queue: {})",
+                &[
+                    &html_escape::encode_text(&endpoint.to_string()),
+                    &overflow.dropped,
+                    &overflow.depth,
+                ],
+            );
+            return Self::notify_reconnect(bridge, notice).await;
+        }
+        if let MetaEvent::Heartbeat(heartbeat) = meta {
+            bridge
+                .stats
+                .heartbeats
+                .insert(endpoint.clone(), heartbeat.time);
+            return Ok(());
+        }
         if let MetaEvent::Lifecycle(meta) = meta {
             match meta.sub_type.as_str() {
                 "connect" => {
+                    bridge
+                        .stats
+                        .endpoints
+                        .insert(endpoint.clone(), Utc::now().timestamp());
+
                     // 更新好友的信息
                     let friend_list = bridge.get_friend_list(endpoint).await?;
                     for info in friend_list.as_ref() {
@@ -444,22 +678,19 @@ impl TelegramPylon {
                         }
                     }
 
-                    // 提示远端连接
-                    let chat = bridge
-                        .get_tg_chat(PackedType::User, bridge.admin_id)
-                        .await?;
-                    let message =
-                        InputMessage::html(format!("<b>[INFO] {} connected</b>", endpoint));
-                    bridge.send_telegram_message(&*chat, message).await?;
+                    // 提示远端连接, 频繁重连时合并提示
+                    if let Some(notice) = bridge.record_reconnect(endpoint, true) {
+                        Self::notify_reconnect(bridge, notice).await?;
+                    }
                 }
                 "disconnect" => {
+                    bridge.stats.endpoints.remove(endpoint);
+                    bridge.stats.heartbeats.remove(endpoint);
+
                     // 提示远程断开
-                    let chat = bridge
-                        .get_tg_chat(PackedType::User, bridge.admin_id)
-                        .await?;
-                    let message =
-                        InputMessage::html(format!("<b>[INFO] {} disconnected</b>", endpoint));
-                    bridge.send_telegram_message(&*chat, message).await?;
+                    if let Some(notice) = bridge.record_reconnect(endpoint, false) {
+                        Self::notify_reconnect(bridge, notice).await?;
+                    }
                 }
                 _ => {}
             }
@@ -467,12 +698,47 @@ impl TelegramPylon {
         Ok(())
     }
 
+    async fn notify_reconnect(bridge: &RelayBridge, notice: String) -> Result<()> {
+        bridge.send_notice(notice).await
+    }
+
+    // 发送抑制窗口结束的端点的重连汇总
+    pub async fn flush_reconnect_notices(bridge: &RelayBridge) -> Result<()> {
+        for notice in bridge.take_reconnect_summaries() {
+            Self::notify_reconnect(bridge, notice).await?;
+        }
+
+        Ok(())
+    }
+
     async fn process_onebot_notice(
         bridge: &RelayBridge,
         endpoint: &Endpoint,
         notice: &NoticeEvent,
     ) -> Result<()> {
         tracing::debug!("Received notice: {:?}", notice);
+        match notice {
+            NoticeEvent::Essence(event) => {
+                return Self::process_onebot_essence(bridge, endpoint, event).await;
+            }
+            NoticeEvent::GroupUpload(event) => {
+                return Self::process_onebot_group_upload(bridge, endpoint, event).await;
+            }
+            NoticeEvent::GroupIncrease(event) => {
+                return Self::process_onebot_group_increase(bridge, endpoint, event).await;
+            }
+            NoticeEvent::GroupDecrease(event) => {
+                return Self::process_onebot_group_decrease(bridge, endpoint, event).await;
+            }
+            NoticeEvent::Edit(event) => {
+                return Self::process_onebot_edit(bridge, endpoint, event).await;
+            }
+            NoticeEvent::Notify(event) => {
+                return Self::process_onebot_notify(bridge, endpoint, event).await;
+            }
+            _ => {}
+        }
+
         let (message_id, sender_name, remote_chat) = match notice {
             NoticeEvent::FriendRecall(event) => {
                 // FIXME: 在私聊里自己撤回的没有对方的标识
@@ -519,77 +785,695 @@ impl TelegramPylon {
             active_model.delivery_status = Set(DeliveryStatus::Recalled);
             active_model.update(&bridge.db).await?;
 
-            let (tg_chat, _, mut title) =
+            let (bot, tg_chat, _, mut title) =
                 Self::fetch_chat_and_title(bridge, endpoint, remote_chat.clone(), sender_name)
                     .await?;
 
-            title.push_str("\n<del>Recalled this message</del>");
-            let message = InputMessage::html(title).reply_to(Some(tg_msg_id));
+            title.push_str(i18n::tr(
+                bridge.language(),
+                "\n<del>Recalled this message</del>",
+            ));
+            let message = OutgoingMessage::html(title).reply_to(Some(tg_msg_id));
 
             // 保存消息映射关系
-            let msg = bridge
-                .bot_client
-                .send_message(tg_chat.as_ref(), message)
+            let msg = bot.send_message(tg_chat.as_ref(), message).await?;
+            let fake_id = format!("fake:{}", Uuid::new_v4().simple());
+            bridge
+                .save_message_by_remote(remote_chat.id, &fake_id, "", &msg, "")
                 .await?;
+        }
+
+        Ok(())
+    }
+
+    // 远端编辑消息时同步修改Telegram中的消息, 无法修改时回复编辑后的内容
+    async fn process_onebot_edit(
+        bridge: &RelayBridge,
+        endpoint: &Endpoint,
+        event: &EditEvent,
+    ) -> Result<()> {
+        // 由Telegram编辑同步过去的消息不再处理
+        if event.user_id == event.self_id {
+            return Ok(());
+        }
+
+        let chat_type = event.get_chat_type();
+        let remote_chat = bridge
+            .get_remote_chat(endpoint, &chat_type, &event.get_chat_id())
+            .await?;
+        let Some(msg) = bridge
+            .find_message_by_remote(remote_chat.id, &event.message_id)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let sender_name = match &event.group_id {
+            Some(group_id) if chat_type == ChatType::Group => bridge
+                .get_group_member_info(endpoint, group_id.clone(), event.user_id.clone(), false)
+                .await?
+                .display_name(),
+            _ => bridge
+                .get_stranger_info(endpoint, event.user_id.clone(), false)
+                .await?
+                .display_name(),
+        };
+        let (bot, tg_chat, _, title) =
+            Self::fetch_chat_and_title(bridge, endpoint, remote_chat.clone(), &sender_name).await?;
+
+        let content: String = event.message.iter().map(|s| s.to_string()).collect();
+        let tg_msg_id = msg.tg_msg_id;
+        let edited = bot
+            .edit_message(
+                tg_chat.as_ref(),
+                tg_msg_id,
+                OutgoingMessage::text(format!("{}\n{}", title, content)),
+            )
+            .await;
+        if let Err(e) = edited {
+            tracing::warn!("Failed to edit message {}: {}", tg_msg_id, e);
+
+            // 保存消息映射关系
+            let message = OutgoingMessage::text(i18n::trf(
+                bridge.language(),
+                "{}\n(edited): {}",
+                &[&title, &content],
+            ))
+            .reply_to(Some(tg_msg_id));
+            let reply = bot.send_message(tg_chat.as_ref(), message).await?;
             let fake_id = format!("fake:{}", Uuid::new_v4().simple());
             bridge
-                .save_message_by_remote(remote_chat.id, &fake_id, &msg, "")
+                .save_message_by_remote(remote_chat.id, &fake_id, "", &reply, "")
                 .await?;
         }
 
+        // 更新原始消息的内容
+        let mut active_model = msg.into_active_model();
+        active_model.content = Set(content);
+        active_model.update(&bridge.db).await?;
+
         Ok(())
     }
 
-    // 获取Telegram消息的目标对话以及标题
-    async fn fetch_chat_and_title(
+    // 精华消息变动时在Telegram中引用原消息提示
+    async fn process_onebot_essence(
+        bridge: &RelayBridge,
+        endpoint: &Endpoint,
+        event: &EssenceEvent,
+    ) -> Result<()> {
+        let template = match event.sub_type.as_str() {
+            "add" => "📌 <b>{}</b> set a message as essence",
+            "delete" => "📌 <b>{}</b> removed an essence message",
+            _ => return Ok(()),
+        };
+        // 由Telegram置顶同步过去的精华消息不再处理
+        if event.operator_id == event.self_id {
+            return Ok(());
+        }
+
+        let remote_chat = bridge
+            .get_remote_chat(endpoint, &ChatType::Group, &event.group_id)
+            .await?;
+        if bridge.is_muted(remote_chat.id).await? {
+            return Ok(());
+        }
+
+        let operator_name = match bridge
+            .get_group_member_info(
+                endpoint,
+                event.group_id.clone(),
+                event.operator_id.clone(),
+                false,
+            )
+            .await
+        {
+            Ok(member) => member.display_name(),
+            Err(_) => event.operator_id.clone(),
+        };
+
+        let (bot, tg_chat, mut reply_to, _) =
+            Self::fetch_chat_and_title(bridge, endpoint, remote_chat.clone(), &operator_name)
+                .await?;
+
+        let mut content = i18n::trf(
+            bridge.language(),
+            template,
+            &[&html_escape::encode_text(&operator_name)],
+        );
+        if let Some(msg) = bridge
+            .find_message_by_remote(remote_chat.id, &event.message_id)
+            .await?
+        {
+            if !msg.content.is_empty() {
+                write!(
+                    &mut content,
+                    "\n<blockquote>{}</blockquote>",
+                    html_escape::encode_text(&msg.content)
+                )
+                .unwrap();
+            }
+            reply_to = Some(msg.tg_msg_id);
+
+            // 同步置顶Telegram中对应的消息
+            let pinned = match event.sub_type.as_str() {
+                "add" => bot.pin_message(tg_chat.as_ref(), msg.tg_msg_id).await,
+                _ => bot.unpin_message(tg_chat.as_ref(), msg.tg_msg_id).await,
+            };
+            if let Err(e) = pinned {
+                tracing::warn!("Failed to sync pinned message: {}", e);
+            }
+        }
+
+        // 保存消息映射关系
+        let message = OutgoingMessage::html(content).reply_to(reply_to);
+        let msg = bot.send_message(tg_chat.as_ref(), message).await?;
+        let fake_id = format!("fake:{}", Uuid::new_v4().simple());
+        bridge
+            .save_message_by_remote(remote_chat.id, &fake_id, "", &msg, "")
+            .await?;
+
+        Ok(())
+    }
+
+    // 新成员入群时提示
+    async fn process_onebot_group_increase(
+        bridge: &RelayBridge,
+        endpoint: &Endpoint,
+        event: &GroupIncreaseEvent,
+    ) -> Result<()> {
+        let remote_chat = bridge
+            .get_remote_chat(endpoint, &ChatType::Group, &event.group_id)
+            .await?;
+        if bridge.get_member_notice(remote_chat.id).await != MemberNotice::All {
+            return Ok(());
+        }
+
+        let member_name =
+            Self::get_member_name(bridge, endpoint, &event.group_id, &event.user_id).await;
+        let language = bridge.language();
+        let mut content = i18n::trf(
+            language,
+            "👋 <b>{}</b> joined",
+            &[&html_escape::encode_text(&member_name)],
+        );
+        // 邀请入群时附带邀请者
+        if let Some(operator_id) = event
+            .operator_id
+            .as_ref()
+            .filter(|id| event.sub_type == "invite" && *id != "0" && **id != event.user_id)
+        {
+            let operator_name =
+                Self::get_member_name(bridge, endpoint, &event.group_id, operator_id).await;
+            content.push_str(&i18n::trf(
+                language,
+                " (invited by {})",
+                &[&html_escape::encode_text(&operator_name)],
+            ));
+        }
+
+        Self::send_member_notice(bridge, endpoint, remote_chat, &member_name, content).await
+    }
+
+    // 成员退群或被移出时提示
+    async fn process_onebot_group_decrease(
+        bridge: &RelayBridge,
+        endpoint: &Endpoint,
+        event: &GroupDecreaseEvent,
+    ) -> Result<()> {
+        let remote_chat = bridge
+            .get_remote_chat(endpoint, &ChatType::Group, &event.group_id)
+            .await?;
+        let kicked = event.sub_type != "leave";
+        match bridge.get_member_notice(remote_chat.id).await {
+            MemberNotice::All => {}
+            MemberNotice::Kick if kicked => {}
+            _ => return Ok(()),
+        }
+
+        let member_name =
+            Self::get_member_name(bridge, endpoint, &event.group_id, &event.user_id).await;
+        let language = bridge.language();
+        let mut content = match event.sub_type.as_str() {
+            "kick_me" => i18n::tr(language, "🚫 <b>The bot</b> was kicked").to_string(),
+            "kick" => i18n::trf(
+                language,
+                "🚫 <b>{}</b> was kicked",
+                &[&html_escape::encode_text(&member_name)],
+            ),
+            _ => i18n::trf(
+                language,
+                "🚪 <b>{}</b> left",
+                &[&html_escape::encode_text(&member_name)],
+            ),
+        };
+        if let Some(operator_id) = event
+            .operator_id
+            .as_ref()
+            .filter(|id| kicked && *id != "0" && **id != event.user_id)
+        {
+            let operator_name =
+                Self::get_member_name(bridge, endpoint, &event.group_id, operator_id).await;
+            content.push_str(&i18n::trf(
+                language,
+                " by {}",
+                &[&html_escape::encode_text(&operator_name)],
+            ));
+        }
+
+        Self::send_member_notice(bridge, endpoint, remote_chat, &member_name, content).await
+    }
+
+    // 群荣誉变更和红包运气王提示, 其它提示类型忽略
+    async fn process_onebot_notify(
+        bridge: &RelayBridge,
+        endpoint: &Endpoint,
+        event: &NotifyEvent,
+    ) -> Result<()> {
+        let (Some(group_id), Some(user_id)) = (&event.group_id, &event.user_id) else {
+            return Ok(());
+        };
+        if group_id == "0" || !matches!(event.sub_type.as_str(), "honor" | "lucky_king") {
+            return Ok(());
+        }
+
+        let remote_chat = bridge
+            .get_remote_chat(endpoint, &ChatType::Group, group_id)
+            .await?;
+        if !bridge.is_honor_notice(remote_chat.id).await {
+            return Ok(());
+        }
+
+        let language = bridge.language();
+        let (member_name, content) = if event.sub_type == "honor" {
+            let honor_type = event
+                .extra_fields
+                .get("honor_type")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let member_name = Self::get_member_name(bridge, endpoint, group_id, user_id).await;
+            let content = i18n::trf(
+                language,
+                "🎉 <b>{}</b> earned {}",
+                &[
+                    &html_escape::encode_text(&member_name),
+                    &honor_name(language, honor_type),
+                ],
+            );
+            (member_name, content)
+        } else {
+            // 运气王事件中 user_id 为红包发送者, target_id 为运气王
+            let target_id = match event.extra_fields.get("target_id") {
+                Some(Value::String(id)) => id.clone(),
+                Some(Value::Number(id)) => id.to_string(),
+                _ => return Ok(()),
+            };
+            let target_name = Self::get_member_name(bridge, endpoint, group_id, &target_id).await;
+            let sender_name = Self::get_member_name(bridge, endpoint, group_id, user_id).await;
+            let content = i18n::trf(
+                language,
+                "🧧 <b>{}</b> is the lucky king of {}'s red packet",
+                &[
+                    &html_escape::encode_text(&target_name),
+                    &html_escape::encode_text(&sender_name),
+                ],
+            );
+            (target_name, content)
+        };
+
+        Self::send_member_notice(bridge, endpoint, remote_chat, &member_name, content).await
+    }
+
+    // 优先使用群名片, 已不在群内时使用用户昵称
+    async fn get_member_name(
+        bridge: &RelayBridge,
+        endpoint: &Endpoint,
+        group_id: &str,
+        user_id: &str,
+    ) -> String {
+        if let Ok(member) = bridge
+            .get_group_member_info(endpoint, group_id.to_owned(), user_id.to_owned(), true)
+            .await
+        {
+            return member.display_name();
+        }
+        match bridge
+            .get_stranger_info(endpoint, user_id.to_owned(), false)
+            .await
+        {
+            Ok(info) => info.display_name(),
+            Err(_) => user_id.to_owned(),
+        }
+    }
+
+    async fn send_member_notice(
+        bridge: &RelayBridge,
+        endpoint: &Endpoint,
+        remote_chat: Arc<entities::remote_chat::Model>,
+        member_name: &str,
+        content: String,
+    ) -> Result<()> {
+        if bridge.is_muted(remote_chat.id).await? {
+            return Ok(());
+        }
+
+        let (bot, tg_chat, reply_to, _) =
+            Self::fetch_chat_and_title(bridge, endpoint, remote_chat.clone(), member_name).await?;
+
+        // 保存消息映射关系
+        let message = OutgoingMessage::html(content).reply_to(reply_to);
+        let msg = bot.send_message(tg_chat.as_ref(), message).await?;
+        let fake_id = format!("fake:{}", Uuid::new_v4().simple());
+        bridge
+            .save_message_by_remote(remote_chat.id, &fake_id, "", &msg, "")
+            .await?;
+
+        Ok(())
+    }
+
+    // 群文件上传后下载并以文件形式转发
+    async fn process_onebot_group_upload(
+        bridge: &RelayBridge,
+        endpoint: &Endpoint,
+        event: &GroupUploadEvent,
+    ) -> Result<()> {
+        let file = match event.extra_fields.get("file") {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        let file_id = file.get("id").and_then(Value::as_str).unwrap_or_default();
+        let file_name = file.get("name").and_then(Value::as_str).unwrap_or(file_id);
+        let busid = file
+            .get("busid")
+            .and_then(Value::as_i64)
+            .unwrap_or_default();
+        if file_id.is_empty() {
+            return Ok(());
+        }
+
+        let remote_chat = bridge
+            .get_remote_chat(endpoint, &ChatType::Group, &event.group_id)
+            .await?;
+        if bridge.is_muted(remote_chat.id).await? {
+            return Ok(());
+        }
+
+        let uploader_name = match bridge
+            .get_group_member_info(
+                endpoint,
+                event.group_id.clone(),
+                event.user_id.clone(),
+                false,
+            )
+            .await
+        {
+            Ok(member) => member.display_name(),
+            Err(_) => event.user_id.clone(),
+        };
+
+        let (bot, tg_chat, reply_to, mut title) =
+            Self::fetch_chat_and_title(bridge, endpoint, remote_chat.clone(), &uploader_name)
+                .await?;
+
+        let message = match bridge
+            .upload_group_file(bot, endpoint, &event.group_id, file_id, busid, file_name)
+            .await
+        {
+            Ok(uploaded) => {
+                write!(&mut title, "\n[群文件] {}", file_name).unwrap();
+                OutgoingMessage::text(title)
+                    .file(uploaded.uploaded)
+                    .reply_to(reply_to)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to upload group file: {}", e);
+                write!(
+                    &mut title,
+                    "\n{} {}",
+                    upload_failed_text("群文件", &e),
+                    file_name
+                )
+                .unwrap();
+                OutgoingMessage::text(title).reply_to(reply_to)
+            }
+        };
+
+        // 保存消息映射关系
+        let msg = bot.send_message(tg_chat.as_ref(), message).await?;
+        let fake_id = format!("fake:{}", Uuid::new_v4().simple());
+        bridge
+            .save_message_by_remote(remote_chat.id, &fake_id, &event.user_id, &msg, file_name)
+            .await?;
+
+        Ok(())
+    }
+
+    // 发送到期的摘要消息, force为true时发送全部
+    pub async fn flush_digests(bridge: &RelayBridge, force: bool) -> Result<()> {
+        for (key, lines) in bridge.take_digests(force) {
+            if let Err(e) = Self::send_digest(bridge, &key, &lines).await {
+                tracing::warn!("Failed to send digest of {:?}: {}", key, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn send_digest(
         bridge: &RelayBridge,
+        key: &RemoteChatKey,
+        lines: &[String],
+    ) -> Result<()> {
+        let (endpoint, chat_type, target_id) = key;
+        let remote_chat = bridge
+            .get_remote_chat(endpoint, chat_type, target_id)
+            .await?;
+        let (bot, chat, reply_to, _) =
+            Self::fetch_chat_and_title(bridge, endpoint, remote_chat.clone(), "").await?;
+
+        // 超出长度的摘要拆分成多条消息发送
+        let mut chunks = Vec::new();
+        let mut content = i18n::trf(
+            bridge.language(),
+            "📋 {} ({} messages)",
+            &[&remote_chat.display_name(), &lines.len()],
+        );
+        // 单行超出长度时按字符硬拆分
+        let pieces = lines.iter().flat_map(|line| {
+            let chars: Vec<char> = line.chars().collect();
+            chars
+                .chunks(DIGEST_MAX_LENGTH)
+                .map(|piece| piece.iter().collect::<String>())
+                .collect::<Vec<_>>()
+        });
+        for line in pieces {
+            if content.chars().count() + line.chars().count() + 1 > DIGEST_MAX_LENGTH {
+                chunks.push(std::mem::take(&mut content));
+            }
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(&line);
+        }
+        chunks.push(content);
+
+        for chunk in chunks {
+            let message = OutgoingMessage::text(chunk).reply_to(reply_to);
+            let msg = bot.send_message(&*chat, message).await?;
+
+            // 保存消息映射关系, 以便回复摘要时能找到远端对话
+            let fake_id = format!("fake:{}", Uuid::new_v4().simple());
+            bridge
+                .save_message_by_remote(remote_chat.id, &fake_id, "", &msg, "")
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // 按对话设置的语言对翻译文本, 未开启或失败时返回None
+    async fn translate_content(
+        bridge: &Bridge,
+        remote_chat_id: i64,
+        content: &str,
+    ) -> Option<String> {
+        let service = bridge.translate.as_ref()?;
+        if content.trim().is_empty() {
+            return None;
+        }
+
+        let settings = bridge.get_chat_settings(remote_chat_id).await.ok()??;
+        let (source, target) = TranslateService::parse_languages(&settings.translate)?;
+        match service.translate(content, source, target).await {
+            Ok(translation) if !translation.is_empty() && translation != content => {
+                Some(translation)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Failed to translate message: {}", e);
+                None
+            }
+        }
+    }
+
+    // 按对话设置的语言转写消息中的语音, 未开启或失败时返回None
+    async fn transcribe_voice(
+        bridge: &Bridge,
+        endpoint: &Endpoint,
+        remote_chat_id: i64,
+        message: &MessageEvent,
+    ) -> Option<String> {
+        bridge.transcribe.as_ref()?;
+        let settings = bridge.get_chat_settings(remote_chat_id).await.ok()??;
+        if settings.transcribe.is_empty() {
+            return None;
+        }
+
+        let segment = message
+            .message
+            .iter()
+            .find(|segment| matches!(segment, Segment::Record(_)))?;
+        match bridge
+            .transcribe_record(endpoint, segment, &settings.transcribe)
+            .await
+        {
+            Ok(transcript) if !transcript.is_empty() => Some(transcript),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Failed to transcribe voice: {}", e);
+                None
+            }
+        }
+    }
+
+    // 超长消息拆分为多条发送, 首条回复原消息, 其余依次回复上一条
+    async fn send_long_message(
+        bot: &TelegramBot,
+        chat: &ChatInfo,
+        text: &str,
+        html: bool,
+        reply_to: Option<i32>,
+    ) -> Result<Vec<Option<SentMessage>>> {
+        let chunks = if html {
+            split_html(text, MESSAGE_MAX_LENGTH)
+        } else {
+            split_text(text, MESSAGE_MAX_LENGTH)
+        };
+
+        let mut ret = Vec::with_capacity(chunks.len());
+        let mut reply_to = reply_to;
+        for chunk in chunks {
+            let message = if html {
+                OutgoingMessage::html(chunk).link_preview(true)
+            } else {
+                OutgoingMessage::text(chunk)
+            };
+            let sent = bot.send_message(chat, message.reply_to(reply_to)).await?;
+            reply_to = Some(sent.id);
+            ret.push(Some(sent));
+        }
+
+        Ok(ret)
+    }
+
+    // 获取Telegram消息的目标对话以及标题
+    async fn fetch_chat_and_title<'a>(
+        bridge: &'a Bridge,
         endpoint: &Endpoint,
         remote_chat: Arc<entities::remote_chat::Model>,
         sender_name: &str,
-    ) -> Result<(Arc<Chat>, Option<i32>, String)> {
+    ) -> Result<(&'a TelegramBot, Arc<ChatInfo>, Option<i32>, String)> {
         let target = bridge
             .get_remote_chat(endpoint, &remote_chat.chat_type, &remote_chat.target_id)
             .await?;
+        // 端点设置了别名时在标题前标明来源
+        let prefix = match bridge.endpoint_alias(endpoint) {
+            Some(alias) => format!("[{}] ", alias),
+            None => String::new(),
+        };
 
         // 查找链接群
         match bridge.find_link_by_remote(remote_chat.id).await? {
             Some(link) => {
-                let packed_type = match link.tg_chat_type {
-                    0b0000_0010 => PackedType::User,
-                    0b0000_0011 => PackedType::Bot,
-                    0b0000_0100 => PackedType::Chat,
-                    0b0010_1000 => PackedType::Megagroup,
-                    0b0011_0000 => PackedType::Broadcast,
-                    0b0011_1000 => PackedType::Gigagroup,
-                    _ => PackedType::User,
-                };
+                let packed_type = tg_helper::unpack_chat_type(link.tg_chat_type);
+                let bot = bridge.get_bot(endpoint);
                 Ok((
-                    bridge.get_tg_chat(packed_type, link.tg_chat_id).await?,
-                    None,
+                    bot,
+                    bot.get_chat(packed_type, link.tg_chat_id).await?,
+                    // 链接到Topic时发送到对应的Topic
+                    Some(link.tg_topic_id).filter(|id| *id > 0),
                     format!("{}:", sender_name),
                 ))
             }
-            None => match bridge.find_archive_by_endpoint(endpoint).await? {
+            None => match bridge.find_archive(endpoint, &remote_chat).await? {
                 // 查找归档群
                 Some(archive) => {
-                    let tg_topic_id = bridge.get_or_create_topic(&archive, &remote_chat).await?;
+                    let bot = bridge.get_bot(endpoint);
+                    match tg_helper::unpack_chat_type(archive.tg_chat_type) {
+                        // 频道没有Topic, 在标题中带上对话名称
+                        PackedType::Broadcast => Ok((
+                            bot,
+                            bot.get_chat(PackedType::Broadcast, archive.tg_chat_id)
+                                .await?,
+                            None,
+                            format!("{}{} | {}:", prefix, remote_chat.topic_title(), sender_name),
+                        )),
+                        packed_type => {
+                            let tg_topic_id =
+                                bridge.get_or_create_topic(&archive, &remote_chat).await?;
+                            Ok((
+                                bot,
+                                bot.get_chat(packed_type, archive.tg_chat_id).await?,
+                                Some(tg_topic_id),
+                                format!("{}:", sender_name),
+                            ))
+                        }
+                    }
+                }
+                // 私聊配置了对话窗口时创建Topic并链接
+                None if remote_chat.chat_type == ChatType::Private
+                    && bridge.conversation_window().is_some() =>
+                {
+                    match bridge.open_conversation_window(&remote_chat).await {
+                        Ok(Some((tg_chat_id, tg_topic_id))) => {
+                            let bot = bridge.get_bot(endpoint);
+                            return Ok((
+                                bot,
+                                bot.get_chat(PackedType::Megagroup, tg_chat_id).await?,
+                                Some(tg_topic_id),
+                                format!("{}:", sender_name),
+                            ));
+                        }
+                        Ok(None) => {}
+                        Err(e) => tracing::warn!("Failed to open conversation window: {}", e),
+                    }
                     Ok((
+                        bridge.primary_bot(),
                         bridge
-                            .get_tg_chat(PackedType::Megagroup, archive.tg_chat_id)
+                            .primary_bot()
+                            .get_chat(PackedType::User, bridge.admin_id)
                             .await?,
-                        Some(tg_topic_id),
-                        format!("{}:", sender_name),
+                        None,
+                        format!("{}👤 {}:", prefix, target.display_name()),
                     ))
                 }
-                // 没有归档群则发送给管理员
+                // 没有归档群则由主Bot发送给管理员, 以便管理员直接回复
                 None => Ok((
+                    bridge.primary_bot(),
                     bridge
-                        .get_tg_chat(PackedType::User, bridge.admin_id)
+                        .primary_bot()
+                        .get_chat(PackedType::User, bridge.admin_id)
                         .await?,
                     None,
                     match &remote_chat.chat_type {
-                        ChatType::Private => format!("👤 {}:", target.name),
-                        ChatType::Group => format!("👥 {} [{}]:", sender_name, target.name),
+                        ChatType::Private => {
+                            format!("{}👤 {}:", prefix, target.display_name())
+                        }
+                        ChatType::Group => {
+                            format!("{}👥 {} [{}]:", prefix, sender_name, target.display_name())
+                        }
+                        ChatType::Guild => {
+                            format!("{}#️⃣ {} [{}]:", prefix, sender_name, target.display_name())
+                        }
                     },
                 )),
             },
@@ -597,11 +1481,181 @@ impl TelegramPylon {
     }
 }
 
+// 以贴纸形式发送上传的图片
+fn sticker_media(upload_info: UploadedInfo) -> OutgoingMedia {
+    OutgoingMedia::Sticker {
+        file: upload_info.uploaded,
+        file_name: upload_info.file_name,
+        mime_type: upload_info.mime_type,
+    }
+}
+
+// 转义文本, 并将其中的@名称替换为指向Telegram用户的链接
+fn linkify_mentions(text: &str, mentions: &[String], user_id: i64) -> String {
+    let mut html = html_escape::encode_text(text).to_string();
+    let mut linked = Vec::new();
+    for name in mentions {
+        if linked.contains(name) {
+            continue;
+        }
+        let mention = html_escape::encode_text(&format!("@{}", name)).to_string();
+        html = html.replace(
+            &mention,
+            &format!("<a href=\"tg://user?id={}\">{}</a>", user_id, mention),
+        );
+        linked.push(name.clone());
+    }
+
+    html
+}
+
+fn quote_html(quote: &str) -> String {
+    format!(
+        "<blockquote>{}</blockquote>\n",
+        html_escape::encode_text(quote)
+    )
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+// 将标识附在媒体说明后, 超过说明长度限制时返回标识以另外发送
+fn push_footer(caption: &mut String, footer: &str) -> Option<String> {
+    if footer.is_empty() {
+        return None;
+    }
+    if utf16_len(caption) + utf16_len(footer) > CAPTION_MAX_LENGTH {
+        return Some(footer.trim_start().to_string());
+    }
+    caption.push_str(footer);
+    None
+}
+
+// 按长度拆分纯文本, 优先在换行处断开
+fn split_text(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut length = 0;
+    // 最近一个换行后的位置: (字节位置, 长度)
+    let mut breakpoint = None;
+
+    for c in text.chars() {
+        if length + c.len_utf16() > limit {
+            let (pos, len) = breakpoint.take().unwrap_or((current.len(), length));
+            let remain = current.split_off(pos);
+            chunks.push(std::mem::replace(&mut current, remain));
+            length -= len;
+        }
+
+        current.push(c);
+        length += c.len_utf16();
+        if c == '\n' {
+            breakpoint = Some((current.len(), length));
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+// 按可见文本长度拆分HTML, 断开处闭合未结束的标签并在下一段重新打开
+fn split_html(html: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut length = 0;
+    // 未闭合的标签: (开始标签, 标签名)
+    let mut open_tags: Vec<(String, String)> = Vec::new();
+    // 最近一个换行后的位置: (字节位置, 可见长度, 未闭合的标签)
+    let mut breakpoint = None;
+
+    let mut rest = html;
+    while let Some(c) = rest.chars().next() {
+        // 标签不计入长度, 转义实体按一个字符计算
+        let (token, visible) = match c {
+            '<' => (&rest[..rest.find('>').map_or(rest.len(), |i| i + 1)], 0),
+            '&' => (
+                &rest[..rest.find(';').filter(|&i| i <= 8).map_or(1, |i| i + 1)],
+                1,
+            ),
+            _ => (&rest[..c.len_utf8()], c.len_utf16()),
+        };
+        rest = &rest[token.len()..];
+
+        if visible > 0 && length + visible > limit {
+            let (pos, len, tags) = breakpoint
+                .take()
+                .unwrap_or_else(|| (current.len(), length, open_tags.clone()));
+            let remain = current.split_off(pos);
+            for (_, name) in tags.iter().rev() {
+                write!(&mut current, "</{}>", name).unwrap();
+            }
+            chunks.push(std::mem::take(&mut current));
+            for (tag, _) in &tags {
+                current.push_str(tag);
+            }
+            current.push_str(&remain);
+            length -= len;
+        }
+
+        if token.starts_with("</") {
+            open_tags.pop();
+        } else if let Some(tag) = token.strip_prefix('<') {
+            let name = tag
+                .split(|c: char| c.is_whitespace() || c == '>')
+                .next()
+                .unwrap_or_default();
+            open_tags.push((token.to_string(), name.to_string()));
+        }
+        current.push_str(token);
+        length += visible;
+        if token == "\n" {
+            breakpoint = Some((current.len(), length, open_tags.clone()));
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
 impl MessageEvent {
     pub fn get_chat_type(&self) -> ChatType {
         match self.message_type.as_str() {
             "group" => ChatType::Group,
+            "guild" => ChatType::Guild,
             _ => ChatType::Private,
         }
     }
 }
+
+// 群荣誉类型对应的名称, 未知类型显示原始值
+fn honor_name(language: Language, honor_type: &str) -> &str {
+    match honor_type {
+        "talkative" => i18n::tr(language, "Dragon King"),
+        "performer" => i18n::tr(language, "Group Fire"),
+        "legend" => i18n::tr(language, "Group Blaze"),
+        "strong_newbie" => i18n::tr(language, "Rising Bamboo Shoot"),
+        "emotion" => i18n::tr(language, "Source of Joy"),
+        _ => honor_type,
+    }
+}
+
+// 原消息的标识: 平台, 对话类型, 对话ID和消息序号, 实现没有提供序号时使用消息ID
+fn source_footer(remote_chat: &entities::remote_chat::Model, message: &MessageEvent) -> String {
+    let seq = ["real_seq", "message_seq"]
+        .iter()
+        .find_map(|key| match message.extra_fields.get(*key) {
+            Some(Value::String(seq)) if !seq.is_empty() => Some(seq.clone()),
+            Some(Value::Number(seq)) => Some(seq.to_string()),
+            _ => None,
+        })
+        .unwrap_or_else(|| message.message_id.clone());
+    format!(
+        "\n🔗 {} {} {} #{}",
+        remote_chat.endpoint.platform, remote_chat.chat_type, remote_chat.target_id, seq
+    )
+}
@@ -1,43 +1,74 @@
-use std::sync::Arc;
-
 use anyhow::Result;
 use base64::Engine;
 use base64::prelude::BASE64_STANDARD;
-use grammers_client::InputMessage;
+use futures_util::future::join_all;
+use grammers_client::session::PackedType;
 use grammers_client::types::{Message, media};
+use grammers_client::{InputMessage, button, reply_markup};
 use grammers_tl_types as tl;
+use sea_orm::EntityTrait;
+use uuid::Uuid;
 
-use super::bridge::{Bridge, RemoteIdLock};
-use super::{entities, telegram_helper as tg_helper};
-use crate::common::{ChatType, Endpoint};
+use super::bridge::{Bridge, CommandCallback, FilterVerdict, RemoteWorkers};
+use super::transport::{OutgoingButton, OutgoingMessage};
+use super::{entities, ffmpeg, onebot_helper as ob_helper, telegram_helper as tg_helper};
+use crate::TelegramPylon;
+use crate::common::{
+    ChatType, Endpoint, FilterDirection, MediaMode, Platform, ReactionAction, Signature,
+    VoiceFormat,
+};
 use crate::onebot::protocol::segment::Segment;
 use crate::telegram::bridge;
-use crate::{TelegramPylon, with_id_lock};
 
+// 无法获取动画信息时按大小判断是否转成GIF
 const GIF_THRESHOLD: usize = 100 * 1024;
+// 远端不支持剧透时附加的提示
+const SPOILER_MARK: &str = "[⚠️剧透] ";
 
 impl TelegramPylon {
     pub async fn process_message(
         bridge: &Bridge,
         message: &Message,
-        remote_id_lock: Arc<RemoteIdLock>,
+        remote_workers: &RemoteWorkers,
+    ) -> Result<()> {
+        Self::process_messages(bridge, std::slice::from_ref(message), remote_workers).await
+    }
+
+    // 处理一组消息(单条消息或相册), 由第一条消息确定对应的远端对话
+    pub async fn process_messages(
+        bridge: &Bridge,
+        messages: &[Message],
+        remote_workers: &RemoteWorkers,
     ) -> Result<()> {
+        let message = &messages[0];
         if !tg_helper::check_sender(bridge, message) {
             return Ok(());
         }
 
-        // 忽略Action消息
-        if message.action().is_some() {
+        // 置顶消息同步为远端群的精华消息, 忽略其他Action消息
+        if let Some(action) = message.action() {
+            if let tl::enums::MessageAction::PinMessage = action {
+                if let Some(msg_id) = tg_helper::get_reply_to_msg_id(message) {
+                    Self::sync_pinned(bridge, message.chat().id(), &[msg_id], true).await?;
+                }
+            }
             return Ok(());
         }
 
         let tg_chat_id = message.chat().id();
-        match bridge.find_link_by_tg(tg_chat_id).await? {
+        let tg_topic_id = tg_helper::get_forum_topic_id(message);
+
+        // Topic开启了私聊中转时, 发送给选定的群成员
+        if let Some(remote_chat) = bridge.get_dm_relay(tg_chat_id, tg_topic_id) {
+            let _guard = remote_workers.lock(remote_chat.to_id()).await;
+            return Self::confirm_or_send(bridge, &remote_chat, messages).await;
+        }
+
+        match bridge.find_link_by_tg(tg_chat_id, tg_topic_id).await? {
             Some((_, remote_chat)) => {
                 if let Some(remote_chat) = remote_chat {
-                    with_id_lock!(remote_id_lock, remote_chat.to_id(), {
-                        return Self::convert_and_send(bridge, &remote_chat, message).await;
-                    });
+                    let _guard = remote_workers.lock(remote_chat.to_id()).await;
+                    return Self::confirm_or_send(bridge, &remote_chat, messages).await;
                 }
             }
             None => {
@@ -50,10 +81,8 @@ impl TelegramPylon {
                             if let Some(remote_chat) =
                                 bridge.find_archive_by_tg(tg_chat_id, tg_topic_id).await?
                             {
-                                with_id_lock!(remote_id_lock, remote_chat.to_id(), {
-                                    return Self::convert_and_send(bridge, &remote_chat, message)
-                                        .await;
-                                });
+                                let _guard = remote_workers.lock(remote_chat.to_id()).await;
+                                return Self::confirm_or_send(bridge, &remote_chat, messages).await;
                             }
                         }
                     } else if let Some(message_id) = header.reply_to_msg_id {
@@ -61,9 +90,8 @@ impl TelegramPylon {
                         if let Some((_, Some(remote_chat))) =
                             bridge.find_message_by_tg(tg_chat_id, message_id).await?
                         {
-                            with_id_lock!(remote_id_lock, remote_chat.to_id(), {
-                                return Self::convert_and_send(bridge, &remote_chat, message).await;
-                            });
+                            let _guard = remote_workers.lock(remote_chat.to_id()).await;
+                            return Self::confirm_or_send(bridge, &remote_chat, messages).await;
                         }
                     }
                 }
@@ -79,26 +107,372 @@ impl TelegramPylon {
         Ok(())
     }
 
-    async fn convert_and_send(
+    // 同步Telegram中置顶或取消置顶的消息到远端群的精华消息
+    pub async fn sync_pinned(
+        bridge: &Bridge,
+        tg_chat_id: i64,
+        msg_ids: &[i32],
+        pinned: bool,
+    ) -> Result<()> {
+        for msg_id in msg_ids {
+            let Some((msg, Some(remote_chat))) =
+                bridge.find_message_by_tg(tg_chat_id, *msg_id).await?
+            else {
+                continue;
+            };
+            if remote_chat.chat_type != ChatType::Group || !msg.has_remote_msg() {
+                continue;
+            }
+
+            let result = match pinned {
+                true => {
+                    bridge
+                        .set_essence_msg(&remote_chat.endpoint, msg.remote_msg_id)
+                        .await
+                }
+                false => {
+                    bridge
+                        .delete_essence_msg(&remote_chat.endpoint, msg.remote_msg_id)
+                        .await
+                }
+            };
+            if let Err(e) = result {
+                tracing::warn!("Failed to sync essence message: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    // 管理员新添加的反应按配置执行快捷操作
+    pub async fn process_reaction(
+        bridge: &Bridge,
+        packed_type: PackedType,
+        tg_chat_id: i64,
+        tg_msg_id: i32,
+        old_reactions: &[tl::enums::Reaction],
+        new_reactions: &[tl::enums::Reaction],
+    ) -> Result<()> {
+        let actions = new_reactions
+            .iter()
+            .filter(|reaction| !old_reactions.contains(reaction))
+            .filter_map(|reaction| match reaction {
+                tl::enums::Reaction::Emoji(emoji) => Some(emoji.emoticon.clone()),
+                tl::enums::Reaction::CustomEmoji(emoji) => Some(emoji.document_id.to_string()),
+                _ => None,
+            })
+            .filter_map(|reaction| bridge.reaction_action(&reaction))
+            .collect::<Vec<_>>();
+        if actions.is_empty() {
+            return Ok(());
+        }
+
+        let bot = bridge.primary_bot();
+        let tg_chat = bot.get_chat(packed_type, tg_chat_id).await?;
+        for action in actions {
+            tracing::info!("Reaction action {:?} on message {}", action, tg_msg_id);
+            match action {
+                ReactionAction::Recall => {
+                    let Some((msg, Some(remote_chat))) =
+                        bridge.find_message_by_tg(tg_chat_id, tg_msg_id).await?
+                    else {
+                        continue;
+                    };
+                    if !msg.has_remote_msg() {
+                        continue;
+                    }
+                    bridge
+                        .delete_msg(&remote_chat.endpoint, msg.remote_msg_id)
+                        .await?;
+                }
+                ReactionAction::Pin => {
+                    // Bot自己置顶不会收到置顶消息, 直接同步到远端
+                    bot.pin_message(tg_chat.as_ref(), tg_msg_id).await?;
+                    Self::sync_pinned(bridge, tg_chat_id, &[tg_msg_id], true).await?;
+                }
+                ReactionAction::Favorite => {
+                    let favorites = match bridge.favorites_chat() {
+                        Some(chat_id) => {
+                            let (packed_type, chat_id) = tg_helper::unpack_bot_api_id(chat_id);
+                            bot.get_chat(packed_type, chat_id).await?
+                        }
+                        None => bot.get_chat(PackedType::User, bridge.admin_id).await?,
+                    };
+                    bot.forward_messages(favorites.as_ref(), &[tg_msg_id], tg_chat.as_ref())
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Bot被移出群组后, 删除对应的链接和归档, 避免之后的消息一直发送失败
+    pub async fn process_bot_removed(bridge: &Bridge, tg_chat_id: i64) -> Result<()> {
+        let removed = bridge.remove_tg_chat(tg_chat_id).await?;
+        if removed.is_empty() {
+            return Ok(());
+        }
+        tracing::info!("Bot was removed from Telegram chat: {}", tg_chat_id);
+
+        let bot = bridge.primary_bot();
+        let me = bot.get_me().await?;
+        let mut html = format!(
+            "<b>[WARN] Bot was removed from chat {}, unlinked:</b>",
+            tg_chat_id
+        );
+        for item in removed {
+            html.push_str(&format!("\n{}", html_escape::encode_text(&item)));
+        }
+
+        // 提供把Bot添加到其他群的按钮, 加入后在群内重新/link或/archive
+        let buttons = vec![vec![OutgoingButton::url(
+            "Re-link in another group",
+            format!(
+                "https://t.me/{}?startgroup=true",
+                me.username.as_deref().unwrap_or_default()
+            ),
+        )]];
+        let chat = bot.get_chat(PackedType::User, bridge.admin_id).await?;
+        bot.send_message(&*chat, OutgoingMessage::html(html).buttons(buttons))
+            .await?;
+
+        Ok(())
+    }
+
+    // 开启了发送确认的对话先回复预览, 点击发送后再转发到远端
+    async fn confirm_or_send(
         bridge: &Bridge,
         remote_chat: &entities::remote_chat::Model,
+        messages: &[Message],
+    ) -> Result<()> {
+        if !bridge.is_confirm_send(remote_chat.id).await {
+            return Self::convert_and_send(bridge, remote_chat, messages).await;
+        }
+
+        let mut html = format!(
+            "<b>Send to {}?</b>",
+            html_escape::encode_text(remote_chat.display_name())
+        );
+        let medias = messages.iter().filter(|m| m.media().is_some()).count();
+        if medias > 0 {
+            html.push_str(&format!("\n[{} media]", medias));
+        }
+        if let Some(caption) = messages.iter().find(|m| !m.text().is_empty()) {
+            html.push_str(&format!(
+                "\n<blockquote expandable>{}</blockquote>",
+                html_escape::encode_text(caption.text())
+            ));
+        }
+
+        let key = bridge.put_pending_send(remote_chat.id, messages.to_vec());
+        let send_cb = CommandCallback::new("confirm", "send", 0, String::new(), key.clone());
+        let discard_cb = CommandCallback::new("confirm", "discard", 0, String::new(), key);
+        let markup = reply_markup::inline(vec![vec![
+            button::inline("Send", bridge.put_callback(&send_cb).await?),
+            button::inline("Discard", bridge.put_callback(&discard_cb).await?),
+        ]]);
+        messages[0]
+            .reply(InputMessage::html(html).reply_markup(&markup))
+            .await?;
+
+        Ok(())
+    }
+
+    // 处理预览消息上的发送或放弃按钮
+    pub async fn process_pending_send(
+        bridge: &Bridge,
         message: &Message,
+        callback: &CommandCallback,
+        send: bool,
     ) -> Result<()> {
-        let (message_type, group_id, user_id) = match remote_chat.chat_type {
-            ChatType::Private => (
-                "private".to_string(),
-                None,
-                Some(remote_chat.target_id.clone()),
+        let Some((remote_chat_id, messages)) = bridge.take_pending_send(&callback.data) else {
+            message
+                .edit(InputMessage::html("<del>Preview expired</del>"))
+                .await?;
+            return Ok(());
+        };
+        if !send {
+            message
+                .edit(InputMessage::html("<del>Discarded by the user</del>"))
+                .await?;
+            return Ok(());
+        }
+
+        let Some(remote_chat) = entities::remote_chat::Entity::find_by_id(remote_chat_id)
+            .one(&bridge.db)
+            .await?
+        else {
+            return Ok(());
+        };
+        let html = match Self::convert_and_send(bridge, &remote_chat, &messages).await {
+            Ok(_) => format!(
+                "<b>Sent to {}</b>",
+                html_escape::encode_text(remote_chat.display_name())
             ),
-            ChatType::Group => (
-                "group".to_string(),
-                Some(remote_chat.target_id.clone()),
-                None,
+            Err(e) => format!(
+                "<b>Failed to send to {}:</b> {}",
+                html_escape::encode_text(remote_chat.display_name()),
+                html_escape::encode_text(&e.to_string())
             ),
         };
+        message.edit(InputMessage::html(html)).await?;
+
+        Ok(())
+    }
+
+    async fn convert_and_send(
+        bridge: &Bridge,
+        remote_chat: &entities::remote_chat::Model,
+        messages: &[Message],
+    ) -> Result<()> {
+        // 相册中的媒体并发下载和转换, 结果仍按原顺序拼接
+        let mut segments: Vec<Segment> = Vec::new();
+        let converted = join_all(
+            messages
+                .iter()
+                .map(|message| Self::convert_message(bridge, remote_chat, message)),
+        )
+        .await;
+        for result in converted {
+            segments.extend(result?);
+        }
+
+        // 相册的说明文字只附加一次
+        if messages.len() > 1 {
+            if let Some(caption) = messages.iter().find(|m| !m.text().is_empty()) {
+                let caption = tg_helper::get_formatted_text(caption);
+                segments.extend(Self::convert_text(bridge, remote_chat, &caption).await);
+            }
+        }
+
+        let message = &messages[0];
+
+        // 内容过滤
+        match bridge
+            .apply_filters(remote_chat.id, FilterDirection::Out, &mut segments)
+            .await?
+        {
+            FilterVerdict::Drop(pattern) => {
+                message
+                    .reply(InputMessage::html(format!(
+                        "<b>Message was blocked by filter</b> {}",
+                        html_escape::encode_text(&pattern)
+                    )))
+                    .await?;
+                return Ok(());
+            }
+            FilterVerdict::Flag(pattern) => {
+                let content: String = segments.iter().map(|s| s.to_string()).collect();
+                if let Err(e) = bridge
+                    .notify_flagged(remote_chat, FilterDirection::Out, &pattern, &content)
+                    .await
+                {
+                    tracing::warn!("Failed to notify flagged message: {}", e);
+                }
+            }
+            FilterVerdict::Pass => {}
+        }
+
+        // 按对话设置加上配置的署名或发送者的名称, none 时不加署名
+        let signature = match bridge.get_signature(remote_chat.id).await {
+            Signature::Default => bridge.default_signature(),
+            Signature::Name => message
+                .sender()
+                .map(|sender| format!("{}: ", sender.name())),
+            Signature::None => None,
+        };
+        if let Some(signature) = signature.filter(|_| !segments.is_empty()) {
+            segments.insert(0, Segment::Text(Segment::text(signature)));
+        }
+
+        if !segments.is_empty() {
+            // 检查是否有回复的消息
+            if let Some(message_id) = tg_helper::get_reply_to_msg_id(message) {
+                if let Some((message, _)) = bridge
+                    .find_message_by_tg(message.chat().id(), message_id)
+                    .await?
+                    .filter(|(message, _)| message.has_remote_msg())
+                {
+                    // QQ如果Reply不是第一个消息段的话, 会往消息末尾添加@
+                    segments.insert(0, Segment::Reply(Segment::reply(message.remote_msg_id)));
+                }
+            }
+
+            let content: String = segments.iter().map(|segment| segment.to_string()).collect();
+
+            // 发送前记录为待发送, 远端接受后更新为已发送, 失败时记录原因以便之后点击按钮重新发送
+            let pending_id = format!("pending:{}", Uuid::new_v4().simple());
+            bridge
+                .save_pending_messages(remote_chat.id, &pending_id, messages, &content)
+                .await?;
+            let payload = serde_json::to_string(&segments)?;
+
+            match bridge.send_to_remote(remote_chat, segments).await {
+                Ok(message_id) => {
+                    bridge
+                        .mark_messages_sent(remote_chat.id, &pending_id, &message_id.message_id)
+                        .await?;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to send message to remote: {}", e);
+
+                    let failed_id = format!("failed:{}", Uuid::new_v4().simple());
+                    bridge
+                        .mark_messages_failed(
+                            remote_chat.id,
+                            &pending_id,
+                            &failed_id,
+                            &e.to_string(),
+                            &payload,
+                        )
+                        .await?;
+
+                    let cb = CommandCallback::new("failed", "retry", 0, String::new(), failed_id);
+                    let markup = vec![vec![button::inline(
+                        "Retry".to_string(),
+                        bridge.put_callback(&cb).await?,
+                    )]];
+                    message
+                        .reply(
+                            InputMessage::html(format!(
+                                "<b>Failed to send message to remote</b>\n{}",
+                                html_escape::encode_text(&e.to_string())
+                            ))
+                            .reply_markup(&reply_markup::inline(markup)),
+                        )
+                        .await?;
+                }
+            }
+        } else {
+            message
+                .reply(InputMessage::html(
+                    "<b>Failed to convert message for remote</b>",
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // 将单条Telegram消息转换成Onebot消息段
+    #[tracing::instrument(skip_all, fields(tg_msg_id = message.id()))]
+    async fn convert_message(
+        bridge: &Bridge,
+        remote_chat: &entities::remote_chat::Model,
+        message: &Message,
+    ) -> Result<Vec<Segment>> {
         let mut segments: Vec<Segment> = Vec::new();
 
         if let Some(media) = message.media() {
+            // 按对话的媒体设置以占位文字代替 (Telegram的媒体没有可公开访问的链接)
+            if let Some(kind) = tg_helper::get_media_kind(&media) {
+                if bridge.get_media_mode(remote_chat.id, kind).await != MediaMode::Upload {
+                    segments.push(Segment::Text(Segment::text(format!("[{}]", kind.label()))));
+                    return Ok(segments);
+                }
+            }
+            let _permit = bridge.acquire_media_worker().await?;
             match &media {
                 media::Media::Photo(_) => {
                     let (file_name, file_data) = bridge.download_media(&media).await?;
@@ -108,20 +482,84 @@ impl TelegramPylon {
                         None,
                         None,
                         None,
+                        None,
                     )));
                 }
+                media::Media::Document(document)
+                    if Self::can_transfer(bridge, document, &remote_chat.endpoint, message) =>
+                {
+                    // 大文件和视频直接写入临时文件, 不经过内存
+                    let (file_name, uri) = bridge
+                        .download_media_to_transfer(&remote_chat.endpoint, &media)
+                        .await?;
+                    if document.raw.video {
+                        segments.push(Segment::Video(Segment::video(uri, Some(file_name), None)));
+                    } else {
+                        segments.push(Segment::File(Segment::file(uri, Some(file_name))));
+                    }
+                }
                 media::Media::Document(document) => {
-                    let (mut file_name, file_data) = bridge.download_media(&media).await?;
-                    if document.raw.voice {
-                        // 语音
-                        // TODO: Telegram的是oga后缀，改成ogg(微信可以播放ogg文件)
-                        if let Some(fixed_name) = bridge::fix_filename(&file_name, "ogg") {
+                    let (mut file_name, mut file_data) = bridge.download_media(&media).await?;
+                    // 超出大小限制的视频尝试压缩
+                    if bridge.exceeds_media_size(file_data.len())
+                        && document.raw.video
+                        && ffmpeg::has_ffmpeg()
+                    {
+                        match ob_helper::compress_video(&file_data).await {
+                            Ok(video_data) => {
+                                if let Some(fixed_name) = bridge::fix_filename(&file_name, "mp4") {
+                                    file_name = fixed_name;
+                                }
+                                file_data = video_data;
+                            }
+                            Err(e) => {
+                                tracing::warn!("Failed to compress video: {}", e);
+                            }
+                        }
+                    }
+                    if bridge.exceeds_media_size(file_data.len()) {
+                        // 仍然超出大小限制的以提示代替
+                        segments.push(Segment::Text(Segment::text(format!(
+                            "[文件过大: {} ({:.1}MB)]",
+                            file_name,
+                            file_data.len() as f64 / 1024.0 / 1024.0
+                        ))));
+                    } else if document.raw.voice {
+                        // 语音, Telegram的是oga后缀, 按端点配置转码, 不转码时改成ogg(微信可以播放ogg文件)
+                        let mut format = bridge.voice_format(&remote_chat.endpoint);
+                        if format != VoiceFormat::Ogg && !ffmpeg::has_ffmpeg() {
+                            tracing::warn!("ffmpeg not found, sending voice as ogg");
+                            format = VoiceFormat::Ogg;
+                        }
+                        let silk_encoder = bridge.silk_encoder();
+                        let extension = match ob_helper::transcode_voice(
+                            &file_data,
+                            format,
+                            silk_encoder.as_deref(),
+                        )
+                        .await
+                        {
+                            Ok((voice_data, extension)) => {
+                                file_data = voice_data;
+                                extension
+                            }
+                            Err(e) => {
+                                tracing::warn!("Failed to transcode voice to {:?}: {}", format, e);
+                                "ogg"
+                            }
+                        };
+                        if let Some(fixed_name) = bridge::fix_filename(&file_name, extension) {
                             file_name = fixed_name;
                         }
                         segments.push(Segment::Record(Segment::record(
                             Self::generate_file_base64(&file_data),
                             Some(file_name),
                         )));
+                    } else if let Some(music) =
+                        Self::generate_music_segment(&remote_chat.endpoint, document, message)
+                    {
+                        // 音乐 (自定义音乐卡片需要可访问的音频地址, 取自说明文字中的链接)
+                        segments.push(music);
                     } else if document.raw.video {
                         // 视频
                         segments.push(Segment::Video(Segment::video(
@@ -130,18 +568,45 @@ impl TelegramPylon {
                             None,
                         )));
                     } else if tg_helper::is_raw_photo(document) {
-                        // 未压缩图片
+                        // 未压缩图片 (压缩过的图片Telegram已经去除了EXIF)
+                        Self::strip_exif(bridge, &mut file_data).await;
                         segments.push(Segment::Image(Segment::image(
                             Self::generate_file_base64(&file_data),
                             Some(file_name),
                             None,
                             None,
                             None,
+                            None,
                         )));
                     } else if tg_helper::is_gif(document) {
                         // GIF表情 (Telegram里使用MP4格式保存的)
-                        // TODO: 大于阈值的以视频发送, 小于的转成GIF(微信发送大的GIF非常慢)
-                        if file_data.len() > GIF_THRESHOLD {
+                        // 较长的动画以视频发送, 短的转成GIF(微信发送大的GIF非常慢)
+                        let as_gif = if !ffmpeg::has_ffmpeg() {
+                            // 无法转换时以视频发送
+                            false
+                        } else if !ffmpeg::has_ffprobe() {
+                            file_data.len() <= GIF_THRESHOLD
+                        } else {
+                            match tg_helper::probe_video(&file_data).await {
+                                Ok(info) => {
+                                    let limit = bridge.gif_limit(&remote_chat.endpoint.platform);
+                                    tracing::debug!(
+                                        "Probed animation: {:.1}s, {} frames, {}x{}",
+                                        info.duration,
+                                        info.frames,
+                                        info.width,
+                                        info.height
+                                    );
+                                    info.duration <= limit.max_duration
+                                        && info.frames <= limit.max_frames
+                                }
+                                Err(e) => {
+                                    tracing::warn!("Failed to probe animation: {}", e);
+                                    file_data.len() <= GIF_THRESHOLD
+                                }
+                            }
+                        };
+                        if !as_gif {
                             segments.push(Segment::Video(Segment::video(
                                 Self::generate_file_base64(&file_data),
                                 Some(file_name),
@@ -161,6 +626,7 @@ impl TelegramPylon {
                                         None,
                                         None,
                                         None,
+                                        None,
                                     )));
                                 }
                                 Err(e) => {
@@ -179,25 +645,9 @@ impl TelegramPylon {
                 media::Media::Sticker(sticker) => {
                     let (mut file_name, file_data) = bridge.download_media(&media).await?;
                     match sticker.document.mime_type() {
-                        Some("video/webm") => match tg_helper::webm_to_gif(&file_data).await {
-                            Ok(gif_data) => {
-                                if let Some(fixed_name) = bridge::fix_filename(&file_name, "gif") {
-                                    file_name = fixed_name;
-                                }
-                                segments.push(Segment::Image(Segment::image(
-                                    Self::generate_file_base64(&gif_data),
-                                    Some(file_name),
-                                    None,
-                                    None,
-                                    None,
-                                )));
-                            }
-                            Err(e) => {
-                                tracing::warn!("Failed to convert webm to gif: {}", e);
-                            }
-                        },
-                        Some("application/x-tgsticker") => {
-                            match tg_helper::tgs_to_gif(sticker.document.id(), &file_data).await {
+                        // 没有ffmpeg时当文件发送
+                        Some("video/webm") if ffmpeg::has_ffmpeg() => {
+                            match tg_helper::webm_to_gif(&file_data).await {
                                 Ok(gif_data) => {
                                     if let Some(fixed_name) =
                                         bridge::fix_filename(&file_name, "gif")
@@ -210,6 +660,42 @@ impl TelegramPylon {
                                         None,
                                         None,
                                         None,
+                                        None,
+                                    )));
+                                }
+                                Err(e) => {
+                                    tracing::warn!("Failed to convert webm to gif: {}", e);
+                                }
+                            }
+                        }
+                        Some("application/x-tgsticker") => {
+                            let max_bytes = bridge
+                                .gif_limit(&remote_chat.endpoint.platform)
+                                .max_bytes
+                                .unwrap_or(usize::MAX);
+                            match tg_helper::tgs_to_gif(
+                                sticker.document.id(),
+                                &file_data,
+                                max_bytes,
+                            )
+                            .await
+                            {
+                                Ok(output) => {
+                                    let (image_data, ext) = match output {
+                                        tg_helper::TgsOutput::Gif(data) => (data, "gif"),
+                                        tg_helper::TgsOutput::Png(data) => (data, "png"),
+                                    };
+                                    if let Some(fixed_name) = bridge::fix_filename(&file_name, ext)
+                                    {
+                                        file_name = fixed_name;
+                                    }
+                                    segments.push(Segment::Image(Segment::image(
+                                        Self::generate_file_base64(&image_data),
+                                        Some(file_name),
+                                        None,
+                                        None,
+                                        None,
+                                        None,
                                     )));
                                 }
                                 Err(e) => {
@@ -256,94 +742,215 @@ impl TelegramPylon {
                         }
                     }
                 }
+                media::Media::Dice(dice) => {
+                    // QQ的掷骰子魔法表情点数由QQ随机决定, 其余以文本发送
+                    if remote_chat.endpoint.platform == Platform::QQ
+                        && dice.raw.emoticon == tg_helper::DICE_EMOTICON
+                    {
+                        segments.push(Segment::Dice(Segment::dice(None)));
+                    } else {
+                        let text = tg_helper::format_dice(dice);
+                        segments.push(Segment::Text(Segment::text(text)));
+                    }
+                }
+                media::Media::Contact(contact) => {
+                    // QQ上vCard带有QQ号的以推荐好友发送, 其余以文本名片发送
+                    match tg_helper::get_contact_qq(contact)
+                        .filter(|_| remote_chat.endpoint.platform == Platform::QQ)
+                    {
+                        Some(id) => {
+                            segments.push(Segment::Contact(Segment::contact("qq".to_string(), id)))
+                        }
+                        None => {
+                            let text = tg_helper::format_contact(contact);
+                            segments.push(Segment::Text(Segment::text(text)));
+                        }
+                    }
+                }
+                media::Media::Poll(poll) => {
+                    let text = tg_helper::format_poll(poll);
+                    segments.extend(Self::convert_text(bridge, remote_chat, &text).await);
+                }
                 media::Media::WebPage(_) => {
                     // 带Preview的消息, 需要添加文本, 和其它媒体区分对待
                     if !message.text().is_empty() {
-                        segments.push(Segment::Text(Segment::text(message.text().to_string())));
+                        let text = tg_helper::get_formatted_text(message);
+                        segments.extend(Self::convert_text(bridge, remote_chat, &text).await);
                     }
                 }
                 _ => {
                     // TODO: add more media support
                 }
             }
-        } else if !message.text().is_empty() {
-            // TODO: 暂时先不处理Telegram的媒体Caption
-            segments.push(Segment::Text(Segment::text(message.text().to_string())));
-        }
 
-        if !segments.is_empty() {
-            // 检查是否有回复的消息
-            let reply_to_msg_id = match message.reply_header() {
-                Some(tl::enums::MessageReplyHeader::Header(header)) => {
-                    if header.forum_topic {
-                        // 如果是Topic消息, 那么reply_to_top_id和reply_to_msg_id同时有值才是回复
-                        if header.reply_to_top_id.is_some() {
-                            header.reply_to_msg_id
-                        } else {
-                            None
+            // 剧透媒体在QQ上转为闪照, 不支持的情况添加提示
+            if tg_helper::has_spoiler(&media) {
+                let mut flashed = false;
+                if remote_chat.endpoint.platform == Platform::QQ {
+                    for segment in segments.iter_mut() {
+                        if let Segment::Image(image) = segment {
+                            image.type_ = Some("flash".to_string());
+                            flashed = true;
                         }
-                    } else {
-                        header.reply_to_msg_id
                     }
                 }
-                _ => None,
-            };
-            if let Some(message_id) = reply_to_msg_id {
-                if let Some((message, _)) = bridge
-                    .find_message_by_tg(message.chat().id(), message_id)
-                    .await?
-                {
-                    // QQ如果Reply不是第一个消息段的话, 会往消息末尾添加@
-                    segments.insert(0, Segment::Reply(Segment::reply(message.remote_msg_id)));
+                if !flashed {
+                    segments.insert(0, Segment::Text(Segment::text(SPOILER_MARK.to_string())));
                 }
             }
+        } else if !message.text().is_empty() {
+            // TODO: 暂时先不处理Telegram的媒体Caption
+            let text = tg_helper::get_formatted_text(message);
+            segments.extend(Self::convert_text(bridge, remote_chat, &text).await);
+        }
 
-            let content: String = segments.iter().map(|segment| segment.to_string()).collect();
+        Ok(segments)
+    }
 
-            match bridge
-                .send_msg(
-                    &remote_chat.endpoint,
-                    message_type,
-                    group_id,
-                    user_id,
-                    segments,
-                )
-                .await
-            {
-                Ok(message_id) => {
-                    bridge
-                        .save_message_by_remote(
-                            remote_chat.id,
-                            &message_id.message_id,
-                            message,
-                            &content,
-                        )
-                        .await?;
+    // 转换文本片段, QQ中的emoji还原为原生表情
+    async fn convert_text(
+        bridge: &Bridge,
+        remote_chat: &entities::remote_chat::Model,
+        text: &str,
+    ) -> Vec<Segment> {
+        let segments = Self::convert_mentions(bridge, remote_chat, text).await;
+        match remote_chat.endpoint.platform {
+            Platform::QQ => segments
+                .into_iter()
+                .flat_map(|segment| match segment {
+                    Segment::Text(seg) => ob_helper::split_qq_face(&seg.text),
+                    segment => vec![segment],
+                })
+                .collect(),
+            _ => segments,
+        }
+    }
+
+    // 将文本中的@昵称按群成员列表转换为At消息段
+    async fn convert_mentions(
+        bridge: &Bridge,
+        remote_chat: &entities::remote_chat::Model,
+        text: &str,
+    ) -> Vec<Segment> {
+        // 微信中使用原生的表情代码
+        let text = &match remote_chat.endpoint.platform {
+            Platform::WeChat => ob_helper::restore_wechat_emoji(text),
+            _ => text.to_string(),
+        };
+
+        if remote_chat.chat_type != ChatType::Group || !text.contains('@') {
+            return vec![Segment::Text(Segment::text(text.to_string()))];
+        }
+
+        let members = match bridge
+            .get_cached_group_members(&remote_chat.endpoint, &remote_chat.target_id)
+            .await
+        {
+            Ok(members) => members,
+            Err(e) => {
+                tracing::warn!("Failed to get group member list: {}", e);
+                return vec![Segment::Text(Segment::text(text.to_string()))];
+            }
+        };
+
+        let mut segments = Vec::new();
+        let mut plain = String::new();
+        let mut rest = text;
+        while let Some(pos) = rest.find('@') {
+            plain.push_str(&rest[..pos]);
+            let after = &rest[pos + 1..];
+
+            // 取匹配最长的群名片/昵称/ID, 避免昵称互为前缀时匹配错误
+            let matched = members
+                .iter()
+                .flat_map(|m| {
+                    [m.display_name(), m.nickname.clone(), m.user_id.clone()]
+                        .into_iter()
+                        .map(move |name| (name, &m.user_id))
+                })
+                .filter(|(name, _)| !name.is_empty() && after.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len());
+            match matched {
+                Some((name, user_id)) => {
+                    if !plain.is_empty() {
+                        segments.push(Segment::Text(Segment::text(std::mem::take(&mut plain))));
+                    }
+                    segments.push(Segment::At(Segment::at(user_id.clone())));
+                    rest = &after[name.len()..];
                 }
-                Err(e) => {
-                    tracing::warn!("Failed to send message to remote: {}", e);
-                    message
-                        .reply(InputMessage::html(
-                            "<b>Failed to send message to remote</b>",
-                        ))
-                        .await?;
+                None => {
+                    plain.push('@');
+                    rest = after;
                 }
             }
-        } else {
-            message
-                .reply(InputMessage::html(
-                    "<b>Failed to convert message for remote</b>",
-                ))
-                .await?;
+        }
+        plain.push_str(rest);
+        if !plain.is_empty() {
+            segments.push(Segment::Text(Segment::text(plain)));
         }
 
-        Ok(())
+        segments
+    }
+
+    // 不需要转换或压缩, 可以保存为临时文件发送的文档
+    fn can_transfer(
+        bridge: &Bridge,
+        document: &media::Document,
+        endpoint: &Endpoint,
+        message: &Message,
+    ) -> bool {
+        let size = document.size() as usize;
+        bridge.should_transfer(size)
+            && !bridge.exceeds_media_size(size)
+            && !document.raw.voice
+            && !tg_helper::is_raw_photo(document)
+            && !tg_helper::is_gif(document)
+            && Self::generate_music_segment(endpoint, document, message).is_none()
+    }
+
+    // 按配置去除图片的EXIF信息, 失败时发送原图
+    async fn strip_exif(bridge: &Bridge, file_data: &mut Vec<u8>) {
+        if !bridge.strip_exif() {
+            return;
+        }
+        // 解码和重新编码比较耗时, 不占用异步线程
+        let image_data = file_data.clone();
+        match tokio::task::spawn_blocking(move || ob_helper::strip_exif(&image_data)).await {
+            Ok(Ok(Some(image_data))) => *file_data = image_data,
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => tracing::warn!("Failed to strip exif: {}", e),
+            Err(e) => tracing::warn!("Failed to strip exif: {}", e),
+        }
     }
 
     fn generate_file_base64(data: &[u8]) -> String {
         format!("base64://{}", BASE64_STANDARD.encode(data))
     }
 
+    fn generate_music_segment(
+        endpoint: &Endpoint,
+        document: &media::Document,
+        message: &Message,
+    ) -> Option<Segment> {
+        // 目前只有QQ支持自定义音乐分享
+        if endpoint.platform != Platform::QQ {
+            return None;
+        }
+
+        let (title, performer) = tg_helper::get_audio_info(document)?;
+        let url = tg_helper::get_first_url(message)?;
+
+        Some(Segment::Music(Segment::music(
+            "custom".to_string(),
+            None,
+            Some(url.clone()),
+            Some(url),
+            Some(title.unwrap_or_else(|| document.name().to_string())),
+            performer,
+            None,
+        )))
+    }
+
     fn generate_location_segment(
         endpoint: &Endpoint,
         title: &str,
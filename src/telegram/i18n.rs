@@ -0,0 +1,347 @@
+use std::fmt::Display;
+
+use phf::phf_map;
+
+use crate::common::Language;
+
+// 中文翻译, 键为英文原文, 带参数的文本以{}占位
+static ZH: phf::Map<&'static str, &'static str> = phf_map! {
+    // 命令说明
+    "Show command list." => "显示命令列表",
+    "Manage remote chat link." => "管理远端对话的链接",
+    "Archive remote chat." => "归档远端对话",
+    "Search messages." => "搜索消息",
+    "Mute remote chat." => "静音远端对话",
+    "Unmute remote chat." => "取消静音远端对话",
+    "Set digest interval of remote chat." => "设置远端对话的摘要间隔",
+    "Set translation languages of remote chat." => "设置远端对话的翻译语言",
+    "Set voice transcription language of remote chat." => "设置远端对话的语音转写语言",
+    "Show remote chat of current group or topic." => "显示当前群组或话题对应的远端对话",
+    "Set display name of remote chat." => "设置远端对话的显示名称",
+    "Set custom emoji icon of archive topic." => "设置归档话题的自定义表情图标",
+    "Set media policy of remote chat." => "设置远端对话的媒体策略",
+    "Mark remote chat as read." => "将远端对话标记为已读",
+    "Share a friend or group to remote chat." => "向远端对话分享好友或群",
+    "Import recent history of remote chat." => "导入远端对话的近期历史消息",
+    "Kick the sender of replied message." => "踢出被回复消息的发送者",
+    "Ban the sender of replied message." => "禁言被回复消息的发送者",
+    "Set special title for the sender of replied message." => "设置被回复消息发送者的专属头衔",
+    "Add replied sticker to sticker pack." => "将被回复的贴纸加入贴纸包",
+    "List messages failed to send to remote." => "列出发送到远端失败的消息",
+    "Manage content filters of remote chat." => "管理远端对话的内容过滤",
+    "Reply privately to the sender of replied message." => "私聊回复被回复消息的发送者",
+    "Rebuild search index from stored messages." => "根据已保存的消息重建搜索索引",
+    "Reload config file." => "重新加载配置文件",
+    "Show status of endpoints or delivery of remote chat." => "显示端点的状态或远端对话的发送状态",
+    "Manage endpoint aliases." => "管理端点别名",
+    "Browse remote chats." => "浏览远端对话",
+    "Manage auto replies of remote chat." => "管理远端对话的自动回复",
+    "Manage canned replies." => "管理快捷回复",
+    "Send a canned reply to remote chat." => "向远端对话发送快捷回复",
+    "Show or reset bot sessions." => "查看或重置Bot的登录会话",
+    // 命令回复
+    "<b>Command not supported</b>" => "<b>不支持的命令</b>",
+    "<b>Currently, archive is only supported in forum groups</b>" => "<b>目前只能在开启话题的群组中归档</b>",
+    "<b>Currently, link creation is only supported in groups</b>" => "<b>目前只能在群组中创建链接</b>",
+    "<b>Currently, search is only supported in mega groups</b>" => "<b>目前只能在超级群组中搜索</b>",
+    "<b>[WARN] Failed to process message</b>" => "<b>[WARN] 消息处理失败</b>",
+    "<b>[WARN] Failed to process command</b>" => "<b>[WARN] 命令处理失败</b>",
+    "<b>[INFO] Config reloaded</b>" => "<b>[INFO] 配置已重新加载</b>",
+    "Applied log level, rate limits, GIF limits, reconnect notices, mark_as_read, \
+    language, command aliases, reaction actions, voice formats, conversation windows and EXIF stripping, other settings take effect after restart" =>
+        "已应用日志级别, 频率限制, GIF限制, 重连提示, 标记已读, 语言, 命令别名, 反应操作, 语音格式, 对话窗口和去除EXIF, 其他设置需重启后生效",
+    "<b>[WARN] Failed to reload config:</b> {}" => "<b>[WARN] 重新加载配置失败:</b> {}",
+    "<b>Invalid rule, e.g. private, group, id:1,100-200 or keyword:name</b>" =>
+        "<b>无效的规则, 如 private, group, id:1,100-200 或 keyword:名称</b>",
+    "Archive: " => "归档: ",
+    "cancel" => "取消",
+    "Archive rule of {}:" => "{} 的归档规则:",
+    "<b>Columns: endpoint, chat_type, target_id, name, tg_chat_type, tg_chat_id, tg_topic_id</b>" =>
+        "<b>列: endpoint, chat_type, target_id, name, tg_chat_type, tg_chat_id, tg_topic_id</b>",
+    "<b>Please send or reply to a CSV file with /link import</b>" =>
+        "<b>请以 /link import 为标题发送或回复CSV文件</b>",
+    "<b>Imported links: {} created, {} skipped, {} failed</b>" =>
+        "<b>导入链接: 创建 {} 个, 跳过 {} 个, 失败 {} 个</b>",
+    "Line {}: {}" => "第 {} 行: {}",
+    "<b>Failed to import links:</b> {}" => "<b>导入链接失败:</b> {}",
+    "<b>There are no remote chats available</b>" => "<b>没有可用的远端对话</b>",
+    "Link: 🔗{}({}) from ({})" => "链接: 🔗{}({}) 来自 ({})",
+    "Link:" => "链接:",
+    "{}{}{}({}) from ({})" => "{}{}{}({}) 来自 ({})",
+    "< Prev" => "< 上一页",
+    "{}/{} | Cancel" => "{}/{} | 取消",
+    "Next >" => "下一页 >",
+    "<b>No remote chats found, filter by platform, endpoint, type, linked, unlinked or name, e.g. /chats qq group unlinked</b>" =>
+        "<b>没有找到远端对话, 可按平台, 端点, 类型, linked, unlinked 或名称过滤, 如 /chats qq group unlinked</b>",
+    "Chats: {} ({})" => "对话: {} ({})",
+    "all" => "全部",
+    "<b>{}</b>\nEndpoint: <code>{}</code>\nType: {}\nTarget ID: <code>{}</code>" =>
+        "<b>{}</b>\n端点: <code>{}</code>\n类型: {}\n目标ID: <code>{}</code>",
+    "\nLinked: <code>{}</code>" => "\n已链接: <code>{}</code>",
+    " (topic {})" => " (话题 {})",
+    "Unlink" => "取消链接",
+    "🔗 Link here" => "🔗 链接到此处",
+    "\nArchived: <code>{}</code> (topic {})" => "\n已归档: <code>{}</code> (话题 {})",
+    "🗃 Open topic" => "🗃 打开话题",
+    "\nArchive: <code>{}</code>" => "\n归档: <code>{}</code>",
+    "🗃 Archive" => "🗃 归档",
+    "< Back" => "< 返回",
+    "<b>Invalid search filter:</b> {}" => "<b>无效的搜索条件:</b> {}",
+    "<b>Please input a keyword or filters: from:name in:chat type:private|group|guild after:YYYY-MM-DD before:YYYY-MM-DD</b>" =>
+        "<b>请输入关键词或过滤条件: from:名称 in:对话 type:private|group|guild after:YYYY-MM-DD before:YYYY-MM-DD</b>",
+    "<blockquote>Have reached the edge of the world.</blockquote>" =>
+        "<blockquote>已经到底了</blockquote>",
+    "Cancel" => "取消",
+    "<b>Mute is only supported in linked groups or archive topics</b>" => "<b>只能在链接的群组或归档话题中静音</b>",
+    "<b>Muted {} until unmuted</b>" => "<b>已静音 {}, 直到取消静音</b>",
+    "<b>Muted {} until {}</b>" => "<b>已静音 {} 至 {}</b>",
+    "<b>Invalid duration, e.g. 30m, 2h, 1d (up to 30d)</b>" =>
+        "<b>无效的时长, 如 30m, 2h, 1d (最长 30d)</b>",
+    // 端点状态
+    "Impl: {} {} (OneBot {})\n" => "实现: {} {} (OneBot {})\n",
+    "Impl: unknown\n" => "实现: 未知\n",
+    "Online: {}, {}\n" => "在线: {}, {}\n",
+    "yes" => "是",
+    "no" => "否",
+    "not good" => "异常",
+    "Online: unknown ({})\n" => "在线: 未知 ({})\n",
+    "Online: unknown (timeout)\n" => "在线: 未知 (超时)\n",
+    "Connected at: {}\n" => "连接时间: {}\n",
+    "Last heartbeat: {}\n" => "最近心跳: {}\n",
+    "Last heartbeat: none\n" => "最近心跳: 无\n",
+    "Pending API calls: {}\n" => "等待中的API调用: {}\n",
+    "Event queue: {}/{}, dropped: {}\n" => "事件队列: {}/{}, 已丢弃: {}\n",
+    "<b>No endpoint connected</b>" => "<b>没有已连接的端点</b>",
+    "<b>Usage: /status link in linked groups or archive topics, or /status link remote_chat_id</b>" =>
+        "<b>用法: 在链接的群组或归档话题中 /status link, 或 /status link 远端对话ID</b>",
+    "<b>Delivery status of {} (24h)</b>\nSent: {}\nPending: {}\nFailed: {}\nRecalled: {}" =>
+        "<b>{} 的发送状态 (24小时)</b>\n已发送: {}\n发送中: {}\n失败: {}\n已撤回: {}",
+    "\nLast error ({}): {}" => "\n最近错误 ({}): {}",
+    // 会话和端点
+    "<b>Usage: /session info or /session reset [bot index], the main bot is 0</b>" =>
+        "<b>用法: /session info 或 /session reset [Bot序号], 主Bot为 0</b>",
+    "<b>Bot {} signed in again</b>" => "<b>Bot {} 已重新登录</b>",
+    "<b>Failed to reset session of bot {}:</b> {}" => "<b>重置 Bot {} 的会话失败:</b> {}",
+    "<b>Endpoints:</b>" => "<b>端点:</b>",
+    "<b>Cleared alias of {}</b>" => "<b>已清除 {} 的别名</b>",
+    "<b>Renamed {} to {}</b>" => "<b>已将 {} 重命名为 {}</b>",
+    "<b>Usage: /endpoint, /endpoint rename platform:self_id alias or /endpoint rename platform:self_id</b>" =>
+        "<b>用法: /endpoint, /endpoint rename 平台:self_id 别名 或 /endpoint rename 平台:self_id</b>",
+    // 对话信息
+    "<b>No remote chat is linked or archived here</b>" => "<b>此处没有链接或归档的远端对话</b>",
+    "<b>{}</b>\nEndpoint: <code>{}</code>\nType: {}\nTarget ID: <code>{}</code>\nName: {}" =>
+        "<b>{}</b>\n端点: <code>{}</code>\n类型: {}\n目标ID: <code>{}</code>\n名称: {}",
+    "\nAlias: {}" => "\n别名: {}",
+    "\nLink: #{} ({} topic {})" => "\n链接: #{} ({} 话题 {})",
+    "\nLink: #{} ({})" => "\n链接: #{} ({})",
+    "\nLink: none" => "\n链接: 无",
+    "\nArchive: #{} ({} topic {})" => "\n归档: #{} ({} 话题 {})",
+    "\nArchive: none" => "\n归档: 无",
+    "\nMuted: until unmuted" => "\n静音: 直到取消静音",
+    "\nMuted: until {}" => "\n静音: 至 {}",
+    "<b>Unmuted {}</b>" => "<b>已取消静音 {}</b>",
+    "<b>Unmute is only supported in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中取消静音</b>",
+    // 摘要, 翻译, 转写
+    "<b>Digest is only supported in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中设置摘要</b>",
+    "<b>Digest of {} is sent every {} minutes</b>" => "<b>{} 的摘要每 {} 分钟发送一次</b>",
+    "<b>Digest of {} is disabled</b>" => "<b>{} 的摘要已关闭</b>",
+    "<b>Disabled digest of {}</b>" => "<b>已关闭 {} 的摘要</b>",
+    "<b>Digest of {} will be sent every {} minutes</b>" => "<b>{} 的摘要将每 {} 分钟发送一次</b>",
+    "<b>Invalid interval, e.g. /digest 30 or /digest off</b>" =>
+        "<b>无效的间隔, 如 /digest 30 或 /digest off</b>",
+    "<b>Translation provider is not configured</b>" => "<b>没有配置翻译服务</b>",
+    "<b>Translation is only supported in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中设置翻译</b>",
+    "<b>Messages of {} are translated as {}</b>" => "<b>{} 的消息按 {} 翻译</b>",
+    "<b>Translation of {} is disabled</b>" => "<b>{} 的翻译已关闭</b>",
+    "<b>Disabled translation of {}</b>" => "<b>已关闭 {} 的翻译</b>",
+    "<b>Messages of {} will be translated as {}</b>" => "<b>{} 的消息将按 {} 翻译</b>",
+    "<b>Invalid languages, e.g. /translate zh-en or /translate off</b>" =>
+        "<b>无效的语言, 如 /translate zh-en 或 /translate off</b>",
+    "<b>Transcription provider is not configured</b>" => "<b>没有配置语音转写服务</b>",
+    "<b>Transcription is only supported in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中设置语音转写</b>",
+    "<b>Voice messages of {} are transcribed as {}</b>" => "<b>{} 的语音消息按 {} 转写</b>",
+    "<b>Transcription of {} is disabled</b>" => "<b>{} 的语音转写已关闭</b>",
+    "<b>Disabled transcription of {}</b>" => "<b>已关闭 {} 的语音转写</b>",
+    "<b>Voice messages of {} will be transcribed as {}</b>" => "<b>{} 的语音消息将按 {} 转写</b>",
+    "<b>Invalid language, e.g. /transcribe zh, /transcribe auto or /transcribe off</b>" =>
+        "<b>无效的语言, 如 /transcribe zh, /transcribe auto 或 /transcribe off</b>",
+    // 对话设置
+    "<b>Settings are only supported in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中修改设置</b>",
+    "Settings of {}:" => "{} 的设置:",
+    "member: {}" => "成员变动: {}",
+    "honor: {}" => "群荣誉: {}",
+    "confirm: {}" => "发送确认: {}",
+    "signature: {}" => "署名: {}",
+    "source: {}" => "原消息标识: {}",
+    "on" => "开",
+    "off" => "关",
+    "<b>Reset name of {}</b>" => "<b>已恢复 {} 的名称</b>",
+    "<b>Rename is only supported in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中重命名</b>",
+    "<b>Topic icon is only supported in archive topics</b>" => "<b>只能在归档话题中设置图标</b>",
+    "<b>Usage: /topicicon custom_emoji|emoji_id or /topicicon default</b>" =>
+        "<b>用法: /topicicon 自定义表情|表情ID 或 /topicicon default</b>",
+    "<b>Changed topic icon of {}</b>" => "<b>已修改 {} 的话题图标</b>",
+    "<b>Failed to change topic icon:</b> {}" => "<b>修改话题图标失败:</b> {}",
+    // 过滤和自动回复
+    "<b>Filter is only supported in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中设置过滤</b>",
+    "<b>No filters of {}</b>" => "<b>{} 没有过滤规则</b>",
+    "<b>Filters of {}:</b>" => "<b>{} 的过滤规则:</b>",
+    "<b>Usage: /filter add drop|redact|flag [both|in|out] keyword|re:regex, /filter remove id or /filter list</b>" =>
+        "<b>用法: /filter add drop|redact|flag [both|in|out] 关键字|re:正则, /filter remove ID 或 /filter list</b>",
+    "<b>Added {} filter of {}:</b> <code>{}</code>" => "<b>已添加 {} 过滤规则 ({}):</b> <code>{}</code>",
+    "<b>Invalid regex:</b> {}" => "<b>无效的正则:</b> {}",
+    "<b>Removed filter #{} of {}</b>" => "<b>已删除过滤规则 #{} ({})</b>",
+    "<b>Filter #{} not found</b>" => "<b>没有找到过滤规则 #{}</b>",
+    "<b>Auto reply is only supported in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中设置自动回复</b>",
+    "<b>No auto replies of {}</b>" => "<b>{} 没有自动回复</b>",
+    "<b>Auto replies of {}:</b>" => "<b>{} 的自动回复:</b>",
+    " (quiet {})" => " (免打扰 {})",
+    "<b>Added auto reply of {}:</b> <code>{}</code>" => "<b>已为 {} 添加自动回复:</b> <code>{}</code>",
+    "<b>Usage: /autoreply add [quiet:22-8] keyword|re:regex =&gt; reply, /autoreply remove id or /autoreply list</b>" =>
+        "<b>用法: /autoreply add [quiet:22-8] 关键字|re:正则 =&gt; 回复, /autoreply remove ID 或 /autoreply list</b>",
+    "<b>Removed auto reply #{} of {}</b>" => "<b>已删除自动回复 #{} ({})</b>",
+    "<b>Auto reply #{} not found</b>" => "<b>没有找到自动回复 #{}</b>",
+    // 已读, 联系人, 快捷回复, 导入
+    "<b>Marked {} as read</b>" => "<b>已将 {} 标记为已读</b>",
+    "<b>No messages of {} to mark</b>" => "<b>{} 没有可标记的消息</b>",
+    "<b>Read is only supported in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中标记已读</b>",
+    "<b>Share contact is only supported in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中分享联系人</b>",
+    "<b>Usage: /share_contact [group] &lt;id&gt;</b>" =>
+        "<b>用法: /share_contact [group] &lt;ID&gt;</b>",
+    "<b>Saved canned reply {}</b>" => "<b>已保存快捷回复 {}</b>",
+    "<b>Usage: /canned add name content, /canned remove name or /canned list, send with /c name, content supports {name}, {id}, {endpoint}, {date} and {time}</b>" =>
+        "<b>用法: /canned add 名称 内容, /canned remove 名称 或 /canned list, 用 /c 名称 发送, 内容支持 {name}, {id}, {endpoint}, {date} 和 {time}</b>",
+    "<b>Removed canned reply {}</b>" => "<b>已删除快捷回复 {}</b>",
+    "<b>Canned reply {} not found</b>" => "<b>没有找到快捷回复 {}</b>",
+    "<b>Canned replies:</b>" => "<b>快捷回复:</b>",
+    "<b>Canned replies can only be sent in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中发送快捷回复</b>",
+    "<b>Importing up to {} messages of {}...</b>" => "<b>正在导入最多 {} 条消息 ({})...</b>",
+    "<b>Imported {} messages of {}</b>" => "<b>已导入 {} 条消息 ({})</b>",
+    "<b>Invalid count, e.g. /import 50 (at most {})</b>" => "<b>无效的数量, 如 /import 50 (最多 {})</b>",
+    "<b>Import is only supported in linked groups or archive topics</b>" =>
+        "<b>只能在链接的群组或归档话题中导入</b>",
+    // 群管理和私聊回复
+    "<b>Please reply to a message from remote group</b>" => "<b>请回复一条来自远端群的消息</b>",
+    "<b>Kicked {}</b>" => "<b>已踢出 {}</b>",
+    "<b>Unbanned {}</b>" => "<b>已解除禁言 {}</b>",
+    "<b>Banned {} for {}</b>" => "<b>已禁言 {} {}</b>",
+    "<b>Invalid duration, e.g. 10m, 2h, 1d (up to 30d) or 0 to unban</b>" =>
+        "<b>无效的时长, 如 10m, 2h, 1d (最长 30d), 0 为解除禁言</b>",
+    "<b>Removed special title of {}</b>" => "<b>已移除 {} 的专属头衔</b>",
+    "<b>Set special title of {} to {}</b>" => "<b>已将 {} 的专属头衔设为 {}</b>",
+    "<b>Failed to process {}</b>" => "<b>处理 {} 失败</b>",
+    "<b>Private reply is only supported in archive topics of groups</b>" => "<b>只能在群的归档话题中私聊回复</b>",
+    "<b>Stopped private reply to {}</b>" => "<b>已停止私聊回复 {}</b>",
+    "<b>Messages in this topic will be sent privately to {}, send /dm without reply to stop</b>" =>
+        "<b>此话题中的消息将私聊发送给 {}, 不回复消息发送 /dm 停止</b>",
+    "<b>Failed to open private chat</b>" => "<b>打开私聊失败</b>",
+    "<b>Please reply to a sticker</b>" => "<b>请回复一个贴纸</b>",
+    "<b>Added to <a href=\"https://t.me/addstickers/{}\">{}</a></b>" =>
+        "<b>已添加到 <a href=\"https://t.me/addstickers/{}\">{}</a></b>",
+    "<b>Failed to add sticker to sticker pack</b>" => "<b>添加贴纸到贴纸包失败</b>",
+    // 一致性检查
+    "🔗 #{} → {} (remote chat missing)" => "🔗 #{} → {} (远端对话不存在)",
+    "🔗 {} → {} (group unavailable)" => "🔗 {} → {} (群组不可用)",
+    "🗃 {} → {} (group unavailable)" => "🗃 {} → {} (群组不可用)",
+    "🗂 {} in {} (topic deleted)" => "🗂 {} 在 {} (话题已删除)",
+    "<b>All stale records are cleaned up</b>" => "<b>失效的记录已全部清理</b>",
+    "Found {} stale records, tap to clean up:" => "发现 {} 条失效的记录, 点击清理:",
+    "ignore" => "忽略",
+    // 搜索索引
+    "<b>Index is complete: {} messages</b>" => "<b>索引完整: {} 条消息</b>",
+    "<b>Index is incomplete: {} stored, {} indexed, run /reindex to rebuild</b>" =>
+        "<b>索引不完整: 已保存 {} 条, 已索引 {} 条, 发送 /reindex 重建</b>",
+    "<b>Search is not enabled</b>" => "<b>没有开启搜索</b>",
+    "<b>Reindexing messages...</b>" => "<b>正在重建索引...</b>",
+    "<b>Reindexing messages: {}/{}</b>" => "<b>正在重建索引: {}/{}</b>",
+    "<b>Reindexed {} messages</b>\nStored: {}\nIndexed: {}{}" =>
+        "<b>已重建 {} 条消息的索引</b>\n已保存: {}\n已索引: {}{}",
+    "\n⚠️ Counts don't match, new messages may have arrived during reindex" =>
+        "\n⚠️ 数量不一致, 重建期间可能收到了新消息",
+    "<b>Failed to reindex messages:</b> {}" => "<b>重建索引失败:</b> {}",
+    // 发送失败的消息
+    "Failed messages:" => "发送失败的消息:",
+    "Retry {}" => "重试 {}",
+    "\nNone" => "\n无",
+    "<b>Message resent to remote</b>" => "<b>消息已重新发送到远端</b>",
+    "Retry" => "重试",
+    "<b>Failed to send message to remote</b>\n{}" => "<b>发送消息到远端失败</b>\n{}",
+    "<del>Cancelled by the user</del>" => "<del>已取消</del>",
+    // 远端通知
+    "\n<del>Recalled this message</del>" => "\n<del>撤回了这条消息</del>",
+    "{}\n(edited): {}" => "{}\n(已编辑): {}",
+    "📌 <b>{}</b> set a message as essence" => "📌 <b>{}</b> 设置了一条精华消息",
+    "📌 <b>{}</b> removed an essence message" => "📌 <b>{}</b> 移除了一条精华消息",
+    "👋 <b>{}</b> joined" => "👋 <b>{}</b> 加入了群聊",
+    " (invited by {})" => " (由 {} 邀请)",
+    "🚫 <b>The bot</b> was kicked" => "🚫 <b>Bot</b> 被移出了群聊",
+    "🚫 <b>{}</b> was kicked" => "🚫 <b>{}</b> 被移出了群聊",
+    "🚪 <b>{}</b> left" => "🚪 <b>{}</b> 退出了群聊",
+    " by {}" => " (操作者 {})",
+    "🎉 <b>{}</b> earned {}" => "🎉 <b>{}</b> 获得了 {}",
+    "🧧 <b>{}</b> is the lucky king of {}'s red packet" => "🧧 <b>{}</b> 是 {} 红包的运气王",
+    "📋 {} ({} messages)" => "📋 {} ({} 条消息)",
+    "Dragon King" => "龙王",
+    "Group Fire" => "群聊之火",
+    "Group Blaze" => "群聊炽焰",
+    "Rising Bamboo Shoot" => "冒尖小春笋",
+    "Source of Joy" => "快乐源泉",
+    // 系统通知
+    "connected" => "已连接",
+    "disconnected" => "已断开",
+    "<b>[INFO] {} flapped {} times in {} min, currently {}</b>" => "<b>[INFO] {} 断开 {} 次 (最近 {} 分钟), 当前{}</b>",
+    "<b>[WARN] Onebot client ({}) is sending events faster than they can be relayed, {} events dropped</b> (queue: {})" =>
+        "<b>[WARN] Onebot 客户端 ({}) 发送事件的速度超过转发速度, 已丢弃 {} 个事件</b> (队列: {})",
+    "<b>[FLAG] Message {} {} matched filter {}</b>\n{}" => "<b>[FLAG] {}{}的消息命中了过滤规则 {}</b>\n{}",
+    "<b>[INFO] Storage maintenance last month</b>\nRemoved {} files, reclaimed {}MB" =>
+        "<b>[INFO] 上月存储清理</b>\n删除 {} 个文件, 回收 {}MB",
+    "<b>📊 Statistics since {}</b>\nTo Telegram: {}\nTo remote: {}\nMedia: {}MB\nFailures: {}" =>
+        "<b>📊 自 {} 以来的统计</b>\n发往Telegram: {}\n发往远端: {}\n媒体: {}MB\n失败: {}",
+    "Search index: {} messages" => "搜索索引: {} 条消息",
+    "<b>Top chats (to Telegram / to remote):</b>" => "<b>最活跃的对话 (发往Telegram / 发往远端):</b>",
+    // 会话状态
+    "Health" => "状态",
+    "good" => "正常",
+    "timeout" => "超时",
+    "unknown" => "未知",
+    "DC" => "数据中心",
+    "Auth key age" => "授权密钥时长",
+    "{} days (since {})" => "{} 天 (自 {})",
+    "to" => "发往",
+    "from" => "来自",
+};
+
+// 取文本在指定语言下的翻译, 没有翻译时使用英文原文
+pub fn tr(language: Language, text: &'static str) -> &'static str {
+    match language {
+        Language::En => text,
+        Language::Zh => ZH.get(text).copied().unwrap_or(text),
+    }
+}
+
+// 翻译带{}占位的文本, 并依次填入参数
+pub fn trf(language: Language, template: &'static str, args: &[&dyn Display]) -> String {
+    let mut result = String::new();
+    let mut args = args.iter();
+    let mut parts = tr(language, template).split("{}").peekable();
+    while let Some(part) = parts.next() {
+        result.push_str(part);
+        if parts.peek().is_some() {
+            if let Some(arg) = args.next() {
+                result.push_str(&arg.to_string());
+            }
+        }
+    }
+    result
+}
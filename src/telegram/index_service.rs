@@ -1,21 +1,25 @@
 use std::{ops::Bound, path::Path, sync::Arc, time::Duration, vec};
 
-use anyhow::Result;
-use grammers_client::types::Message;
+use anyhow::{Result, anyhow};
+use chrono::{Local, NaiveDate, TimeZone};
 use tantivy::{
-    DateOptions, DateTime, Index, IndexReader, Order, SnippetGenerator, TantivyDocument, Term,
+    DateOptions, DateTime, Index, IndexReader, Order, SnippetGenerator, TantivyDocument,
+    TantivyError, Term,
     collector::TopDocs,
     directory::MmapDirectory,
     doc,
     query::{BooleanQuery, Occur, Query, QueryParser, RangeQuery, TermQuery},
     schema::{
-        FAST, INDEXED, IndexRecordOption, STORED, Schema, TextFieldIndexing, TextOptions, Value,
+        FAST, INDEXED, IndexRecordOption, STORED, STRING, Schema, TextFieldIndexing, TextOptions,
+        Value,
     },
     tokenizer::{LowerCaser, Stemmer, TextAnalyzer},
 };
 use tokio::sync::{mpsc, oneshot};
 
-use super::telegram_helper as tg_helper;
+use super::entities::{message, remote_chat};
+use super::transport::SentMessage;
+use crate::common::ChatType;
 
 // 通道的缓冲区大小
 const BUFFER_SIZE: usize = 1024;
@@ -26,17 +30,88 @@ const COMMIT_TIME: Duration = Duration::from_secs(30);
 // 最长的片段长度
 const SNIPPET_MAX_CHARS: usize = 50;
 
+// 搜索条件, 如 /search from:张三 in:工作群 type:group after:2024-01-01 before:2024-02-01 关键词
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    pub keyword: String,
+    // 发送者名称或ID
+    pub sender: Option<String>,
+    // 远端对话名称
+    pub chat: Option<String>,
+    pub chat_type: Option<ChatType>,
+    // 起止时间戳, 包含after当天, 不包含before当天
+    pub after: Option<i64>,
+    pub before: Option<i64>,
+}
+
+impl SearchQuery {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut query = Self::default();
+        let mut keywords = Vec::new();
+        for token in text.split_whitespace() {
+            match token.split_once(':') {
+                Some(("from", sender)) if !sender.is_empty() => {
+                    query.sender = Some(sender.to_string())
+                }
+                Some(("in", chat)) if !chat.is_empty() => query.chat = Some(chat.to_string()),
+                Some(("type", chat_type)) => {
+                    query.chat_type = Some(chat_type.parse().map_err(|e: String| anyhow!(e))?)
+                }
+                Some(("after", date)) => query.after = Some(parse_date(date)?),
+                Some(("before", date)) => query.before = Some(parse_date(date)?),
+                _ => keywords.push(token),
+            }
+        }
+        query.keyword = keywords.join(" ");
+
+        Ok(query)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyword.is_empty()
+            && self.sender.is_none()
+            && self.chat.is_none()
+            && self.chat_type.is_none()
+            && self.after.is_none()
+            && self.before.is_none()
+    }
+}
+
+// 解析本地时区的日期, 返回当天零点的时间戳
+fn parse_date(date: &str) -> Result<i64> {
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| anyhow!("invalid date: {}, e.g. 2024-01-01", date))?;
+    Local
+        .from_local_datetime(&day.and_hms_opt(0, 0, 0).unwrap())
+        .earliest()
+        .map(|time| time.timestamp())
+        .ok_or_else(|| anyhow!("invalid date: {}", date))
+}
+
+// 写入线程的维护操作
+enum WriterTask {
+    // 清空索引
+    Clear,
+    // 写入已发送的文档并提交
+    Flush,
+    // 删除该时间之前的文档
+    Purge(i64),
+}
+
 #[derive(Clone)]
 pub struct IndexService {
     schema: Schema,
     reader: Arc<IndexReader>,
     query_parser: QueryParser,
+    sender_parser: QueryParser,
+    chat_parser: QueryParser,
     doc_sender: mpsc::Sender<TantivyDocument>,
     commit_sender: mpsc::Sender<oneshot::Sender<()>>,
+    task_sender: mpsc::Sender<(WriterTask, oneshot::Sender<()>)>,
 }
 
 impl IndexService {
-    pub async fn new() -> Result<Self> {
+    pub async fn new(data_dir: &Path) -> Result<Self> {
         // 定义索引的Schema
         let mut schema_builder = Schema::builder();
         schema_builder.add_i64_field("chat_id", FAST | INDEXED);
@@ -59,16 +134,42 @@ impl IndexService {
                 )
                 .set_stored(),
         );
+        // 发送者的名称和ID, 远端对话的名称和类型, 用于过滤搜索结果
+        let name_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("jieba")
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let sender_field = schema_builder.add_text_field("sender", name_options.clone());
+        let chat_name_field = schema_builder.add_text_field("chat_name", name_options);
+        schema_builder.add_text_field("chat_type", STRING);
         let schema = schema_builder.build();
 
         // 确保目录存在
-        let index_path = Path::new("tantivy");
+        let index_path = data_dir.join("tantivy");
         if !index_path.exists() {
-            std::fs::create_dir_all(index_path)?;
+            std::fs::create_dir_all(&index_path)?;
         }
 
-        let dir = MmapDirectory::open(index_path)?;
-        let index = Index::open_or_create(dir, schema.clone())?;
+        let index = match Index::open_or_create(MmapDirectory::open(&index_path)?, schema.clone()) {
+            Ok(index) => index,
+            // 旧版本的索引缺少新增的字段, 备份后重建
+            Err(TantivyError::SchemaError(e)) => {
+                let backup_path = data_dir.join("tantivy.bak");
+                tracing::warn!(
+                    "Index schema changed ({}), moving old index to {:?}",
+                    e,
+                    backup_path
+                );
+                if backup_path.exists() {
+                    std::fs::remove_dir_all(&backup_path)?;
+                }
+                std::fs::rename(&index_path, &backup_path)?;
+                std::fs::create_dir_all(&index_path)?;
+                Index::open_or_create(MmapDirectory::open(&index_path)?, schema.clone())?
+            }
+            Err(e) => return Err(e.into()),
+        };
 
         // 注册分词器
         let tokenizer = tantivy_jieba::JiebaTokenizer {};
@@ -79,12 +180,19 @@ impl IndexService {
         index.tokenizers().register("jieba", analyzer);
 
         let query_parser: QueryParser = QueryParser::for_index(&index, vec![content_field]);
+        // 名称的每个词都需要匹配
+        let mut sender_parser = QueryParser::for_index(&index, vec![sender_field]);
+        sender_parser.set_conjunction_by_default();
+        let mut chat_parser = QueryParser::for_index(&index, vec![chat_name_field]);
+        chat_parser.set_conjunction_by_default();
 
         let mut index_writer = index.writer(50_000_000)?;
 
         let (doc_sender, mut doc_receiver) = mpsc::channel(BUFFER_SIZE);
         let (commit_sender, mut commit_receiver) =
             mpsc::channel::<oneshot::Sender<()>>(BUFFER_SIZE);
+        let (task_sender, mut task_receiver) =
+            mpsc::channel::<(WriterTask, oneshot::Sender<()>)>(BUFFER_SIZE);
 
         // 启动索引写入线程
         tokio::spawn(async move {
@@ -112,6 +220,46 @@ impl IndexService {
                             commit_timestamp = std::time::Instant::now();
                         }
                     }
+                    Some((task, sender)) = task_receiver.recv() => {
+                        let purge = matches!(task, WriterTask::Purge(_));
+                        match task {
+                            WriterTask::Clear => {
+                                if let Err(e) = index_writer.delete_all_documents() {
+                                    tracing::warn!("Failed to clear index: {}", e);
+                                }
+                            }
+                            // 先写入通道中剩余的文档, 保证之前发送的文档都已提交
+                            WriterTask::Flush => {
+                                while let Ok(doc) = doc_receiver.try_recv() {
+                                    if let Err(e) = index_writer.add_document(doc) {
+                                        tracing::error!("Failed to add document to index: {}", e);
+                                    }
+                                }
+                            }
+                            WriterTask::Purge(before) => {
+                                let query = RangeQuery::new_date_bounds(
+                                    "timestamp".to_string(),
+                                    Bound::Unbounded,
+                                    Bound::Excluded(DateTime::from_timestamp_secs(before)),
+                                );
+                                if let Err(e) = index_writer.delete_query(Box::new(query)) {
+                                    tracing::warn!("Failed to purge index: {}", e);
+                                }
+                            }
+                        }
+                        if let Err(e) = index_writer.commit() {
+                            tracing::warn!("Failed to commit index: {}", e);
+                        }
+                        // 提交后清理不再使用的分段文件
+                        if purge {
+                            if let Err(e) = index_writer.garbage_collect_files().wait() {
+                                tracing::warn!("Failed to collect index garbage: {}", e);
+                            }
+                        }
+                        added_docs = 0;
+                        commit_timestamp = std::time::Instant::now();
+                        let _ = sender.send(());
+                    }
                     Some(sender) = commit_receiver.recv() => {
                         if let Err(e) = index_writer.commit() {
                             tracing::warn!("Failed to commit index: {}", e);
@@ -129,39 +277,121 @@ impl IndexService {
             schema,
             reader: Arc::new(index.reader()?),
             query_parser,
+            sender_parser,
+            chat_parser,
             doc_sender,
             commit_sender,
+            task_sender,
         })
     }
 
     // 将Telegram消息添加到索引
-    pub async fn index_message(&self, message: &Message) -> Result<()> {
-        let document = doc!(
-            self.schema.get_field("chat_id").unwrap() => message.chat().id(),
-            self.schema.get_field("message_id").unwrap() => message.id() as i64,
-            self.schema.get_field("reply_to").unwrap() => {
-                tg_helper::get_topic_id(message).map_or(0, |v| v as i64)
-            },
-            self.schema.get_field("timestamp").unwrap() => {
-                DateTime::from_timestamp_secs(message.raw.date as i64)
-            },
-            self.schema.get_field("content").unwrap() => message.text(),
+    pub async fn index_message(
+        &self,
+        message: &SentMessage,
+        sender_id: &str,
+        sender_name: &str,
+        remote_chat: &remote_chat::Model,
+    ) -> Result<()> {
+        let document = self.build_document(
+            message.chat_id,
+            message.id,
+            message.reply_to.unwrap_or(0),
+            message.date,
+            &message.text,
+            &[sender_name, sender_id],
+            remote_chat,
+        );
+
+        Ok(self.doc_sender.send(document).await?)
+    }
+
+    // 将数据库中保存的消息添加到索引, 用于重建索引
+    pub async fn index_record(
+        &self,
+        record: &message::Model,
+        tg_topic_id: i32,
+        remote_chat: &remote_chat::Model,
+    ) -> Result<()> {
+        let document = self.build_document(
+            record.tg_chat_id,
+            record.tg_msg_id,
+            tg_topic_id,
+            record.created_at,
+            &record.content,
+            &[&record.remote_sender_id],
+            remote_chat,
         );
 
         Ok(self.doc_sender.send(document).await?)
     }
 
+    #[allow(clippy::too_many_arguments)]
+    fn build_document(
+        &self,
+        chat_id: i64,
+        message_id: i32,
+        reply_to: i32,
+        timestamp: i64,
+        content: &str,
+        senders: &[&str],
+        remote_chat: &remote_chat::Model,
+    ) -> TantivyDocument {
+        let mut document = doc!(
+            self.schema.get_field("chat_id").unwrap() => chat_id,
+            self.schema.get_field("message_id").unwrap() => message_id as i64,
+            self.schema.get_field("reply_to").unwrap() => reply_to as i64,
+            self.schema.get_field("timestamp").unwrap() => DateTime::from_timestamp_secs(timestamp),
+            self.schema.get_field("content").unwrap() => content,
+            self.schema.get_field("chat_name").unwrap() => remote_chat.display_name(),
+            self.schema.get_field("chat_type").unwrap() => remote_chat.chat_type.to_string(),
+        );
+        let sender_field = self.schema.get_field("sender").unwrap();
+        for sender in senders.iter().filter(|sender| !sender.is_empty()) {
+            document.add_text(sender_field, sender);
+        }
+
+        document
+    }
+
+    // 清空索引
+    pub async fn clear(&self) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.task_sender.send((WriterTask::Clear, sender)).await?;
+        receiver.await?;
+        Ok(self.reader.reload()?)
+    }
+
+    // 删除该时间之前的文档
+    pub async fn purge(&self, before: i64) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.task_sender
+            .send((WriterTask::Purge(before), sender))
+            .await?;
+        receiver.await?;
+        Ok(self.reader.reload()?)
+    }
+
+    // 提交已发送的文档, 之后的搜索可以查到
+    pub async fn flush(&self) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.task_sender.send((WriterTask::Flush, sender)).await?;
+        receiver.await?;
+        Ok(self.reader.reload()?)
+    }
+
     // 搜索Telegram消息, 返回(消息ID, 时间戳, 片段)
     pub async fn search_messages(
         &self,
         chat_id: i64,
         reply_to: Option<i32>,
-        keyword: &str,
+        search: &SearchQuery,
         last_id: Option<i32>,
         page_size: u64,
     ) -> Result<Vec<(i32, i64, String)>> {
         let message_id_field = self.schema.get_field("message_id").unwrap();
         let timestamp_field = self.schema.get_field("timestamp").unwrap();
+        let content_field = self.schema.get_field("content").unwrap();
 
         let searcher = self.reader.searcher();
 
@@ -201,8 +431,48 @@ impl IndexService {
         }
 
         // 添加关键词的查询条件
-        if !keyword.trim().is_empty() {
-            occurs.push((Occur::Must, self.query_parser.parse_query(keyword)?));
+        if !search.keyword.trim().is_empty() {
+            occurs.push((Occur::Must, self.query_parser.parse_query(&search.keyword)?));
+        }
+
+        // 添加发送者和对话的过滤条件
+        if let Some(sender) = &search.sender {
+            occurs.push((
+                Occur::Must,
+                self.sender_parser.parse_query_lenient(sender).0,
+            ));
+        }
+        if let Some(chat) = &search.chat {
+            occurs.push((Occur::Must, self.chat_parser.parse_query_lenient(chat).0));
+        }
+        if let Some(chat_type) = &search.chat_type {
+            occurs.push((
+                Occur::Must,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(
+                        self.schema.get_field("chat_type").unwrap(),
+                        &chat_type.to_string(),
+                    ),
+                    IndexRecordOption::Basic,
+                )),
+            ));
+        }
+
+        // 添加时间范围的查询条件
+        if search.after.is_some() || search.before.is_some() {
+            let to_bound = |timestamp: Option<i64>, bound: fn(DateTime) -> Bound<DateTime>| {
+                timestamp.map_or(Bound::Unbounded, |timestamp| {
+                    bound(DateTime::from_timestamp_secs(timestamp))
+                })
+            };
+            occurs.push((
+                Occur::Must,
+                Box::new(RangeQuery::new_date_bounds(
+                    "timestamp".to_string(),
+                    to_bound(search.after, Bound::Included),
+                    to_bound(search.before, Bound::Excluded),
+                )),
+            ));
         }
 
         // 生成查询
@@ -215,8 +485,7 @@ impl IndexService {
         )?;
 
         // 片段生成器
-        let mut snippet_generator =
-            SnippetGenerator::create(&searcher, &query, self.schema.get_field("content").unwrap())?;
+        let mut snippet_generator = SnippetGenerator::create(&searcher, &query, content_field)?;
         snippet_generator.set_max_num_chars(SNIPPET_MAX_CHARS);
 
         let mut result = Vec::new();
@@ -234,18 +503,31 @@ impl IndexService {
                 .as_datetime()
                 .unwrap();
 
+            // 只有过滤条件时没有可高亮的关键词, 截取消息开头
             let snippet = snippet_generator.snippet_from_doc(&retrieved_doc);
+            let snippet = match snippet.fragment().is_empty() {
+                true => {
+                    let content = retrieved_doc
+                        .get_first(content_field)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default();
+                    let head: String = content.chars().take(SNIPPET_MAX_CHARS).collect();
+                    html_escape::encode_text(&head).to_string()
+                }
+                false => snippet.to_html(),
+            };
 
-            result.push((
-                message_id as i32,
-                timestamp.into_timestamp_secs(),
-                snippet.to_html(),
-            ));
+            result.push((message_id as i32, timestamp.into_timestamp_secs(), snippet));
         }
 
         Ok(result)
     }
 
+    // 索引中的文档数
+    pub fn num_docs(&self) -> u64 {
+        self.reader.searcher().num_docs()
+    }
+
     // 提交索引
     pub async fn commit(&self) -> Result<()> {
         let (sender, receiver) = oneshot::channel();
@@ -0,0 +1,135 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+// 缓存目录
+const CACHE_DIR: &str = "media_cache";
+// 内存缓存的最大容量
+const MEMORY_CACHE_SIZE: usize = 64 * 1024 * 1024;
+// 超过该大小的文件不放入内存缓存
+const MEMORY_ENTRY_MAX_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Default)]
+struct MemoryCache {
+    entries: HashMap<String, Arc<Vec<u8>>>,
+    // 按最近使用排序, 末尾为最新
+    order: VecDeque<String>,
+    size: usize,
+}
+
+impl MemoryCache {
+    fn get(&mut self, key: &str) -> Option<Arc<Vec<u8>>> {
+        let data = self.entries.get(key)?.clone();
+        self.touch(key);
+        Some(data)
+    }
+
+    fn put(&mut self, key: &str, data: Arc<Vec<u8>>) {
+        if data.len() > MEMORY_ENTRY_MAX_SIZE {
+            return;
+        }
+
+        if let Some(old) = self.entries.insert(key.to_owned(), data.clone()) {
+            self.size -= old.len();
+            self.touch(key);
+        } else {
+            self.order.push_back(key.to_owned());
+        }
+        self.size += data.len();
+
+        // 淘汰最久未使用的数据
+        while self.size > MEMORY_CACHE_SIZE {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(old) = self.entries.remove(&oldest) {
+                self.size -= old.len();
+            }
+        }
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(pos).unwrap();
+            self.order.push_back(key);
+        }
+    }
+}
+
+// 按内容标识缓存下载过的Telegram媒体, 避免重复下载
+#[derive(Clone)]
+pub struct MediaCache {
+    dir: PathBuf,
+    disk_limit: usize,
+    memory: Arc<Mutex<MemoryCache>>,
+}
+
+impl MediaCache {
+    pub async fn new(data_dir: &Path, disk_limit: usize) -> Result<Self> {
+        let dir = data_dir.join(CACHE_DIR);
+        tokio::fs::create_dir_all(&dir).await?;
+
+        Ok(Self {
+            dir,
+            disk_limit,
+            memory: Arc::new(Mutex::new(MemoryCache::default())),
+        })
+    }
+
+    pub async fn get(&self, key: &str) -> Option<Arc<Vec<u8>>> {
+        if let Some(data) = self.memory.lock().unwrap().get(key) {
+            return Some(data);
+        }
+
+        let data = Arc::new(tokio::fs::read(self.dir.join(key)).await.ok()?);
+        self.memory.lock().unwrap().put(key, data.clone());
+
+        Some(data)
+    }
+
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> Arc<Vec<u8>> {
+        let data = Arc::new(data);
+        self.memory.lock().unwrap().put(key, data.clone());
+
+        if data.len() <= self.disk_limit {
+            if let Err(e) = tokio::fs::write(self.dir.join(key), data.as_slice()).await {
+                tracing::warn!("Failed to write media cache: {}", e);
+            } else if let Err(e) = self.trim_disk().await {
+                tracing::warn!("Failed to trim media cache: {}", e);
+            }
+        }
+
+        data
+    }
+
+    // 磁盘缓存超过容量时删除最早的文件
+    async fn trim_disk(&self) -> Result<()> {
+        let mut files = Vec::new();
+        let mut total = 0;
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if metadata.is_file() {
+                total += metadata.len() as usize;
+                files.push((metadata.modified()?, metadata.len() as usize, entry.path()));
+            }
+        }
+
+        if total <= self.disk_limit {
+            return Ok(());
+        }
+
+        files.sort_by_key(|(modified, _, _)| *modified);
+        for (_, size, path) in files {
+            if total <= self.disk_limit {
+                break;
+            }
+            tokio::fs::remove_file(&path).await?;
+            total -= size;
+        }
+
+        Ok(())
+    }
+}
@@ -1,22 +1,246 @@
 use sea_orm::{
-    DbErr, DeriveIden, DeriveMigrationName,
+    ConnectionTrait, DbBackend, DbErr, DeriveIden, DeriveMigrationName,
     prelude::async_trait,
-    sea_query::{Index, Table},
+    sea_query::{ColumnDef, Iden, Index, IntoIden, Table},
 };
 use sea_orm_migration::{
-    MigrationTrait, MigratorTrait, SchemaManager,
-    schema::{integer, pk_auto, string},
+    MigrationName, MigrationTrait, MigratorTrait, SchemaManager,
+    schema::{big_integer, boolean, integer, pk_auto, string, text, text_null},
 };
 
 #[derive(DeriveMigrationName)]
 pub struct CreateTableMigration;
 
+// 同一文件内的迁移需要手动指定名称
+pub struct CreateChatSettingsMigration;
+
+impl MigrationName for CreateChatSettingsMigration {
+    fn name(&self) -> &str {
+        "m0002_create_chat_settings"
+    }
+}
+
+pub struct AddDigestIntervalMigration;
+
+impl MigrationName for AddDigestIntervalMigration {
+    fn name(&self) -> &str {
+        "m0003_add_digest_interval"
+    }
+}
+
+pub struct AddMessageSenderMigration;
+
+impl MigrationName for AddMessageSenderMigration {
+    fn name(&self) -> &str {
+        "m0004_add_message_sender"
+    }
+}
+
+pub struct CreateCallbackMigration;
+
+impl MigrationName for CreateCallbackMigration {
+    fn name(&self) -> &str {
+        "m0005_create_callback"
+    }
+}
+
+pub struct AddRemoteChatAliasMigration;
+
+impl MigrationName for AddRemoteChatAliasMigration {
+    fn name(&self) -> &str {
+        "m0006_add_remote_chat_alias"
+    }
+}
+
+pub struct AddChatTranslateMigration;
+
+impl MigrationName for AddChatTranslateMigration {
+    fn name(&self) -> &str {
+        "m0007_add_chat_translate"
+    }
+}
+
+pub struct AddLinkTopicMigration;
+
+impl MigrationName for AddLinkTopicMigration {
+    fn name(&self) -> &str {
+        "m0008_add_link_topic"
+    }
+}
+
+pub struct AddMessageFailuresMigration;
+
+impl MigrationName for AddMessageFailuresMigration {
+    fn name(&self) -> &str {
+        "m0009_add_message_failures"
+    }
+}
+
+pub struct AddChatTranscribeMigration;
+
+impl MigrationName for AddChatTranscribeMigration {
+    fn name(&self) -> &str {
+        "m0010_add_chat_transcribe"
+    }
+}
+
+pub struct AddArchiveRuleMigration;
+
+impl MigrationName for AddArchiveRuleMigration {
+    fn name(&self) -> &str {
+        "m0011_add_archive_rule"
+    }
+}
+
+pub struct AddChatMediaPolicyMigration;
+
+impl MigrationName for AddChatMediaPolicyMigration {
+    fn name(&self) -> &str {
+        "m0012_add_chat_media_policy"
+    }
+}
+
+pub struct AddArchiveChatTypeMigration;
+
+impl MigrationName for AddArchiveChatTypeMigration {
+    fn name(&self) -> &str {
+        "m0013_add_archive_chat_type"
+    }
+}
+
+pub struct AddChatMemberNoticeMigration;
+
+impl MigrationName for AddChatMemberNoticeMigration {
+    fn name(&self) -> &str {
+        "m0014_add_chat_member_notice"
+    }
+}
+
+pub struct AddMessagePayloadMigration;
+
+impl MigrationName for AddMessagePayloadMigration {
+    fn name(&self) -> &str {
+        "m0015_add_message_payload"
+    }
+}
+
+pub struct CreateFilterMigration;
+
+impl MigrationName for CreateFilterMigration {
+    fn name(&self) -> &str {
+        "m0016_create_filter"
+    }
+}
+
+pub struct AddChatConfirmSendMigration;
+
+impl MigrationName for AddChatConfirmSendMigration {
+    fn name(&self) -> &str {
+        "m0017_add_chat_confirm_send"
+    }
+}
+
+pub struct AddChatSignatureMigration;
+
+impl MigrationName for AddChatSignatureMigration {
+    fn name(&self) -> &str {
+        "m0018_add_chat_signature"
+    }
+}
+
+pub struct CreateEndpointAliasMigration;
+
+impl MigrationName for CreateEndpointAliasMigration {
+    fn name(&self) -> &str {
+        "m0019_create_endpoint_alias"
+    }
+}
+
+pub struct CreateTgChatMigration;
+
+impl MigrationName for CreateTgChatMigration {
+    fn name(&self) -> &str {
+        "m0020_create_tg_chat"
+    }
+}
+
+pub struct CreateCannedReplyMigration;
+
+impl MigrationName for CreateCannedReplyMigration {
+    fn name(&self) -> &str {
+        "m0021_create_canned_reply"
+    }
+}
+
+pub struct CreateAutoReplyMigration;
+
+impl MigrationName for CreateAutoReplyMigration {
+    fn name(&self) -> &str {
+        "m0022_create_auto_reply"
+    }
+}
+
+pub struct CreateConversationWindowMigration;
+
+impl MigrationName for CreateConversationWindowMigration {
+    fn name(&self) -> &str {
+        "m0023_create_conversation_window"
+    }
+}
+
+pub struct AddMessageErrorMigration;
+
+impl MigrationName for AddMessageErrorMigration {
+    fn name(&self) -> &str {
+        "m0024_add_message_error"
+    }
+}
+
+pub struct AddChatHonorNoticeMigration;
+
+impl MigrationName for AddChatHonorNoticeMigration {
+    fn name(&self) -> &str {
+        "m0025_add_chat_honor_notice"
+    }
+}
+
+pub struct AddChatSourceLinkMigration;
+
+impl MigrationName for AddChatSourceLinkMigration {
+    fn name(&self) -> &str {
+        "m0026_add_chat_source_link"
+    }
+}
+
+pub struct WidenColumnsMigration;
+
+impl MigrationName for WidenColumnsMigration {
+    fn name(&self) -> &str {
+        "m0027_widen_columns"
+    }
+}
+
+pub struct AddTgChatBotIdMigration;
+
+impl MigrationName for AddTgChatBotIdMigration {
+    fn name(&self) -> &str {
+        "m0028_add_tg_chat_bot_id"
+    }
+}
+
+// 自增主键使用64位整数, 与实体中的i64对应
+fn big_pk_auto<T: IntoIden>(name: T) -> ColumnDef {
+    big_integer(name).auto_increment().primary_key().take()
+}
+
 #[derive(DeriveIden)]
 enum Archive {
     Table,
     Id,
     Endpoint,
     TgChatId,
+    TgChatType,
+    Rule,
     CreatedAt,
     UpdatedAt,
 }
@@ -29,6 +253,7 @@ enum RemoteChat {
     ChatType,
     TargetId,
     Name,
+    Alias,
     CreatedAt,
     UpdatedAt,
 }
@@ -39,6 +264,7 @@ enum Link {
     Id,
     TgChatType,
     TgChatId,
+    TgTopicId,
     RemoteChatId,
     CreatedAt,
     UpdatedAt,
@@ -63,8 +289,112 @@ enum Message {
     TgMsgId,
     RemoteChatId,
     RemoteMsgId,
+    RemoteSenderId,
     Content,
     DeliveryStatus,
+    Failures,
+    Payload,
+    Error,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ChatSettings {
+    Table,
+    Id,
+    RemoteChatId,
+    MutedUntil,
+    DigestInterval,
+    Translate,
+    Transcribe,
+    MediaPolicy,
+    MemberNotice,
+    ConfirmSend,
+    Signature,
+    HonorNotice,
+    SourceLink,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Filter {
+    Table,
+    Id,
+    RemoteChatId,
+    Direction,
+    Action,
+    Pattern,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum EndpointAlias {
+    Table,
+    Id,
+    Endpoint,
+    Alias,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum AutoReply {
+    Table,
+    Id,
+    RemoteChatId,
+    Pattern,
+    Reply,
+    QuietHours,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ConversationWindow {
+    Table,
+    Id,
+    RemoteChatId,
+    TgChatId,
+    TgTopicId,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum CannedReply {
+    Table,
+    Id,
+    Name,
+    Content,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum TgChat {
+    Table,
+    Id,
+    BotId,
+    ChatId,
+    ChatType,
+    AccessHash,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Callback {
+    Table,
+    Id,
+    Hash,
+    Category,
+    Action,
+    Page,
+    Keyword,
+    Data,
     CreatedAt,
     UpdatedAt,
 }
@@ -255,11 +585,1123 @@ impl MigrationTrait for CreateTableMigration {
     }
 }
 
+#[async_trait::async_trait]
+impl MigrationTrait for CreateChatSettingsMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ChatSettings::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ChatSettings::Id))
+                    .col(integer(ChatSettings::RemoteChatId))
+                    .col(integer(ChatSettings::MutedUntil).default(0))
+                    .col(integer(ChatSettings::CreatedAt))
+                    .col(integer(ChatSettings::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("chat_settings_unq_remote_chat")
+                    .table(ChatSettings::Table)
+                    .col(ChatSettings::RemoteChatId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ChatSettings::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddDigestIntervalMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .add_column(integer(ChatSettings::DigestInterval).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .drop_column(ChatSettings::DigestInterval)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddMessageSenderMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(string(Message::RemoteSenderId).default(""))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::RemoteSenderId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateCallbackMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Callback::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Callback::Id))
+                    .col(string(Callback::Hash))
+                    .col(string(Callback::Category))
+                    .col(string(Callback::Action))
+                    .col(integer(Callback::Page))
+                    .col(string(Callback::Keyword))
+                    .col(string(Callback::Data))
+                    .col(integer(Callback::CreatedAt))
+                    .col(integer(Callback::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("callback_unq_hash")
+                    .table(Callback::Table)
+                    .col(Callback::Hash)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Callback::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddRemoteChatAliasMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RemoteChat::Table)
+                    .add_column(string(RemoteChat::Alias).default(""))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RemoteChat::Table)
+                    .drop_column(RemoteChat::Alias)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddChatTranslateMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .add_column(string(ChatSettings::Translate).default(""))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .drop_column(ChatSettings::Translate)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddLinkTopicMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Link::Table)
+                    .add_column(integer(Link::TgTopicId).default(0))
+                    .to_owned(),
+            )
+            .await?;
+        // 同一个群的不同Topic可以链接不同的远端对话
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("link_unq_tg_chat")
+                    .table(Link::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("link_unq_tg_chat_topic")
+                    .table(Link::Table)
+                    .col(Link::TgChatId)
+                    .col(Link::TgTopicId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("link_unq_tg_chat_topic")
+                    .table(Link::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Link::Table)
+                    .drop_column(Link::TgTopicId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("link_unq_tg_chat")
+                    .table(Link::Table)
+                    .col(Link::TgChatId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddMessageFailuresMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // MySQL的TEXT列不能有默认值, 使用可空列
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(text_null(Message::Failures))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::Failures)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddChatTranscribeMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .add_column(string(ChatSettings::Transcribe).default(""))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .drop_column(ChatSettings::Transcribe)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddArchiveRuleMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Archive::Table)
+                    .add_column(string(Archive::Rule).default(""))
+                    .to_owned(),
+            )
+            .await?;
+        // 同一个端点可以按不同规则归档到多个群
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("archive_unq_endpoint")
+                    .table(Archive::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("archive_unq_endpoint_rule")
+                    .table(Archive::Table)
+                    .col(Archive::Endpoint)
+                    .col(Archive::Rule)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("archive_unq_endpoint_rule")
+                    .table(Archive::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Archive::Table)
+                    .drop_column(Archive::Rule)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("archive_unq_endpoint")
+                    .table(Archive::Table)
+                    .col(Archive::Endpoint)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddChatMediaPolicyMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .add_column(string(ChatSettings::MediaPolicy).default(""))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .drop_column(ChatSettings::MediaPolicy)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddArchiveChatTypeMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 已有的归档均为超级群组 (PackedType::Megagroup)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Archive::Table)
+                    .add_column(integer(Archive::TgChatType).default(0b0010_1000))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Archive::Table)
+                    .drop_column(Archive::TgChatType)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddChatMemberNoticeMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .add_column(string(ChatSettings::MemberNotice).default("all"))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .drop_column(ChatSettings::MemberNotice)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddMessagePayloadMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // MySQL的TEXT列不能有默认值, 使用可空列
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(text_null(Message::Payload))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::Payload)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateFilterMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Filter::Table)
+                    .if_not_exists()
+                    .col(big_pk_auto(Filter::Id))
+                    .col(big_integer(Filter::RemoteChatId))
+                    .col(string(Filter::Direction))
+                    .col(string(Filter::Action))
+                    .col(string(Filter::Pattern))
+                    .col(big_integer(Filter::CreatedAt))
+                    .col(big_integer(Filter::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("filter_idx_remote_chat")
+                    .table(Filter::Table)
+                    .col(Filter::RemoteChatId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Filter::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddChatConfirmSendMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .add_column(boolean(ChatSettings::ConfirmSend).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .drop_column(ChatSettings::ConfirmSend)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddChatSignatureMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .add_column(string(ChatSettings::Signature).default("default"))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .drop_column(ChatSettings::Signature)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateEndpointAliasMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EndpointAlias::Table)
+                    .if_not_exists()
+                    .col(big_pk_auto(EndpointAlias::Id))
+                    .col(string(EndpointAlias::Endpoint))
+                    .col(string(EndpointAlias::Alias))
+                    .col(big_integer(EndpointAlias::CreatedAt))
+                    .col(big_integer(EndpointAlias::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("endpoint_alias_unq_endpoint")
+                    .table(EndpointAlias::Table)
+                    .col(EndpointAlias::Endpoint)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EndpointAlias::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateTgChatMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TgChat::Table)
+                    .if_not_exists()
+                    .col(big_pk_auto(TgChat::Id))
+                    .col(big_integer(TgChat::ChatId))
+                    .col(integer(TgChat::ChatType))
+                    .col(big_integer(TgChat::AccessHash))
+                    .col(big_integer(TgChat::CreatedAt))
+                    .col(big_integer(TgChat::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("tg_chat_unq_chat")
+                    .table(TgChat::Table)
+                    .col(TgChat::ChatId)
+                    .col(TgChat::ChatType)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TgChat::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateCannedReplyMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CannedReply::Table)
+                    .if_not_exists()
+                    .col(big_pk_auto(CannedReply::Id))
+                    .col(string(CannedReply::Name))
+                    .col(text(CannedReply::Content))
+                    .col(big_integer(CannedReply::CreatedAt))
+                    .col(big_integer(CannedReply::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("canned_reply_unq_name")
+                    .table(CannedReply::Table)
+                    .col(CannedReply::Name)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CannedReply::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateAutoReplyMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AutoReply::Table)
+                    .if_not_exists()
+                    .col(big_pk_auto(AutoReply::Id))
+                    .col(big_integer(AutoReply::RemoteChatId))
+                    .col(string(AutoReply::Pattern))
+                    .col(text(AutoReply::Reply))
+                    .col(string(AutoReply::QuietHours).default(""))
+                    .col(big_integer(AutoReply::CreatedAt))
+                    .col(big_integer(AutoReply::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("auto_reply_idx_remote_chat")
+                    .table(AutoReply::Table)
+                    .col(AutoReply::RemoteChatId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AutoReply::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateConversationWindowMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ConversationWindow::Table)
+                    .if_not_exists()
+                    .col(big_pk_auto(ConversationWindow::Id))
+                    .col(big_integer(ConversationWindow::RemoteChatId))
+                    .col(big_integer(ConversationWindow::TgChatId))
+                    .col(integer(ConversationWindow::TgTopicId))
+                    .col(big_integer(ConversationWindow::CreatedAt))
+                    .col(big_integer(ConversationWindow::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("conversation_window_unq_remote_chat")
+                    .table(ConversationWindow::Table)
+                    .col(ConversationWindow::RemoteChatId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ConversationWindow::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddMessageErrorMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // MySQL的TEXT列不能有默认值, 使用可空列
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(text_null(Message::Error))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::Error)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddChatHonorNoticeMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .add_column(boolean(ChatSettings::HonorNotice).default(true))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .drop_column(ChatSettings::HonorNotice)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for AddChatSourceLinkMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .add_column(boolean(ChatSettings::SourceLink).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatSettings::Table)
+                    .drop_column(ChatSettings::SourceLink)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+// 修改自增主键的类型, MySQL需要重新声明自增, Postgres保留原有的序列默认值
+// (Postgres上声明自增会生成不能用于修改列的bigserial类型)
+fn big_id<T: IntoIden>(name: T, backend: DbBackend) -> ColumnDef {
+    match backend {
+        DbBackend::MySql => big_integer(name).auto_increment().take(),
+        _ => big_integer(name),
+    }
+}
+
+// 最初的表使用32位整数和字符串, 在Postgres和MySQL上改为与实体对应的64位整数和文本,
+// SQLite的整数本身是64位且不限制字符串长度, 无需修改
+#[async_trait::async_trait]
+impl MigrationTrait for WidenColumnsMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let backend = manager.get_database_backend();
+        if backend == DbBackend::Sqlite {
+            return Ok(());
+        }
+
+        let tables = [
+            Table::alter()
+                .table(Archive::Table)
+                .modify_column(big_id(Archive::Id, backend))
+                .modify_column(big_integer(Archive::TgChatId))
+                .modify_column(big_integer(Archive::CreatedAt))
+                .modify_column(big_integer(Archive::UpdatedAt))
+                .to_owned(),
+            Table::alter()
+                .table(RemoteChat::Table)
+                .modify_column(big_id(RemoteChat::Id, backend))
+                .modify_column(big_integer(RemoteChat::CreatedAt))
+                .modify_column(big_integer(RemoteChat::UpdatedAt))
+                .to_owned(),
+            Table::alter()
+                .table(Link::Table)
+                .modify_column(big_id(Link::Id, backend))
+                .modify_column(big_integer(Link::TgChatId))
+                .modify_column(big_integer(Link::RemoteChatId))
+                .modify_column(big_integer(Link::CreatedAt))
+                .modify_column(big_integer(Link::UpdatedAt))
+                .to_owned(),
+            Table::alter()
+                .table(Topic::Table)
+                .modify_column(big_id(Topic::Id, backend))
+                .modify_column(big_integer(Topic::ArchiveId))
+                .modify_column(big_integer(Topic::RemoteChatId))
+                .modify_column(big_integer(Topic::CreatedAt))
+                .modify_column(big_integer(Topic::UpdatedAt))
+                .to_owned(),
+            Table::alter()
+                .table(Message::Table)
+                .modify_column(big_id(Message::Id, backend))
+                .modify_column(big_integer(Message::TgChatId))
+                .modify_column(big_integer(Message::RemoteChatId))
+                .modify_column(text(Message::Content))
+                .modify_column(big_integer(Message::CreatedAt))
+                .modify_column(big_integer(Message::UpdatedAt))
+                .to_owned(),
+            Table::alter()
+                .table(ChatSettings::Table)
+                .modify_column(big_id(ChatSettings::Id, backend))
+                .modify_column(big_integer(ChatSettings::RemoteChatId))
+                .modify_column(big_integer(ChatSettings::MutedUntil).default(0))
+                .modify_column(big_integer(ChatSettings::DigestInterval).default(0))
+                .modify_column(big_integer(ChatSettings::CreatedAt))
+                .modify_column(big_integer(ChatSettings::UpdatedAt))
+                .to_owned(),
+            Table::alter()
+                .table(Callback::Table)
+                .modify_column(big_id(Callback::Id, backend))
+                .modify_column(big_integer(Callback::Page))
+                .modify_column(text(Callback::Data))
+                .modify_column(big_integer(Callback::CreatedAt))
+                .modify_column(big_integer(Callback::UpdatedAt))
+                .to_owned(),
+        ];
+        for table in tables {
+            manager.alter_table(table).await?;
+        }
+
+        // Postgres的自增序列默认为32位, 一并改为64位
+        if backend == DbBackend::Postgres {
+            let sequences = [
+                Archive::Table.to_string(),
+                RemoteChat::Table.to_string(),
+                Link::Table.to_string(),
+                Topic::Table.to_string(),
+                Message::Table.to_string(),
+                ChatSettings::Table.to_string(),
+                Callback::Table.to_string(),
+            ];
+            for table in sequences {
+                manager
+                    .get_connection()
+                    .execute_unprepared(&format!(
+                        "ALTER SEQUENCE IF EXISTS \"{}_id_seq\" AS bigint",
+                        table
+                    ))
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // 缩小类型可能截断已有数据, 不做回退
+        Ok(())
+    }
+}
+
+// access_hash只对获取它的Bot有效, 对话按Bot区分
+#[async_trait::async_trait]
+impl MigrationTrait for AddTgChatBotIdMigration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 已保存的access_hash不知道属于哪个Bot, 直接清除, 收到消息后会重新保存
+        manager
+            .get_connection()
+            .execute_unprepared(&format!("DELETE FROM {}", TgChat::Table.to_string()))
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TgChat::Table)
+                    .add_column(big_integer(TgChat::BotId).default(0))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("tg_chat_unq_chat")
+                    .table(TgChat::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("tg_chat_unq_bot_chat")
+                    .table(TgChat::Table)
+                    .col(TgChat::BotId)
+                    .col(TgChat::ChatId)
+                    .col(TgChat::ChatType)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(&format!("DELETE FROM {}", TgChat::Table.to_string()))
+            .await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("tg_chat_unq_bot_chat")
+                    .table(TgChat::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TgChat::Table)
+                    .drop_column(TgChat::BotId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("tg_chat_unq_chat")
+                    .table(TgChat::Table)
+                    .col(TgChat::ChatId)
+                    .col(TgChat::ChatType)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(CreateTableMigration)]
+        vec![
+            Box::new(CreateTableMigration),
+            Box::new(CreateChatSettingsMigration),
+            Box::new(AddDigestIntervalMigration),
+            Box::new(AddMessageSenderMigration),
+            Box::new(CreateCallbackMigration),
+            Box::new(AddRemoteChatAliasMigration),
+            Box::new(AddChatTranslateMigration),
+            Box::new(AddLinkTopicMigration),
+            Box::new(AddMessageFailuresMigration),
+            Box::new(AddChatTranscribeMigration),
+            Box::new(AddArchiveRuleMigration),
+            Box::new(AddChatMediaPolicyMigration),
+            Box::new(AddArchiveChatTypeMigration),
+            Box::new(AddChatMemberNoticeMigration),
+            Box::new(AddMessagePayloadMigration),
+            Box::new(CreateFilterMigration),
+            Box::new(AddChatConfirmSendMigration),
+            Box::new(AddChatSignatureMigration),
+            Box::new(CreateEndpointAliasMigration),
+            Box::new(CreateTgChatMigration),
+            Box::new(CreateCannedReplyMigration),
+            Box::new(CreateAutoReplyMigration),
+            Box::new(CreateConversationWindowMigration),
+            Box::new(AddMessageErrorMigration),
+            Box::new(AddChatHonorNoticeMigration),
+            Box::new(AddChatSourceLinkMigration),
+            Box::new(WidenColumnsMigration),
+            Box::new(AddTgChatBotIdMigration),
+        ]
     }
 }
@@ -0,0 +1,175 @@
+// 不连接Telegram的传输实现, 记录发送的消息, 用于测试转发流程
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+use grammers_client::Update;
+use grammers_client::session::PackedType;
+use grammers_client::types::{Media, PackedChat};
+use grammers_tl_types as tl;
+use sea_orm::prelude::async_trait;
+
+use super::transport::{
+    BotUser, ChatInfo, CommandScope, OutgoingMessage, SentMessage, TelegramTransport, UploadedFile,
+};
+
+#[derive(Default)]
+pub struct MockTransport {
+    sent: Mutex<Vec<(PackedChat, OutgoingMessage)>>,
+}
+
+impl MockTransport {
+    fn record(&self, chat: PackedChat, message: OutgoingMessage) -> SentMessage {
+        let mut sent = self.sent.lock().unwrap();
+        let reply_to = message.reply_to;
+        let text = message.text.clone();
+        sent.push((chat, message));
+        SentMessage {
+            chat_id: chat.id,
+            id: sent.len() as i32,
+            reply_to,
+            date: Utc::now().timestamp(),
+            text,
+        }
+    }
+
+    // 发送到指定对话的消息, 按发送顺序排列
+    pub fn sent_to(&self, ty: PackedType, chat_id: i64) -> Vec<OutgoingMessage> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(chat, _)| chat.ty == ty && chat.id == chat_id)
+            .map(|(_, message)| message.clone())
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl TelegramTransport for MockTransport {
+    async fn send_message(
+        &self,
+        chat: PackedChat,
+        message: OutgoingMessage,
+    ) -> Result<SentMessage> {
+        Ok(self.record(chat, message))
+    }
+
+    async fn send_album(
+        &self,
+        chat: PackedChat,
+        medias: Vec<OutgoingMessage>,
+    ) -> Result<Vec<Option<SentMessage>>> {
+        Ok(medias
+            .into_iter()
+            .map(|message| Some(self.record(chat, message)))
+            .collect())
+    }
+
+    async fn edit_message(&self, _: PackedChat, _: i32, _: OutgoingMessage) -> Result<()> {
+        Ok(())
+    }
+
+    async fn pin_message(&self, _: PackedChat, _: i32) -> Result<()> {
+        Ok(())
+    }
+
+    async fn unpin_message(&self, _: PackedChat, _: i32) -> Result<()> {
+        Ok(())
+    }
+
+    async fn forward_messages(
+        &self,
+        _: PackedChat,
+        message_ids: &[i32],
+        _: PackedChat,
+    ) -> Result<Vec<Option<SentMessage>>> {
+        Ok(vec![None; message_ids.len()])
+    }
+
+    async fn upload_bytes(&self, data: &[u8], name: String) -> Result<UploadedFile> {
+        Ok(UploadedFile::Inline {
+            name,
+            data: Arc::new(data.to_vec()),
+        })
+    }
+
+    async fn unpack_chat(&self, chat: PackedChat) -> Result<ChatInfo> {
+        Ok(ChatInfo {
+            packed: chat,
+            available: true,
+        })
+    }
+
+    async fn get_me(&self) -> Result<BotUser> {
+        Ok(BotUser {
+            id: 1,
+            username: Some("porter_bot".to_string()),
+        })
+    }
+
+    async fn set_commands(&self, _: CommandScope, _: Vec<(String, String)>) -> Result<()> {
+        Ok(())
+    }
+
+    async fn create_forum_topic(&self, _: PackedChat, _: String, _: Option<i64>) -> Result<i32> {
+        Ok(1)
+    }
+
+    async fn edit_forum_topic(
+        &self,
+        _: PackedChat,
+        _: i32,
+        _: Option<String>,
+        _: Option<i64>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    async fn delete_forum_topic(&self, _: PackedChat, _: i32) -> Result<()> {
+        Ok(())
+    }
+
+    async fn get_forum_topics(&self, _: PackedChat, topic_ids: Vec<i32>) -> Result<HashSet<i32>> {
+        Ok(topic_ids.into_iter().collect())
+    }
+
+    async fn add_sticker_to_set(&self, _: String, _: tl::enums::InputStickerSetItem) -> Result<()> {
+        Err(anyhow!("stickers are not supported"))
+    }
+
+    async fn create_sticker_set(
+        &self,
+        _: PackedChat,
+        _: String,
+        _: String,
+        _: tl::enums::InputStickerSetItem,
+    ) -> Result<()> {
+        Err(anyhow!("stickers are not supported"))
+    }
+
+    fn download<'a>(&'a self, _: &Media) -> BoxStream<'a, Result<Vec<u8>>> {
+        futures_util::stream::once(async { Err(anyhow!("download is not supported")) }).boxed()
+    }
+
+    // 没有来自Telegram的更新
+    async fn next_update(&self) -> Result<Option<Update>> {
+        std::future::pending().await
+    }
+
+    fn dc_id(&self) -> Option<i32> {
+        None
+    }
+
+    fn session_file(&self) -> Option<&Path> {
+        None
+    }
+
+    async fn reset_session(&self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,20 +1,50 @@
-use aho_corasick::AhoCorasick;
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
 use anyhow::Result;
-use grammers_tl_types::enums::InputGeoPoint;
-use grammers_tl_types::types::InputMediaVenue;
-use image::GenericImageView;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use image::codecs::jpeg::JpegEncoder;
+use image::{GenericImageView, ImageDecoder, ImageFormat};
 use lazy_static::lazy_static;
 use phf::phf_map;
 use serde_json::Value;
 use serde_json_path::JsonPath;
-use tempfile::NamedTempFile;
 use tokio::process::Command;
 use webp::Encoder;
 
+use super::storage_maintenance;
+use super::transport::Venue;
+use crate::common::{MediaKind, VoiceFormat};
 use crate::onebot::protocol::segment::Segment;
 
 const QQ_FACE_UNKNOWN_PREFIX: &str = "/[Face";
 
+// 媒体片段的可访问链接
+pub fn get_media_url(segment: &Segment) -> Option<String> {
+    let url = match segment {
+        Segment::Image(seg) => seg.url.clone().or_else(|| Some(seg.file.clone())),
+        Segment::Video(seg) => seg.url.clone().or_else(|| Some(seg.file.clone())),
+        Segment::MarketFace(seg) => seg.url.clone(),
+        Segment::Record(seg) => Some(seg.file.clone()),
+        Segment::File(seg) => Some(seg.file.clone()),
+        _ => None,
+    };
+    url.filter(|url| url.starts_with("http"))
+}
+
+// 需要上传的媒体片段对应的媒体类型
+pub fn get_media_kind(segment: &Segment) -> Option<MediaKind> {
+    match segment {
+        Segment::Image(_) | Segment::MarketFace(_) if is_sticker(segment) => {
+            Some(MediaKind::Sticker)
+        }
+        Segment::Image(_) => Some(MediaKind::Image),
+        Segment::Record(_) => Some(MediaKind::Voice),
+        Segment::Video(_) => Some(MediaKind::Video),
+        Segment::File(_) => Some(MediaKind::File),
+        _ => None,
+    }
+}
+
 pub fn is_sticker(segment: &Segment) -> bool {
     match segment {
         Segment::MarketFace(_) => true,
@@ -64,9 +94,31 @@ pub fn img_to_webp(image_data: &[u8]) -> Result<Vec<u8>> {
     Ok(webp_data.to_vec())
 }
 
+// 去除JPEG图片的EXIF信息并按其中的方向旋转, 重新编码时不会写入EXIF, 其它格式不处理
+pub fn strip_exif(image_data: &[u8]) -> Result<Option<Vec<u8>>> {
+    let reader = image::ImageReader::new(std::io::Cursor::new(image_data)).with_guessed_format()?;
+    if reader.format() != Some(ImageFormat::Jpeg) {
+        return Ok(None);
+    }
+
+    let mut decoder = reader.into_decoder()?;
+    // 没有EXIF信息时不重新编码
+    if decoder.exif_metadata()?.is_none() {
+        return Ok(None);
+    }
+    let orientation = decoder.orientation()?;
+    let mut img = image::DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+
+    let mut jpeg_data = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg_data, 90).encode_image(&img.to_rgb8())?;
+
+    Ok(Some(jpeg_data))
+}
+
 pub async fn gif_to_webm(input_data: &[u8]) -> Result<Vec<u8>> {
     // 创建临时文件 (通过管道作为输入只能顺序访问, 在转换时容易出现问题)
-    let temp_file = NamedTempFile::new()?;
+    let temp_file = storage_maintenance::temp_file()?;
     let input_path = temp_file
         .path()
         .to_str()
@@ -108,9 +160,58 @@ pub async fn gif_to_webm(input_data: &[u8]) -> Result<Vec<u8>> {
     Ok(output.stdout)
 }
 
+pub async fn compress_video(input_data: &[u8]) -> Result<Vec<u8>> {
+    // 创建临时文件 (通过管道作为输入只能顺序访问, 在转换时容易出现问题)
+    let temp_file = storage_maintenance::temp_file()?;
+    let input_path = temp_file
+        .path()
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("Invalid temp path"))?;
+
+    // 将输入数据写入临时文件
+    tokio::fs::write(input_path, input_data).await?;
+
+    let child = Command::new("ffmpeg")
+        .args([
+            "-i",
+            input_path,
+            "-c:v",
+            "libx264",
+            "-preset",
+            "veryfast",
+            "-crf",
+            "30",
+            "-maxrate",
+            "1M",
+            "-bufsize",
+            "2M",
+            "-vf",
+            "scale='min(1280,iw)':-2",
+            "-c:a",
+            "aac",
+            "-b:a",
+            "64K",
+            "-movflags",
+            "frag_keyframe+empty_moov",
+            "-f",
+            "mp4",
+            "pipe:1",
+        ])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::inherit())
+        .spawn()?;
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("ffmpeg exited: {}", output.status));
+    }
+
+    Ok(output.stdout)
+}
+
 pub async fn wav_to_ogg(input_data: &[u8]) -> Result<Vec<u8>> {
     // 创建临时文件 (通过管道作为输入只能顺序访问, 在转换时容易出现问题)
-    let temp_file = NamedTempFile::new()?;
+    let temp_file = storage_maintenance::temp_file()?;
     let input_path = temp_file
         .path()
         .to_str()
@@ -135,7 +236,7 @@ pub async fn wav_to_ogg(input_data: &[u8]) -> Result<Vec<u8>> {
     Ok(output.stdout)
 }
 
-pub fn extract_location_from_json(json: &Value) -> Result<InputMediaVenue> {
+pub fn extract_location_from_json(json: &Value) -> Result<Venue> {
     let title = JsonPath::parse("$.meta.*.name")?
         .query(json)
         .exactly_one()
@@ -155,68 +256,159 @@ pub fn extract_location_from_json(json: &Value) -> Result<InputMediaVenue> {
         .exactly_one()
         .map(|v| v.as_str().unwrap().parse::<f64>().unwrap())?;
 
-    Ok(InputMediaVenue {
-        geo_point: InputGeoPoint::Point(grammers_tl_types::types::InputGeoPoint {
-            lat,
-            long,
-            accuracy_radius: None,
-        }),
+    Ok(Venue {
+        latitude: lat,
+        longitude: long,
         title,
         address,
-        provider: String::new(),
-        venue_id: String::new(),
-        venue_type: String::new(),
     })
 }
 
-pub fn extract_share_from_json(json: &Value) -> Result<String> {
-    let (title, description, source, url);
+// QQ头像的默认地址, 适用于Onebot实现未返回头像的情况
+pub fn qq_avatar_url(contact_type: &str, id: &str) -> String {
+    match contact_type {
+        "group" => format!("https://p.qlogo.cn/gh/{}/{}/640", id, id),
+        _ => format!("https://q1.qlogo.cn/g?b=qq&nk={}&s=640", id),
+    }
+}
+
+// 根据音乐平台和歌曲ID生成播放页地址
+pub fn music_url(music_type: &str, id: &str) -> Option<String> {
+    match music_type {
+        "qq" => Some(format!("https://y.qq.com/n/ryqq/songDetail/{}", id)),
+        "163" => Some(format!("https://music.163.com/#/song?id={}", id)),
+        _ => None,
+    }
+}
 
-    let doc_node = JsonPath::parse("$.meta.*.qqdocurl")?.query(json);
-    if doc_node.is_empty() {
-        let jump_node = JsonPath::parse("$.meta.*.jumpUrl")?.query(json);
-        if jump_node.is_empty() {
-            return Ok(String::new());
+// 提取群公告的标题和内容, encode为1时内容经过base64编码
+pub fn extract_announce_from_json(json: &Value) -> Result<String> {
+    let node = JsonPath::parse("$.meta.mannounce")?.query(json);
+    let announce = match node.exactly_one() {
+        Ok(announce) => announce,
+        Err(_) => return Ok(String::new()),
+    };
+
+    let encoded = announce.get("encode").and_then(Value::as_i64) == Some(1);
+    let decode = |key: &str| -> String {
+        let value = announce.get(key).and_then(Value::as_str).unwrap_or("");
+        if encoded {
+            BASE64_STANDARD
+                .decode(value)
+                .ok()
+                .and_then(|data| String::from_utf8(data).ok())
+                .unwrap_or_default()
         } else {
-            url = jump_node.exactly_one().map(|v| v.as_str().unwrap())?;
-            source = JsonPath::parse("$.meta.*.tag")?
-                .query(json)
-                .exactly_one()
-                .map(|v| v.as_str().unwrap())?;
-            description = JsonPath::parse("$.meta.*.desc")?
-                .query(json)
-                .exactly_one()
-                .map(|v| v.as_str().unwrap())?;
-            title = JsonPath::parse("$.prompt")?
-                .query(json)
-                .exactly_one()
-                .map(|v| v.as_str().unwrap())?;
+            value.to_string()
         }
-    } else {
-        url = doc_node.exactly_one().map(|v| v.as_str().unwrap())?;
-        source = JsonPath::parse("$.meta.*.title")?
-            .query(json)
-            .exactly_one()
-            .map(|v| v.as_str().unwrap())?;
-        description = JsonPath::parse("$.meta.*.desc")?
-            .query(json)
-            .exactly_one()
-            .map(|v| v.as_str().unwrap())?;
-        title = JsonPath::parse("$.prompt")?
-            .query(json)
-            .exactly_one()
-            .map(|v| v.as_str().unwrap())?;
-    }
+    };
 
     Ok(format!(
-        "<u>{}</u>\n\n{}\n\nvia <a href=\"{}\">{}</a>",
-        html_escape::encode_text(title),
-        html_escape::encode_text(description),
-        html_escape::encode_text(url),
-        html_escape::encode_text(source),
+        "📢 <b>群公告</b> {}\n<blockquote>{}</blockquote>",
+        html_escape::encode_text(&decode("title")),
+        html_escape::encode_text(&decode("text")),
     ))
 }
 
+// 识别QQ的红包和转账卡片, 不是时返回None
+pub fn extract_payment_from_json(json: &Value) -> Option<String> {
+    let app = json.get("app").and_then(Value::as_str).unwrap_or("");
+    let prompt = json.get("prompt").and_then(Value::as_str).unwrap_or("");
+    let title = JsonPath::parse("$.meta.*.title")
+        .ok()
+        .and_then(|path| path.query(json).exactly_one().ok()?.as_str())
+        .unwrap_or("");
+
+    if prompt.starts_with("[QQ红包]") || app.contains("redpacket") {
+        let greeting = match title {
+            "" => prompt.trim_start_matches("[QQ红包]"),
+            title => title,
+        };
+        Some(payment_card("🧧", "红包", greeting))
+    } else if prompt.starts_with("[转账]") || app.contains("qwallet") {
+        Some(payment_card(
+            "💰",
+            "转账",
+            prompt.trim_start_matches("[转账]"),
+        ))
+    } else {
+        None
+    }
+}
+
+// 识别微信的红包(type 2001)和转账(type 2000)消息, 不是时返回None
+pub fn extract_payment_from_xml(xml: &str) -> Option<String> {
+    match xml_tag(xml, "type")?.as_str() {
+        "2001" => {
+            let greeting = xml_tag(xml, "sendertitle").unwrap_or_default();
+            Some(payment_card("🧧", "红包", &greeting))
+        }
+        "2000" => {
+            let amount = xml_tag(xml, "feedesc").unwrap_or_default();
+            let memo = xml_tag(xml, "pay_memo").unwrap_or_default();
+            Some(payment_card(
+                "💰",
+                "转账",
+                format!("{} {}", amount, memo).trim(),
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn payment_card(icon: &str, name: &str, description: &str) -> String {
+    format!(
+        "{} <b>{}</b> {}\n<i>请在手机上查看</i>",
+        icon,
+        name,
+        html_escape::encode_text(description.trim()),
+    )
+}
+
+// 取出XML中第一个该名称的标签内的XML, 标签可以带属性, 自闭合的标签内容为空
+pub fn xml_section<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}", tag);
+    let mut offset = 0;
+    let start = loop {
+        let pos = offset + xml[offset..].find(&open)?;
+        let rest = &xml[pos + open.len()..];
+        // 跳过名称以该标签开头的其他标签, 如 <mediaList> 之于 <media>
+        if rest.starts_with(['>', '/']) || rest.starts_with(char::is_whitespace) {
+            let end = rest.find('>')?;
+            if rest[..end].ends_with('/') {
+                return Some("");
+            }
+            break pos + open.len() + end + 1;
+        }
+        offset = pos + open.len();
+    };
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(&xml[start..end])
+}
+
+// 取出XML中第一个该名称的标签的文本, 去掉CDATA包装并解码实体, 空的返回None
+pub fn xml_tag(xml: &str, tag: &str) -> Option<String> {
+    let value = xml_section(xml, tag)?.trim();
+    let value = match value
+        .strip_prefix("<![CDATA[")
+        .and_then(|v| v.strip_suffix("]]>"))
+    {
+        Some(value) => value.to_string(),
+        None => html_escape::decode_html_entities(value).to_string(),
+    };
+    Some(value.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+// 取出XML中第一个该名称的标签的属性值
+pub fn xml_attr(xml: &str, tag: &str, attr: &str) -> Option<String> {
+    let start = xml.find(&format!("<{} ", tag))?;
+    let end = start + xml[start..].find('>')?;
+    let element = &xml[start..end];
+    let value_start = element.find(&format!(" {}=\"", attr))? + attr.len() + 3;
+    let value_end = value_start + element[value_start..].find('"')?;
+    Some(html_escape::decode_html_entities(&element[value_start..value_end]).to_string())
+}
+
 static QQ_EMOJI: phf::Map<&'static str, &'static str> = phf_map! {
     "0" => "/惊讶",
     "1" => "/撇嘴",
@@ -495,6 +687,135 @@ static QQ_EMOJI: phf::Map<&'static str, &'static str> = phf_map! {
     "348" => "/福萝卜",
 };
 
+// QQ表情ID对应的emoji, 没有对应emoji的表情使用名称
+// 多个表情对应同一个emoji时, 反向映射取第一个
+const QQ_FACE_EMOJI: &[(&str, &str)] = &[
+    ("0", "😮"),
+    ("1", "😕"),
+    ("2", "😍"),
+    ("3", "😳"),
+    ("4", "😏"),
+    ("5", "😢"),
+    ("6", "☺️"),
+    ("7", "🤐"),
+    ("8", "😴"),
+    ("9", "😭"),
+    ("10", "😅"),
+    ("11", "😡"),
+    ("12", "😜"),
+    ("13", "😁"),
+    ("14", "🙂"),
+    ("15", "🙁"),
+    ("16", "😎"),
+    ("18", "😫"),
+    ("19", "🤮"),
+    ("20", "🤭"),
+    ("21", "🥰"),
+    ("22", "🙄"),
+    ("23", "😤"),
+    ("24", "🤤"),
+    ("25", "😪"),
+    ("26", "😱"),
+    ("27", "😓"),
+    ("28", "😄"),
+    ("29", "😌"),
+    ("30", "💪"),
+    ("31", "🤬"),
+    ("32", "🤔"),
+    ("33", "🤫"),
+    ("34", "😵"),
+    ("35", "😖"),
+    ("36", "😩"),
+    ("37", "💀"),
+    ("38", "🔨"),
+    ("39", "👋"),
+    ("41", "🥶"),
+    ("42", "💑"),
+    ("43", "🤸"),
+    ("46", "🐷"),
+    ("49", "🤗"),
+    ("53", "🎂"),
+    ("54", "⚡"),
+    ("55", "💣"),
+    ("56", "🔪"),
+    ("57", "⚽"),
+    ("59", "💩"),
+    ("60", "☕"),
+    ("61", "🍚"),
+    ("63", "🌹"),
+    ("64", "🥀"),
+    ("66", "❤️"),
+    ("67", "💔"),
+    ("69", "🎁"),
+    ("74", "☀️"),
+    ("75", "🌙"),
+    ("76", "👍"),
+    ("77", "👎"),
+    ("78", "🤝"),
+    ("79", "✌️"),
+    ("85", "😘"),
+    ("86", "😠"),
+    ("89", "🍉"),
+    ("96", "😰"),
+    ("97", "😥"),
+    ("99", "👏"),
+    ("104", "🥱"),
+    ("105", "😒"),
+    ("106", "🥺"),
+    ("107", "🥲"),
+    ("110", "😨"),
+    ("113", "🍺"),
+    ("114", "🏀"),
+    ("115", "🏓"),
+    ("116", "💋"),
+    ("117", "🐞"),
+    ("120", "👊"),
+    ("122", "🤟"),
+    ("123", "🙅"),
+    ("124", "👌"),
+    ("137", "🧨"),
+    ("138", "🏮"),
+    ("140", "🎤"),
+    ("144", "🎉"),
+    ("145", "🙏"),
+    ("147", "🍭"),
+    ("148", "🍼"),
+    ("151", "✈️"),
+    ("158", "💵"),
+    ("168", "💊"),
+    ("169", "🔫"),
+    ("171", "🍵"),
+    ("172", "😉"),
+    ("174", "😮‍💨"),
+    ("175", "😋"),
+    ("179", "🐶"),
+    ("180", "🤩"),
+    ("182", "😂"),
+    ("185", "🦙"),
+    ("187", "👻"),
+    ("188", "🥚"),
+    ("190", "🌼"),
+    ("192", "🧧"),
+    ("193", "😆"),
+    ("194", "😞"),
+    ("197", "😑"),
+    ("198", "😬"),
+    ("202", "😶"),
+    ("205", "💐"),
+    ("228", "🎊"),
+    ("229", "🥂"),
+    ("241", "🥳"),
+    ("264", "🤦"),
+    ("268", "❓"),
+    ("277", "🐕"),
+    ("278", "💦"),
+    ("283", "🤣"),
+    ("284", "😐"),
+    ("285", "🐟"),
+    ("286", "😈"),
+    ("291", "😲"),
+];
+
 const WECHAT_EMOJI_REPLACEMENTS: &[(&str, &str)] = &[
     ("[微笑]", "😃"),
     ("[Smile]", "😃"),
@@ -748,10 +1069,49 @@ lazy_static! {
             .map(|(_, new)| *new)
             .collect()
     };
+    // 反向映射, 同一个emoji对应多个代码时取第一个(中文)代码
+    static ref EMOJI_REVERSE_PAIRS: Vec<(&'static str, &'static str)> = {
+        let mut pairs: Vec<(&str, &str)> = Vec::new();
+        for (code, emoji) in WECHAT_EMOJI_REPLACEMENTS {
+            if !pairs.iter().any(|(e, _)| e == emoji) {
+                pairs.push((*emoji, *code));
+            }
+        }
+        pairs
+    };
+    static ref EMOJI_REVERSE_REPLACER: AhoCorasick = {
+        // 优先匹配带变体选择符的emoji
+        AhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(EMOJI_REVERSE_PAIRS.iter().map(|(emoji, _)| *emoji))
+            .unwrap()
+    };
+    static ref EMOJI_REVERSE_REPLACEMENTS: Vec<&'static str> =
+        EMOJI_REVERSE_PAIRS.iter().map(|(_, code)| *code).collect();
+    // emoji到QQ表情ID的反向映射, 同时匹配不带变体选择符的写法
+    static ref QQ_FACE_REVERSE_PAIRS: Vec<(String, &'static str)> = {
+        let mut pairs: Vec<(String, &str)> = Vec::new();
+        for (id, emoji) in QQ_FACE_EMOJI {
+            for emoji in [emoji.to_string(), emoji.replace('\u{FE0F}', "")] {
+                if !pairs.iter().any(|(e, _)| *e == emoji) {
+                    pairs.push((emoji, *id));
+                }
+            }
+        }
+        pairs
+    };
+    static ref QQ_FACE_MATCHER: AhoCorasick = {
+        AhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(QQ_FACE_REVERSE_PAIRS.iter().map(|(emoji, _)| emoji))
+            .unwrap()
+    };
 }
 
 pub fn replace_qq_face(id: &str) -> String {
-    if let Some(face) = QQ_EMOJI.get(id) {
+    if let Some((_, emoji)) = QQ_FACE_EMOJI.iter().find(|(face_id, _)| *face_id == id) {
+        emoji.to_string()
+    } else if let Some(face) = QQ_EMOJI.get(id) {
         face.to_string()
     } else {
         let mut result = String::with_capacity(QQ_FACE_UNKNOWN_PREFIX.len() + id.len() + 1);
@@ -762,6 +1122,126 @@ pub fn replace_qq_face(id: &str) -> String {
     }
 }
 
+// 将文本中的emoji拆分为QQ表情片段
+pub fn split_qq_face(text: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut last = 0;
+    for m in QQ_FACE_MATCHER.find_iter(text) {
+        if m.start() > last {
+            segments.push(Segment::Text(Segment::text(
+                text[last..m.start()].to_string(),
+            )));
+        }
+        let id = QQ_FACE_REVERSE_PAIRS[m.pattern().as_usize()].1;
+        segments.push(Segment::Face(Segment::face(id.to_string())));
+        last = m.end();
+    }
+    if last < text.len() {
+        segments.push(Segment::Text(Segment::text(text[last..].to_string())));
+    }
+    segments
+}
+
 pub fn replace_wechat_emoji(content: &str) -> String {
     EMOJI_REPLACER.replace_all(content, &EMOJI_REPLACEMENTS)
 }
+
+// 将emoji转换回微信的表情代码, 以便在微信中原生显示
+pub fn restore_wechat_emoji(content: &str) -> String {
+    EMOJI_REVERSE_REPLACER.replace_all(content, &EMOJI_REVERSE_REPLACEMENTS)
+}
+
+// 默认的SILK编码器 (silk-v3-decoder项目的encoder)
+const DEFAULT_SILK_ENCODER: &str = "silk_v3_encoder";
+// QQ语音使用的采样率
+const SILK_SAMPLE_RATE: &str = "24000";
+
+// 将Telegram的OPUS语音转成目标格式, 返回数据和扩展名
+pub async fn transcode_voice(
+    input_data: &[u8],
+    format: VoiceFormat,
+    silk_encoder: Option<&str>,
+) -> Result<(Vec<u8>, &'static str)> {
+    match format {
+        VoiceFormat::Ogg => Ok((input_data.to_vec(), "ogg")),
+        VoiceFormat::Amr => Ok((ogg_to_amr(input_data).await?, "amr")),
+        VoiceFormat::Silk => Ok((ogg_to_silk(input_data, silk_encoder).await?, "silk")),
+    }
+}
+
+async fn ogg_to_amr(input_data: &[u8]) -> Result<Vec<u8>> {
+    // 创建临时文件 (通过管道作为输入只能顺序访问, 在转换时容易出现问题)
+    let temp_file = storage_maintenance::temp_file()?;
+    let input_path = temp_file
+        .path()
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("Invalid temp path"))?;
+
+    // 将输入数据写入临时文件
+    tokio::fs::write(input_path, input_data).await?;
+
+    let child = Command::new("ffmpeg")
+        .args([
+            "-i",
+            input_path,
+            "-ar",
+            "8000",
+            "-ac",
+            "1",
+            "-c:a",
+            "libopencore_amrnb",
+            "-b:a",
+            "12.2k",
+            "-f",
+            "amr",
+            "pipe:1",
+        ])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::inherit())
+        .spawn()?;
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("ffmpeg exited: {}", output.status));
+    }
+
+    Ok(output.stdout)
+}
+
+// 先用ffmpeg解码成PCM, 再由SILK编码器编码 (带QQ需要的头部)
+async fn ogg_to_silk(input_data: &[u8], encoder: Option<&str>) -> Result<Vec<u8>> {
+    let input_file = storage_maintenance::temp_file()?;
+    tokio::fs::write(input_file.path(), input_data).await?;
+    let pcm_file = storage_maintenance::temp_file()?;
+    let silk_file = storage_maintenance::temp_file()?;
+
+    let status = Command::new("ffmpeg")
+        .arg("-y")
+        .arg("-i")
+        .arg(input_file.path())
+        .args(["-ar", SILK_SAMPLE_RATE, "-ac", "1", "-f", "s16le"])
+        .arg(pcm_file.path())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::inherit())
+        .status()
+        .await?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("ffmpeg exited: {}", status));
+    }
+
+    let encoder = encoder.unwrap_or(DEFAULT_SILK_ENCODER);
+    let status = Command::new(encoder)
+        .arg(pcm_file.path())
+        .arg(silk_file.path())
+        .args(["-tencent", "-rate", SILK_SAMPLE_RATE, "-quiet"])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::inherit())
+        .status()
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to run {}: {}", encoder, e))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} exited: {}", encoder, status));
+    }
+
+    Ok(tokio::fs::read(silk_file.path()).await?)
+}
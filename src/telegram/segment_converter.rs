@@ -0,0 +1,1176 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures_util::future::join_all;
+use lazy_static::lazy_static;
+use sea_orm::prelude::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::bridge::{Bridge, ChatModel, MediaTooLarge, TelegramBot, UploadedInfo};
+use super::card_parser::{self, Card};
+use super::onebot_helper as ob_helper;
+use super::telegram_helper as tg_helper;
+use super::transport::Venue;
+use crate::common::{ChatType, Endpoint, MediaKind, MediaMode, Platform};
+use crate::onebot::protocol::event::MessageEvent;
+use crate::onebot::protocol::segment::Segment;
+
+// 引用预览的最大长度
+const QUOTE_MAX_LENGTH: usize = 100;
+
+pub enum TgMsgType {
+    Text,
+    // 动画骰子, 值为骰子的表情
+    Dice(String),
+    Html,
+    Photo,
+    Sticker,
+    Voice,
+    Video,
+    Document,
+    Location,
+    // 带图片的HTML卡片
+    Card,
+}
+
+// 转换过程中累积的消息内容
+pub struct ConvertContext<'a> {
+    pub bridge: &'a Bridge,
+    pub bot: &'a TelegramBot,
+    pub endpoint: &'a Endpoint,
+    pub remote_chat: Arc<ChatModel>,
+    pub message: &'a MessageEvent,
+    pub msg_type: TgMsgType,
+    pub content: String,
+    pub media_uploaded: Vec<UploadedInfo>,
+    // 无法和主消息一起发送的媒体, 在主消息之后依次回复发送
+    pub followups: Vec<(TgMsgType, UploadedInfo)>,
+    pub location: Option<Venue>,
+    // 需要提及管理员的名称
+    pub mentions: Vec<String>,
+    pub spoiler: bool,
+    pub reply_to: Option<i32>,
+    // 回复的消息没有对应的Telegram消息时, 引用原消息内容
+    pub quote: Option<String>,
+    // 回复的是转发过去的投票时, 投票的各选项
+    pub poll_options: Option<Vec<String>>,
+    // 转换失败的片段, 不影响其余片段的发送
+    pub failures: Vec<SegmentFailure>,
+    // 预先并发上传的媒体片段: 片段序号 -> 上传结果
+    pub prefetched: HashMap<usize, Result<UploadedInfo>>,
+    // 正在转换的片段序号
+    pub position: usize,
+}
+
+// 转换失败的片段及原因
+#[derive(Serialize)]
+pub struct SegmentFailure {
+    pub name: String,
+    pub error: String,
+    pub segment: Segment,
+}
+
+// 转换后是否继续处理后续片段
+pub enum Flow {
+    Continue,
+    Break,
+}
+
+#[async_trait::async_trait]
+pub trait SegmentConverter: Send + Sync {
+    // 不处理该类型的片段时返回None, 交给下一个转换器
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>>;
+}
+
+lazy_static! {
+    // 已注册的片段转换器, 按顺序交给第一个处理该片段的转换器
+    static ref PIPELINE: Vec<Box<dyn SegmentConverter>> = vec![
+        Box::new(TextConverter),
+        Box::new(FaceConverter),
+        Box::new(AtConverter),
+        Box::new(ImageConverter),
+        Box::new(MediaConverter),
+        Box::new(ReplyConverter),
+        Box::new(ForwardConverter),
+        Box::new(LocationConverter),
+        Box::new(DiceConverter),
+        Box::new(ShareConverter),
+        Box::new(ContactConverter),
+        Box::new(MusicConverter),
+        Box::new(JsonConverter),
+        Box::new(XmlConverter),
+    ];
+}
+
+impl ConvertContext<'_> {
+    // 遍历消息里的各片段进行转换处理
+    #[tracing::instrument(skip_all, fields(remote_chat_id = self.remote_chat.id))]
+    pub async fn convert_all(&mut self) -> Result<()> {
+        let message = self.message;
+        self.prefetch_uploads().await;
+        'segments: for (position, segment) in message.message.iter().enumerate() {
+            self.position = position;
+            for converter in PIPELINE.iter() {
+                match converter.convert(self, segment).await {
+                    Ok(None) => continue,
+                    Ok(Some(Flow::Continue)) => {}
+                    Ok(Some(Flow::Break)) => break 'segments,
+                    Err(e) => {
+                        tracing::warn!("Failed to convert segment: {}", e);
+                        self.fail(segment, "消息片段", &e);
+                    }
+                }
+                break;
+            }
+        }
+        self.resolve_poll_vote();
+
+        Ok(())
+    }
+
+    // 回复投票的消息只有序号时, 显示为所选的选项
+    // Bot无法代替远端用户在Telegram上投票, 只能以文本的形式转达, 文本消息不需要转义
+    fn resolve_poll_vote(&mut self) {
+        let Some(options) = &self.poll_options else {
+            return;
+        };
+        let Ok(index) = self.content.trim().parse::<usize>() else {
+            return;
+        };
+        if let Some(option) = index.checked_sub(1).and_then(|i| options.get(i)) {
+            self.content = format!("🗳 投票: {}. {}", index, option);
+        }
+    }
+
+    // 消息中有多个需要上传的媒体时并发下载, 转换和上传, 转换时按原顺序取用结果
+    async fn prefetch_uploads(&mut self) {
+        let mut positions = Vec::new();
+        for (position, segment) in self.message.message.iter().enumerate() {
+            if let Some(kind) = ob_helper::get_media_kind(segment) {
+                if self.bridge.get_media_mode(self.remote_chat.id, kind).await == MediaMode::Upload
+                {
+                    positions.push(position);
+                }
+            }
+        }
+        if positions.len() < 2 {
+            return;
+        }
+
+        let (bridge, bot, endpoint, message) = (self.bridge, self.bot, self.endpoint, self.message);
+        let results = join_all(
+            positions
+                .iter()
+                .map(|position| bridge.upload_segment(bot, endpoint, &message.message[*position])),
+        )
+        .await;
+        self.prefetched = positions.into_iter().zip(results).collect();
+    }
+
+    // 上传当前片段的媒体, 已预先上传的直接取用结果
+    async fn upload_segment(&mut self, segment: &Segment) -> Result<UploadedInfo> {
+        match self.prefetched.remove(&self.position) {
+            Some(result) => result,
+            None => {
+                self.bridge
+                    .upload_segment(self.bot, self.endpoint, segment)
+                    .await
+            }
+        }
+    }
+
+    // 图片和视频可以合并为相册, 其他类型的媒体之间无法合并, 另外发送
+    fn push_media(&mut self, msg_type: TgMsgType, uploaded: UploadedInfo) {
+        let album = |t: &TgMsgType| matches!(t, TgMsgType::Photo | TgMsgType::Video);
+        match &self.msg_type {
+            TgMsgType::Text | TgMsgType::Html => {
+                self.media_uploaded.push(uploaded);
+                self.msg_type = msg_type;
+            }
+            current if album(current) && album(&msg_type) => {
+                self.media_uploaded.push(uploaded);
+                // 多个媒体按相册发送
+                self.msg_type = TgMsgType::Photo;
+            }
+            _ => self.followups.push((msg_type, uploaded)),
+        }
+    }
+
+    // 按对话的媒体设置只发送链接或占位文字, 不需要上传时返回true
+    async fn skip_upload(&mut self, segment: &Segment, kind: MediaKind) -> bool {
+        match self.bridge.get_media_mode(self.remote_chat.id, kind).await {
+            MediaMode::Upload => false,
+            MediaMode::Link => {
+                match ob_helper::get_media_url(segment) {
+                    Some(url) => write!(&mut self.content, "[{}] {}", kind.label(), url).unwrap(),
+                    None => write!(&mut self.content, "[{}]", kind.label()).unwrap(),
+                }
+                true
+            }
+            MediaMode::Drop => {
+                write!(&mut self.content, "[{}]", kind.label()).unwrap();
+                true
+            }
+        }
+    }
+
+    // 卡片消息以HTML发送, 有缩略图时附带图片
+    async fn push_card(&mut self, card: &Card) {
+        self.content.push_str(&card.to_html());
+        self.msg_type = TgMsgType::Html;
+        if let Some(url) = card.thumbnail.as_ref().filter(|u| u.starts_with("http")) {
+            match self.bridge.upload_url(self.bot, url).await {
+                Ok(uploaded) => {
+                    self.media_uploaded.push(uploaded);
+                    self.msg_type = TgMsgType::Card;
+                }
+                Err(e) => tracing::warn!("Failed to upload card thumbnail: {}", e),
+            }
+        }
+    }
+
+    fn fail(&mut self, segment: &Segment, name: &str, e: &anyhow::Error) {
+        self.failures.push(SegmentFailure {
+            name: name.to_string(),
+            error: e.to_string(),
+            segment: segment.clone(),
+        });
+    }
+
+    // 失败片段的折叠汇总, 没有失败时返回None
+    pub fn failure_report(&self) -> Option<String> {
+        if self.failures.is_empty() {
+            return None;
+        }
+
+        let mut report = format!("⚠️ {}个片段转发失败", self.failures.len());
+        for failure in &self.failures {
+            write!(&mut report, "\n[{}] {}", failure.name, failure.error).unwrap();
+        }
+
+        Some(format!(
+            "<blockquote expandable>{}</blockquote>",
+            html_escape::encode_text(&report)
+        ))
+    }
+}
+
+struct TextConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for TextConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::Text(seg) = segment else {
+            return Ok(None);
+        };
+        match ctx.endpoint.platform {
+            Platform::WeChat => {
+                ctx.content
+                    .push_str(&ob_helper::replace_wechat_emoji(&seg.text));
+            }
+            _ => {
+                ctx.content.push_str(&seg.text);
+            }
+        }
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct FaceConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for FaceConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::Face(seg) = segment else {
+            return Ok(None);
+        };
+        match ctx.endpoint.platform {
+            Platform::QQ => {
+                ctx.content
+                    .push_str(ob_helper::replace_qq_face(&seg.id).as_str());
+            }
+            _ => {
+                ctx.content.push_str("/[Face");
+                ctx.content.push_str(&seg.id);
+                ctx.content.push(']');
+            }
+        }
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct AtConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for AtConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::At(seg) = segment else {
+            return Ok(None);
+        };
+        // 只有群消息可以查询成员名称, 频道消息只带频道和子频道ID
+        let group_id = match ctx.message.get_chat_type() {
+            ChatType::Group => ctx.message.group_id.clone(),
+            _ => None,
+        };
+        let name = match group_id {
+            Some(group_id) => match ctx
+                .bridge
+                .get_group_member_info(ctx.endpoint, group_id, seg.id.clone(), true)
+                .await
+            {
+                Ok(member) => member.display_name(),
+                Err(_) => seg.id.clone(),
+            },
+            None => seg.id.clone(),
+        };
+        ctx.content.push('@');
+        ctx.content.push_str(&name);
+        // @自己时提及管理员, 以便收到Telegram通知
+        if seg.id == ctx.endpoint.id {
+            ctx.mentions.push(name);
+        }
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct ImageConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for ImageConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::Image(seg) = segment else {
+            return Ok(None);
+        };
+        let kind = match ob_helper::is_sticker(segment) {
+            true => MediaKind::Sticker,
+            false => MediaKind::Image,
+        };
+        if ctx.skip_upload(segment, kind).await {
+            return Ok(Some(Flow::Continue));
+        }
+        match ctx.upload_segment(segment).await {
+            Ok(uploaded) => {
+                ctx.content.push_str(&segment.to_string());
+                // 闪照以剧透遮罩的方式发送
+                if seg.type_.as_deref() == Some("flash") {
+                    ctx.spoiler = true;
+                }
+                if ob_helper::is_sticker(segment) {
+                    ctx.push_media(TgMsgType::Sticker, uploaded);
+                } else {
+                    ctx.push_media(TgMsgType::Photo, uploaded);
+                }
+            }
+            Err(e) => {
+                ctx.content.push_str(&upload_failed_text("图片", &e));
+                tracing::warn!("Failed to upload photo: {}", e);
+                ctx.fail(segment, "图片", &e);
+            }
+        }
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+// 魔法表情, 语音, 视频和文件都是上传后按对应类型发送
+struct MediaConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for MediaConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let (name, kind, msg_type, media_kind) = match segment {
+            Segment::MarketFace(_) => ("表情", "sticker", TgMsgType::Sticker, MediaKind::Sticker),
+            Segment::Record(_) => ("语音", "record", TgMsgType::Voice, MediaKind::Voice),
+            Segment::Video(_) => ("视频", "video", TgMsgType::Video, MediaKind::Video),
+            Segment::File(_) => ("文件", "file", TgMsgType::Document, MediaKind::File),
+            _ => return Ok(None),
+        };
+        if ctx.skip_upload(segment, media_kind).await {
+            return Ok(Some(Flow::Continue));
+        }
+        match ctx.upload_segment(segment).await {
+            Ok(uploaded) => {
+                write!(&mut ctx.content, "[{}]", name).unwrap();
+                ctx.push_media(msg_type, uploaded);
+            }
+            Err(e) => {
+                ctx.content.push_str(&upload_failed_text(name, &e));
+                tracing::warn!("Failed to upload {}: {}", kind, e);
+                ctx.fail(segment, name, &e);
+            }
+        }
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct ReplyConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for ReplyConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::Reply(seg) = segment else {
+            return Ok(None);
+        };
+        if let Some(entity) = ctx
+            .bridge
+            .find_message_by_remote(ctx.remote_chat.id, &seg.id)
+            .await?
+        {
+            ctx.reply_to = Some(entity.tg_msg_id);
+            ctx.poll_options = tg_helper::parse_poll_options(&entity.content);
+        } else {
+            match ctx.bridge.get_msg(ctx.endpoint, seg.id.clone()).await {
+                Ok(detail) => {
+                    let content: String = detail.message.iter().map(|s| s.to_string()).collect();
+                    let mut quote = format!("{}: {}", detail.sender.display_name(), content);
+                    if let Some((pos, _)) = quote.char_indices().nth(QUOTE_MAX_LENGTH) {
+                        quote.truncate(pos);
+                        quote.push('…');
+                    }
+                    ctx.quote = Some(quote);
+                }
+                Err(e) => tracing::warn!("Failed to get replied message {}: {}", seg.id, e),
+            }
+        }
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct ForwardConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for ForwardConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::Forward(seg) = segment else {
+            return Ok(None);
+        };
+        ctx.content.push_str("[合并消息]\n");
+        for msg in &ctx
+            .bridge
+            .get_forward_msg(ctx.endpoint, seg.id.clone())
+            .await?
+            .messages
+        {
+            writeln!(&mut ctx.content, "{}", msg).unwrap();
+        }
+        ctx.content.pop();
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct LocationConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for LocationConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::Location(seg) = segment else {
+            return Ok(None);
+        };
+        ctx.location = Some(Venue {
+            latitude: seg.lat,
+            longitude: seg.lon,
+            title: seg.title.as_deref().unwrap_or("").to_string(),
+            address: seg.content.as_deref().unwrap_or("").to_string(),
+        });
+        ctx.msg_type = TgMsgType::Location;
+
+        // 部分客户端不显示位置的地图, 另外附上静态地图图片
+        if let Some(url) = ctx.bridge.static_map_url(seg.lat, seg.lon) {
+            match ctx.bridge.upload_url(ctx.bot, &url).await {
+                Ok(uploaded) => ctx.media_uploaded.push(uploaded),
+                Err(e) => tracing::warn!("Failed to upload static map: {}", e),
+            }
+        }
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct DiceConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for DiceConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        match segment {
+            Segment::Dice(seg) => {
+                // 发送Telegram的动画骰子, 远端实际的点数以文字附上
+                ctx.msg_type = TgMsgType::Dice(tg_helper::DICE_EMOTICON.to_string());
+                ctx.content.push_str(tg_helper::DICE_EMOTICON);
+                if let Some(result) = &seg.result {
+                    ctx.content.push(' ');
+                    ctx.content.push_str(result);
+                }
+            }
+            Segment::Rps(seg) => {
+                ctx.content
+                    .push_str(tg_helper::rps_emoji(seg.result.as_deref()));
+            }
+            _ => return Ok(None),
+        }
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct ShareConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for ShareConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::Share(seg) = segment else {
+            return Ok(None);
+        };
+        write!(
+            &mut ctx.content,
+            "<u>{}</u>\n\n{}\n\nvia <a href=\"{}\">{}</a>",
+            html_escape::encode_text(&seg.title),
+            html_escape::encode_text(seg.content.as_deref().unwrap_or("")),
+            html_escape::encode_text(&seg.url),
+            html_escape::encode_text(&seg.title),
+        )
+        .unwrap();
+        ctx.msg_type = TgMsgType::Html;
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct ContactConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for ContactConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::Contact(seg) = segment else {
+            return Ok(None);
+        };
+        let (bridge, endpoint) = (ctx.bridge, ctx.endpoint);
+
+        // 推荐的好友或群, 附带名称和头像
+        let (label, name, avatar) = match seg.type_.as_str() {
+            "group" => match bridge.get_group_info(endpoint, seg.id.clone(), false).await {
+                Ok(info) => ("推荐群聊", info.display_name(), info.avatar.clone()),
+                Err(_) => ("推荐群聊", seg.id.clone(), None),
+            },
+            _ => match bridge
+                .get_stranger_info(endpoint, seg.id.clone(), false)
+                .await
+            {
+                Ok(info) => ("推荐好友", info.display_name(), info.avatar.clone()),
+                Err(_) => ("推荐好友", seg.id.clone(), None),
+            },
+        };
+        write!(
+            &mut ctx.content,
+            "<b>[{}]</b>\n{}\nID: <code>{}</code>",
+            label,
+            html_escape::encode_text(&name),
+            html_escape::encode_text(&seg.id),
+        )
+        .unwrap();
+
+        let avatar = match (avatar, &endpoint.platform) {
+            (Some(url), _) if url.starts_with("http") => Some(url),
+            (_, Platform::QQ) => Some(ob_helper::qq_avatar_url(&seg.type_, &seg.id)),
+            _ => None,
+        };
+        if let Some(url) = avatar {
+            match bridge.upload_url(ctx.bot, &url).await {
+                Ok(uploaded) => ctx.media_uploaded.push(uploaded),
+                Err(e) => tracing::warn!("Failed to upload avatar: {}", e),
+            }
+        }
+        ctx.msg_type = TgMsgType::Card;
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct MusicConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for MusicConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::Music(seg) = segment else {
+            return Ok(None);
+        };
+        let url = seg.url.clone().or_else(|| {
+            seg.id
+                .as_ref()
+                .and_then(|id| ob_helper::music_url(&seg.type_, id))
+        });
+        write!(
+            &mut ctx.content,
+            "🎵 <b>{}</b>",
+            html_escape::encode_text(seg.title.as_deref().unwrap_or("音乐分享"))
+        )
+        .unwrap();
+        if let Some(artist) = seg.content.as_ref().filter(|s| !s.is_empty()) {
+            write!(&mut ctx.content, "\n{}", html_escape::encode_text(artist)).unwrap();
+        }
+        if let Some(url) = url {
+            write!(
+                &mut ctx.content,
+                "\n<a href=\"{}\">▶️ 播放</a>",
+                html_escape::encode_double_quoted_attribute(&url)
+            )
+            .unwrap();
+        }
+
+        if let Some(image) = seg.image.as_ref().filter(|s| s.starts_with("http")) {
+            match ctx.bridge.upload_url(ctx.bot, image).await {
+                Ok(uploaded) => ctx.media_uploaded.push(uploaded),
+                Err(e) => tracing::warn!("Failed to upload music cover: {}", e),
+            }
+        }
+        ctx.msg_type = TgMsgType::Card;
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct JsonConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for JsonConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::Json(seg) = segment else {
+            return Ok(None);
+        };
+        if let Ok(v) = serde_json::from_str::<Value>(&seg.data) {
+            let app = v.get("app").and_then(Value::as_str).unwrap_or("");
+            let view = v.get("view").and_then(Value::as_str).unwrap_or("");
+            if let Some(payment) = ob_helper::extract_payment_from_json(&v) {
+                ctx.content.push_str(&payment);
+                ctx.msg_type = TgMsgType::Html;
+                return Ok(Some(Flow::Break));
+            } else if app == "com.tencent.mannounce" {
+                let announce = ob_helper::extract_announce_from_json(&v)?;
+                if !announce.is_empty() {
+                    ctx.content.push_str(&announce);
+                    ctx.msg_type = TgMsgType::Html;
+                    return Ok(Some(Flow::Break));
+                }
+            } else if view == "LocationShare" {
+                ctx.location = Some(ob_helper::extract_location_from_json(&v)?);
+                ctx.msg_type = TgMsgType::Location;
+                return Ok(Some(Flow::Break));
+            }
+            if let Some(card) = card_parser::parse_json(&v) {
+                ctx.push_card(&card).await;
+                return Ok(Some(Flow::Break));
+            }
+        }
+
+        ctx.content.push_str(&seg.data);
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+struct XmlConverter;
+
+#[async_trait::async_trait]
+impl SegmentConverter for XmlConverter {
+    async fn convert(
+        &self,
+        ctx: &mut ConvertContext<'_>,
+        segment: &Segment,
+    ) -> Result<Option<Flow>> {
+        let Segment::Xml(seg) = segment else {
+            return Ok(None);
+        };
+        if let Some(payment) = ob_helper::extract_payment_from_xml(&seg.data) {
+            ctx.content.push_str(&payment);
+            ctx.msg_type = TgMsgType::Html;
+            return Ok(Some(Flow::Break));
+        }
+        // 不认识的XML消息保持忽略
+        if let Some(card) = card_parser::parse_xml(&seg.data) {
+            ctx.push_card(&card).await;
+            return Ok(Some(Flow::Break));
+        }
+
+        Ok(Some(Flow::Continue))
+    }
+}
+
+// 媒体上传失败的提示文本, 因过大而失败时附带原始链接
+pub fn upload_failed_text(name: &str, e: &anyhow::Error) -> String {
+    match e.downcast_ref::<MediaTooLarge>() {
+        Some(MediaTooLarge { size, url }) => {
+            let size = *size as f64 / 1024.0 / 1024.0;
+            match url {
+                Some(url) => format!("[{}过大({:.1}MB)] {}", name, size, url),
+                None => format!("[{}过大({:.1}MB)]", name, size),
+            }
+        }
+        None => format!("[{}上传失败]", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ActiveModelTrait, ActiveValue::Set, Database};
+    use sea_orm_migration::MigratorTrait;
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::common::TelegramConfig;
+    use crate::telegram::bridge::DynamicSettings;
+    use crate::telegram::entities;
+    use crate::telegram::media_cache::MediaCache;
+    use crate::telegram::migration::Migrator;
+    use crate::telegram::mock_transport::MockTransport;
+    use crate::telegram::transport::SentMessage;
+
+    // Onebot API的接收端已关闭, 所有API调用立即失败
+    struct Fixture {
+        bridge: Bridge,
+        endpoint: Endpoint,
+        remote_chat: Arc<ChatModel>,
+        _data_dir: tempfile::TempDir,
+    }
+
+    impl Fixture {
+        async fn new(platform: Platform) -> Self {
+            let data_dir = tempfile::tempdir().unwrap();
+            let db = Database::connect(format!(
+                "sqlite://{}?mode=rwc",
+                data_dir.path().join("test.db").display()
+            ))
+            .await
+            .unwrap();
+            Migrator::up(&db, None).await.unwrap();
+
+            let config: TelegramConfig = serde_json::from_value(json!({
+                "admin_id": 1000,
+                "api_id": 1,
+                "api_hash": "test",
+                "bot_token": "1:test",
+                "enable_search": false,
+            }))
+            .unwrap();
+            let (api_sender, _) = mpsc::channel(1);
+            let bridge = Bridge::new(
+                config.admin_id,
+                None,
+                vec![Arc::new(MockTransport::default())],
+                HashMap::new(),
+                db.clone(),
+                None,
+                None,
+                None,
+                MediaCache::new(data_dir.path(), 1024 * 1024).await.unwrap(),
+                None,
+                None,
+                None,
+                1,
+                DynamicSettings::from_config(&config, &HashMap::new()).unwrap(),
+                Arc::new(|_: &str| Ok(())),
+                api_sender,
+            );
+
+            let endpoint = Endpoint {
+                platform,
+                id: "10000".to_string(),
+            };
+            let remote_chat = entities::remote_chat::ActiveModel {
+                endpoint: Set(endpoint.clone()),
+                chat_type: Set(ChatType::Group),
+                target_id: Set("30000".to_string()),
+                name: Set("Group".to_string()),
+                alias: Set(String::new()),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+
+            Self {
+                bridge,
+                endpoint,
+                remote_chat: Arc::new(remote_chat),
+                _data_dir: data_dir,
+            }
+        }
+
+        async fn convert<'a>(&'a self, message: &'a MessageEvent) -> ConvertContext<'a> {
+            let mut ctx = ConvertContext {
+                bridge: &self.bridge,
+                bot: self.bridge.primary_bot(),
+                endpoint: &self.endpoint,
+                remote_chat: self.remote_chat.clone(),
+                message,
+                msg_type: TgMsgType::Text,
+                content: String::new(),
+                media_uploaded: Vec::new(),
+                followups: Vec::new(),
+                location: None,
+                mentions: Vec::new(),
+                spoiler: false,
+                reply_to: None,
+                quote: None,
+                poll_options: None,
+                failures: Vec::new(),
+                prefetched: HashMap::new(),
+                position: 0,
+            };
+            ctx.convert_all().await.unwrap();
+            ctx
+        }
+    }
+
+    fn group_message(segments: Value) -> MessageEvent {
+        serde_json::from_value(json!({
+            "time": 0,
+            "self_id": 10000,
+            "message_type": "group",
+            "sub_type": "normal",
+            "message_id": 1,
+            "group_id": 30000,
+            "user_id": 20000,
+            "message": segments,
+            "sender": { "user_id": 20000, "nickname": "Alice" },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn text_is_appended() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message = group_message(json!([
+            { "type": "text", "data": { "text": "hello " } },
+            { "type": "text", "data": { "text": "[微笑]" } },
+        ]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(ctx.content, "hello [微笑]");
+        assert!(matches!(ctx.msg_type, TgMsgType::Text));
+    }
+
+    #[tokio::test]
+    async fn wechat_text_replaces_emoji_codes() {
+        let fixture = Fixture::new(Platform::WeChat).await;
+        let message = group_message(json!([{ "type": "text", "data": { "text": "[微笑]" } }]));
+        assert_eq!(fixture.convert(&message).await.content, "😃");
+    }
+
+    #[tokio::test]
+    async fn face_is_converted_by_platform() {
+        let message = group_message(json!([{ "type": "face", "data": { "id": "0" } }]));
+        let fixture = Fixture::new(Platform::QQ).await;
+        assert_eq!(fixture.convert(&message).await.content, "😮");
+        let fixture = Fixture::new(Platform::WeChat).await;
+        assert_eq!(fixture.convert(&message).await.content, "/[Face0]");
+    }
+
+    #[tokio::test]
+    async fn at_falls_back_to_id_and_mentions_self() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message = group_message(json!([
+            { "type": "at", "data": { "qq": "12345" } },
+            { "type": "text", "data": { "text": " " } },
+            { "type": "at", "data": { "qq": "10000" } },
+        ]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(ctx.content, "@12345 @10000");
+        assert_eq!(ctx.mentions, vec!["10000".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn at_in_guild_channel_uses_id() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message: MessageEvent = serde_json::from_value(json!({
+            "time": 0,
+            "self_id": 10000,
+            "message_type": "guild",
+            "sub_type": "channel",
+            "message_id": 1,
+            "guild_id": 40000,
+            "channel_id": 50000,
+            "user_id": 20000,
+            "message": [
+                { "type": "at", "data": { "qq": "12345" } },
+                { "type": "text", "data": { "text": " hi" } },
+            ],
+            "sender": { "user_id": 20000, "nickname": "Alice" },
+        }))
+        .unwrap();
+        assert_eq!(fixture.convert(&message).await.content, "@12345 hi");
+    }
+
+    #[tokio::test]
+    async fn image_upload_failure_is_reported() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message = group_message(json!([{ "type": "image", "data": { "file": "a.jpg" } }]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(ctx.content, "[图片上传失败]");
+        assert!(ctx.media_uploaded.is_empty());
+        assert_eq!(ctx.failures.len(), 1);
+        assert_eq!(ctx.failures[0].name, "图片");
+    }
+
+    #[tokio::test]
+    async fn media_upload_failure_is_reported() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message = group_message(json!([{ "type": "video", "data": { "file": "a.mp4" } }]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(ctx.content, "[视频上传失败]");
+        assert_eq!(ctx.failures.len(), 1);
+        assert_eq!(ctx.failures[0].name, "视频");
+    }
+
+    #[tokio::test]
+    async fn reply_to_relayed_message() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let sent = SentMessage {
+            chat_id: -100,
+            id: 7,
+            ..Default::default()
+        };
+        fixture
+            .bridge
+            .save_message_by_remote(fixture.remote_chat.id, "42", "20000", &sent, "hello")
+            .await
+            .unwrap();
+
+        let message = group_message(json!([{ "type": "reply", "data": { "id": "42" } }]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(ctx.reply_to, Some(7));
+        assert!(ctx.quote.is_none());
+    }
+
+    #[tokio::test]
+    async fn reply_to_poll_with_index_shows_option() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let sent = SentMessage {
+            chat_id: -100,
+            id: 8,
+            ..Default::default()
+        };
+        let poll = "📊 投票: Lunch?\n1. Rice\n2. Fish & Chips";
+        fixture
+            .bridge
+            .save_message_by_remote(fixture.remote_chat.id, "44", "20000", &sent, poll)
+            .await
+            .unwrap();
+
+        let message = group_message(json!([
+            { "type": "reply", "data": { "id": "44" } },
+            { "type": "text", "data": { "text": " 2 " } },
+        ]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(ctx.reply_to, Some(8));
+        assert_eq!(ctx.content, "🗳 投票: 2. Fish & Chips");
+    }
+
+    #[tokio::test]
+    async fn reply_to_unknown_message_is_ignored() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message = group_message(json!([{ "type": "reply", "data": { "id": "43" } }]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(ctx.reply_to, None);
+        assert!(ctx.quote.is_none());
+        assert!(ctx.failures.is_empty());
+    }
+
+    #[tokio::test]
+    async fn forward_failure_is_reported() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message = group_message(json!([{ "type": "forward", "data": { "id": "1" } }]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(ctx.failures.len(), 1);
+        assert_eq!(ctx.failures[0].name, "消息片段");
+    }
+
+    #[tokio::test]
+    async fn location_sets_venue() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message = group_message(json!([{
+            "type": "location",
+            "data": { "lat": 31.2, "lon": 121.5, "title": "Tower", "content": "Street 1" },
+        }]));
+        let ctx = fixture.convert(&message).await;
+        let venue = ctx.location.unwrap();
+        assert_eq!((venue.latitude, venue.longitude), (31.2, 121.5));
+        assert_eq!(
+            (venue.title.as_str(), venue.address.as_str()),
+            ("Tower", "Street 1")
+        );
+        assert!(matches!(ctx.msg_type, TgMsgType::Location));
+        // 没有配置静态地图
+        assert!(ctx.media_uploaded.is_empty());
+    }
+
+    #[tokio::test]
+    async fn dice_and_rps() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message = group_message(json!([{ "type": "dice", "data": { "result": "5" } }]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(ctx.content, "🎲 5");
+        assert!(matches!(ctx.msg_type, TgMsgType::Dice(ref emoji) if emoji == "🎲"));
+
+        let message = group_message(json!([{ "type": "rps", "data": { "result": "1" } }]));
+        assert_eq!(fixture.convert(&message).await.content, "✋");
+    }
+
+    #[tokio::test]
+    async fn share_is_escaped() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message = group_message(json!([{
+            "type": "share",
+            "data": { "url": "https://example.com/?a=1&b=2", "title": "A & B", "content": "desc" },
+        }]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(
+            ctx.content,
+            "<u>A &amp; B</u>\n\ndesc\n\nvia <a href=\"https://example.com/?a=1&amp;b=2\">A &amp; B</a>"
+        );
+        assert!(matches!(ctx.msg_type, TgMsgType::Html));
+    }
+
+    #[tokio::test]
+    async fn contact_falls_back_to_id() {
+        let fixture = Fixture::new(Platform::WeChat).await;
+        let message = group_message(json!([{
+            "type": "contact",
+            "data": { "type": "qq", "id": "55555" },
+        }]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(
+            ctx.content,
+            "<b>[推荐好友]</b>\n55555\nID: <code>55555</code>"
+        );
+        assert!(matches!(ctx.msg_type, TgMsgType::Card));
+    }
+
+    #[tokio::test]
+    async fn music_card() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message = group_message(json!([{
+            "type": "music",
+            "data": {
+                "type": "custom",
+                "url": "https://music.example/1",
+                "title": "Song",
+                "content": "Artist",
+            },
+        }]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(
+            ctx.content,
+            "🎵 <b>Song</b>\nArtist\n<a href=\"https://music.example/1\">▶️ 播放</a>"
+        );
+        assert!(matches!(ctx.msg_type, TgMsgType::Card));
+    }
+
+    #[tokio::test]
+    async fn json_location_share_stops_conversion() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let data = json!({
+            "app": "com.tencent.map",
+            "view": "LocationShare",
+            "meta": {
+                "Location.Search": {
+                    "name": "Tower",
+                    "address": "Street 1",
+                    "lat": "31.2",
+                    "lng": "121.5",
+                },
+            },
+        });
+        let message = group_message(json!([
+            { "type": "json", "data": { "data": data.to_string() } },
+            { "type": "text", "data": { "text": "ignored" } },
+        ]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(ctx.location.unwrap().title, "Tower");
+        assert!(matches!(ctx.msg_type, TgMsgType::Location));
+        assert!(ctx.content.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unknown_json_is_kept_as_text() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let data = json!({ "app": "com.example.unknown" }).to_string();
+        let message = group_message(json!([{ "type": "json", "data": { "data": data } }]));
+        assert_eq!(fixture.convert(&message).await.content, data);
+    }
+
+    #[tokio::test]
+    async fn unknown_xml_is_ignored() {
+        let fixture = Fixture::new(Platform::QQ).await;
+        let message = group_message(json!([
+            { "type": "xml", "data": { "data": "<msg/>" } },
+            { "type": "text", "data": { "text": "after" } },
+        ]));
+        let ctx = fixture.convert(&message).await;
+        assert_eq!(ctx.content, "after");
+        assert!(matches!(ctx.msg_type, TgMsgType::Text));
+    }
+}
@@ -0,0 +1,233 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use chrono::{Datelike, Local, Utc};
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+use super::index_service::IndexService;
+use crate::common::RetentionConfig;
+
+// 日志目录和文件名前缀, 与main中的日志设置一致
+const LOG_DIR: &str = "logs";
+const LOG_PREFIX: &str = "porter.log";
+// 索引目录
+const INDEX_DIR: &str = "tantivy";
+// 本程序创建的临时文件的前缀, 清理时只删除该前缀的文件
+const TEMP_PREFIX: &str = ".teleporter-";
+// 本月累计清理量的记录文件, 重启后仍能发送月度报告
+const MONTHLY_FILE: &str = "maintenance.json";
+// 默认的日志保留天数
+const DEFAULT_LOG_DAYS: u64 = 30;
+// 默认的临时文件保留小时数
+const DEFAULT_TEMP_HOURS: u64 = 24;
+
+// 一次清理删除的文件数和回收的空间
+#[derive(Default)]
+pub struct MaintenanceReport {
+    pub logs: (u64, u64),
+    pub temp_files: (u64, u64),
+    pub index_bytes: u64,
+}
+
+impl MaintenanceReport {
+    pub fn files(&self) -> u64 {
+        self.logs.0 + self.temp_files.0
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.logs.1 + self.temp_files.1 + self.index_bytes
+    }
+}
+
+// 当月累计删除的文件数和回收的空间, 用于月度报告
+#[derive(Default, Serialize, Deserialize)]
+struct MonthlyReport {
+    month: i32,
+    files: u64,
+    bytes: u64,
+}
+
+// 在系统临时目录中创建带本程序前缀的临时文件
+pub fn temp_file() -> std::io::Result<NamedTempFile> {
+    temp_file_with_suffix("")
+}
+
+pub fn temp_file_with_suffix(suffix: &str) -> std::io::Result<NamedTempFile> {
+    tempfile::Builder::new()
+        .prefix(TEMP_PREFIX)
+        .suffix(suffix)
+        .tempfile()
+}
+
+// 按保留策略清理日志, 搜索索引和残留的临时文件
+pub struct StorageMaintenance {
+    data_dir: PathBuf,
+    config: RetentionConfig,
+    monthly: Mutex<MonthlyReport>,
+}
+
+impl StorageMaintenance {
+    pub fn new(data_dir: &Path, config: RetentionConfig) -> Self {
+        // 记录文件不存在或损坏时从当月开始重新累计
+        let monthly = std::fs::read(data_dir.join(MONTHLY_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_else(|| MonthlyReport {
+                month: current_month(),
+                ..Default::default()
+            });
+
+        Self {
+            data_dir: data_dir.to_path_buf(),
+            config,
+            monthly: Mutex::new(monthly),
+        }
+    }
+
+    pub async fn run(&self, index: Option<&IndexService>) -> Result<MaintenanceReport> {
+        let mut report = MaintenanceReport {
+            logs: self.cleanup_logs().await?,
+            temp_files: self.cleanup_temp_files().await?,
+            ..Default::default()
+        };
+
+        // 删除过期的文档后清理不再使用的分段文件
+        if let (Some(index), Some(days)) = (index, self.config.index_days) {
+            let index_dir = self.data_dir.join(INDEX_DIR);
+            let size = dir_size(&index_dir).await?;
+            index
+                .purge(Utc::now().timestamp() - days * 24 * 60 * 60)
+                .await?;
+            report.index_bytes = size.saturating_sub(dir_size(&index_dir).await?);
+        }
+
+        {
+            let mut monthly = self.monthly.lock().unwrap();
+            monthly.files += report.files();
+            monthly.bytes += report.bytes();
+            self.save_monthly(&monthly)?;
+        }
+
+        Ok(report)
+    }
+
+    // 进入新的月份时取出上月累计的(文件数, 空间)
+    pub fn take_monthly(&self) -> Result<Option<(u64, u64)>> {
+        let month = current_month();
+        let mut monthly = self.monthly.lock().unwrap();
+        if monthly.month == month {
+            return Ok(None);
+        }
+        let last = std::mem::replace(
+            &mut *monthly,
+            MonthlyReport {
+                month,
+                ..Default::default()
+            },
+        );
+        self.save_monthly(&monthly)?;
+
+        Ok(Some((last.files, last.bytes)))
+    }
+
+    fn save_monthly(&self, monthly: &MonthlyReport) -> Result<()> {
+        std::fs::write(
+            self.data_dir.join(MONTHLY_FILE),
+            serde_json::to_vec(monthly)?,
+        )?;
+
+        Ok(())
+    }
+
+    // 删除超过保留天数的日志, 总大小超过上限时从最早的开始删除, 当前日志总是保留
+    async fn cleanup_logs(&self) -> Result<(u64, u64)> {
+        let mut logs = list_files(&self.data_dir.join(LOG_DIR), |name| {
+            name.starts_with(LOG_PREFIX)
+        })
+        .await?;
+        logs.sort_by_key(|(_, _, modified)| *modified);
+        logs.pop();
+
+        let max_age =
+            Duration::from_secs(self.config.log_days.unwrap_or(DEFAULT_LOG_DAYS) * 24 * 60 * 60);
+        let max_size = self
+            .config
+            .log_size
+            .map(|size| (size * 1024.0 * 1024.0 * 1024.0) as u64);
+        let mut total: u64 = logs.iter().map(|(_, size, _)| size).sum();
+
+        let mut removed = (0, 0);
+        for (path, size, modified) in logs {
+            let expired = is_expired(modified, max_age);
+            let oversize = max_size.is_some_and(|max_size| total > max_size);
+            if !expired && !oversize {
+                continue;
+            }
+            tokio::fs::remove_file(&path).await?;
+            total -= size;
+            removed.0 += 1;
+            removed.1 += size;
+        }
+
+        Ok(removed)
+    }
+
+    // 删除系统临时目录中本程序残留的临时文件 (进程异常退出时不会被自动删除)
+    async fn cleanup_temp_files(&self) -> Result<(u64, u64)> {
+        let max_age =
+            Duration::from_secs(self.config.temp_hours.unwrap_or(DEFAULT_TEMP_HOURS) * 60 * 60);
+        let files = list_files(&std::env::temp_dir(), |name| name.starts_with(TEMP_PREFIX)).await?;
+
+        let mut removed = (0, 0);
+        for (path, size, modified) in files {
+            // 可能属于同一主机上的其他实例, 删除失败时跳过
+            if is_expired(modified, max_age) && tokio::fs::remove_file(&path).await.is_ok() {
+                removed.0 += 1;
+                removed.1 += size;
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+fn current_month() -> i32 {
+    let now = Local::now();
+    now.year() * 12 + now.month0() as i32
+}
+
+fn is_expired(modified: SystemTime, max_age: Duration) -> bool {
+    SystemTime::now()
+        .duration_since(modified)
+        .is_ok_and(|age| age > max_age)
+}
+
+// 目录下符合条件的文件: (路径, 大小, 修改时间), 目录不存在时为空
+async fn list_files(
+    dir: &Path,
+    filter: impl Fn(&str) -> bool,
+) -> Result<Vec<(PathBuf, u64, SystemTime)>> {
+    let mut files = Vec::new();
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return Ok(files);
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        if metadata.is_file() && entry.file_name().to_str().is_some_and(&filter) {
+            files.push((entry.path(), metadata.len(), metadata.modified()?));
+        }
+    }
+
+    Ok(files)
+}
+
+async fn dir_size(dir: &Path) -> Result<u64> {
+    Ok(list_files(dir, |_| true)
+        .await?
+        .iter()
+        .map(|(_, size, _)| size)
+        .sum())
+}
@@ -3,25 +3,38 @@ use std::{io, slice};
 use anyhow::Result;
 use flate2::bufread::GzDecoder;
 use grammers_client::{
+    InvocationError,
     session::PackedType,
     types::{
-        Chat, Message,
-        media::{Document, Venue},
+        Chat, Message, PackedChat,
+        media::{self, Document, Venue},
     },
 };
 use grammers_tl_types as tl;
 use grammers_tl_types::enums::MessageEntity;
 use rgb::{RGBA8, alt::BGRA8};
 use rlottie::{Animation, Size, Surface};
-use tempfile::NamedTempFile;
+use serde_json::Value;
 use tokio::process::Command;
 
+use super::bot_api::BotApiError;
 use super::bridge::Bridge;
+use super::storage_maintenance;
+use crate::common::MediaKind;
 
 type Rgba = rgb::RGBA<u8, bool>;
 
 const GIF_FPS: f64 = 15.0;
 const GIF_SIZE: usize = 256;
+// TGS贴纸转成的GIF超过大小上限时依次尝试的参数: (帧率, 尺寸, 每个颜色通道保留的位数)
+const TGS_ATTEMPTS: &[(f64, usize, u8)] = &[
+    (GIF_FPS, GIF_SIZE, 8),
+    (GIF_FPS, GIF_SIZE, 5),
+    (10.0, GIF_SIZE, 5),
+    (10.0, 192, 4),
+    (8.0, 128, 4),
+    (5.0, 128, 3),
+];
 
 macro_rules! auto_vectorize {
 	(
@@ -77,6 +90,60 @@ pub fn get_command(message: &Message) -> Option<String> {
     None
 }
 
+// 命令后面的参数, 命令可能是别名, 不能按固定长度截取
+pub fn get_command_args(message: &Message) -> &str {
+    message
+        .text()
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .map_or("", |(_, args)| args.trim())
+}
+
+// 将消息的格式转换为远端可读的标记, 如 *粗体*, `代码`, 链接文字后附上地址
+pub fn get_formatted_text(message: &Message) -> String {
+    let text = message.text();
+    let Some(entities) = message.fmt_entities() else {
+        return text.to_string();
+    };
+
+    // (UTF-16位置, 0为结束/1为开始, 排序值, 标记), 同一位置先结束后开始, 外层先开始后结束
+    let mut marks: Vec<(usize, u8, i64, String)> = Vec::new();
+    for entity in entities {
+        let (offset, length, open, close) = match entity {
+            MessageEntity::Bold(e) => (e.offset, e.length, "*".into(), "*".into()),
+            MessageEntity::Italic(e) => (e.offset, e.length, "_".into(), "_".into()),
+            MessageEntity::Strike(e) => (e.offset, e.length, "~".into(), "~".into()),
+            MessageEntity::Spoiler(e) => (e.offset, e.length, "||".into(), "||".into()),
+            MessageEntity::Code(e) => (e.offset, e.length, "`".into(), "`".into()),
+            MessageEntity::Pre(e) => (e.offset, e.length, "```\n".into(), "\n```".into()),
+            MessageEntity::Blockquote(e) => (e.offset, e.length, "「".into(), "」".into()),
+            MessageEntity::TextUrl(e) => {
+                (e.offset, e.length, String::new(), format!(" ({})", e.url))
+            }
+            _ => continue,
+        };
+        let (offset, length) = (offset as usize, length as usize);
+        marks.push((offset, 1, -(length as i64), open));
+        marks.push((offset + length, 0, -(offset as i64), close));
+    }
+    marks.sort_by_key(|(pos, kind, order, _)| (*pos, *kind, *order));
+
+    let units: Vec<u16> = text.encode_utf16().collect();
+    let mut result = String::new();
+    let mut last = 0;
+    for (pos, _, _, mark) in marks {
+        let pos = pos.min(units.len());
+        if pos > last {
+            result.push_str(&String::from_utf16_lossy(&units[last..pos]));
+            last = pos;
+        }
+        result.push_str(&mark);
+    }
+    result.push_str(&String::from_utf16_lossy(&units[last..]));
+
+    result
+}
+
 pub fn check_sender(bridge: &Bridge, message: &Message) -> bool {
     // 非Bot发送的消息
     if !message.outgoing() {
@@ -125,6 +192,142 @@ pub fn get_topic_id(message: &Message) -> Option<i32> {
     }
 }
 
+// 还原数据库中保存的对话类型
+pub fn unpack_chat_type(value: i32) -> PackedType {
+    match value {
+        0b0000_0010 => PackedType::User,
+        0b0000_0011 => PackedType::Bot,
+        0b0000_0100 => PackedType::Chat,
+        0b0010_1000 => PackedType::Megagroup,
+        0b0011_0000 => PackedType::Broadcast,
+        0b0011_1000 => PackedType::Gigagroup,
+        _ => PackedType::User,
+    }
+}
+
+// 解析Bot API格式的对话ID, -100开头为频道或超级群组, 其他负数为普通群组
+pub fn unpack_bot_api_id(id: i64) -> (PackedType, i64) {
+    const CHANNEL_OFFSET: i64 = -1_000_000_000_000;
+    if id < CHANNEL_OFFSET {
+        (PackedType::Megagroup, CHANNEL_OFFSET - id)
+    } else if id < 0 {
+        (PackedType::Chat, -id)
+    } else {
+        (PackedType::User, id)
+    }
+}
+
+// 转换为Bot API的对话ID, 超级群和频道加上 -100 前缀, 普通群取负数
+pub fn pack_bot_api_id(chat: PackedChat) -> i64 {
+    const CHANNEL_OFFSET: i64 = -1_000_000_000_000;
+    match chat.ty {
+        PackedType::User | PackedType::Bot => chat.id,
+        PackedType::Chat => -chat.id,
+        PackedType::Megagroup | PackedType::Broadcast | PackedType::Gigagroup => {
+            CHANNEL_OFFSET - chat.id
+        }
+    }
+}
+
+// 判断群组是否仍然可用 (未解散且Bot仍在群里)
+pub fn is_chat_available(chat: &Chat) -> bool {
+    match chat {
+        Chat::Group(group) => match &group.raw {
+            tl::enums::Chat::Chat(chat) => !chat.left && !chat.deactivated,
+            tl::enums::Chat::Channel(channel) => !channel.left,
+            _ => false,
+        },
+        _ => true,
+    }
+}
+
+// 判断错误是否为指定名称的RPC错误, Bot API的错误按描述匹配
+pub fn is_rpc_error(error: &anyhow::Error, name: &str) -> bool {
+    if let Some(e) = error.downcast_ref::<BotApiError>() {
+        return e.description.contains(name);
+    }
+    match error.downcast_ref::<InvocationError>() {
+        Some(e) => e.is(name),
+        None => false,
+    }
+}
+
+// 判断错误是否因为对话的access_hash无效或Bot已无法访问该对话
+pub fn is_peer_invalid(error: &anyhow::Error) -> bool {
+    [
+        "PEER_ID_INVALID",
+        "CHANNEL_INVALID",
+        "USER_ID_INVALID",
+        "CHAT_ID_INVALID",
+        "chat not found",
+    ]
+    .iter()
+    .any(|name| is_rpc_error(error, name))
+}
+
+// 判断错误是否因为发送的目标Topic已被删除
+pub fn is_topic_missing(error: &anyhow::Error) -> bool {
+    [
+        "TOPIC_DELETED",
+        "TOPIC_ID_INVALID",
+        "message thread not found",
+    ]
+    .iter()
+    .any(|name| is_rpc_error(error, name))
+}
+
+// 获取论坛群消息所在的Topic ID, 非论坛群或General中的消息返回0
+pub fn get_forum_topic_id(message: &Message) -> i32 {
+    match message.reply_header() {
+        Some(tl::enums::MessageReplyHeader::Header(header)) if header.forum_topic => header
+            .reply_to_top_id
+            .or(header.reply_to_msg_id)
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+// 获取消息回复的源消息ID (Topic里仅指向Topic的不算回复)
+pub fn get_reply_to_msg_id(message: &Message) -> Option<i32> {
+    match message.reply_header() {
+        Some(tl::enums::MessageReplyHeader::Header(header)) => {
+            if header.forum_topic {
+                // 如果是Topic消息, 那么reply_to_top_id和reply_to_msg_id同时有值才是回复
+                if header.reply_to_top_id.is_some() {
+                    header.reply_to_msg_id
+                } else {
+                    None
+                }
+            } else {
+                header.reply_to_msg_id
+            }
+        }
+        _ => None,
+    }
+}
+
+// 时长上限, 与Onebot禁言的上限一致
+const MAX_DURATION: i64 = 30 * 24 * 60 * 60;
+
+// 解析时长 (如 30s, 10m, 2h, 1d, 纯数字按分钟计), 返回秒数, 溢出或超过30天时返回None
+pub fn parse_duration(input: &str) -> Option<i64> {
+    let input = input.trim();
+    let (value, unit) = match input.find(|c: char| !c.is_ascii_digit()) {
+        Some(pos) => input.split_at(pos),
+        None => (input, "m"),
+    };
+    let value = value.parse::<i64>().ok()?;
+
+    let seconds = match unit {
+        "s" => Some(value),
+        "m" => value.checked_mul(60),
+        "h" => value.checked_mul(60 * 60),
+        "d" => value.checked_mul(24 * 60 * 60),
+        _ => None,
+    }?;
+    (seconds <= MAX_DURATION).then_some(seconds)
+}
+
 pub fn is_raw_photo(document: &Document) -> bool {
     match document.raw.document.as_ref() {
         Some(tl::enums::Document::Document(d)) => {
@@ -139,6 +342,68 @@ pub fn is_raw_photo(document: &Document) -> bool {
     }
 }
 
+// 构造添加到贴纸包的贴纸, 表情取自贴纸原有的表情
+pub fn get_sticker_item(document: &Document) -> Option<tl::enums::InputStickerSetItem> {
+    match document.raw.document.as_ref() {
+        Some(tl::enums::Document::Document(d)) => {
+            let emoji = d
+                .attributes
+                .iter()
+                .find_map(|attr| match attr {
+                    tl::enums::DocumentAttribute::Sticker(sticker) if !sticker.alt.is_empty() => {
+                        Some(sticker.alt.clone())
+                    }
+                    _ => None,
+                })
+                .unwrap_or_else(|| "😊".to_string());
+            Some(
+                tl::types::InputStickerSetItem {
+                    document: tl::types::InputDocument {
+                        id: d.id,
+                        access_hash: d.access_hash,
+                        file_reference: d.file_reference.clone(),
+                    }
+                    .into(),
+                    emoji,
+                    mask_coords: None,
+                    keywords: None,
+                }
+                .into(),
+            )
+        }
+        _ => None,
+    }
+}
+
+// Telegram媒体对应的媒体类型, 用于按对话的媒体设置处理
+pub fn get_media_kind(media: &media::Media) -> Option<MediaKind> {
+    match media {
+        media::Media::Photo(_) => Some(MediaKind::Image),
+        media::Media::Sticker(_) => Some(MediaKind::Sticker),
+        media::Media::Document(document) => {
+            if document.raw.voice {
+                Some(MediaKind::Voice)
+            } else if document.raw.video {
+                Some(MediaKind::Video)
+            } else if is_raw_photo(document) || is_gif(document) {
+                Some(MediaKind::Image)
+            } else {
+                Some(MediaKind::File)
+            }
+        }
+        _ => None,
+    }
+}
+
+// 是否为剧透(遮罩)媒体
+pub fn has_spoiler(media: &media::Media) -> bool {
+    match media {
+        media::Media::Photo(photo) => photo.raw.spoiler,
+        media::Media::Document(document) => document.raw.spoiler,
+        _ => false,
+    }
+}
+
 pub fn is_gif(document: &Document) -> bool {
     if document.raw.video {
         return false;
@@ -156,6 +421,56 @@ pub fn is_gif(document: &Document) -> bool {
     }
 }
 
+// 获取音频文件的标题和演唱者, 语音消息返回None
+pub fn get_audio_info(document: &Document) -> Option<(Option<String>, Option<String>)> {
+    if document.raw.voice {
+        return None;
+    };
+    match document.raw.document.as_ref() {
+        Some(tl::enums::Document::Document(d)) => {
+            for attr in &d.attributes {
+                if let tl::enums::DocumentAttribute::Audio(audio) = attr {
+                    if !audio.voice {
+                        return Some((audio.title.clone(), audio.performer.clone()));
+                    }
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+// 获取消息中第一个自定义表情的ID
+pub fn get_custom_emoji_id(message: &Message) -> Option<i64> {
+    message
+        .fmt_entities()?
+        .iter()
+        .find_map(|entity| match entity {
+            MessageEntity::CustomEmoji(emoji) => Some(emoji.document_id),
+            _ => None,
+        })
+}
+
+// 获取消息中的第一个链接, 实体的偏移量按UTF-16计算
+pub fn get_first_url(message: &Message) -> Option<String> {
+    for entity in message.fmt_entities()? {
+        match entity {
+            MessageEntity::Url(url) => {
+                let text: Vec<u16> = message.text().encode_utf16().collect();
+                let start = url.offset as usize;
+                let end = (url.offset + url.length) as usize;
+                return text
+                    .get(start..end)
+                    .map(|slice| String::from_utf16_lossy(slice));
+            }
+            MessageEntity::TextUrl(text_url) => return Some(text_url.url.clone()),
+            _ => {}
+        }
+    }
+    None
+}
+
 pub fn get_geo(venue: &Venue) -> Option<(f64, f64)> {
     match &venue.raw_venue.geo {
         tl::enums::GeoPoint::Empty => None,
@@ -163,9 +478,156 @@ pub fn get_geo(venue: &Venue) -> Option<(f64, f64)> {
     }
 }
 
+// 投票转为文本时的标记, 远端回复这条消息时据此还原选项
+const POLL_MARK: &str = "📊";
+
+// 将投票转为文本, 每行一个带序号的选项
+pub fn format_poll(poll: &media::Poll) -> String {
+    let raw = &poll.raw;
+    let tl::enums::TextWithEntities::Entities(question) = &raw.question;
+
+    let mut text = format!(
+        "{} {}: {}",
+        POLL_MARK,
+        if raw.quiz { "测验" } else { "投票" },
+        question.text
+    );
+    for (i, answer) in raw.answers.iter().enumerate() {
+        let tl::enums::PollAnswer::Answer(answer) = answer;
+        let tl::enums::TextWithEntities::Entities(option) = &answer.text;
+        text.push_str(&format!("\n{}. {}", i + 1, option.text));
+    }
+    if !raw.closed {
+        text.push_str("\n(回复选项序号告知你的选择)");
+    }
+
+    text
+}
+
+// 从投票转成的文本中取出各选项, 不是投票返回None
+pub fn parse_poll_options(content: &str) -> Option<Vec<String>> {
+    // 投票本身是回复时, 标记前还有回复的片段
+    let (_, poll) = content.split_once(POLL_MARK)?;
+    let options: Vec<String> = poll
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let (index, option) = line.split_once(". ")?;
+            index.parse::<usize>().ok().map(|_| option.to_string())
+        })
+        .collect();
+    if options.is_empty() {
+        None
+    } else {
+        Some(options)
+    }
+}
+
+// 骰子的表情, 与QQ的掷骰子魔法表情对应
+pub const DICE_EMOTICON: &str = "🎲";
+
+// 将Telegram的骰子转为文本, 如 🎯 6
+pub fn format_dice(dice: &media::Dice) -> String {
+    format!("{} {}", dice.raw.emoticon, dice.raw.value)
+}
+
+// 将分享的联系人转为文本名片
+pub fn format_contact(contact: &media::Contact) -> String {
+    let raw = &contact.raw_contact;
+    let name = format!("{} {}", raw.first_name, raw.last_name);
+    let mut text = format!("👤 名片: {}", name.trim());
+    if !raw.phone_number.is_empty() {
+        text.push_str(&format!("\n电话: {}", raw.phone_number));
+    }
+    text
+}
+
+// 从联系人的vCard中取出QQ号 (X-QQ 或 IMPP:qq:)
+pub fn get_contact_qq(contact: &media::Contact) -> Option<String> {
+    contact.raw_contact.vcard.lines().find_map(|line| {
+        let (key, value) = line.trim().split_once(':')?;
+        let key = key.split(';').next()?.to_ascii_uppercase();
+        let value = match key.as_str() {
+            "X-QQ" => value,
+            "IMPP" => value.strip_prefix("qq:").or(value.strip_prefix("QQ:"))?,
+            _ => return None,
+        };
+        let value = value.trim();
+        (!value.is_empty() && value.chars().all(|c| c.is_ascii_digit())).then(|| value.to_string())
+    })
+}
+
+// QQ猜拳结果对应的手势, Telegram没有猜拳的动画骰子
+pub fn rps_emoji(result: Option<&str>) -> &'static str {
+    match result {
+        Some("1") => "✋",
+        Some("2") => "✌️",
+        Some("3") => "✊",
+        _ => "✊✌️✋",
+    }
+}
+
+// 视频的时长, 帧数和尺寸
+pub struct VideoInfo {
+    pub duration: f64,
+    pub frames: u64,
+    pub width: u64,
+    pub height: u64,
+}
+
+pub async fn probe_video(input_data: &[u8]) -> Result<VideoInfo> {
+    let temp_file = storage_maintenance::temp_file()?;
+    let input_path = temp_file
+        .path()
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("Invalid temp path"))?;
+
+    tokio::fs::write(input_path, input_data).await?;
+
+    let child = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-count_packets",
+            "-show_entries",
+            "stream=width,height,duration,nb_read_packets:format=duration",
+            "-of",
+            "json",
+            input_path,
+        ])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::inherit())
+        .spawn()?;
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("ffprobe exited: {}", output.status));
+    }
+
+    // ffprobe输出的数值有的是字符串
+    let v: Value = serde_json::from_slice(&output.stdout)?;
+    let stream = &v["streams"][0];
+    let as_f64 = |v: &Value| {
+        v.as_f64()
+            .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+    };
+    let duration = as_f64(&stream["duration"])
+        .or_else(|| as_f64(&v["format"]["duration"]))
+        .ok_or_else(|| anyhow::anyhow!("Unknown video duration"))?;
+
+    Ok(VideoInfo {
+        duration,
+        frames: as_f64(&stream["nb_read_packets"]).unwrap_or_default() as u64,
+        width: stream["width"].as_u64().unwrap_or_default(),
+        height: stream["height"].as_u64().unwrap_or_default(),
+    })
+}
+
 pub async fn video_to_gif(input_data: &[u8]) -> Result<Vec<u8>> {
     // 创建临时文件 (通过管道作为输入只能顺序访问, 在转换时容易出现问题)
-    let temp_file = NamedTempFile::new()?;
+    let temp_file = storage_maintenance::temp_file()?;
     let input_path = temp_file
         .path()
         .to_str()
@@ -202,7 +664,7 @@ pub async fn video_to_gif(input_data: &[u8]) -> Result<Vec<u8>> {
 
 pub async fn webm_to_gif(input_data: &[u8]) -> Result<Vec<u8>> {
     // 创建临时文件 (通过管道作为输入只能顺序访问, 在转换时容易出现问题)
-    let temp_file = NamedTempFile::new()?;
+    let temp_file = storage_maintenance::temp_file()?;
     let input_path = temp_file
         .path()
         .to_str()
@@ -236,66 +698,123 @@ pub async fn webm_to_gif(input_data: &[u8]) -> Result<Vec<u8>> {
     Ok(output.stdout)
 }
 
-pub async fn tgs_to_gif(id: i64, input_data: &[u8]) -> Result<Vec<u8>> {
+// TGS贴纸的转换结果
+pub enum TgsOutput {
+    Gif(Vec<u8>),
+    // 无法压缩到大小上限内时, 第一帧的静态图片
+    Png(Vec<u8>),
+}
+
+// 将TGS贴纸转换为GIF, 超过大小上限时依次降低帧率, 尺寸和颜色重新编码,
+// 仍然超过时返回第一帧的PNG
+pub async fn tgs_to_gif(id: i64, input_data: &[u8], max_bytes: usize) -> Result<TgsOutput> {
     // 解压tgs文件还原成lottie json
     let mut decoder = GzDecoder::new(input_data);
     let mut decompressed_data = Vec::new();
 
     io::copy(&mut decoder, &mut decompressed_data)?;
 
-    match Animation::from_data(decompressed_data, id.to_string(), ".") {
-        Some(mut animation) => {
-            // 计算帧采样间隔
-            let origianl_fps = animation.framerate();
-            let frame_step = (origianl_fps / GIF_FPS).round() as usize;
-            if frame_step < 1 {
-                return Err(anyhow::anyhow!("Original frame rate is too low"));
-            }
+    let mut animation = Animation::from_data(decompressed_data, id.to_string(), ".")
+        .ok_or_else(|| anyhow::anyhow!("Failed to parse tgs file"))?;
 
-            // 输出的GIF数据
-            let mut gif_data = Vec::new();
-
-            {
-                //let size = animation.size();
-                let size = Size::new(GIF_SIZE, GIF_SIZE);
-
-                // 创建GIF编码器
-                let gif_width = size.width as u16;
-                let gif_height = size.height as u16;
-                let mut encoder = gif::Encoder::new(&mut gif_data, gif_width, gif_height, &[])?;
-                encoder.set_repeat(gif::Repeat::Infinite)?;
-                let frame_delay = (100.0 * frame_step as f64 / animation.framerate()) as u16;
-
-                let buffer_len = size.width * size.height;
-                let mut surface = Surface::new(size);
-                let mut buffer = vec![RGBA8::default(); buffer_len];
-                let frame_count = animation.totalframe();
-                let bg = Rgba::new_alpha(0, 0, 0, true);
-
-                for frame in (0..frame_count).step_by(frame_step) {
-                    // 渲染当前帧
-                    animation.render(frame, &mut surface);
-                    // 转换
-                    argb_to_rgba(bg, surface.data(), &mut buffer);
-
-                    let data = unsafe {
-                        slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, buffer_len * 4)
-                    };
-
-                    // 创建GIF帧
-                    let mut frame = gif::Frame::from_rgba_speed(gif_width, gif_height, data, 10);
-                    frame.delay = frame_delay;
-                    if bg.a {
-                        frame.dispose = gif::DisposalMethod::Background;
-                    }
-                    encoder.write_frame(&frame)?;
+    for (fps, size, color_bits) in TGS_ATTEMPTS {
+        let gif_data = encode_tgs_gif(&mut animation, *fps, *size, *color_bits)?;
+        if gif_data.len() <= max_bytes {
+            return Ok(TgsOutput::Gif(gif_data));
+        }
+        tracing::debug!(
+            "Converted tgs is too large: {} bytes ({}fps, {}px, {} bits)",
+            gif_data.len(),
+            fps,
+            size,
+            color_bits
+        );
+    }
+
+    Ok(TgsOutput::Png(render_tgs_png(&mut animation, GIF_SIZE)?))
+}
+
+fn encode_tgs_gif(
+    animation: &mut Animation,
+    fps: f64,
+    size: usize,
+    color_bits: u8,
+) -> Result<Vec<u8>> {
+    // 计算帧采样间隔
+    let origianl_fps = animation.framerate();
+    let frame_step = (origianl_fps / fps).round() as usize;
+    if frame_step < 1 {
+        return Err(anyhow::anyhow!("Original frame rate is too low"));
+    }
+
+    // 输出的GIF数据
+    let mut gif_data = Vec::new();
+
+    {
+        let size = Size::new(size, size);
+
+        // 创建GIF编码器
+        let gif_width = size.width as u16;
+        let gif_height = size.height as u16;
+        let mut encoder = gif::Encoder::new(&mut gif_data, gif_width, gif_height, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        let frame_delay = (100.0 * frame_step as f64 / animation.framerate()) as u16;
+
+        let buffer_len = size.width * size.height;
+        let mut surface = Surface::new(size);
+        let mut buffer = vec![RGBA8::default(); buffer_len];
+        let frame_count = animation.totalframe();
+        let bg = Rgba::new_alpha(0, 0, 0, true);
+        // 去掉颜色的低位, 减少颜色数量以提高压缩率
+        let color_mask = 0xffu8 << (8 - color_bits);
+
+        for frame in (0..frame_count).step_by(frame_step) {
+            // 渲染当前帧
+            animation.render(frame, &mut surface);
+            // 转换
+            argb_to_rgba(bg, surface.data(), &mut buffer);
+            if color_bits < 8 {
+                for pixel in buffer.iter_mut() {
+                    pixel.r &= color_mask;
+                    pixel.g &= color_mask;
+                    pixel.b &= color_mask;
                 }
             }
 
-            Ok(gif_data)
+            let data = unsafe {
+                slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, buffer_len * 4)
+            };
+
+            // 创建GIF帧
+            let mut frame = gif::Frame::from_rgba_speed(gif_width, gif_height, data, 10);
+            frame.delay = frame_delay;
+            if bg.a {
+                frame.dispose = gif::DisposalMethod::Background;
+            }
+            encoder.write_frame(&frame)?;
         }
-        None => Err(anyhow::anyhow!("Failed to parse tgs file")),
     }
+
+    Ok(gif_data)
+}
+
+fn render_tgs_png(animation: &mut Animation, size: usize) -> Result<Vec<u8>> {
+    let size = Size::new(size, size);
+    let mut surface = Surface::new(size);
+    let mut buffer = vec![RGBA8::default(); size.width * size.height];
+    animation.render(0, &mut surface);
+    argb_to_rgba(Rgba::new_alpha(0, 0, 0, true), surface.data(), &mut buffer);
+
+    let data = buffer.iter().flat_map(|p| [p.r, p.g, p.b, p.a]).collect();
+    let image = image::RgbaImage::from_raw(size.width as u32, size.height as u32, data)
+        .ok_or_else(|| anyhow::anyhow!("Invalid frame size"))?;
+    let mut png_data = Vec::new();
+    image.write_to(
+        &mut std::io::Cursor::new(&mut png_data),
+        image::ImageFormat::Png,
+    )?;
+
+    Ok(png_data)
 }
 
 auto_vectorize! {
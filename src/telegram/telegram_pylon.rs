@@ -1,86 +1,208 @@
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::{Context, Result};
-use dashmap::DashMap;
-use grammers_client::session::Session;
-use grammers_client::{Client, Config, FixedReconnect, InitParams, InputMessage, Update};
+use anyhow::Result;
+use chrono::Local;
+use grammers_client::session::PackedType;
+use grammers_client::{InputMessage, Update};
+use grammers_tl_types as tl;
 use sea_orm::{Database, DatabaseConnection};
 use sea_orm_migration::MigratorTrait;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{Semaphore, broadcast, mpsc};
+use tracing::Instrument;
+use uuid::Uuid;
 
-use crate::common::TelegramConfig;
+use crate::common::{
+    BotTransport, CONFIG_PATH, CronSchedule, Endpoint, LogLevelReloader, RetentionConfig,
+    TelegramConfig, WebConfig,
+};
 use crate::onebot::protocol::{OnebotEvent, OnebotRequest};
-use crate::telegram::bridge::{Bridge, RemoteIdLock, TgIdLock};
-use crate::telegram::telegram_helper as tg_helper;
-use crate::with_id_lock;
+use crate::telegram::bridge::{Bridge, DynamicSettings, RemoteWorkers, TgWorkers};
 
+use super::bot_api::BotApiTransport;
 use super::bridge::RelayBridge;
+use super::ffmpeg;
+use super::file_transfer::FileTransfer;
+use super::i18n;
 use super::index_service::IndexService;
+use super::media_cache::MediaCache;
 use super::migration;
+use super::storage_maintenance::StorageMaintenance;
+use super::transcribe_service::TranscribeService;
+use super::translate_service::TranslateService;
+use super::transport::{BotSession, ChatInfo, GrammersTransport, TelegramTransport};
+use super::web_service;
 
-const DB_FILE: &str = "porter.db";
+// 媒体缓存的默认容量(MB)
+const MEDIA_CACHE_SIZE: usize = 512;
+// 默认同时处理的媒体数量
+const MEDIA_WORKERS: usize = 4;
+// 默认同时处理的对话数量
+const CHAT_WORKERS: usize = 16;
+// 默认每个上传分片的最大尝试次数
+const UPLOAD_ATTEMPTS: usize = 3;
+// 等待相册消息到齐的时间
+const ALBUM_WAIT: Duration = Duration::from_secs(1);
+// 检查摘要消息是否到期的间隔
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+// 清理过期回调数据的间隔
+const CALLBACK_CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// 检查是否到了发送统计报告时间的间隔
+const STATS_REPORT_CHECK_INTERVAL: Duration = Duration::from_secs(20);
+// 按保留策略清理存储的间隔
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
 
 const BOT_SESSION: &str = "bot.session";
-const RECONNECTION_POLICY: FixedReconnect = FixedReconnect {
-    attempts: usize::MAX,
-    delay: Duration::from_secs(5),
-};
+// 配置文件变化后等待写入完成再重新加载
+const CONFIG_RELOAD_DELAY: Duration = Duration::from_secs(1);
 
 pub struct TelegramPylon {
     admin_id: i64,
-    client: Client,
+    notice_chat: Option<i64>,
+    // 第一个为主Bot
+    transports: Vec<Arc<dyn TelegramTransport>>,
+    bot_routes: HashMap<Endpoint, usize>,
     db: DatabaseConnection,
     index: Option<IndexService>,
+    translate: Option<TranslateService>,
+    transcribe: Option<TranscribeService>,
+    media_cache: MediaCache,
+    file_transfer: Option<Arc<FileTransfer>>,
+    maintenance: Option<Arc<StorageMaintenance>>,
+    max_media_size: Option<usize>,
+    media_workers: usize,
+    chat_workers: usize,
+    // 可重新加载的设置
+    settings: DynamicSettings,
+    log_reloader: LogLevelReloader,
+    web: Option<WebConfig>,
+    // 发送统计报告的时间
+    stats_report: Option<CronSchedule>,
 }
 
 impl TelegramPylon {
-    pub async fn new(config: TelegramConfig) -> Result<Self> {
+    pub async fn new(
+        config: TelegramConfig,
+        data_dir: &Path,
+        database_url: &str,
+        rate_limit: HashMap<String, u32>,
+        retention: Option<RetentionConfig>,
+        web: Option<WebConfig>,
+        log_reloader: LogLevelReloader,
+    ) -> Result<Self> {
+        let upload_attempts = config.upload_attempts.unwrap_or(UPLOAD_ATTEMPTS).max(1);
+        let session = BotSession {
+            token: config.bot_token.clone(),
+            file: data_dir.join(BOT_SESSION),
+        };
+        // 主Bot接收更新, 命令和回调, 只能使用 MTProto
+        let mut transports: Vec<Arc<dyn TelegramTransport>> = vec![Arc::new(
+            GrammersTransport::connect(&config, session, upload_attempts).await?,
+        )];
+
+        for bot in config.extra_bots.iter().flatten() {
+            let bot_id = bot.bot_token.split(':').next().unwrap_or_default();
+            let session = BotSession {
+                token: bot.bot_token.clone(),
+                file: data_dir.join(format!("bot_{}.session", bot_id)),
+            };
+            transports.push(match bot.transport {
+                BotTransport::Mtproto => {
+                    Arc::new(GrammersTransport::connect(&config, session, upload_attempts).await?)
+                }
+                BotTransport::BotApi => Arc::new(BotApiTransport::new(
+                    &bot.bot_token,
+                    bot.api_url.as_deref(),
+                )?),
+            });
+        }
+
+        Self::with_transports(
+            config,
+            transports,
+            data_dir,
+            database_url,
+            rate_limit,
+            retention,
+            web,
+            log_reloader,
+        )
+        .await
+    }
+
+    // 使用已连接的Telegram接口, 第一个为主Bot, 其余与配置中的extra_bots依次对应
+    #[allow(clippy::too_many_arguments)]
+    pub async fn with_transports(
+        config: TelegramConfig,
+        transports: Vec<Arc<dyn TelegramTransport>>,
+        data_dir: &Path,
+        database_url: &str,
+        rate_limit: HashMap<String, u32>,
+        retention: Option<RetentionConfig>,
+        web: Option<WebConfig>,
+        log_reloader: LogLevelReloader,
+    ) -> Result<Self> {
         // 初始化数据库
-        let db = Database::connect(format!("sqlite://{}?mode=rwc", DB_FILE)).await?;
+        let db = Database::connect(database_url).await?;
         migration::Migrator::up(&db, None).await?;
 
-        let session = Session::load_file_or_create(BOT_SESSION)
-            .context("failed to load or create session for telegram bot")?;
-        let client = Client::connect(Config {
-            session,
-            api_id: config.api_id,
-            api_hash: config.api_hash,
-            params: InitParams {
-                catch_up: false,
-                reconnection_policy: &RECONNECTION_POLICY,
-                proxy_url: config.proxy_url,
-                ..Default::default()
-            },
-        })
-        .await
-        .context("failed to connect to telegram")?;
-
-        let is_authorized = client
-            .is_authorized()
-            .await
-            .context("failed to check telegram bot authorization state")?;
-
-        if !is_authorized {
-            client
-                .bot_sign_in(&config.bot_token)
-                .await
-                .context("failed to sign in telegram bot")?;
-
-            client
-                .session()
-                .save_to_file(BOT_SESSION)
-                .context("failed to save session for telegram bot")?;
+        // 额外的Bot按端点分流发送消息
+        let mut bot_routes = HashMap::new();
+        for (index, bot) in config.extra_bots.iter().flatten().enumerate() {
+            if index + 1 >= transports.len() {
+                return Err(anyhow::anyhow!(
+                    "missing transport for extra bot {}",
+                    index + 1
+                ));
+            }
+            for endpoint in &bot.endpoints {
+                let endpoint = endpoint
+                    .parse::<Endpoint>()
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                bot_routes.insert(endpoint, index + 1);
+            }
         }
 
+        let settings = DynamicSettings::from_config(&config, &rate_limit)?;
+
         Ok(Self {
             admin_id: config.admin_id,
-            client,
+            notice_chat: config.notice_chat,
+            transports,
+            bot_routes,
             db,
             index: match config.enable_search {
-                true => Some(IndexService::new().await?),
+                true => Some(IndexService::new(data_dir).await?),
                 false => None,
             },
+            translate: config.translate.map(TranslateService::new).transpose()?,
+            transcribe: config.transcribe.map(TranscribeService::new).transpose()?,
+            media_cache: MediaCache::new(
+                data_dir,
+                config.media_cache_size.unwrap_or(MEDIA_CACHE_SIZE) * 1024 * 1024,
+            )
+            .await?,
+            file_transfer: match config.file_transfer {
+                Some(file_transfer) => {
+                    Some(Arc::new(FileTransfer::new(data_dir, file_transfer).await?))
+                }
+                None => None,
+            },
+            maintenance: retention
+                .map(|retention| Arc::new(StorageMaintenance::new(data_dir, retention))),
+            max_media_size: config.max_media_size.map(|size| size * 1024 * 1024),
+            media_workers: config.media_workers.unwrap_or(MEDIA_WORKERS).max(1),
+            chat_workers: config.chat_workers.unwrap_or(CHAT_WORKERS).max(1),
+            settings,
+            log_reloader,
+            web,
+            stats_report: config
+                .stats_report
+                .map(|schedule| schedule.parse::<CronSchedule>())
+                .transpose()
+                .map_err(|e| anyhow::anyhow!(e))?,
         })
     }
 
@@ -95,15 +217,63 @@ impl TelegramPylon {
         // 初始化处理用辅助
         let bridge = Arc::new(Bridge::new(
             self.admin_id,
-            self.client.clone(),
+            self.notice_chat,
+            self.transports.clone(),
+            self.bot_routes.clone(),
             self.db.clone(),
             self.index.clone(),
+            self.translate.clone(),
+            self.transcribe.clone(),
+            self.media_cache.clone(),
+            self.file_transfer.clone(),
+            self.maintenance.clone(),
+            self.max_media_size,
+            self.media_workers,
+            self.settings.clone(),
+            self.log_reloader.clone(),
             api_sender,
         ));
 
+        // 注册命令列表以便客户端自动补全
+        if let Err(e) = Self::register_commands(&bridge).await {
+            tracing::warn!("Failed to register bot commands: {}", e);
+        }
+
+        // 检测媒体转换工具, 缺少时跳过相应的转换
+        if let Err(e) = Self::report_ffmpeg(&bridge).await {
+            tracing::warn!("Failed to report ffmpeg status: {}", e);
+        }
+
+        // 预先加载远端对话的缓存
+        match bridge.prime_remote_chat_cache().await {
+            Ok(count) => tracing::info!("Loaded {} remote chats into cache", count),
+            Err(e) => tracing::warn!("Failed to prime remote chat cache: {}", e),
+        }
+
+        if let Err(e) = bridge.load_endpoint_aliases().await {
+            tracing::warn!("Failed to load endpoint aliases: {}", e);
+        }
+
+        // 上次退出时未完成的发送标记为失败
+        match bridge.fail_stale_pending_messages().await {
+            Ok(0) => {}
+            Ok(count) => tracing::warn!("Marked {} interrupted pending messages as failed", count),
+            Err(e) => tracing::warn!("Failed to mark stale pending messages: {}", e),
+        }
+
+        // 在后台检查链接和归档是否仍然有效
+        let check_bridge = bridge.clone();
+        tokio::spawn(async move {
+            if let Err(e) = Self::check_consistency(&check_bridge, None).await {
+                tracing::warn!("Failed to check consistency: {}", e);
+            }
+        });
+
         // 接收Onebot的事件进行处理
-        let remote_id_lock: Arc<RemoteIdLock> = Arc::new(DashMap::new());
-        let remote_id_lock_clone = remote_id_lock.clone();
+        // 每个对话的消息由各自的工作任务依次处理, 两个方向共用同时处理的对话数量上限
+        let chat_permits = Arc::new(Semaphore::new(self.chat_workers));
+        let remote_workers = Arc::new(RemoteWorkers::new(chat_permits.clone()));
+        let remote_workers_clone = remote_workers.clone();
         let bridge_clone = bridge.clone();
         let mut event_shutdown_rx = shutdown_rx.resubscribe();
         let event_handle = tokio::spawn(async move {
@@ -115,15 +285,18 @@ impl TelegramPylon {
                             event.raw.get_chat_type(),
                             event.raw.get_chat_id(),
                         );
-                        let id_lock = remote_id_lock.clone();
                         let bridge = bridge_clone.clone();
-                        tokio::spawn(async move {
-                            with_id_lock!(id_lock, remote_chat_key, {
-                                if let Err(e) = Self::handle_event(&bridge, event).await {
-                                    tracing::warn!("Failed to handle Onebot event: {}", e);
-                                }
-                            });
-                        });
+                        let span = tracing::info_span!(
+                            "onebot_event",
+                            trace_id = %new_trace_id(),
+                            endpoint = %remote_chat_key.0,
+                            chat_id = %remote_chat_key.2,
+                        );
+                        remote_workers.spawn(remote_chat_key, async move {
+                            if let Err(e) = Self::handle_event(&bridge, event).await {
+                                tracing::warn!("Failed to handle Onebot event: {}", e);
+                            }
+                        }.instrument(span));
                     }
                     Ok(_) = event_shutdown_rx.recv() => {
                         tracing::info!("Shutting down TelegramPylon event handler");
@@ -133,17 +306,127 @@ impl TelegramPylon {
             }
         });
 
+        // 定时发送摘要消息以及清理过期回调数据
+        let bridge_clone = bridge.clone();
+        let stats_report = self.stats_report;
+        let mut digest_shutdown_rx = shutdown_rx.resubscribe();
+        let digest_handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(DIGEST_CHECK_INTERVAL);
+            let mut cleanup_interval = tokio::time::interval(CALLBACK_CLEANUP_INTERVAL);
+            let mut report_interval = tokio::time::interval(STATS_REPORT_CHECK_INTERVAL);
+            let mut maintenance_interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+            // 上次发送报告的分钟, 避免同一分钟内重复发送
+            let mut last_report = Local::now().timestamp() / 60;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if let Err(e) = Self::flush_digests(&bridge_clone, false).await {
+                            tracing::warn!("Failed to flush digests: {}", e);
+                        }
+                        if let Err(e) = Self::flush_reconnect_notices(&bridge_clone).await {
+                            tracing::warn!("Failed to flush reconnect notices: {}", e);
+                        }
+                    }
+                    _ = cleanup_interval.tick() => {
+                        if let Err(e) = bridge_clone.cleanup_callbacks().await {
+                            tracing::warn!("Failed to cleanup callbacks: {}", e);
+                        }
+                        if let Err(e) = bridge_clone.cleanup_transfers().await {
+                            tracing::warn!("Failed to cleanup transfer files: {}", e);
+                        }
+                        match bridge_clone.cleanup_conversation_windows().await {
+                            Ok(count) if count > 0 => {
+                                tracing::info!("Removed {} inactive conversation windows", count);
+                            }
+                            Ok(_) => {}
+                            Err(e) => tracing::warn!("Failed to cleanup conversation windows: {}", e),
+                        }
+                    }
+                    _ = maintenance_interval.tick() => {
+                        if let Err(e) = bridge_clone.run_maintenance().await {
+                            tracing::warn!("Failed to run storage maintenance: {}", e);
+                        }
+                    }
+                    _ = report_interval.tick(), if stats_report.is_some() => {
+                        let now = Local::now();
+                        if now.timestamp() / 60 != last_report
+                            && stats_report.is_some_and(|schedule| schedule.matches(&now))
+                        {
+                            last_report = now.timestamp() / 60;
+                            if let Err(e) = Self::send_stats_report(&bridge_clone).await {
+                                tracing::warn!("Failed to send stats report: {}", e);
+                            }
+                        }
+                    }
+                    Ok(_) = digest_shutdown_rx.recv() => {
+                        tracing::info!("Shutting down TelegramPylon digest handler");
+                        if let Err(e) = Self::flush_digests(&bridge_clone, true).await {
+                            tracing::warn!("Failed to flush digests: {}", e);
+                        }
+                        break;
+                    }
+                }
+            }
+        });
+
+        // 管理页面
+        let web_handle = self.web.clone().map(|config| {
+            let bridge = bridge.clone();
+            let web_shutdown_rx = shutdown_rx.resubscribe();
+            tokio::spawn(async move {
+                if let Err(e) = web_service::run(config, bridge, web_shutdown_rx).await {
+                    tracing::warn!("Failed to run web service: {}", e);
+                }
+            })
+        });
+
+        // 额外的Bot只负责发送, 丢弃其收到的更新
+        let mut drain_handles = Vec::new();
+        for transport in self.transports.iter().skip(1) {
+            let transport = transport.clone();
+            let mut drain_shutdown_rx = shutdown_rx.resubscribe();
+            drain_handles.push(tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        update = transport.next_update() => {
+                            if let Err(e) = update {
+                                tracing::warn!("Failed to receive extra bot update: {}", e);
+                            }
+                        }
+                        Ok(_) = drain_shutdown_rx.recv() => {
+                            break;
+                        }
+                    }
+                }
+            }));
+        }
+
+        // 配置文件变化时自动重新加载
+        match Self::watch_config(bridge.clone(), shutdown_rx.resubscribe()) {
+            Ok(handle) => drain_handles.push(handle),
+            Err(e) => tracing::warn!("Failed to watch config file: {}", e),
+        }
+
         // 接收Telegram的消息进行处理
-        let tg_id_lock: Arc<TgIdLock> = Arc::new(DashMap::new());
+        let tg_workers = Arc::new(TgWorkers::new(chat_permits));
         let bridge_clone = bridge.clone();
+        // 用于识别Bot自身被移出群组
+        let bot_id = match bridge.primary_bot().get_me().await {
+            Ok(me) => me.id,
+            Err(e) => {
+                tracing::warn!("Failed to get bot info: {}", e);
+                0
+            }
+        };
         let message_handle = tokio::spawn(async move {
             loop {
                 tokio::select! {
                     _ = async {
                         if let Err(e) = Self::handle_message(
-                            tg_id_lock.clone(),
-                            remote_id_lock_clone.clone(),
+                            tg_workers.clone(),
+                            remote_workers_clone.clone(),
                             bridge_clone.clone(),
+                            bot_id,
                         ).await {
                             tracing::warn!("Failed to handle Telegram message: {}", e);
                         }
@@ -159,64 +442,329 @@ impl TelegramPylon {
             }
         });
 
-        let _ = tokio::try_join!(event_handle, message_handle);
+        let _ = tokio::try_join!(event_handle, digest_handle, message_handle);
+        for handle in drain_handles.into_iter().chain(web_handle) {
+            let _ = handle.await;
+        }
         tracing::info!("TelegramPylon shutdown complete");
     }
 
     async fn handle_message(
-        tg_id_lock: Arc<TgIdLock>,
-        remote_id_lock: Arc<RemoteIdLock>,
+        tg_workers: Arc<TgWorkers>,
+        remote_workers: Arc<RemoteWorkers>,
         bridge: RelayBridge,
+        bot_id: i64,
     ) -> Result<()> {
-        match bridge.bot_client.next_update().await? {
+        let Some(update) = bridge.primary_bot().transport().next_update().await? else {
+            return Ok(());
+        };
+
+        match update {
             Update::NewMessage(message) => {
                 tracing::debug!("Receive Telegram new message: {:?}", message);
 
-                tokio::spawn(async move {
-                    with_id_lock!(tg_id_lock, message.chat().id(), {
-                        match tg_helper::get_command(&message) {
+                // 保存对话和发送者的access_hash, 之后可以直接解析
+                for chat in std::iter::once(message.chat()).chain(message.sender()) {
+                    if let Err(e) = bridge
+                        .primary_bot()
+                        .remember_chat(&ChatInfo::from(&chat))
+                        .await
+                    {
+                        tracing::warn!("Failed to remember Telegram chat: {}", e);
+                    }
+                }
+
+                // 相册消息先缓存, 等同组的消息到齐后作为一条消息处理
+                if let Some(grouped_id) = message.raw.grouped_id {
+                    if bridge.get_command(&message).is_none() {
+                        let span = telegram_span(message.chat().id(), message.id());
+                        if bridge.push_album(grouped_id, message) {
+                            tokio::spawn(
+                                async move {
+                                    tokio::time::sleep(ALBUM_WAIT).await;
+                                    let messages = bridge.take_album(grouped_id);
+                                    let tg_chat_id = messages[0].chat().id();
+                                    tg_workers.spawn(
+                                        tg_chat_id,
+                                        async move {
+                                            if let Err(e) = Self::process_messages(
+                                                &bridge,
+                                                &messages,
+                                                &remote_workers,
+                                            )
+                                            .await
+                                            {
+                                                tracing::warn!(
+                                                    "Failed to process Telegram album: {}",
+                                                    e
+                                                );
+                                                let _ = messages[0]
+                                                    .reply(InputMessage::html(i18n::tr(
+                                                        bridge.language(),
+                                                        "<b>[WARN] Failed to process message</b>",
+                                                    )))
+                                                    .await;
+                                            }
+                                        }
+                                        .in_current_span(),
+                                    );
+                                }
+                                .instrument(span),
+                            );
+                        }
+                        return Ok(());
+                    }
+                }
+
+                let span = telegram_span(message.chat().id(), message.id());
+                tg_workers.spawn(
+                    message.chat().id(),
+                    async move {
+                        match bridge.get_command(&message) {
                             Some(command) => {
                                 if let Err(e) =
                                     Self::process_command(&bridge, &message, &command).await
                                 {
                                     tracing::warn!("Failed to process Telegram command: {}", e);
                                     let _ = message
-                                        .reply(InputMessage::html(
+                                        .reply(InputMessage::html(i18n::tr(
+                                            bridge.language(),
                                             "<b>[WARN] Failed to process command</b>",
-                                        ))
+                                        )))
                                         .await;
                                 }
                             }
                             None => {
                                 if let Err(e) =
-                                    Self::process_message(&bridge, &message, remote_id_lock).await
+                                    Self::process_message(&bridge, &message, &remote_workers).await
                                 {
                                     tracing::warn!("Failed to process Telegram message: {}", e);
                                     let _ = message
-                                        .reply(InputMessage::html(
+                                        .reply(InputMessage::html(i18n::tr(
+                                            bridge.language(),
                                             "<b>[WARN] Failed to process message</b>",
-                                        ))
+                                        )))
                                         .await;
                                 }
                             }
                         }
-                    });
+                    }
+                    .instrument(span),
+                );
+            }
+            // 取消置顶没有对应的Action消息, 从原始更新中获取
+            Update::Raw(tl::enums::Update::PinnedChannelMessages(update)) if !update.pinned => {
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        Self::sync_pinned(&bridge, update.channel_id, &update.messages, false).await
+                    {
+                        tracing::warn!("Failed to sync unpinned messages: {}", e);
+                    }
+                });
+            }
+            Update::Raw(tl::enums::Update::PinnedMessages(update)) if !update.pinned => {
+                let tg_chat_id = match update.peer {
+                    tl::enums::Peer::User(peer) => peer.user_id,
+                    tl::enums::Peer::Chat(peer) => peer.chat_id,
+                    tl::enums::Peer::Channel(peer) => peer.channel_id,
+                };
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        Self::sync_pinned(&bridge, tg_chat_id, &update.messages, false).await
+                    {
+                        tracing::warn!("Failed to sync unpinned messages: {}", e);
+                    }
+                });
+            }
+            // 管理员对消息添加的反应
+            Update::Raw(tl::enums::Update::BotMessageReaction(update)) if matches!(&update.actor, tl::enums::Peer::User(user) if user.user_id == bridge.admin_id) =>
+            {
+                let (packed_type, tg_chat_id) = match update.peer {
+                    tl::enums::Peer::User(peer) => (PackedType::User, peer.user_id),
+                    tl::enums::Peer::Chat(peer) => (PackedType::Chat, peer.chat_id),
+                    tl::enums::Peer::Channel(peer) => (PackedType::Megagroup, peer.channel_id),
+                };
+                let span = telegram_span(tg_chat_id, update.msg_id);
+                tokio::spawn(
+                    async move {
+                        if let Err(e) = Self::process_reaction(
+                            &bridge,
+                            packed_type,
+                            tg_chat_id,
+                            update.msg_id,
+                            &update.old_reactions,
+                            &update.new_reactions,
+                        )
+                        .await
+                        {
+                            tracing::warn!("Failed to process Telegram reaction: {}", e);
+                        }
+                    }
+                    .instrument(span),
+                );
+            }
+            // Bot被移出群组或频道
+            Update::Raw(tl::enums::Update::ChannelParticipant(update))
+                if update.user_id == bot_id
+                    && matches!(
+                        update.new_participant,
+                        None | Some(tl::enums::ChannelParticipant::Banned(_))
+                            | Some(tl::enums::ChannelParticipant::Left(_))
+                    ) =>
+            {
+                tokio::spawn(async move {
+                    if let Err(e) = Self::process_bot_removed(&bridge, update.channel_id).await {
+                        tracing::warn!("Failed to process bot removal: {}", e);
+                    }
+                });
+            }
+            Update::Raw(tl::enums::Update::ChatParticipant(update))
+                if update.user_id == bot_id && update.new_participant.is_none() =>
+            {
+                tokio::spawn(async move {
+                    if let Err(e) = Self::process_bot_removed(&bridge, update.chat_id).await {
+                        tracing::warn!("Failed to process bot removal: {}", e);
+                    }
                 });
             }
             Update::CallbackQuery(callback) => {
                 tracing::debug!("Receive Telegram callback: {:?}", callback);
 
-                tokio::spawn(async move {
-                    with_id_lock!(tg_id_lock, callback.chat().id(), {
+                let span = telegram_span(callback.chat().id(), 0);
+                tg_workers.spawn(
+                    callback.chat().id(),
+                    async move {
                         if let Err(e) = Self::process_callback(&bridge, &callback).await {
                             tracing::warn!("Failed to process Telegram callback: {}", e);
                         }
-                    });
-                });
+                    }
+                    .instrument(span),
+                );
             }
             _ => {}
         }
 
         Ok(())
     }
+
+    async fn report_ffmpeg(bridge: &Bridge) -> Result<()> {
+        let (ffmpeg, ffprobe) = ffmpeg::detect().await;
+        let status = |name: &str, version: &Option<String>| match version {
+            Some(version) => {
+                tracing::info!("Found {} {}", name, version);
+                format!("{} {}", name, version)
+            }
+            None => {
+                tracing::warn!("{} not found, related media conversions are disabled", name);
+                format!("{} not found", name)
+            }
+        };
+        let html = format!(
+            "<b>[{}] {}, {}</b>",
+            if ffmpeg.is_some() && ffprobe.is_some() {
+                "INFO"
+            } else {
+                "WARN"
+            },
+            status("ffmpeg", &ffmpeg),
+            status("ffprobe", &ffprobe)
+        );
+
+        bridge.send_notice(html).await
+    }
+
+    fn watch_config(
+        bridge: RelayBridge,
+        mut shutdown_rx: broadcast::Receiver<()>,
+    ) -> Result<tokio::task::JoinHandle<()>> {
+        let (change_sender, mut change_receiver) = mpsc::channel::<()>(1);
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    if (event.kind.is_modify() || event.kind.is_create())
+                        && event
+                            .paths
+                            .iter()
+                            .any(|path| path.file_name().is_some_and(|name| name == CONFIG_PATH))
+                    {
+                        let _ = change_sender.try_send(());
+                    }
+                }
+            })?;
+        // 监听所在目录, 编辑器保存时可能替换整个文件
+        let config_path = std::fs::canonicalize(CONFIG_PATH)?;
+        let config_dir = config_path.parent().unwrap_or(Path::new("."));
+        notify::Watcher::watch(
+            &mut watcher,
+            config_dir,
+            notify::RecursiveMode::NonRecursive,
+        )?;
+
+        Ok(tokio::spawn(async move {
+            // 任务结束前保持监听
+            let _watcher = watcher;
+            loop {
+                tokio::select! {
+                    Some(_) = change_receiver.recv() => {
+                        tokio::time::sleep(CONFIG_RELOAD_DELAY).await;
+                        while change_receiver.try_recv().is_ok() {}
+
+                        let html = Self::reload_config(&bridge);
+                        if let Err(e) = bridge.send_notice(html).await {
+                            tracing::warn!("Failed to notify config reload: {}", e);
+                        }
+                    }
+                    Ok(_) = shutdown_rx.recv() => {
+                        break;
+                    }
+                }
+            }
+        }))
+    }
+
+    // 重新加载配置, 返回发送给管理员的结果
+    pub fn reload_config(bridge: &Bridge) -> String {
+        match bridge.reload_config() {
+            Ok(_) => {
+                tracing::info!("Config reloaded");
+                let language = bridge.language();
+                format!(
+                    "{}\n{}",
+                    i18n::tr(language, "<b>[INFO] Config reloaded</b>"),
+                    i18n::tr(
+                        language,
+                        "Applied log level, rate limits, GIF limits, reconnect notices, mark_as_read, \
+                        language, command aliases, reaction actions, voice formats, conversation windows and EXIF stripping, other settings take effect after restart"
+                    )
+                )
+            }
+            Err(e) => {
+                tracing::warn!("Failed to reload config: {}", e);
+                i18n::trf(
+                    bridge.language(),
+                    "<b>[WARN] Failed to reload config:</b> {}",
+                    &[&html_escape::encode_text(&e.to_string())],
+                )
+            }
+        }
+    }
+
+    async fn send_stats_report(bridge: &Bridge) -> Result<()> {
+        let html = bridge.take_stats_report().await?;
+        bridge.send_notice(html).await
+    }
+}
+
+// 每条转发的消息生成一个trace id, 贯穿下载, 转换, 上传和保存等步骤
+fn new_trace_id() -> String {
+    Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+fn telegram_span(tg_chat_id: i64, tg_msg_id: i32) -> tracing::Span {
+    tracing::info_span!(
+        "telegram_message",
+        trace_id = %new_trace_id(),
+        tg_chat_id,
+        tg_msg_id,
+    )
 }
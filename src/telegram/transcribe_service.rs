@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+use tokio::process::Command;
+
+use super::{ffmpeg, storage_maintenance};
+use crate::common::TranscribeConfig;
+
+// 转写请求的超时时间
+const TRANSCRIBE_TIMEOUT: Duration = Duration::from_secs(120);
+
+const WHISPER_CPP_BINARY: &str = "whisper-cli";
+const OPENAI_API_URL: &str = "https://api.openai.com/v1";
+const OPENAI_MODEL: &str = "whisper-1";
+
+#[derive(Clone)]
+enum Provider {
+    WhisperCpp,
+    OpenAI,
+}
+
+#[derive(Clone)]
+pub struct TranscribeService {
+    provider: Provider,
+    binary: String,
+    model: String,
+    api_key: String,
+    api_url: String,
+    http_client: reqwest::Client,
+}
+
+impl TranscribeService {
+    pub fn new(config: TranscribeConfig) -> Result<Self> {
+        let provider = match config.provider.to_lowercase().as_str() {
+            "whisper_cpp" => Provider::WhisperCpp,
+            "openai" => Provider::OpenAI,
+            _ => {
+                return Err(anyhow!(
+                    "unsupported transcribe provider: {}",
+                    config.provider
+                ));
+            }
+        };
+        let model = match (&provider, config.model) {
+            (_, Some(model)) => model,
+            (Provider::OpenAI, None) => OPENAI_MODEL.to_string(),
+            (Provider::WhisperCpp, None) => {
+                return Err(anyhow!("model is required for whisper.cpp"));
+            }
+        };
+
+        Ok(Self {
+            provider,
+            binary: config
+                .binary
+                .unwrap_or_else(|| WHISPER_CPP_BINARY.to_string()),
+            model,
+            api_key: config.api_key.unwrap_or_default(),
+            api_url: config.api_url.unwrap_or_else(|| OPENAI_API_URL.to_string()),
+            http_client: reqwest::Client::builder()
+                .timeout(TRANSCRIBE_TIMEOUT)
+                .build()?,
+        })
+    }
+
+    // 校验语言设置, auto 表示自动检测
+    pub fn is_valid_language(setting: &str) -> bool {
+        setting == "auto"
+            || (!setting.is_empty() && setting.chars().all(|c| c.is_ascii_alphabetic()))
+    }
+
+    pub async fn transcribe(&self, data: &[u8], language: &str) -> Result<String> {
+        let language = Some(language).filter(|lang| *lang != "auto");
+        let text = match self.provider {
+            Provider::WhisperCpp => self.transcribe_whisper_cpp(data, language).await?,
+            Provider::OpenAI => self.transcribe_openai(data, language).await?,
+        };
+
+        Ok(text.trim().to_string())
+    }
+
+    async fn transcribe_whisper_cpp(&self, data: &[u8], language: Option<&str>) -> Result<String> {
+        let input_file = storage_maintenance::temp_file()?;
+        tokio::fs::write(input_file.path(), data).await?;
+
+        // whisper.cpp 只接受16kHz单声道wav
+        if !ffmpeg::has_ffmpeg() {
+            return Err(anyhow!(
+                "ffmpeg is required to convert voice for whisper.cpp"
+            ));
+        }
+        let wav_file = storage_maintenance::temp_file_with_suffix(".wav")?;
+        let output = Command::new("ffmpeg")
+            .arg("-y")
+            .arg("-i")
+            .arg(input_file.path())
+            .args(["-ar", "16000", "-ac", "1", "-c:a", "pcm_s16le"])
+            .arg(wav_file.path())
+            .stderr(std::process::Stdio::null())
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow!("ffmpeg exited: {}", output.status));
+        }
+
+        let output = Command::new(&self.binary)
+            .args(["-m", &self.model, "-l", language.unwrap_or("auto")])
+            .args(["-nt", "-np", "-f"])
+            .arg(wav_file.path())
+            .stderr(std::process::Stdio::null())
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow!("whisper.cpp exited: {}", output.status));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    async fn transcribe_openai(&self, data: &[u8], language: Option<&str>) -> Result<String> {
+        let mut form = reqwest::multipart::Form::new()
+            .text("model", self.model.clone())
+            .part(
+                "file",
+                reqwest::multipart::Part::bytes(data.to_vec()).file_name("voice.ogg"),
+            );
+        if let Some(language) = language {
+            form = form.text("language", language.to_string());
+        }
+
+        let response = self
+            .http_client
+            .post(format!(
+                "{}/audio/transcriptions",
+                self.api_url.trim_end_matches('/')
+            ))
+            .bearer_auth(&self.api_key)
+            .multipart(form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+
+        response["text"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow!("invalid transcription response: {}", response))
+    }
+}
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+
+use crate::common::TranslateConfig;
+
+// 翻译请求的超时时间
+const TRANSLATE_TIMEOUT: Duration = Duration::from_secs(30);
+
+const DEEPL_API_URL: &str = "https://api-free.deepl.com/v2/translate";
+const GOOGLE_API_URL: &str = "https://translation.googleapis.com/language/translate/v2";
+const OPENAI_API_URL: &str = "https://api.openai.com/v1";
+const OPENAI_MODEL: &str = "gpt-4o-mini";
+
+#[derive(Clone)]
+enum Provider {
+    DeepL,
+    Google,
+    OpenAI,
+}
+
+#[derive(Clone)]
+pub struct TranslateService {
+    provider: Provider,
+    api_key: String,
+    api_url: String,
+    model: String,
+    http_client: reqwest::Client,
+}
+
+impl TranslateService {
+    pub fn new(config: TranslateConfig) -> Result<Self> {
+        let (provider, default_url) = match config.provider.to_lowercase().as_str() {
+            "deepl" => (Provider::DeepL, DEEPL_API_URL),
+            "google" => (Provider::Google, GOOGLE_API_URL),
+            "openai" => (Provider::OpenAI, OPENAI_API_URL),
+            _ => {
+                return Err(anyhow!(
+                    "unsupported translate provider: {}",
+                    config.provider
+                ));
+            }
+        };
+
+        Ok(Self {
+            provider,
+            api_key: config.api_key,
+            api_url: config.api_url.unwrap_or_else(|| default_url.to_string()),
+            model: config.model.unwrap_or_else(|| OPENAI_MODEL.to_string()),
+            http_client: reqwest::Client::builder()
+                .timeout(TRANSLATE_TIMEOUT)
+                .build()?,
+        })
+    }
+
+    // 解析语言对设置, 如 zh-en 或者只指定目标语言 en
+    pub fn parse_languages(setting: &str) -> Option<(Option<&str>, &str)> {
+        let valid = |lang: &str| !lang.is_empty() && lang.chars().all(|c| c.is_ascii_alphabetic());
+        match setting.split_once('-') {
+            Some((source, target)) if valid(source) && valid(target) => {
+                Some((Some(source), target))
+            }
+            None if valid(setting) => Some((None, setting)),
+            _ => None,
+        }
+    }
+
+    pub async fn translate(
+        &self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> Result<String> {
+        match self.provider {
+            Provider::DeepL => self.translate_deepl(text, source, target).await,
+            Provider::Google => self.translate_google(text, source, target).await,
+            Provider::OpenAI => self.translate_openai(text, source, target).await,
+        }
+    }
+
+    async fn translate_deepl(
+        &self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> Result<String> {
+        let mut body = json!({
+            "text": [text],
+            "target_lang": target.to_uppercase(),
+        });
+        if let Some(source) = source {
+            body["source_lang"] = json!(source.to_uppercase());
+        }
+
+        let response = self
+            .http_client
+            .post(&self.api_url)
+            .header("Authorization", format!("DeepL-Auth-Key {}", self.api_key))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+
+        response["translations"][0]["text"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow!("invalid DeepL response: {}", response))
+    }
+
+    async fn translate_google(
+        &self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> Result<String> {
+        let mut body = json!({
+            "q": text,
+            "target": target,
+            "format": "text",
+        });
+        if let Some(source) = source {
+            body["source"] = json!(source);
+        }
+
+        let response = self
+            .http_client
+            .post(&self.api_url)
+            .query(&[("key", &self.api_key)])
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+
+        response["data"]["translations"][0]["translatedText"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow!("invalid Google response: {}", response))
+    }
+
+    async fn translate_openai(
+        &self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> Result<String> {
+        let instruction = match source {
+            Some(source) => format!(
+                "Translate the following text from language code '{}' to language code '{}'. \
+                Output only the translation.",
+                source, target
+            ),
+            None => format!(
+                "Translate the following text to language code '{}'. Output only the translation.",
+                target
+            ),
+        };
+        let body = json!({
+            "model": self.model,
+            "messages": [
+                {"role": "system", "content": instruction},
+                {"role": "user", "content": text},
+            ],
+        });
+
+        let response = self
+            .http_client
+            .post(format!(
+                "{}/chat/completions",
+                self.api_url.trim_end_matches('/')
+            ))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+
+        response["choices"][0]["message"]["content"]
+            .as_str()
+            .map(|s| s.trim().to_string())
+            .ok_or_else(|| anyhow!("invalid OpenAI response: {}", response))
+    }
+}
@@ -0,0 +1,930 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures_util::stream::BoxStream;
+use futures_util::{StreamExt, TryStreamExt};
+use grammers_client::session::Session;
+use grammers_client::types::input_media::InputMedia;
+use grammers_client::types::media::Uploaded;
+use grammers_client::types::{Chat, Media, Message, PackedChat};
+use grammers_client::{
+    Client, Config, FixedReconnect, InitParams, InputMessage, Update, button, reply_markup,
+};
+use grammers_tl_types as tl;
+use sea_orm::prelude::async_trait;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use super::telegram_helper as tg_helper;
+use crate::common::TelegramConfig;
+
+// 上传分片的大小
+const UPLOAD_PART_SIZE: usize = 512 * 1024;
+// 超过该大小的文件按大文件分片上传
+const BIG_UPLOAD_SIZE: usize = 10 * 1024 * 1024;
+// 大文件同时上传的分片数
+const UPLOAD_WORKERS: usize = 4;
+// 分片上传失败后的重试间隔, 每次翻倍
+const UPLOAD_RETRY_DELAY: Duration = Duration::from_secs(1);
+const RECONNECTION_POLICY: FixedReconnect = FixedReconnect {
+    attempts: usize::MAX,
+    delay: Duration::from_secs(5),
+};
+
+// Bot发送消息所用的Telegram接口, 可替换为Bot API或测试用的实现
+// 参数和返回值都是与传输方式无关的类型, 只有接收更新和下载媒体沿用MTProto的消息
+#[async_trait::async_trait]
+pub trait TelegramTransport: Send + Sync {
+    async fn send_message(&self, chat: PackedChat, message: OutgoingMessage)
+    -> Result<SentMessage>;
+
+    // 每个元素为相册中的一个媒体, 文字为其说明
+    async fn send_album(
+        &self,
+        chat: PackedChat,
+        medias: Vec<OutgoingMessage>,
+    ) -> Result<Vec<Option<SentMessage>>>;
+
+    async fn edit_message(
+        &self,
+        chat: PackedChat,
+        message_id: i32,
+        message: OutgoingMessage,
+    ) -> Result<()>;
+
+    async fn pin_message(&self, chat: PackedChat, message_id: i32) -> Result<()>;
+
+    async fn unpin_message(&self, chat: PackedChat, message_id: i32) -> Result<()>;
+
+    async fn forward_messages(
+        &self,
+        destination: PackedChat,
+        message_ids: &[i32],
+        source: PackedChat,
+    ) -> Result<Vec<Option<SentMessage>>>;
+
+    async fn upload_bytes(&self, data: &[u8], name: String) -> Result<UploadedFile>;
+
+    async fn unpack_chat(&self, chat: PackedChat) -> Result<ChatInfo>;
+
+    async fn get_me(&self) -> Result<BotUser>;
+
+    async fn set_commands(
+        &self,
+        scope: CommandScope,
+        commands: Vec<(String, String)>,
+    ) -> Result<()>;
+
+    // 在话题群中创建Topic, 返回Topic ID
+    async fn create_forum_topic(
+        &self,
+        chat: PackedChat,
+        title: String,
+        icon_emoji_id: Option<i64>,
+    ) -> Result<i32>;
+
+    async fn edit_forum_topic(
+        &self,
+        chat: PackedChat,
+        topic_id: i32,
+        title: Option<String>,
+        icon_emoji_id: Option<i64>,
+    ) -> Result<()>;
+
+    // 删除Topic及其中的全部消息
+    async fn delete_forum_topic(&self, chat: PackedChat, topic_id: i32) -> Result<()>;
+
+    // 返回其中仍然存在的Topic ID
+    async fn get_forum_topics(&self, chat: PackedChat, topic_ids: Vec<i32>)
+    -> Result<HashSet<i32>>;
+
+    async fn add_sticker_to_set(
+        &self,
+        short_name: String,
+        sticker: tl::enums::InputStickerSetItem,
+    ) -> Result<()>;
+
+    async fn create_sticker_set(
+        &self,
+        owner: PackedChat,
+        title: String,
+        short_name: String,
+        sticker: tl::enums::InputStickerSetItem,
+    ) -> Result<()>;
+
+    // 分块下载收到的媒体
+    fn download<'a>(&'a self, media: &Media) -> BoxStream<'a, Result<Vec<u8>>>;
+
+    // 接收下一个更新, 无法转换成MTProto更新的实现在确认收到后返回None
+    async fn next_update(&self) -> Result<Option<Update>>;
+
+    // 登录会话所在的数据中心, 没有会话时返回None
+    fn dc_id(&self) -> Option<i32>;
+
+    // 登录会话的文件, 没有会话时返回None
+    fn session_file(&self) -> Option<&Path>;
+
+    // 重新登录并保存会话
+    async fn reset_session(&self) -> Result<()>;
+}
+
+// 待发送的消息, 由各传输方式转换成对应的请求
+#[derive(Clone, Debug, Default)]
+pub struct OutgoingMessage {
+    pub text: String,
+    // 文字是否为HTML格式
+    pub html: bool,
+    pub reply_to: Option<i32>,
+    pub link_preview: bool,
+    pub media: Option<OutgoingMedia>,
+    // 内联按钮, 每个元素为一行
+    pub buttons: Vec<Vec<OutgoingButton>>,
+}
+
+impl OutgoingMessage {
+    pub fn text<T: Into<String>>(text: T) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn html<T: Into<String>>(html: T) -> Self {
+        Self {
+            text: html.into(),
+            html: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn reply_to(mut self, reply_to: Option<i32>) -> Self {
+        self.reply_to = reply_to;
+        self
+    }
+
+    pub fn link_preview(mut self, link_preview: bool) -> Self {
+        self.link_preview = link_preview;
+        self
+    }
+
+    pub fn photo(self, file: UploadedFile) -> Self {
+        self.media(OutgoingMedia::Photo {
+            file,
+            spoiler: false,
+        })
+    }
+
+    // 按文件类型以视频, 音频等形式发送
+    pub fn document(self, file: UploadedFile) -> Self {
+        self.media(OutgoingMedia::Document(file))
+    }
+
+    // 总是以文件形式发送
+    pub fn file(self, file: UploadedFile) -> Self {
+        self.media(OutgoingMedia::File(file))
+    }
+
+    pub fn media(mut self, media: OutgoingMedia) -> Self {
+        self.media = Some(media);
+        self
+    }
+
+    pub fn buttons(mut self, buttons: Vec<Vec<OutgoingButton>>) -> Self {
+        self.buttons = buttons;
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum OutgoingMedia {
+    Photo {
+        file: UploadedFile,
+        spoiler: bool,
+    },
+    Document(UploadedFile),
+    File(UploadedFile),
+    Sticker {
+        file: UploadedFile,
+        file_name: String,
+        mime_type: String,
+    },
+    Dice(String),
+    Venue(Venue),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Venue {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub title: String,
+    pub address: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum OutgoingButton {
+    Url { text: String, url: String },
+    Callback { text: String, data: String },
+}
+
+impl OutgoingButton {
+    pub fn url<T: Into<String>, U: Into<String>>(text: T, url: U) -> Self {
+        Self::Url {
+            text: text.into(),
+            url: url.into(),
+        }
+    }
+
+    pub fn inline<T: Into<String>, D: Into<String>>(text: T, data: D) -> Self {
+        Self::Callback {
+            text: text.into(),
+            data: data.into(),
+        }
+    }
+}
+
+// 已上传的文件
+#[derive(Clone, Debug)]
+pub enum UploadedFile {
+    // 已通过MTProto分片上传
+    Mtproto(Uploaded),
+    // 尚未上传, 由Bot API随请求一起发送
+    Inline { name: String, data: Arc<Vec<u8>> },
+}
+
+// 已发送的消息
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SentMessage {
+    pub chat_id: i64,
+    pub id: i32,
+    // 所在的Topic或回复的消息
+    pub reply_to: Option<i32>,
+    pub date: i64,
+    pub text: String,
+}
+
+impl From<&Message> for SentMessage {
+    fn from(message: &Message) -> Self {
+        Self {
+            chat_id: message.chat().id(),
+            id: message.id(),
+            reply_to: tg_helper::get_topic_id(message),
+            date: message.raw.date as i64,
+            text: message.text().to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChatInfo {
+    pub packed: PackedChat,
+    // 群组是否仍然可用 (未解散且Bot仍在群里)
+    pub available: bool,
+}
+
+impl ChatInfo {
+    pub fn pack(&self) -> PackedChat {
+        self.packed
+    }
+}
+
+impl From<&Chat> for ChatInfo {
+    fn from(chat: &Chat) -> Self {
+        Self {
+            packed: chat.pack(),
+            available: tg_helper::is_chat_available(chat),
+        }
+    }
+}
+
+impl From<&ChatInfo> for PackedChat {
+    fn from(chat: &ChatInfo) -> Self {
+        chat.packed
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BotUser {
+    pub id: i64,
+    pub username: Option<String>,
+}
+
+// 命令列表的显示范围
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandScope {
+    ChatAdmins,
+    Users,
+}
+
+// Bot的登录会话
+#[derive(Clone)]
+pub struct BotSession {
+    pub token: String,
+    pub file: PathBuf,
+}
+
+// 基于grammers的MTProto实现
+pub struct GrammersTransport {
+    // 重置会话时整体替换为新的连接
+    client: RwLock<Client>,
+    session: BotSession,
+    api_id: i32,
+    api_hash: String,
+    proxy_url: Option<String>,
+    // 每个上传分片的最大尝试次数
+    upload_attempts: usize,
+    // 通知等待更新的任务切换到新的连接
+    reset: Notify,
+}
+
+impl GrammersTransport {
+    // 连接Telegram, 会话未授权时以Bot token登录并保存会话
+    pub async fn connect(
+        config: &TelegramConfig,
+        session: BotSession,
+        upload_attempts: usize,
+    ) -> Result<Self> {
+        let transport = Self {
+            client: RwLock::new(
+                Self::connect_client(
+                    config.api_id,
+                    &config.api_hash,
+                    config.proxy_url.as_deref(),
+                    Session::load_file_or_create(&session.file)
+                        .context("failed to load or create session for telegram bot")?,
+                )
+                .await?,
+            ),
+            session,
+            api_id: config.api_id,
+            api_hash: config.api_hash.clone(),
+            proxy_url: config.proxy_url.clone(),
+            upload_attempts,
+            reset: Notify::new(),
+        };
+
+        let client = transport.client();
+        let is_authorized = client
+            .is_authorized()
+            .await
+            .context("failed to check telegram bot authorization state")?;
+
+        if !is_authorized {
+            transport.sign_in(&client).await?;
+        }
+
+        Ok(transport)
+    }
+
+    async fn connect_client(
+        api_id: i32,
+        api_hash: &str,
+        proxy_url: Option<&str>,
+        session: Session,
+    ) -> Result<Client> {
+        Client::connect(Config {
+            session,
+            api_id,
+            api_hash: api_hash.to_string(),
+            params: InitParams {
+                catch_up: false,
+                reconnection_policy: &RECONNECTION_POLICY,
+                proxy_url: proxy_url.map(str::to_string),
+                ..Default::default()
+            },
+        })
+        .await
+        .context("failed to connect to telegram")
+    }
+
+    // 以Bot token登录并保存会话
+    async fn sign_in(&self, client: &Client) -> Result<()> {
+        client
+            .bot_sign_in(&self.session.token)
+            .await
+            .context("failed to sign in telegram bot")?;
+
+        client
+            .session()
+            .save_to_file(&self.session.file)
+            .context("failed to save session for telegram bot")
+    }
+
+    // 当前的连接, Client内部共享连接, 克隆的开销很小
+    fn client(&self) -> Client {
+        self.client.read().unwrap().clone()
+    }
+
+    async fn upload_part(
+        &self,
+        file_id: i64,
+        index: usize,
+        total: usize,
+        big: bool,
+        part: &[u8],
+    ) -> Result<()> {
+        let mut delay = UPLOAD_RETRY_DELAY;
+        for attempt in 1..=self.upload_attempts {
+            let result = match big {
+                true => {
+                    self.client()
+                        .invoke(&tl::functions::upload::SaveBigFilePart {
+                            file_id,
+                            file_part: index as i32,
+                            file_total_parts: total as i32,
+                            bytes: part.to_vec(),
+                        })
+                        .await
+                }
+                false => {
+                    self.client()
+                        .invoke(&tl::functions::upload::SaveFilePart {
+                            file_id,
+                            file_part: index as i32,
+                            bytes: part.to_vec(),
+                        })
+                        .await
+                }
+            };
+            match result {
+                Ok(true) => return Ok(()),
+                Ok(false) => tracing::warn!("Upload part {}/{} was rejected", index + 1, total),
+                Err(e) => tracing::warn!(
+                    "Failed to upload part {}/{} (attempt {}): {}",
+                    index + 1,
+                    total,
+                    attempt,
+                    e
+                ),
+            }
+            if attempt < self.upload_attempts {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Failed to upload part {}/{} after {} attempts",
+            index + 1,
+            total,
+            self.upload_attempts
+        ))
+    }
+
+    async fn uploaded(&self, file: UploadedFile) -> Result<Uploaded> {
+        match file {
+            UploadedFile::Mtproto(uploaded) => Ok(uploaded),
+            UploadedFile::Inline { name, data } => self.upload(&data, name).await,
+        }
+    }
+
+    // 分片上传内存中的文件, 失败的分片单独重试, 已上传的分片不会重传
+    async fn upload(&self, data: &[u8], name: String) -> Result<Uploaded> {
+        if data.is_empty() {
+            return Err(anyhow::anyhow!("Cannot upload empty file {}", name));
+        }
+
+        let file_id = Uuid::new_v4().as_u64_pair().0 as i64;
+        let big = data.len() > BIG_UPLOAD_SIZE;
+        let total = data.len().div_ceil(UPLOAD_PART_SIZE);
+        let uploaded = &AtomicUsize::new(0);
+        let name_ref = &name;
+        futures_util::stream::iter(data.chunks(UPLOAD_PART_SIZE).enumerate())
+            .map(|(index, part)| async move {
+                self.upload_part(file_id, index, total, big, part).await?;
+                let done = uploaded.fetch_add(1, Ordering::Relaxed) + 1;
+                // 大文件每完成约10%记录一次进度
+                if big && (done * 10 / total) != ((done - 1) * 10 / total) {
+                    tracing::info!("Uploading {}: {}/{} parts", name_ref, done, total);
+                }
+                Ok::<(), anyhow::Error>(())
+            })
+            .buffer_unordered(if big { UPLOAD_WORKERS } else { 1 })
+            .try_collect::<Vec<()>>()
+            .await?;
+
+        let raw = match big {
+            true => tl::types::InputFileBig {
+                id: file_id,
+                parts: total as i32,
+                name,
+            }
+            .into(),
+            false => tl::types::InputFile {
+                id: file_id,
+                parts: total as i32,
+                name,
+                md5_checksum: String::new(),
+            }
+            .into(),
+        };
+
+        Ok(Uploaded { raw })
+    }
+
+    async fn input_message(&self, message: OutgoingMessage) -> Result<InputMessage> {
+        let mut input = match message.html {
+            true => InputMessage::html(&message.text),
+            false => InputMessage::text(&message.text),
+        }
+        .reply_to(message.reply_to)
+        .link_preview(message.link_preview);
+        if !message.buttons.is_empty() {
+            input = input.reply_markup(&reply_markup::inline(
+                message
+                    .buttons
+                    .into_iter()
+                    .map(|row| row.into_iter().map(inline_button).collect())
+                    .collect::<Vec<Vec<_>>>(),
+            ));
+        }
+
+        Ok(match message.media {
+            Some(OutgoingMedia::Photo {
+                file,
+                spoiler: false,
+            }) => input.photo(self.uploaded(file).await?),
+            Some(OutgoingMedia::Photo {
+                file,
+                spoiler: true,
+            }) => input.media(tl::types::InputMediaUploadedPhoto {
+                spoiler: true,
+                file: self.uploaded(file).await?.raw,
+                stickers: None,
+                ttl_seconds: None,
+            }),
+            Some(OutgoingMedia::Document(file)) => input.document(self.uploaded(file).await?),
+            Some(OutgoingMedia::File(file)) => input.file(self.uploaded(file).await?),
+            Some(OutgoingMedia::Sticker {
+                file,
+                file_name,
+                mime_type,
+            }) => input.media(sticker_media(
+                self.uploaded(file).await?,
+                file_name,
+                mime_type,
+            )),
+            Some(OutgoingMedia::Dice(emoticon)) => {
+                input.media(tl::types::InputMediaDice { emoticon })
+            }
+            Some(OutgoingMedia::Venue(venue)) => input.media(tl::types::InputMediaVenue {
+                geo_point: tl::types::InputGeoPoint {
+                    lat: venue.latitude,
+                    long: venue.longitude,
+                    accuracy_radius: None,
+                }
+                .into(),
+                title: venue.title,
+                address: venue.address,
+                provider: String::new(),
+                venue_id: String::new(),
+                venue_type: String::new(),
+            }),
+            None => input,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl TelegramTransport for GrammersTransport {
+    async fn send_message(
+        &self,
+        chat: PackedChat,
+        message: OutgoingMessage,
+    ) -> Result<SentMessage> {
+        let message = self.input_message(message).await?;
+        Ok(SentMessage::from(
+            &self.client().send_message(chat, message).await?,
+        ))
+    }
+
+    async fn send_album(
+        &self,
+        chat: PackedChat,
+        medias: Vec<OutgoingMessage>,
+    ) -> Result<Vec<Option<SentMessage>>> {
+        let mut album = Vec::with_capacity(medias.len());
+        for media in medias {
+            let input = match media.html {
+                true => InputMedia::caption_html(&media.text),
+                false => InputMedia::caption(&media.text),
+            }
+            .reply_to(media.reply_to);
+            album.push(match media.media {
+                Some(OutgoingMedia::Photo { file, .. }) => input.photo(self.uploaded(file).await?),
+                Some(OutgoingMedia::Document(file)) => input.document(self.uploaded(file).await?),
+                _ => return Err(anyhow::anyhow!("Unsupported album media")),
+            });
+        }
+
+        Ok(self
+            .client()
+            .send_album(chat, album)
+            .await?
+            .iter()
+            .map(|message| message.as_ref().map(SentMessage::from))
+            .collect())
+    }
+
+    async fn edit_message(
+        &self,
+        chat: PackedChat,
+        message_id: i32,
+        message: OutgoingMessage,
+    ) -> Result<()> {
+        let message = self.input_message(message).await?;
+        Ok(self
+            .client()
+            .edit_message(chat, message_id, message)
+            .await?)
+    }
+
+    async fn pin_message(&self, chat: PackedChat, message_id: i32) -> Result<()> {
+        Ok(self.client().pin_message(chat, message_id).await?)
+    }
+
+    async fn unpin_message(&self, chat: PackedChat, message_id: i32) -> Result<()> {
+        Ok(self.client().unpin_message(chat, message_id).await?)
+    }
+
+    async fn forward_messages(
+        &self,
+        destination: PackedChat,
+        message_ids: &[i32],
+        source: PackedChat,
+    ) -> Result<Vec<Option<SentMessage>>> {
+        Ok(self
+            .client()
+            .forward_messages(destination, message_ids, source)
+            .await?
+            .iter()
+            .map(|message| message.as_ref().map(SentMessage::from))
+            .collect())
+    }
+
+    async fn upload_bytes(&self, data: &[u8], name: String) -> Result<UploadedFile> {
+        Ok(UploadedFile::Mtproto(self.upload(data, name).await?))
+    }
+
+    async fn unpack_chat(&self, chat: PackedChat) -> Result<ChatInfo> {
+        Ok(ChatInfo::from(&self.client().unpack_chat(chat).await?))
+    }
+
+    async fn get_me(&self) -> Result<BotUser> {
+        let me = self.client().get_me().await?;
+        Ok(BotUser {
+            id: me.id(),
+            username: me.username().map(str::to_string),
+        })
+    }
+
+    async fn set_commands(
+        &self,
+        scope: CommandScope,
+        commands: Vec<(String, String)>,
+    ) -> Result<()> {
+        self.client()
+            .invoke(&tl::functions::bots::SetBotCommands {
+                scope: match scope {
+                    CommandScope::ChatAdmins => tl::enums::BotCommandScope::ChatAdmins,
+                    CommandScope::Users => tl::enums::BotCommandScope::Users,
+                },
+                lang_code: String::new(),
+                commands: commands
+                    .into_iter()
+                    .map(|(command, description)| {
+                        tl::types::BotCommand {
+                            command,
+                            description,
+                        }
+                        .into()
+                    })
+                    .collect(),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn create_forum_topic(
+        &self,
+        chat: PackedChat,
+        title: String,
+        icon_emoji_id: Option<i64>,
+    ) -> Result<i32> {
+        let req = tl::functions::channels::CreateForumTopic {
+            channel: input_channel(chat),
+            title,
+            icon_color: None,
+            icon_emoji_id,
+            random_id: rand::random::<i64>(),
+            send_as: None,
+        };
+        match self.client().invoke(&req).await? {
+            tl::enums::Updates::Updates(updates) => {
+                for update in &updates.updates {
+                    if let tl::enums::Update::NewChannelMessage(message) = update {
+                        if let tl::enums::Message::Service(service) = &message.message {
+                            if let tl::enums::MessageAction::TopicCreate(_) = service.action {
+                                return Ok(service.id);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => return Err(anyhow::anyhow!("Unsupported update type")),
+        }
+
+        Err(anyhow::anyhow!("Failed to create topic"))
+    }
+
+    async fn edit_forum_topic(
+        &self,
+        chat: PackedChat,
+        topic_id: i32,
+        title: Option<String>,
+        icon_emoji_id: Option<i64>,
+    ) -> Result<()> {
+        self.client()
+            .invoke(&tl::functions::channels::EditForumTopic {
+                channel: input_channel(chat),
+                topic_id,
+                title,
+                icon_emoji_id,
+                closed: None,
+                hidden: None,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_forum_topic(&self, chat: PackedChat, topic_id: i32) -> Result<()> {
+        self.client()
+            .invoke(&tl::functions::channels::DeleteTopicHistory {
+                channel: input_channel(chat),
+                top_msg_id: topic_id,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_forum_topics(
+        &self,
+        chat: PackedChat,
+        topic_ids: Vec<i32>,
+    ) -> Result<HashSet<i32>> {
+        let tl::enums::messages::ForumTopics::Topics(result) = self
+            .client()
+            .invoke(&tl::functions::channels::GetForumTopicsById {
+                channel: input_channel(chat),
+                topics: topic_ids,
+            })
+            .await?;
+
+        Ok(result
+            .topics
+            .iter()
+            .filter_map(|topic| match topic {
+                tl::enums::ForumTopic::Topic(topic) => Some(topic.id),
+                tl::enums::ForumTopic::Deleted(_) => None,
+            })
+            .collect())
+    }
+
+    async fn add_sticker_to_set(
+        &self,
+        short_name: String,
+        sticker: tl::enums::InputStickerSetItem,
+    ) -> Result<()> {
+        self.client()
+            .invoke(&tl::functions::stickers::AddStickerToSet {
+                stickerset: tl::types::InputStickerSetShortName { short_name }.into(),
+                sticker,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn create_sticker_set(
+        &self,
+        owner: PackedChat,
+        title: String,
+        short_name: String,
+        sticker: tl::enums::InputStickerSetItem,
+    ) -> Result<()> {
+        self.client()
+            .invoke(&tl::functions::stickers::CreateStickerSet {
+                masks: false,
+                emojis: false,
+                text_color: false,
+                user_id: tl::types::InputUser {
+                    user_id: owner.id,
+                    access_hash: owner.access_hash.unwrap_or(0),
+                }
+                .into(),
+                title,
+                short_name,
+                thumb: None,
+                stickers: vec![sticker],
+                software: None,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    fn download<'a>(&'a self, media: &Media) -> BoxStream<'a, Result<Vec<u8>>> {
+        futures_util::stream::try_unfold(
+            self.client().iter_download(media),
+            |mut download| async move {
+                Ok::<_, anyhow::Error>(download.next().await?.map(|chunk| (chunk, download)))
+            },
+        )
+        .boxed()
+    }
+
+    async fn next_update(&self) -> Result<Option<Update>> {
+        let client = self.client();
+        tokio::select! {
+            update = client.next_update() => Ok(Some(update?)),
+            // 会话已重置, 由调用方重新从新的连接接收
+            _ = self.reset.notified() => Ok(None),
+        }
+    }
+
+    fn dc_id(&self) -> Option<i32> {
+        self.client().session().get_user().map(|user| user.dc)
+    }
+
+    fn session_file(&self) -> Option<&Path> {
+        Some(&self.session.file)
+    }
+
+    // 以全新的会话重新连接并登录Bot, 成功后保存会话并替换当前连接, 失败时保留原连接
+    async fn reset_session(&self) -> Result<()> {
+        let client = Self::connect_client(
+            self.api_id,
+            &self.api_hash,
+            self.proxy_url.as_deref(),
+            Session::new(),
+        )
+        .await?;
+        self.sign_in(&client).await?;
+
+        *self.client.write().unwrap() = client;
+        self.reset.notify_waiters();
+
+        Ok(())
+    }
+}
+
+fn input_channel(chat: PackedChat) -> tl::enums::InputChannel {
+    tl::types::InputChannel {
+        channel_id: chat.id,
+        access_hash: chat.access_hash.unwrap_or(0),
+    }
+    .into()
+}
+
+pub fn inline_button(button: OutgoingButton) -> button::Inline {
+    match button {
+        OutgoingButton::Url { text, url } => button::url(text, url),
+        OutgoingButton::Callback { text, data } => button::inline(text, data),
+    }
+}
+
+// 以贴纸形式发送上传的图片
+fn sticker_media(
+    uploaded: Uploaded,
+    file_name: String,
+    mime_type: String,
+) -> tl::types::InputMediaUploadedDocument {
+    tl::types::InputMediaUploadedDocument {
+        nosound_video: false,
+        force_file: false,
+        spoiler: false,
+        file: uploaded.raw,
+        thumb: None,
+        mime_type,
+        attributes: vec![
+            (tl::types::DocumentAttributeFilename { file_name }).into(),
+            (tl::types::DocumentAttributeSticker {
+                mask: false,
+                alt: "😊".to_string(),
+                stickerset: tl::enums::InputStickerSet::Empty,
+                mask_coords: None,
+            })
+            .into(),
+        ],
+        stickers: None,
+        ttl_seconds: None,
+        video_cover: None,
+        video_timestamp: None,
+    }
+}
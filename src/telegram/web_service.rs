@@ -0,0 +1,427 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::Ordering;
+
+use anyhow::Result;
+use axum::Router;
+use axum::body::Body;
+use axum::extract::{Form, Path, Request, State};
+use axum::http::{HeaderMap, Method, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use chrono::{Local, TimeZone, Utc};
+use grammers_client::session::PackedType;
+use lazy_static::lazy_static;
+use reqwest::Url;
+use sea_orm::{EntityTrait, QueryOrder};
+use serde::Deserialize;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio_util::io::ReaderStream;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use super::bridge::RelayBridge;
+use super::entities;
+use super::telegram_helper as tg_helper;
+use crate::common::{Endpoint, WebConfig};
+use crate::onebot::event_queue::EventQueue;
+
+// 保留的最近错误数量
+const RECENT_ERROR_LIMIT: usize = 50;
+
+lazy_static! {
+    // 最近的警告和错误日志: (时间, 内容)
+    static ref RECENT_ERRORS: Mutex<VecDeque<(i64, String)>> = Mutex::new(VecDeque::new());
+}
+
+// 收集警告及以上级别的日志, 供管理页面展示
+pub struct RecentErrorLayer;
+
+impl<S: Subscriber> Layer<S> for RecentErrorLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() > Level::WARN {
+            return;
+        }
+
+        let mut visitor = MessageVisitor(String::new());
+        event.record(&mut visitor);
+
+        let mut errors = RECENT_ERRORS.lock().unwrap();
+        errors.push_back((Utc::now().timestamp(), visitor.0));
+        if errors.len() > RECENT_ERROR_LIMIT {
+            errors.pop_front();
+        }
+    }
+}
+
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            write!(&mut self.0, "{:?}", value).unwrap();
+        }
+    }
+}
+
+#[derive(Clone)]
+struct WebState {
+    bridge: RelayBridge,
+    // 期望的Basic认证头
+    authorization: String,
+}
+
+// 处理函数的错误, 以500返回错误信息
+struct WebError(anyhow::Error);
+
+impl<E: Into<anyhow::Error>> From<E> for WebError {
+    fn from(e: E) -> Self {
+        Self(e.into())
+    }
+}
+
+impl IntoResponse for WebError {
+    fn into_response(self) -> Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, self.0.to_string()).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct CreateLinkForm {
+    remote_chat_id: i64,
+    tg_chat_id: i64,
+    tg_chat_type: String,
+}
+
+#[derive(Deserialize)]
+struct CreateArchiveForm {
+    endpoint: String,
+    tg_chat_id: i64,
+    tg_chat_type: String,
+    #[serde(default)]
+    rule: String,
+}
+
+#[derive(Deserialize)]
+struct DeleteForm {
+    id: i64,
+}
+
+pub(crate) async fn run(
+    config: WebConfig,
+    bridge: RelayBridge,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) -> Result<()> {
+    let state = WebState {
+        bridge,
+        authorization: format!(
+            "Basic {}",
+            BASE64_STANDARD.encode(format!("{}:{}", config.username, config.password))
+        ),
+    };
+
+    let app = Router::new()
+        .route("/", get(dashboard))
+        .route("/link/create", post(create_link))
+        .route("/link/delete", post(delete_link))
+        .route("/archive/create", post(create_archive))
+        .route("/archive/delete", post(delete_archive))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        // 临时文件供Onebot实现下载, 不需要认证
+        .route("/transfer/{name}", get(download_transfer))
+        .with_state(state);
+
+    let listener = TcpListener::bind(&config.addr).await?;
+    tracing::info!("WebService listening on: {}", config.addr);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            let _ = shutdown_rx.recv().await;
+            tracing::info!("Shutting down WebService");
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn authorize(State(state): State<WebState>, request: Request, next: Next) -> Response {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .map(|h| h.as_bytes())
+        .unwrap_or_default();
+    if !constant_time_eq(authorization, state.authorization.as_bytes()) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Basic realm=\"teleporter\"")],
+        )
+            .into_response();
+    }
+
+    // 浏览器会自动带上Basic认证, 修改状态的请求需要来自本站, 防止跨站提交表单
+    if request.method() == Method::POST && !is_same_origin(request.headers()) {
+        return (StatusCode::FORBIDDEN, "Cross-origin request rejected").into_response();
+    }
+
+    next.run(request).await
+}
+
+// 比较耗时与内容无关, 避免通过响应时间逐字节猜出凭据
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Origin (没有时用Referer) 的主机和端口与Host一致
+fn is_same_origin(headers: &HeaderMap) -> bool {
+    let Some(host) = headers.get(header::HOST).and_then(|h| h.to_str().ok()) else {
+        return false;
+    };
+    let Some(origin) = headers
+        .get(header::ORIGIN)
+        .or_else(|| headers.get(header::REFERER))
+        .and_then(|h| h.to_str().ok())
+        .and_then(|origin| Url::parse(origin).ok())
+    else {
+        return false;
+    };
+    match (origin.host_str(), origin.port()) {
+        (Some(origin_host), Some(port)) => host == format!("{}:{}", origin_host, port),
+        (Some(origin_host), None) => host == origin_host,
+        _ => false,
+    }
+}
+
+async fn dashboard(State(state): State<WebState>) -> Result<Html<String>, WebError> {
+    let bridge = &state.bridge;
+    let stats = &bridge.stats;
+    let mut page = String::from(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Teleporter</title>\
+        <style>body{font-family:sans-serif;margin:2em}table{border-collapse:collapse;margin-bottom:1em}\
+        td,th{border:1px solid #ccc;padding:4px 8px;text-align:left}form{display:inline}</style>\
+        </head><body><h1>Teleporter</h1>",
+    );
+
+    // 运行状态
+    write!(
+        &mut page,
+        "<h2>Status</h2><table>\
+        <tr><th>Started at</th><td>{}</td></tr>\
+        <tr><th>To Telegram</th><td>{}</td></tr>\
+        <tr><th>To remote</th><td>{}</td></tr></table>",
+        format_time(stats.started_at),
+        stats.to_telegram.load(Ordering::Relaxed),
+        stats.to_remote.load(Ordering::Relaxed),
+    )?;
+
+    page.push_str(
+        "<h2>Endpoints</h2><table><tr><th>Endpoint</th><th>Connected at</th>\
+        <th>Event queue</th><th>Dropped</th></tr>",
+    );
+    let queues = EventQueue::stats();
+    for entry in stats.endpoints.iter() {
+        let queue = queues.iter().find(|q| &q.endpoint == entry.key());
+        write!(
+            &mut page,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&entry.key().to_string()),
+            format_time(*entry.value()),
+            queue.map_or(String::new(), |q| format!("{}/{}", q.depth, q.capacity)),
+            queue.map_or(0, |q| q.total_dropped),
+        )?;
+    }
+    page.push_str("</table>");
+
+    // 链接
+    let remote_chats = entities::remote_chat::Entity::find()
+        .order_by_asc(entities::remote_chat::Column::Id)
+        .all(&bridge.db)
+        .await?;
+    page.push_str(
+        "<h2>Links</h2><table><tr><th>ID</th><th>Remote chat</th>\
+        <th>Telegram chat</th><th></th></tr>",
+    );
+    for (link, remote_chat) in entities::link::Entity::find()
+        .find_also_related(entities::remote_chat::Entity)
+        .all(&bridge.db)
+        .await?
+    {
+        write!(
+            &mut page,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            link.id,
+            escape(&remote_chat.map_or(String::new(), |c| c.topic_title())),
+            match link.tg_topic_id {
+                0 => link.tg_chat_id.to_string(),
+                topic_id => format!("{}#{}", link.tg_chat_id, topic_id),
+            },
+            delete_button("/link/delete", link.id)
+        )?;
+    }
+    page.push_str(
+        "</table><form method=\"post\" action=\"/link/create\"><select name=\"remote_chat_id\">",
+    );
+    for remote_chat in &remote_chats {
+        write!(
+            &mut page,
+            "<option value=\"{}\">{} ({})</option>",
+            remote_chat.id,
+            escape(&remote_chat.topic_title()),
+            escape(&remote_chat.endpoint.to_string())
+        )?;
+    }
+    page.push_str(
+        "</select> <input name=\"tg_chat_id\" placeholder=\"Telegram chat ID\" required> \
+        <select name=\"tg_chat_type\"><option value=\"megagroup\">Supergroup</option>\
+        <option value=\"chat\">Group</option></select> \
+        <button type=\"submit\">Link</button></form>",
+    );
+
+    // 归档
+    page.push_str(
+        "<h2>Archives</h2><table><tr><th>ID</th><th>Endpoint</th>\
+        <th>Rule</th><th>Telegram chat</th><th>Type</th><th></th></tr>",
+    );
+    for archive in entities::archive::Entity::find().all(&bridge.db).await? {
+        write!(
+            &mut page,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            archive.id,
+            escape(&archive.endpoint.to_string()),
+            escape(entities::archive::Model::rule_name(&archive.rule)),
+            archive.tg_chat_id,
+            match tg_helper::unpack_chat_type(archive.tg_chat_type) {
+                PackedType::Broadcast => "Channel",
+                _ => "Forum",
+            },
+            delete_button("/archive/delete", archive.id)
+        )?;
+    }
+    page.push_str(
+        "</table><form method=\"post\" action=\"/archive/create\">\
+        <input name=\"endpoint\" placeholder=\"qq:12345\" required> \
+        <input name=\"tg_chat_id\" placeholder=\"Telegram chat ID\" required> \
+        <select name=\"tg_chat_type\"><option value=\"megagroup\">Forum</option>\
+        <option value=\"broadcast\">Channel</option></select> \
+        <input name=\"rule\" placeholder=\"Rule (private, group, id:1,100-200)\"> \
+        <button type=\"submit\">Archive</button></form>",
+    );
+
+    // 最近的错误
+    page.push_str("<h2>Recent errors</h2><table><tr><th>Time</th><th>Message</th></tr>");
+    for (time, message) in RECENT_ERRORS.lock().unwrap().iter().rev() {
+        write!(
+            &mut page,
+            "<tr><td>{}</td><td>{}</td></tr>",
+            format_time(*time),
+            escape(message)
+        )?;
+    }
+    page.push_str("</table></body></html>");
+
+    Ok(Html(page))
+}
+
+async fn create_link(
+    State(state): State<WebState>,
+    Form(form): Form<CreateLinkForm>,
+) -> Result<Redirect, WebError> {
+    let tg_chat_type = match form.tg_chat_type.as_str() {
+        "chat" => PackedType::Chat,
+        _ => PackedType::Megagroup,
+    };
+    state
+        .bridge
+        .create_link(tg_chat_type, form.tg_chat_id, 0, form.remote_chat_id)
+        .await?;
+
+    Ok(Redirect::to("/"))
+}
+
+async fn delete_link(
+    State(state): State<WebState>,
+    Form(form): Form<DeleteForm>,
+) -> Result<Redirect, WebError> {
+    state.bridge.delete_link(form.id).await?;
+
+    Ok(Redirect::to("/"))
+}
+
+async fn create_archive(
+    State(state): State<WebState>,
+    Form(form): Form<CreateArchiveForm>,
+) -> Result<Redirect, WebError> {
+    let endpoint = form
+        .endpoint
+        .parse::<Endpoint>()
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let tg_chat_type = match form.tg_chat_type.as_str() {
+        "broadcast" => PackedType::Broadcast,
+        _ => PackedType::Megagroup,
+    };
+    let rule = form.rule.trim();
+    if !entities::archive::Model::is_valid_rule(rule) {
+        return Err(anyhow::anyhow!("invalid archive rule: {}", rule).into());
+    }
+    state
+        .bridge
+        .create_archive(&endpoint, form.tg_chat_id, tg_chat_type, rule)
+        .await?;
+
+    Ok(Redirect::to("/"))
+}
+
+async fn delete_archive(
+    State(state): State<WebState>,
+    Form(form): Form<DeleteForm>,
+) -> Result<Redirect, WebError> {
+    state.bridge.delete_archive(form.id).await?;
+
+    Ok(Redirect::to("/"))
+}
+
+// 临时文件名是随机生成的, 只有拿到地址的Onebot实现可以下载
+async fn download_transfer(
+    State(state): State<WebState>,
+    Path(name): Path<String>,
+) -> Result<Response, WebError> {
+    let Some(path) = state.bridge.transfer_path(&name) else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let file = tokio::fs::File::open(path).await?;
+    let content_type = mime_guess::from_path(&name).first_or_octet_stream();
+    Ok((
+        [(header::CONTENT_TYPE, content_type.to_string())],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+fn escape(text: &str) -> String {
+    html_escape::encode_text(text).to_string()
+}
+
+fn format_time(timestamp: i64) -> String {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .unwrap()
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+fn delete_button(action: &str, id: i64) -> String {
+    format!(
+        "<form method=\"post\" action=\"{}\"><input type=\"hidden\" name=\"id\" value=\"{}\">\
+        <button type=\"submit\">Delete</button></form>",
+        action, id
+    )
+}
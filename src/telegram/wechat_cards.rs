@@ -0,0 +1,138 @@
+use super::card_parser::{Card, CardMatcher};
+use super::onebot_helper::{xml_section, xml_tag};
+
+// 正文作为标题时的最大字数
+const TITLE_MAX_LENGTH: usize = 50;
+
+// 视频号动态: appmsg type 51, <finderFeed><nickname/><desc/><mediaList><media><thumbUrl/><coverUrl/></media></mediaList></finderFeed>
+pub struct FinderFeedMatcher;
+
+impl CardMatcher for FinderFeedMatcher {
+    fn parse_xml(&self, xml: &str) -> Option<Card> {
+        let feed = xml_section(xml, "finderFeed")?;
+
+        let author = xml_tag(feed, "nickname")?;
+        let (title, description) = split_title(&xml_tag(feed, "desc").unwrap_or_default())
+            .unwrap_or_else(|| (author.clone(), String::new()));
+        let media = xml_section(feed, "media").unwrap_or_default();
+
+        Some(Card {
+            icon: "🎬",
+            kind: "视频号",
+            title,
+            description,
+            url: appmsg_url(xml, feed),
+            thumbnail: ["thumbUrl", "coverUrl", "fullCoverUrl"]
+                .iter()
+                .find_map(|tag| xml_tag(media, tag))
+                .filter(|u| u.starts_with("http")),
+            source: author,
+        })
+    }
+}
+
+// 视频号直播: appmsg type 63, <finderLive><nickname/><desc/><media><coverUrl/></media></finderLive>
+pub struct FinderLiveMatcher;
+
+impl CardMatcher for FinderLiveMatcher {
+    fn parse_xml(&self, xml: &str) -> Option<Card> {
+        let live = xml_section(xml, "finderLive")?;
+
+        let author = xml_tag(live, "nickname")?;
+        let (title, description) = split_title(&xml_tag(live, "desc").unwrap_or_default())
+            .unwrap_or_else(|| (author.clone(), String::new()));
+
+        Some(Card {
+            icon: "📺",
+            kind: "视频号直播",
+            title,
+            description,
+            url: appmsg_url(xml, live),
+            thumbnail: ["coverUrl", "headUrl"]
+                .iter()
+                .find_map(|tag| xml_tag(live, tag))
+                .filter(|u| u.starts_with("http")),
+            source: author,
+        })
+    }
+}
+
+// 视频号名片: appmsg type 50, <findernamecard><nickname/><avatar/><auth_job/></findernamecard>
+pub struct FinderNameCardMatcher;
+
+impl CardMatcher for FinderNameCardMatcher {
+    fn parse_xml(&self, xml: &str) -> Option<Card> {
+        let card = xml_section(xml, "findernamecard")?;
+
+        Some(Card {
+            icon: "👤",
+            kind: "视频号名片",
+            title: xml_tag(card, "nickname")?,
+            description: xml_tag(card, "auth_job").unwrap_or_default(),
+            url: appmsg_url(xml, card),
+            thumbnail: xml_tag(card, "avatar").filter(|u| u.starts_with("http")),
+            source: String::new(),
+        })
+    }
+}
+
+// 朋友圈: <TimelineObject><username/><contentDesc/><ContentObject><title/><description/><contentUrl/>
+// <mediaList><media><thumb type="1"/></media></mediaList></ContentObject></TimelineObject>
+pub struct TimelineMatcher;
+
+impl CardMatcher for TimelineMatcher {
+    fn parse_xml(&self, xml: &str) -> Option<Card> {
+        let timeline = xml_section(xml, "TimelineObject")?;
+
+        let content = xml_section(timeline, "ContentObject").unwrap_or_default();
+        let desc = xml_tag(timeline, "contentDesc").unwrap_or_default();
+        // 分享链接时标题为链接的标题, 纯文字和图片时使用正文的第一行
+        let (title, description) = match xml_tag(content, "title") {
+            Some(title) if desc.is_empty() => {
+                (title, xml_tag(content, "description").unwrap_or_default())
+            }
+            Some(title) => (title, desc),
+            None => split_title(&desc).unwrap_or_else(|| ("[图片]".to_string(), String::new())),
+        };
+        let media = xml_section(content, "media").unwrap_or_default();
+
+        Some(Card {
+            icon: "🌐",
+            kind: "朋友圈",
+            title,
+            description,
+            url: xml_tag(content, "contentUrl").filter(|u| u.starts_with("http")),
+            thumbnail: xml_tag(media, "thumb").filter(|u| u.starts_with("http")),
+            source: xml_tag(timeline, "nickname")
+                .or_else(|| xml_tag(timeline, "username"))
+                .unwrap_or_default(),
+        })
+    }
+}
+
+// 取出appmsg自身的链接, 卡片内的<url>是媒体地址, 需要先去掉卡片部分
+fn appmsg_url(xml: &str, section: &str) -> Option<String> {
+    let appmsg = xml_section(xml, "appmsg")?.replacen(section, "", 1);
+    xml_tag(&appmsg, "url").filter(|u| u.starts_with("http"))
+}
+
+// 以正文的第一行作为标题, 其余部分作为描述, 第一行过长时截断并保留完整正文
+fn split_title(text: &str) -> Option<(String, String)> {
+    let text = text.trim();
+    let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+    let first = first.trim();
+    if first.is_empty() {
+        return None;
+    }
+
+    match first.chars().count() > TITLE_MAX_LENGTH {
+        true => Some((
+            format!(
+                "{}…",
+                first.chars().take(TITLE_MAX_LENGTH).collect::<String>()
+            ),
+            text.to_string(),
+        )),
+        false => Some((first.to_string(), rest.trim().to_string())),
+    }
+}
@@ -0,0 +1,200 @@
+// 用模拟的Onebot客户端和不连接Telegram的接口测试转发流程
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use grammers_client::session::PackedType;
+use serde_json::{Value, json};
+use tokio::sync::{broadcast, mpsc};
+
+use teleporter::common::{LogLevelReloader, TeleporterConfig};
+use teleporter::onebot::mock_client::{self, Script};
+use teleporter::onebot::onebot_pylon::OnebotPylon;
+use teleporter::telegram::mock_transport::MockTransport;
+use teleporter::telegram::telegram_pylon::TelegramPylon;
+
+const ADMIN_ID: i64 = 1000;
+const BUFFER_SIZE: usize = 64;
+
+fn load_config(addr: &str) -> TeleporterConfig {
+    let toml = format!(
+        r#"
+        [telegram]
+        admin_id = {ADMIN_ID}
+        api_id = 1
+        api_hash = "test"
+        bot_token = "1:test"
+        enable_search = false
+
+        [[onebot]]
+        addr = "{addr}"
+
+        [general]
+        log_level = "info"
+        "#
+    );
+    config::Config::builder()
+        .add_source(config::File::from_str(&toml, config::FileFormat::Toml))
+        .build()
+        .unwrap()
+        .try_deserialize()
+        .unwrap()
+}
+
+fn free_addr() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+// 等待Onebot监听就绪
+async fn wait_listening(addr: &str) {
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(addr).await.is_ok() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Onebot listener {} is not ready", addr);
+}
+
+fn private_message(message_id: i64, text: &str) -> Value {
+    json!({
+        "time": Utc::now().timestamp(),
+        "self_id": 10000,
+        "post_type": "message",
+        "message_type": "private",
+        "sub_type": "friend",
+        "message_id": message_id,
+        "user_id": 20000,
+        "message": [{ "type": "text", "data": { "text": text } }],
+        "sender": { "user_id": 20000, "nickname": "Alice" },
+    })
+}
+
+// 启动两端并执行脚本, 返回模拟客户端收到的API请求
+async fn relay(transport: Arc<MockTransport>, script: Script) -> Vec<Value> {
+    let data_dir = tempfile::tempdir().unwrap();
+    let addr = free_addr();
+    let config = load_config(&addr);
+    let database_url = format!(
+        "sqlite://{}?mode=rwc",
+        data_dir.path().join("test.db").display()
+    );
+    let log_reloader: LogLevelReloader = Arc::new(|_: &str| Ok(()));
+
+    let rate_limit = config.rate_limit();
+    let telegram_pylon = TelegramPylon::with_transports(
+        config.telegram,
+        vec![transport],
+        data_dir.path(),
+        &database_url,
+        rate_limit,
+        None,
+        None,
+        log_reloader,
+    )
+    .await
+    .unwrap();
+    let onebot_pylon = OnebotPylon::new(config.onebot.into_iter().next().unwrap())
+        .await
+        .unwrap();
+
+    let (event_sender, event_receiver) = mpsc::channel(BUFFER_SIZE);
+    let (api_sender, api_receiver) = mpsc::channel(BUFFER_SIZE);
+    let (pylon_api_sender, pylon_api_receiver) = mpsc::channel(BUFFER_SIZE);
+    let (shutdown_tx, _) = broadcast::channel(1);
+
+    let telegram_shutdown = shutdown_tx.subscribe();
+    let telegram_handle = tokio::spawn(async move {
+        telegram_pylon
+            .run(event_receiver, api_sender, telegram_shutdown)
+            .await;
+    });
+    let routes = vec![(onebot_pylon.clone(), pylon_api_sender)];
+    let onebot_shutdown = shutdown_tx.subscribe();
+    let onebot_handle = tokio::spawn(async move {
+        onebot_pylon
+            .run(event_sender, pylon_api_receiver, onebot_shutdown)
+            .await;
+    });
+    let route_handle = tokio::spawn(OnebotPylon::route_requests(
+        routes,
+        api_receiver,
+        shutdown_tx.subscribe(),
+    ));
+
+    wait_listening(&addr).await;
+    let requests = mock_client::run(&format!("ws://{}", addr), script)
+        .await
+        .unwrap();
+
+    let _ = shutdown_tx.send(());
+    let _ = tokio::time::timeout(Duration::from_secs(10), async {
+        let _ = tokio::join!(telegram_handle, onebot_handle, route_handle);
+    })
+    .await;
+
+    requests
+}
+
+fn script(events: Vec<Value>) -> Script {
+    serde_json::from_value(json!({
+        "self_id": "10000",
+        "responses": {
+            "get_friend_list": [],
+            "get_group_list": [],
+            "get_stranger_info": { "user_id": 20000, "nickname": "Alice" },
+        },
+        "events": events
+            .into_iter()
+            .map(|event| json!({ "delay_ms": 500, "event": event }))
+            .collect::<Vec<_>>(),
+        "linger_ms": 3000,
+    }))
+    .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn private_message_is_relayed_to_admin() {
+    let transport = Arc::new(MockTransport::default());
+    let requests = relay(
+        transport.clone(),
+        script(vec![private_message(1, "hello from qq")]),
+    )
+    .await;
+
+    let sent = transport.sent_to(PackedType::User, ADMIN_ID);
+    assert!(
+        sent.iter()
+            .any(|message| message.text.contains("Alice") && message.text.contains("hello from qq")),
+        "no relayed message in {:?}",
+        sent
+    );
+    assert!(
+        requests
+            .iter()
+            .any(|request| request["action"] == "get_stranger_info"),
+        "sender was not resolved: {:?}",
+        requests
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn duplicate_message_is_relayed_once() {
+    let transport = Arc::new(MockTransport::default());
+    relay(
+        transport.clone(),
+        script(vec![
+            private_message(2, "only once"),
+            private_message(2, "only once"),
+        ]),
+    )
+    .await;
+
+    let relayed = transport
+        .sent_to(PackedType::User, ADMIN_ID)
+        .into_iter()
+        .filter(|message| message.text.contains("only once"))
+        .count();
+    assert_eq!(relayed, 1);
+}